@@ -72,42 +72,42 @@ mod tests {
         // {}
         let mut s = Bitset64::new();
         assert_eq!(s.len(), 0);
-        assert_eq!(s.is_empty(), true);
+        assert!(s.is_empty());
         assert_eq!(s.max(), None);
         assert_eq!(s.iter().collect::<Vec<_>>(), vec![]);
 
         // {2}
         s = s.add(2);
         assert_eq!(s.len(), 1);
-        assert_eq!(s.is_empty(), false);
+        assert!(!s.is_empty());
         assert_eq!(s.max(), Some(2));
         assert_eq!(s.iter().collect::<Vec<_>>(), vec![2]);
 
         // {2,9}
         s = s.add(9);
         assert_eq!(s.len(), 2);
-        assert_eq!(s.is_empty(), false);
+        assert!(!s.is_empty());
         assert_eq!(s.max(), Some(9));
         assert_eq!(s.iter().collect::<Vec<_>>(), vec![2, 9]);
 
         // {2,5,9}
         s = s.add(5);
         assert_eq!(s.len(), 3);
-        assert_eq!(s.is_empty(), false);
+        assert!(!s.is_empty());
         assert_eq!(s.max(), Some(9));
         assert_eq!(s.iter().collect::<Vec<_>>(), vec![2, 5, 9]);
 
         // {2,5,9}
         s = s.add(9);
         assert_eq!(s.len(), 3);
-        assert_eq!(s.is_empty(), false);
+        assert!(!s.is_empty());
         assert_eq!(s.max(), Some(9));
         assert_eq!(s.iter().collect::<Vec<_>>(), vec![2, 5, 9]);
 
         // !{2,5,9}
         s = s.inverse();
         assert_eq!(s.len(), 61);
-        assert_eq!(s.is_empty(), false);
+        assert!(!s.is_empty());
         assert_eq!(s.max(), Some(63));
 
         let mut expexted = vec![0, 1, 3, 4, 6, 7, 8];
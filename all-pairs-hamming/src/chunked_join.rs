@@ -1,8 +1,14 @@
 //! A fast and compact implementation of similarity self-join on binary sketches in the Hamming space.
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::ops::ControlFlow;
+use std::path::Path;
+
 use hashbrown::HashSet;
 
 use crate::errors::{AllPairsHammingError, Result};
 use crate::multi_sort::MultiSort;
+use crate::progress::{ProgressHook, Stage};
 use crate::sketch::Sketch;
 
 /// A fast and compact implementation of similarity self-join on binary sketches in the Hamming space.
@@ -36,7 +42,7 @@ use crate::sketch::Sketch;
 ///   IEEE Transactions on Knowledge and Data Engineering, 2021
 pub struct ChunkedJoiner<S> {
     chunks: Vec<Vec<S>>,
-    shows_progress: bool,
+    progress: Option<ProgressHook>,
 }
 
 impl<S> ChunkedJoiner<S>
@@ -48,13 +54,21 @@ where
     pub fn new(num_chunks: usize) -> Self {
         Self {
             chunks: vec![vec![]; num_chunks],
-            shows_progress: false,
+            progress: None,
         }
     }
 
-    /// Prints the progress with stderr?
-    pub const fn shows_progress(mut self, yes: bool) -> Self {
-        self.shows_progress = yes;
+    /// Emits the progress as `tracing` events with [`ProgressHook::tracing()`]?
+    /// This replaces the hook set with [`Self::progress_hook()`].
+    pub fn shows_progress(mut self, yes: bool) -> Self {
+        self.progress = yes.then(ProgressHook::tracing);
+        self
+    }
+
+    /// Sets a hook receiving the progress of joining chunks and verifying candidate pairs.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn progress_hook(mut self, hook: Option<ProgressHook>) -> Self {
+        self.progress = hook;
         self
     }
 
@@ -76,62 +90,342 @@ where
         Ok(())
     }
 
+    /// Creates an instance handling the first `num_chunks` chunks of the stored sketches.
+    /// If `num_chunks` is larger than [`Self::num_chunks()`], an error is returned.
+    pub fn prefix(&self, num_chunks: usize) -> Result<Self> {
+        if self.num_chunks() < num_chunks {
+            let msg = format!(
+                "The number of chunks must be no more than {}.",
+                self.num_chunks()
+            );
+            return Err(AllPairsHammingError::input(msg));
+        }
+        Ok(Self {
+            chunks: self.chunks[..num_chunks].to_vec(),
+            progress: self.progress.clone(),
+        })
+    }
+
+    /// Splits the stored sketches at `num_chunks`, keeping the first `num_chunks` chunks
+    /// and returning an instance handling the rest, without copying the sketches.
+    /// If `num_chunks` is larger than [`Self::num_chunks()`], an error is returned.
+    pub fn split_off(&mut self, num_chunks: usize) -> Result<Self> {
+        if self.num_chunks() < num_chunks {
+            let msg = format!(
+                "The number of chunks must be no more than {}.",
+                self.num_chunks()
+            );
+            return Err(AllPairsHammingError::input(msg));
+        }
+        Ok(Self {
+            chunks: self.chunks.split_off(num_chunks),
+            progress: self.progress.clone(),
+        })
+    }
+
+    /// Appends the chunks of `other` after the stored ones, without copying the sketches.
+    /// If the numbers of stored sketches are mismatched, an error is returned.
+    pub fn append(&mut self, mut other: Self) -> Result<()> {
+        if self.num_chunks() != 0
+            && other.num_chunks() != 0
+            && self.num_sketches() != other.num_sketches()
+        {
+            return Err(AllPairsHammingError::input(
+                "The numbers of sketches must be the same.".to_string(),
+            ));
+        }
+        self.chunks.append(&mut other.chunks);
+        Ok(())
+    }
+
     /// Finds all similar pairs whose normalized Hamming distance is within `radius`,
     /// returning triplets of the left-side id, the right-side id, and thier distance.
     pub fn similar_pairs(&self, radius: f64) -> Vec<(usize, usize, f64)> {
+        let mut matched = vec![];
+        self.for_each_similar_pair(radius, |i, j, dist| matched.push((i, j, dist)));
+        matched
+    }
+
+    /// Finds all similar pairs whose normalized Hamming distance is within `radius`,
+    /// calling `f` with the left-side id, the right-side id, and their distance for each pair
+    /// instead of collecting them.
+    pub fn for_each_similar_pair<F>(&self, radius: f64, f: F)
+    where
+        F: FnMut(usize, usize, f64),
+    {
+        // Never fails without checkpoints.
+        self.for_each_similar_pair_in(radius, None, f).unwrap();
+    }
+
+    /// Finds all similar pairs whose normalized Hamming distance is within `radius`,
+    /// calling `f` with the left-side id, the right-side id, and their distance for each pair,
+    /// and stops at the first error returned by `f`.
+    ///
+    /// Unlike [`Self::for_each_similar_pair()`], the candidate pairs are verified as soon as
+    /// they are found in each chunk instead of being collected over all chunks, so the memory
+    /// usage is bounded independently of the number of candidates.
+    /// Instead, the pairs are not ordered, and the candidates found in preceding chunks are
+    /// filtered out by comparing their chunks again.
+    ///
+    /// # Errors
+    ///
+    /// The first error returned by `f` is returned.
+    pub fn try_for_each_similar_pair<F, E>(&self, radius: f64, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(usize, usize, f64) -> Result<(), E>,
+    {
+        let dimension = S::dim() * self.num_chunks();
+        let hamradius = (dimension as f64 * radius).ceil() as usize;
+        let bound = (dimension as f64 * radius) as usize;
+        // Only the last hamradius+1 chunks are searched, with the radii of
+        // the general pigeonhole principle as in Self::for_each_similar_pair().
+        let first = self.chunks.len().saturating_sub(hamradius + 1);
+        let radii: Vec<_> = (first..self.chunks.len())
+            .map(|j| (j + hamradius + 1 - self.chunks.len()) / self.chunks.len())
+            .collect();
+
+        let num_chunks = self.chunks.len() - first;
+        let mut num_candidates = 0;
+        if let Some(progress) = &self.progress {
+            progress.report(Stage::ChunksJoined, 0, Some(num_chunks));
+            progress.report(Stage::PairsVerified, 0, None);
+        }
+        for (k, &r) in radii.iter().enumerate() {
+            let chunk = &self.chunks[first + k];
+            let _span = tracing::info_span!("sort_and_verify", chunk = first + k, r).entered();
+            let flow = MultiSort::new().try_for_each_similar_pair(chunk, r, |i, j| {
+                let found_before = radii[..k].iter().enumerate().any(|(l, &r)| {
+                    let chunk = &self.chunks[first + l];
+                    chunk[i].hamdist(chunk[j]) <= r
+                });
+                if found_before {
+                    return ControlFlow::Continue(());
+                }
+                num_candidates += 1;
+                if let Some(progress) = &self.progress {
+                    if num_candidates % (1 << 20) == 0 {
+                        progress.report(Stage::PairsVerified, num_candidates, None);
+                    }
+                }
+                if let Some(dist) = self.hamming_distance(i, j, bound) {
+                    let dist = dist as f64 / dimension as f64;
+                    if dist <= radius {
+                        if let Err(e) = f(i, j, dist) {
+                            return ControlFlow::Break(e);
+                        }
+                    }
+                }
+                ControlFlow::Continue(())
+            });
+            if let ControlFlow::Break(e) = flow {
+                return Err(e);
+            }
+            if let Some(progress) = &self.progress {
+                progress.report(Stage::ChunksJoined, k + 1, Some(num_chunks));
+            }
+        }
+        if let Some(progress) = &self.progress {
+            progress.report(Stage::PairsVerified, num_candidates, Some(num_candidates));
+        }
+        Ok(())
+    }
+
+    /// Finds all similar pairs in the same manner as [`Self::for_each_similar_pair()`],
+    /// persisting the candidate pairs found in each chunk to the checkpoint file at `path`.
+    ///
+    /// If the checkpoint file exists, the search resumes from the chunk after the last
+    /// completed one. The file is removed after the search is completed.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the checkpoint file cannot be read or written, or
+    /// it was written for different sketches or a different radius.
+    pub fn for_each_similar_pair_with_checkpoint<P, F>(
+        &self,
+        radius: f64,
+        path: P,
+        f: F,
+    ) -> Result<()>
+    where
+        P: AsRef<Path>,
+        F: FnMut(usize, usize, f64),
+    {
+        self.for_each_similar_pair_in(radius, Some(path.as_ref()), f)?;
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    fn for_each_similar_pair_in<F>(
+        &self,
+        radius: f64,
+        checkpoint: Option<&Path>,
+        mut f: F,
+    ) -> Result<()>
+    where
+        F: FnMut(usize, usize, f64),
+    {
         let dimension = S::dim() * self.num_chunks();
         let hamradius = (dimension as f64 * radius).ceil() as usize;
-        if self.shows_progress {
-            eprintln!(
+        if self.progress.is_some() {
+            tracing::debug!(
                 "[ChunkedJoiner::similar_pairs] #dimensions={dimension}, hamradius={hamradius}"
             );
         }
 
+        let mut header = CheckpointHeader {
+            radius,
+            num_chunks: self.num_chunks(),
+            num_sketches: self.num_sketches(),
+            checksum: self.checksum(),
+            next_chunk: 0,
+        };
         // TODO: Threading.
         let mut candidates = HashSet::new();
+        if let Some(path) = checkpoint.filter(|path| path.exists()) {
+            header.next_chunk = load_checkpoint(path, &header, &mut candidates)?;
+            if self.progress.is_some() {
+                tracing::info!(
+                    "[ChunkedJoiner::similar_pairs] Resumed from chunk {} with #candidates={}",
+                    header.next_chunk,
+                    candidates.len()
+                );
+            }
+        }
+        // Only the last hamradius+1 chunks are searched.
+        let first = self.chunks.len().saturating_sub(hamradius + 1);
+        if let Some(progress) = &self.progress {
+            progress.report(Stage::ChunksJoined, 0, Some(self.chunks.len() - first));
+        }
         for (j, chunk) in self.chunks.iter().enumerate() {
             // Based on the general pigeonhole principle.
             // https://doi.org/10.1109/TKDE.2019.2899597
-            if j + hamradius + 1 < self.chunks.len() {
+            if j < first.max(header.next_chunk) {
                 continue;
             }
             let r = (j + hamradius + 1 - self.chunks.len()) / self.chunks.len();
-            MultiSort::new().similar_pairs(chunk, r, &mut candidates);
+            tracing::info_span!("sort", chunk = j, r)
+                .in_scope(|| MultiSort::new().similar_pairs(chunk, r, &mut candidates));
+            if let Some(path) = checkpoint {
+                header.next_chunk = j + 1;
+                save_checkpoint(path, &header, &candidates)?;
+            }
 
-            if self.shows_progress {
-                eprintln!(
-                    "[ChunkedJoiner::similar_pairs] Processed {}/{}...",
-                    j + 1,
-                    self.chunks.len()
+            if let Some(progress) = &self.progress {
+                progress.report(
+                    Stage::ChunksJoined,
+                    j + 1 - first,
+                    Some(self.chunks.len() - first),
                 );
-                eprintln!(
+                tracing::debug!(
                     "[ChunkedJoiner::similar_pairs] #candidates={}",
                     candidates.len()
                 );
             }
         }
-        if self.shows_progress {
-            eprintln!("[ChunkedJoiner::similar_pairs] Done");
-        }
 
+        let _span = tracing::info_span!("verify", num_candidates = candidates.len()).entered();
         let mut candidates: Vec<_> = candidates.into_iter().collect();
         candidates.sort_unstable();
 
         let bound = (dimension as f64 * radius) as usize;
-        let mut matched = vec![];
+        let mut num_matched = 0;
 
-        for (i, j) in candidates {
+        let num_candidates = candidates.len();
+        if let Some(progress) = &self.progress {
+            progress.report(Stage::PairsVerified, 0, Some(num_candidates));
+        }
+        for (k, (i, j)) in candidates.into_iter().enumerate() {
+            if let Some(progress) = &self.progress {
+                if (k + 1) % (1 << 20) == 0 {
+                    progress.report(Stage::PairsVerified, k + 1, Some(num_candidates));
+                }
+            }
             if let Some(dist) = self.hamming_distance(i, j, bound) {
                 let dist = dist as f64 / dimension as f64;
                 if dist <= radius {
-                    matched.push((i, j, dist));
+                    f(i, j, dist);
+                    num_matched += 1;
                 }
             }
         }
-        if self.shows_progress {
-            eprintln!("[ChunkedJoiner::similar_pairs] #matched={}", matched.len());
+        if let Some(progress) = &self.progress {
+            progress.report(Stage::PairsVerified, num_candidates, Some(num_candidates));
+            tracing::info!("[ChunkedJoiner::similar_pairs] #matched={num_matched}");
         }
-        matched
+        Ok(())
+    }
+
+    /// Serializes the stored sketches into a writer in a binary format.
+    pub fn serialize_into<W>(&self, mut wtr: W) -> Result<()>
+    where
+        W: Write,
+    {
+        wtr.write_all(JOINER_MAGIC)?;
+        for x in [S::dim(), self.num_chunks(), self.num_sketches()] {
+            wtr.write_all(&(x as u64).to_le_bytes())?;
+        }
+        let num_bytes = S::dim() / 8;
+        for chunk in &self.chunks {
+            for x in chunk {
+                wtr.write_all(&x.to_u128().unwrap().to_le_bytes()[..num_bytes])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deserializes the sketches serialized with [`Self::serialize_into()`] from a reader.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the data is not valid or was serialized for another sketch type.
+    pub fn deserialize_from<R>(mut rdr: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut magic = [0; 8];
+        rdr.read_exact(&mut magic)?;
+        if &magic != JOINER_MAGIC {
+            return Err(AllPairsHammingError::input(
+                "The serialized sketches are broken.".to_string(),
+            ));
+        }
+        if read_u64(&mut rdr)? != S::dim() as u64 {
+            return Err(AllPairsHammingError::input(
+                "The sketches were serialized in another dimension.".to_string(),
+            ));
+        }
+        let num_chunks = read_u64(&mut rdr)? as usize;
+        let num_sketches = read_u64(&mut rdr)? as usize;
+        let num_bytes = S::dim() / 8;
+        let mut chunks = Vec::with_capacity(num_chunks);
+        for _ in 0..num_chunks {
+            let mut chunk = Vec::with_capacity(num_sketches);
+            for _ in 0..num_sketches {
+                let mut buf = [0; 16];
+                rdr.read_exact(&mut buf[..num_bytes])?;
+                chunk.push(S::from_u128(u128::from_le_bytes(buf)).unwrap());
+            }
+            chunks.push(chunk);
+        }
+        Ok(Self {
+            chunks,
+            progress: None,
+        })
+    }
+
+    /// Computes the checksum of the stored sketches to validate checkpoints.
+    fn checksum(&self) -> u64 {
+        // FNV-1a over the 64-bit words of the sketches.
+        let mut h = 0xcbf2_9ce4_8422_2325u64;
+        for chunk in &self.chunks {
+            for x in chunk {
+                let x = x.to_u128().unwrap();
+                for w in [x as u64, (x >> 64) as u64] {
+                    h = (h ^ w).wrapping_mul(0x0100_0000_01b3);
+                }
+            }
+        }
+        h
     }
 
     /// Gets the number of chunks.
@@ -144,11 +438,25 @@ where
         self.chunks.first().map(|v| v.len()).unwrap_or(0)
     }
 
+    /// Gets an iterator over the chunks of the `i`-th sketch.
+    pub fn sketch(&self, i: usize) -> impl Iterator<Item = S> + '_ {
+        self.chunks.iter().map(move |chunk| chunk[i])
+    }
+
     /// Gets the memory usage in bytes.
     pub fn memory_in_bytes(&self) -> usize {
         self.num_chunks() * self.num_sketches() * std::mem::size_of::<S>()
     }
 
+    /// Estimates the peak memory usage in bytes to store and join `num_sketches` sketches
+    /// of `num_chunks` chunks, excluding candidate pairs whose number depends on the data.
+    pub fn estimate_memory_in_bytes(num_sketches: usize, num_chunks: usize) -> usize {
+        let sketches = num_chunks * num_sketches * std::mem::size_of::<S>();
+        // MultiSort sorts pairs of ids and sketches in a chunk with a buffer of the same size.
+        let workspace = 2 * num_sketches * std::mem::size_of::<(usize, S)>();
+        sketches + workspace
+    }
+
     fn hamming_distance(&self, i: usize, j: usize, bound: usize) -> Option<usize> {
         let mut dist = 0;
         for chunk in &self.chunks {
@@ -161,6 +469,96 @@ where
     }
 }
 
+const CHECKPOINT_MAGIC: &[u8; 8] = b"APHCKPT\x01";
+const JOINER_MAGIC: &[u8; 8] = b"APHJOIN\x01";
+
+/// Settings of a search and its progress written in checkpoints.
+struct CheckpointHeader {
+    radius: f64,
+    num_chunks: usize,
+    num_sketches: usize,
+    checksum: u64,
+    next_chunk: usize,
+}
+
+impl CheckpointHeader {
+    fn words(&self) -> [u64; 5] {
+        [
+            self.radius.to_bits(),
+            self.num_chunks as u64,
+            self.num_sketches as u64,
+            self.checksum,
+            self.next_chunk as u64,
+        ]
+    }
+}
+
+/// Writes the candidates to the checkpoint file, replacing the old one atomically.
+fn save_checkpoint(
+    path: &Path,
+    header: &CheckpointHeader,
+    candidates: &HashSet<(usize, usize)>,
+) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    {
+        let mut wtr = BufWriter::new(File::create(&tmp_path)?);
+        wtr.write_all(CHECKPOINT_MAGIC)?;
+        for w in header.words() {
+            wtr.write_all(&w.to_le_bytes())?;
+        }
+        wtr.write_all(&(candidates.len() as u64).to_le_bytes())?;
+        for &(i, j) in candidates {
+            wtr.write_all(&(i as u64).to_le_bytes())?;
+            wtr.write_all(&(j as u64).to_le_bytes())?;
+        }
+        wtr.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    }
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/// Reads the candidates from the checkpoint file, returning the next chunk to be searched.
+fn load_checkpoint(
+    path: &Path,
+    header: &CheckpointHeader,
+    candidates: &mut HashSet<(usize, usize)>,
+) -> Result<usize> {
+    let mut rdr = BufReader::new(File::open(path)?);
+    let mut magic = [0; 8];
+    rdr.read_exact(&mut magic)?;
+    if &magic != CHECKPOINT_MAGIC {
+        return Err(AllPairsHammingError::input(
+            "The checkpoint file is broken.".to_string(),
+        ));
+    }
+    let expected = header.words();
+    for &w in &expected[..4] {
+        if read_u64(&mut rdr)? != w {
+            return Err(AllPairsHammingError::input(
+                "The checkpoint was written for different sketches or a different radius."
+                    .to_string(),
+            ));
+        }
+    }
+    let next_chunk = read_u64(&mut rdr)? as usize;
+    let len = read_u64(&mut rdr)?;
+    for _ in 0..len {
+        let i = read_u64(&mut rdr)? as usize;
+        let j = read_u64(&mut rdr)? as usize;
+        candidates.insert((i, j));
+    }
+    Ok(next_chunk)
+}
+
+fn read_u64<R>(rdr: &mut R) -> Result<u64>
+where
+    R: Read,
+{
+    let mut buf = [0; 8];
+    rdr.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -182,10 +580,8 @@ mod tests {
 
     fn naive_search(sketches: &[u16], radius: f64) -> Vec<(usize, usize, f64)> {
         let mut results = vec![];
-        for i in 0..sketches.len() {
-            let x = sketches[i];
-            for j in i + 1..sketches.len() {
-                let y = sketches[j];
+        for (i, &x) in sketches.iter().enumerate() {
+            for (j, &y) in sketches.iter().enumerate().skip(i + 1) {
                 let dist = x.hamdist(y);
                 let dist = dist as f64 / 16.;
                 if dist <= radius {
@@ -207,6 +603,16 @@ mod tests {
         let mut results = joiner.similar_pairs(radius);
         results.sort_by_key(|&(i, j, _)| (i, j));
         assert_eq!(results, expected);
+
+        let mut results = vec![];
+        joiner
+            .try_for_each_similar_pair(radius, |i, j, dist| -> Result<(), ()> {
+                results.push((i, j, dist));
+                Ok(())
+            })
+            .unwrap();
+        results.sort_by_key(|&(i, j, _)| (i, j));
+        assert_eq!(results, expected);
     }
 
     #[test]
@@ -216,6 +622,121 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_try_for_each_similar_pair_error() {
+        let mut joiner = ChunkedJoiner::new(2);
+        for s in example_sketches() {
+            joiner.add([(s & 0xFF) as u8, (s >> 8) as u8]).unwrap();
+        }
+        let mut num_calls = 0;
+        let result = joiner.try_for_each_similar_pair(1., |_, _, _| {
+            num_calls += 1;
+            Err("failed")
+        });
+        assert_eq!(result, Err("failed"));
+        assert_eq!(num_calls, 1);
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let radius = 0.5;
+        let mut joiner = ChunkedJoiner::new(2);
+        for s in example_sketches() {
+            joiner.add([(s & 0xFF) as u8, (s >> 8) as u8]).unwrap();
+        }
+        let mut expected = joiner.similar_pairs(radius);
+        expected.sort_by_key(|&(i, j, _)| (i, j));
+
+        let path = std::env::temp_dir().join(format!("aph-test-{}.ckpt", std::process::id()));
+        let mut header = CheckpointHeader {
+            radius,
+            num_chunks: joiner.num_chunks(),
+            num_sketches: joiner.num_sketches(),
+            checksum: joiner.checksum(),
+            next_chunk: 0,
+        };
+
+        // Resumes after the first chunk.
+        let mut candidates = HashSet::new();
+        // The radius of the first chunk is (0 + 8 + 1 - 2) / 2 = 3.
+        MultiSort::new().similar_pairs(&joiner.chunks[0], 3, &mut candidates);
+        header.next_chunk = 1;
+        save_checkpoint(&path, &header, &candidates).unwrap();
+        let mut results = vec![];
+        joiner
+            .for_each_similar_pair_with_checkpoint(radius, &path, |i, j, d| results.push((i, j, d)))
+            .unwrap();
+        results.sort_by_key(|&(i, j, _)| (i, j));
+        assert_eq!(results, expected);
+        assert!(!path.exists());
+
+        // Rejects a checkpoint for another radius.
+        header.radius = 0.25;
+        save_checkpoint(&path, &header, &candidates).unwrap();
+        assert!(joiner
+            .for_each_similar_pair_with_checkpoint(radius, &path, |_, _, _| {})
+            .is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mut joiner = ChunkedJoiner::new(2);
+        for s in example_sketches() {
+            joiner.add([s, !s]).unwrap();
+        }
+        let mut data = vec![];
+        joiner.serialize_into(&mut data).unwrap();
+        let other = ChunkedJoiner::<u16>::deserialize_from(&data[..]).unwrap();
+        assert_eq!(other.chunks, joiner.chunks);
+        assert_eq!(other.similar_pairs(0.3), joiner.similar_pairs(0.3));
+
+        assert!(ChunkedJoiner::<u32>::deserialize_from(&data[..]).is_err());
+        assert!(ChunkedJoiner::<u16>::deserialize_from(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_estimate_memory_in_bytes() {
+        let mut joiner = ChunkedJoiner::<u64>::new(3);
+        joiner.add([1, 2, 3]).unwrap();
+        joiner.add([4, 5, 6]).unwrap();
+        let estimated = ChunkedJoiner::<u64>::estimate_memory_in_bytes(2, 3);
+        assert_eq!(estimated, joiner.memory_in_bytes() + 2 * 2 * 16);
+    }
+
+    #[test]
+    fn test_prefix() {
+        let mut joiner = ChunkedJoiner::new(3);
+        joiner.add([1u8, 2, 3]).unwrap();
+        joiner.add([4u8, 5, 6]).unwrap();
+
+        let prefix = joiner.prefix(2).unwrap();
+        assert_eq!(prefix.num_chunks(), 2);
+        assert_eq!(prefix.num_sketches(), 2);
+        assert_eq!(prefix.sketch(1).collect::<Vec<_>>(), vec![4, 5]);
+        assert!(joiner.prefix(4).is_err());
+    }
+
+    #[test]
+    fn test_split_off_and_append() {
+        let mut joiner = ChunkedJoiner::new(3);
+        joiner.add([1u8, 2, 3]).unwrap();
+        joiner.add([4u8, 5, 6]).unwrap();
+
+        assert!(joiner.split_off(4).is_err());
+        let rest = joiner.split_off(2).unwrap();
+        assert_eq!(joiner.sketch(1).collect::<Vec<_>>(), vec![4, 5]);
+        assert_eq!(rest.sketch(1).collect::<Vec<_>>(), vec![6]);
+
+        let mut other = ChunkedJoiner::new(1);
+        other.add([7u8]).unwrap();
+        assert!(joiner.append(other).is_err());
+
+        joiner.append(rest).unwrap();
+        assert_eq!(joiner.num_chunks(), 3);
+        assert_eq!(joiner.sketch(1).collect::<Vec<_>>(), vec![4, 5, 6]);
+    }
+
     #[test]
     fn test_short_sketch() {
         let mut joiner = ChunkedJoiner::new(2);
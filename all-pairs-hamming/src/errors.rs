@@ -1,6 +1,6 @@
 //! Error definitions.
 use std::error::Error;
-use std::{fmt, result};
+use std::{fmt, io, result};
 
 /// A specialized Result type for this library.
 pub type Result<T, E = AllPairsHammingError> = result::Result<T, E>;
@@ -10,17 +10,34 @@ pub type Result<T, E = AllPairsHammingError> = result::Result<T, E>;
 pub enum AllPairsHammingError {
     /// Contains [`InputError`].
     Input(InputError),
+
+    /// Contains [`io::Error`].
+    Io(io::Error),
 }
 
 impl fmt::Display for AllPairsHammingError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Input(e) => e.fmt(f),
+            Self::Io(e) => write!(f, "IoError: {e}"),
+        }
+    }
+}
+
+impl Error for AllPairsHammingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
         }
     }
 }
 
-impl Error for AllPairsHammingError {}
+impl From<io::Error> for AllPairsHammingError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
 
 impl AllPairsHammingError {
     pub(crate) const fn input(msg: String) -> Self {
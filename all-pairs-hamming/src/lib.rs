@@ -9,6 +9,7 @@ mod bitset64;
 pub mod chunked_join;
 pub mod errors;
 pub mod multi_sort;
+pub mod progress;
 pub mod simple_join;
 pub mod sketch;
 
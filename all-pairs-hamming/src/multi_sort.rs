@@ -1,6 +1,6 @@
 //! The core part of [`crate::ChunkedJoiner`].
 use std::cell::RefCell;
-use std::ops::Range;
+use std::ops::{ControlFlow, Range};
 
 use hashbrown::HashSet;
 
@@ -10,6 +10,8 @@ use crate::sketch::Sketch;
 const SORT_SHIFT: usize = 8;
 const SORT_MASK: usize = (1 << SORT_SHIFT) - 1;
 const DEFAULT_THRESHOLD_IN_SORT: usize = 1000;
+/// Maximum number of blocks, i.e., the capacity of [`Bitset64`].
+const MAX_BLOCKS: usize = 64;
 
 #[derive(Clone, Debug, Default)]
 struct Record<S> {
@@ -69,7 +71,7 @@ where
 
     /// Sets the number of blocks.
     pub fn num_blocks(mut self, num_blocks: usize) -> Self {
-        if num_blocks <= S::dim() {
+        if num_blocks <= S::dim().min(MAX_BLOCKS) {
             self.num_blocks = num_blocks;
         }
         self
@@ -86,14 +88,34 @@ where
     /// Finds all similar pairs whose Hamming distance is within `radius`,
     /// inserting the results in a given hash table.
     pub fn similar_pairs(
-        mut self,
+        self,
         sketches: &[S],
         radius: usize,
         results: &mut HashSet<(usize, usize)>,
     ) {
+        // Never breaks.
+        let _ = self.try_for_each_similar_pair(sketches, radius, |i, j| {
+            results.insert((i, j));
+            ControlFlow::<()>::Continue(())
+        });
+    }
+
+    /// Finds all similar pairs whose Hamming distance is within `radius`,
+    /// calling `f` with the smaller id and the larger one of each pair as soon as it is found.
+    /// Each pair is found only once, and the search is stopped when `f` returns
+    /// [`ControlFlow::Break`], whose value is returned.
+    pub fn try_for_each_similar_pair<F, B>(
+        mut self,
+        sketches: &[S],
+        radius: usize,
+        mut f: F,
+    ) -> ControlFlow<B>
+    where
+        F: FnMut(usize, usize) -> ControlFlow<B>,
+    {
         if self.num_blocks == 0 || self.num_blocks < radius {
             // Following Tabei's paper.
-            self.num_blocks = S::dim().min(radius + 3);
+            self.num_blocks = S::dim().min(radius + 3).min(MAX_BLOCKS);
         }
 
         self.build_masks_and_offsets();
@@ -105,7 +127,11 @@ where
             .enumerate()
             .map(|(id, &sketch)| Record { id, sketch })
             .collect();
-        self.similar_pairs_recur(&mut records, Bitset64::new(), results);
+        if self.num_blocks <= radius {
+            // No block needs to collide, e.g., for a large radius in sketches of u128.
+            return self.verify_all_pairs(&records, Bitset64::new(), &mut f);
+        }
+        self.similar_pairs_recur(&mut records, Bitset64::new(), &mut f)
     }
 
     fn build_masks_and_offsets(&mut self) {
@@ -122,15 +148,17 @@ where
         self.offsets = offsets;
     }
 
-    fn similar_pairs_recur(
+    fn similar_pairs_recur<F, B>(
         &self,
         records: &mut [Record<S>],
         blocks: Bitset64,
-        results: &mut HashSet<(usize, usize)>,
-    ) {
+        f: &mut F,
+    ) -> ControlFlow<B>
+    where
+        F: FnMut(usize, usize) -> ControlFlow<B>,
+    {
         if blocks.len() == self.num_blocks - self.radius {
-            self.verify_all_pairs(records, blocks, results);
-            return;
+            return self.verify_all_pairs(records, blocks, f);
         }
 
         let mut ranges = vec![];
@@ -140,17 +168,21 @@ where
             self.sort_sketches(b, records);
             self.collision_ranges(b, records, &mut ranges);
             for r in ranges.iter().cloned() {
-                self.similar_pairs_recur(&mut records[r], blocks.add(b), results);
+                self.similar_pairs_recur(&mut records[r], blocks.add(b), f)?;
             }
         }
+        ControlFlow::Continue(())
     }
 
-    fn verify_all_pairs(
+    fn verify_all_pairs<F, B>(
         &self,
         records: &[Record<S>],
         blocks: Bitset64,
-        results: &mut HashSet<(usize, usize)>,
-    ) {
+        f: &mut F,
+    ) -> ControlFlow<B>
+    where
+        F: FnMut(usize, usize) -> ControlFlow<B>,
+    {
         for i in 0..records.len() {
             let x = &records[i];
             for y in records.iter().skip(i + 1) {
@@ -160,10 +192,11 @@ where
                 {
                     debug_assert_ne!(x.id, y.id);
                     // Keeps the tuple order to ease debug.
-                    results.insert((x.id.min(y.id), x.id.max(y.id)));
+                    f(x.id.min(y.id), x.id.max(y.id))?;
                 }
             }
         }
+        ControlFlow::Continue(())
     }
 
     fn check_canonical(&self, x: S, y: S, blocks: Bitset64) -> bool {
@@ -190,7 +223,7 @@ where
 
     fn quick_sort_sketches(&self, block_id: usize, records: &mut [Record<S>]) {
         let mask = self.masks[block_id];
-        records.sort_unstable_by(|x, y| (x.sketch & mask).cmp(&(y.sketch & mask)));
+        records.sort_unstable_by_key(|x| x.sketch & mask);
     }
 
     fn radix_sort_sketches(&self, block_id: usize, records: &mut [Record<S>]) {
@@ -278,10 +311,8 @@ mod tests {
 
     fn naive_search(sketches: &[u16], radius: usize) -> Vec<(usize, usize)> {
         let mut results = vec![];
-        for i in 0..sketches.len() {
-            let x = sketches[i];
-            for j in i + 1..sketches.len() {
-                let y = sketches[j];
+        for (i, &x) in sketches.iter().enumerate() {
+            for (j, &y) in sketches.iter().enumerate().skip(i + 1) {
                 if x.hamdist(y) <= radius {
                     results.push((i, j));
                 }
@@ -303,6 +334,47 @@ mod tests {
         assert_eq!(results, expected);
     }
 
+    #[test]
+    fn test_similar_pairs_for_large_radius() {
+        let sketches: Vec<u128> = example_sketches()
+            .into_iter()
+            .map(|x| u128::from(x) * 0x0001_0001_0001_0001_0001_0001_0001_0001)
+            .collect();
+        for radius in [60, 64, 70] {
+            let mut expected = vec![];
+            for (i, &x) in sketches.iter().enumerate() {
+                for (j, &y) in sketches.iter().enumerate().skip(i + 1) {
+                    if x.hamdist(y) <= radius {
+                        expected.push((i, j));
+                    }
+                }
+            }
+            let mut results = HashSet::new();
+            MultiSort::new().similar_pairs(&sketches, radius, &mut results);
+            let mut results: Vec<_> = results.into_iter().collect();
+            results.sort_unstable();
+            assert_eq!(results, expected);
+        }
+    }
+
+    #[test]
+    fn test_try_for_each_similar_pair() {
+        let sketches = example_sketches();
+        let mut results = vec![];
+        let flow = MultiSort::new().try_for_each_similar_pair(&sketches, 8, |i, j| {
+            results.push((i, j));
+            ControlFlow::<()>::Continue(())
+        });
+        assert_eq!(flow, ControlFlow::Continue(()));
+        // Each pair is found only once.
+        results.sort_unstable();
+        assert_eq!(results, naive_search(&sketches, 8));
+
+        let flow = MultiSort::new()
+            .try_for_each_similar_pair(&sketches, 8, |i, j| ControlFlow::Break((i, j)));
+        assert!(matches!(flow, ControlFlow::Break(pair) if results.contains(&pair)));
+    }
+
     #[test]
     fn test_similar_pairs_for_all() {
         for radius in 0..=16 {
@@ -0,0 +1,161 @@
+//! Progress reports of the stages of similarity search, delivered to a callback hook.
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Stage of processing whose progress is reported to a [`ProgressHook`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Documents read from the input.
+    DocumentsRead,
+    /// Sketches built from the documents.
+    SketchesBuilt,
+    /// Chunks of sketches joined into candidate pairs.
+    ChunksJoined,
+    /// Candidate pairs verified with their Hamming distances.
+    PairsVerified,
+}
+
+impl Stage {
+    /// Gets the unit of processed items.
+    pub const fn unit(self) -> &'static str {
+        match self {
+            Self::DocumentsRead => "documents",
+            Self::SketchesBuilt => "sketches",
+            Self::ChunksJoined => "chunks",
+            Self::PairsVerified => "candidates",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::DocumentsRead => write!(f, "documents read"),
+            Self::SketchesBuilt => write!(f, "sketches built"),
+            Self::ChunksJoined => write!(f, "chunks joined"),
+            Self::PairsVerified => write!(f, "pairs verified"),
+        }
+    }
+}
+
+/// Callback hook receiving the progress of each stage as the number of processed items
+/// and the total number if known.
+///
+/// The library never prints the progress itself, and applications decide how to show it,
+/// e.g., with [`Self::tracing()`] or progress bars.
+/// Each stage is started with a report of zero items and finished with that of the total number.
+/// The hook may be called from multiple threads, and the numbers of a stage may arrive out of order.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+///
+/// use all_pairs_hamming::progress::{ProgressHook, Stage};
+/// use all_pairs_hamming::ChunkedJoiner;
+///
+/// let joined = Arc::new(AtomicUsize::new(0));
+/// let hook = {
+///     let joined = joined.clone();
+///     ProgressHook::new(move |stage, done, _| {
+///         if stage == Stage::ChunksJoined {
+///             joined.store(done, Ordering::Relaxed);
+///         }
+///     })
+/// };
+/// let mut joiner = ChunkedJoiner::<u8>::new(2).progress_hook(Some(hook));
+/// joiner.add([0b1111, 0b1001]).unwrap();
+/// joiner.add([0b1101, 0b1001]).unwrap();
+/// joiner.similar_pairs(0.15);
+/// assert_eq!(joined.load(Ordering::Relaxed), 2);
+/// ```
+#[derive(Clone)]
+pub struct ProgressHook(Arc<dyn Fn(Stage, usize, Option<usize>) + Send + Sync>);
+
+impl ProgressHook {
+    /// Creates a hook calling `f` with the stage, the number of processed items,
+    /// and the total number if known.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(Stage, usize, Option<usize>) + Send + Sync + 'static,
+    {
+        Self(Arc::new(f))
+    }
+
+    /// Creates a hook emitting the progress as `tracing` events
+    /// with the processing rate and the estimated remaining time,
+    /// which are measured from the start of each stage.
+    ///
+    /// The clock is read only in reports, since it is unavailable on wasm32-unknown-unknown.
+    pub fn tracing() -> Self {
+        let starts = Mutex::new(HashMap::new());
+        Self::new(move |stage, done, total| {
+            let mut starts = starts.lock().unwrap();
+            if done == 0 {
+                starts.insert(stage, Instant::now());
+                return;
+            }
+            let elapsed = starts
+                .entry(stage)
+                .or_insert_with(Instant::now)
+                .elapsed()
+                .as_secs_f64();
+            tracing::info!("[{stage}] {}", message(stage, done, total, elapsed));
+        })
+    }
+
+    /// Reports that `done` items of `total` have been processed in the stage.
+    pub fn report(&self, stage: Stage, done: usize, total: Option<usize>) {
+        (self.0)(stage, done, total);
+    }
+}
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProgressHook").finish_non_exhaustive()
+    }
+}
+
+fn message(stage: Stage, done: usize, total: Option<usize>, elapsed: f64) -> String {
+    let unit = stage.unit();
+    let rate = if elapsed > 0. {
+        done as f64 / elapsed
+    } else {
+        0.
+    };
+    match total {
+        Some(total) => {
+            let eta = if rate > 0. {
+                format!("{:.1} sec", (total - done.min(total)) as f64 / rate)
+            } else {
+                "unknown".to_string()
+            };
+            format!("Processed {done}/{total} {unit} ({rate:.1} {unit}/sec, ETA {eta})...")
+        }
+        None => format!("Processed {done} {unit} ({rate:.1} {unit}/sec)..."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message() {
+        assert_eq!(
+            message(Stage::ChunksJoined, 4, Some(10), 2.),
+            "Processed 4/10 chunks (2.0 chunks/sec, ETA 3.0 sec)..."
+        );
+        assert_eq!(
+            message(Stage::ChunksJoined, 0, Some(10), 0.),
+            "Processed 0/10 chunks (0.0 chunks/sec, ETA unknown)..."
+        );
+        assert_eq!(
+            message(Stage::DocumentsRead, 6, None, 2.),
+            "Processed 6 documents (3.0 documents/sec)..."
+        );
+    }
+}
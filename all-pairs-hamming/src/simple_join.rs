@@ -1,5 +1,6 @@
 //! A naive implementation of similarity self-join on binary sketches in the Hamming space.
 use crate::errors::{AllPairsHammingError, Result};
+use crate::progress::{ProgressHook, Stage};
 use crate::sketch::Sketch;
 
 /// A naive implementation of similarity self-join on binary sketches in the Hamming space,
@@ -8,7 +9,7 @@ use crate::sketch::Sketch;
 pub struct SimpleJoiner<S> {
     sketches: Vec<Vec<S>>,
     num_chunks: usize,
-    shows_progress: bool,
+    progress: Option<ProgressHook>,
 }
 
 impl<S> SimpleJoiner<S>
@@ -21,13 +22,22 @@ where
         Self {
             sketches: vec![],
             num_chunks,
-            shows_progress: false,
+            progress: None,
         }
     }
 
-    /// Prints the progress with stderr?
-    pub const fn shows_progress(mut self, yes: bool) -> Self {
-        self.shows_progress = yes;
+    /// Emits the progress as `tracing` events with [`ProgressHook::tracing()`]?
+    /// This replaces the hook set with [`Self::progress_hook()`].
+    pub fn shows_progress(mut self, yes: bool) -> Self {
+        self.progress = yes.then(ProgressHook::tracing);
+        self
+    }
+
+    /// Sets a hook receiving the progress of verifying pairs,
+    /// reported in the number of left-side sketches compared with all the others.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn progress_hook(mut self, hook: Option<ProgressHook>) -> Self {
+        self.progress = hook;
         self
     }
 
@@ -57,20 +67,23 @@ where
     /// returning triplets of the left-side id, the right-side id, and thier distance.
     pub fn similar_pairs(&self, radius: f64) -> Vec<(usize, usize, f64)> {
         let dimension = S::dim() * self.num_chunks();
-        if self.shows_progress {
-            eprintln!("[SimpleJoiner::similar_pairs] #dimensions={dimension}");
+        if self.progress.is_some() {
+            tracing::debug!("[SimpleJoiner::similar_pairs] #dimensions={dimension}");
         }
+        let _span = tracing::info_span!("verify").entered();
 
         let bound = (dimension as f64 * radius) as usize;
         let mut matched = vec![];
 
-        for i in 0..self.sketches.len() {
-            if self.shows_progress && (i + 1) % 10000 == 0 {
-                eprintln!(
-                    "[SimpleJoiner::similar_pairs] Processed {}/{}...",
-                    i + 1,
-                    self.sketches.len()
-                );
+        let num_sketches = self.sketches.len();
+        if let Some(progress) = &self.progress {
+            progress.report(Stage::PairsVerified, 0, Some(num_sketches));
+        }
+        for i in 0..num_sketches {
+            if let Some(progress) = &self.progress {
+                if (i + 1) % 10000 == 0 {
+                    progress.report(Stage::PairsVerified, i + 1, Some(num_sketches));
+                }
             }
             for j in i + 1..self.sketches.len() {
                 if let Some(dist) = self.hamming_distance(i, j, bound) {
@@ -81,9 +94,9 @@ where
                 }
             }
         }
-        if self.shows_progress {
-            eprintln!("[SimpleJoiner::similar_pairs] Done");
-            eprintln!("[SimpleJoiner::similar_pairs] #matched={}", matched.len());
+        if let Some(progress) = &self.progress {
+            progress.report(Stage::PairsVerified, num_sketches, Some(num_sketches));
+            tracing::info!("[SimpleJoiner::similar_pairs] #matched={}", matched.len());
         }
         matched
     }
@@ -138,10 +151,8 @@ mod tests {
 
     fn naive_search(sketches: &[u16], radius: f64) -> Vec<(usize, usize, f64)> {
         let mut results = vec![];
-        for i in 0..sketches.len() {
-            let x = sketches[i];
-            for j in i + 1..sketches.len() {
-                let y = sketches[j];
+        for (i, &x) in sketches.iter().enumerate() {
+            for (j, &y) in sketches.iter().enumerate().skip(i + 1) {
                 let dist = x.hamdist(y);
                 let dist = dist as f64 / 16.;
                 if dist <= radius {
@@ -94,6 +94,26 @@ impl Sketch for u64 {
     }
 }
 
+impl Sketch for u128 {
+    #[inline(always)]
+    fn dim() -> usize {
+        128
+    }
+    #[inline(always)]
+    fn hamdist(self, rhs: Self) -> usize {
+        (self ^ rhs).count_ones() as usize
+    }
+    #[inline(always)]
+    fn mask(rng: Range<usize>) -> Self {
+        debug_assert!(rng.end <= Self::dim());
+        if rng.len() == Self::dim() {
+            Self::MAX
+        } else {
+            ((1 << rng.len()) - 1) << rng.start
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,4 +125,11 @@ mod tests {
         assert_eq!(u8::mask(4..8), 0b11110000);
         assert_eq!(u8::mask(0..8), 0b11111111);
     }
+
+    #[test]
+    fn test_mask_u128() {
+        assert_eq!(u128::mask(0..4), 0b1111);
+        assert_eq!(u128::mask(64..128), u128::from(u64::MAX) << 64);
+        assert_eq!(u128::mask(0..128), u128::MAX);
+    }
 }
@@ -0,0 +1,329 @@
+// Ids and sources of documents are not needed to advise parameters.
+#[allow(dead_code)]
+mod input;
+mod logging;
+mod memory;
+mod metric;
+mod truth;
+mod weight;
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+use all_pairs_hamming::ChunkedJoiner;
+use clap::Parser;
+use hashbrown::HashSet;
+
+use input::{DocumentReader, InputFormat};
+use logging::init_logger;
+use memory::{fit_num_chunks, human, ByteSize};
+use metric::Metric;
+use truth::{cosine_pairs, jaccard_pairs, sample_documents};
+use weight::{IdfWeights, TfWeights};
+
+use find_simdoc::feature::{FeatureConfig, FeatureExtractor};
+use find_simdoc::tfidf::TermWeighter;
+use find_simdoc::{CosineSearcher, JaccardSearcher};
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "find-simdoc-advise",
+    about = "A program to recommend the number of chunks achieving a target recall on sampled documents."
+)]
+struct Args {
+    /// File paths to document files to be searched, or directories of them.
+    /// Multiple files are concatenated in the given order.
+    /// Empty lines are rejected with an error.
+    #[clap(short = 'i', long, required = true, multiple_values = true)]
+    document_path: Vec<PathBuf>,
+
+    /// Format of the document file.
+    /// "text" is a document per line.
+    /// "jsonl" is a JSON object per line, whose text is given by the text field.
+    #[clap(long, default_value = "text")]
+    input_format: InputFormat,
+
+    /// Field of texts in JSON objects.
+    #[clap(long, default_value = "text")]
+    text_field: String,
+
+    /// Distance metric.
+    /// "jaccard" is for the jaccard program.
+    /// "cosine" is for the cosine program.
+    #[clap(short = 'M', long, default_value = "jaccard", possible_values = ["jaccard", "cosine"])]
+    metric: Metric,
+
+    /// Search radius in the range of [0,1].
+    #[clap(short = 'r', long)]
+    radius: f64,
+
+    /// Recall to be achieved in the range of (0,1], which is measured against
+    /// the exact search on the sampled documents.
+    #[clap(long, default_value = "0.95")]
+    target_recall: f64,
+
+    /// Number of documents sampled uniformly at random from the input.
+    /// The exact search on them takes a quadratic time.
+    #[clap(long, default_value = "2000")]
+    sample_size: usize,
+
+    /// Maximum number of chunks to be swept.
+    #[clap(long, default_value = "32")]
+    max_chunks: usize,
+
+    /// Memory budget for sketches and their join (e.g., 512M or 16G).
+    /// If given, the recommended #chunks is reduced to fit the budget.
+    #[clap(long)]
+    max_memory: Option<ByteSize>,
+
+    /// Delimiter for recognizing words as tokens in feature extraction.
+    /// If None, characters are used for tokens.
+    #[clap(short = 'd', long)]
+    delimiter: Option<char>,
+
+    /// Regular expression for recognizing words as tokens in feature extraction,
+    /// used instead of the delimiter character (e.g., "[\s\p{P}]+").
+    #[clap(long, conflicts_with = "delimiter")]
+    delimiter_regex: Option<String>,
+
+    /// Window size for w-shingling in feature extraction (must be more than 0).
+    #[clap(short = 'w', long, default_value = "1")]
+    window_size: usize,
+
+    /// Maximum window size for w-shingling, with which features combine shingles
+    /// of all the window sizes from window_size to max_window_size.
+    #[clap(long)]
+    max_window_size: Option<usize>,
+
+    /// Converts texts into lowercase before feature extraction.
+    #[clap(long)]
+    lowercase: bool,
+
+    /// Applies the NFKC normalization to texts before feature extraction,
+    /// which unifies full-width and half-width forms of characters, for example.
+    #[clap(long)]
+    nfkc: bool,
+
+    /// Removes punctuation characters from texts before feature extraction.
+    #[clap(long)]
+    strip_punct: bool,
+
+    /// Weighting variant of term frequency for the Cosine distance.
+    /// "binary" is the boolean value.
+    /// "standard" is the raw count.
+    /// "sublinear" is the logarithmically scaled frequency.
+    #[clap(short = 'T', long, default_value = "standard")]
+    tf: TfWeights,
+
+    /// Weighting variant of inverse document frequency for the Cosine distance.
+    /// "unary" is always 1.
+    /// "standard" is the standard idf.
+    /// "smooth" is the smoothed idf.
+    #[clap(short = 'I', long, default_value = "smooth")]
+    idf: IdfWeights,
+
+    /// Seed value for random values.
+    #[clap(short = 's', long)]
+    seed: Option<u64>,
+
+    /// Number of threads used in parallel processing.
+    /// If not given or 0, the number of threads is determined automatically.
+    #[clap(short = 't', long)]
+    threads: Option<usize>,
+
+    /// Increases the verbosity of logs to stderr (-v for debug and -vv for trace).
+    #[clap(short = 'v', long, parse(from_occurrences), conflicts_with = "quiet")]
+    verbose: u64,
+
+    /// Suppresses the progress and messages to stderr except warnings.
+    #[clap(short = 'q', long)]
+    quiet: bool,
+}
+
+/// Results of a search with sketches on sampled documents.
+struct Trial {
+    results: Vec<(usize, usize, f64)>,
+    build_sec: f64,
+    join_sec: f64,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    init_logger(args.verbose, args.quiet);
+
+    if args.target_recall <= 0. || 1. < args.target_recall {
+        return Err("target_recall must be in (0,1].".into());
+    }
+    if args.sample_size < 2 {
+        return Err("sample_size must be at least 2.".into());
+    }
+    if args.max_chunks == 0 {
+        return Err("max_chunks must not be 0.".into());
+    }
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+
+    let reader = DocumentReader::new(&args.document_path, args.input_format, &args.text_field)?;
+    let num_docs = reader.num_documents()?;
+    let window_size = args.window_size;
+    let max_window_size = args.max_window_size.unwrap_or(window_size);
+    let seed = args.seed.unwrap_or_else(rand::random::<u64>);
+
+    let (_, texts) = sample_documents(&reader, Some(args.sample_size), seed)?;
+
+    let config = FeatureConfig::new(window_size, args.delimiter, seed)?
+        .lowercase(args.lowercase)
+        .normalize_nfkc(args.nfkc)
+        .strip_punctuation(args.strip_punct)
+        .delimiter_regex(args.delimiter_regex.as_deref())?
+        .window_range(window_size..=max_window_size)?;
+    let extractor = FeatureExtractor::new(&config);
+
+    let tf = args
+        .tf
+        .build()
+        .map(|tf| Arc::new(tf) as Arc<dyn TermWeighter>);
+    // The idf is built from the sampled documents, approximating that of all the documents.
+    let idf = args
+        .idf
+        .build(texts.iter(), &config)?
+        .map(|idf| Arc::new(idf) as Arc<dyn TermWeighter>);
+
+    tracing::info!("Finding all similar pairs with exact distances...");
+    let start = Instant::now();
+    let truth: HashSet<_> = match args.metric {
+        Metric::Jaccard => jaccard_pairs(&extractor, &texts, args.radius),
+        Metric::Cosine => cosine_pairs(
+            &extractor,
+            &texts,
+            tf.as_deref(),
+            idf.as_deref(),
+            args.radius,
+        ),
+        Metric::WeightedJaccard | Metric::Containment => unreachable!(),
+    }
+    .into_iter()
+    .map(|(i, j, _)| (i, j))
+    .collect();
+    tracing::info!(
+        "Found {} pairs in {} sec",
+        truth.len(),
+        start.elapsed().as_secs_f64()
+    );
+    if truth.is_empty() {
+        return Err(
+            "No similar pairs are found in the sampled documents. Increase sample_size or radius."
+                .into(),
+        );
+    }
+
+    let run = |texts: &[String], num_chunks: usize| -> Result<Trial, Box<dyn Error>> {
+        let documents = texts.iter();
+        let (results, build_sec, join_sec) = match args.metric {
+            Metric::Jaccard => {
+                let start = Instant::now();
+                let searcher = JaccardSearcher::new(window_size, args.delimiter, Some(seed))?
+                    .lowercase(args.lowercase)
+                    .normalize_nfkc(args.nfkc)
+                    .strip_punctuation(args.strip_punct)
+                    .delimiter_regex(args.delimiter_regex.as_deref())?
+                    .window_range(window_size..=max_window_size)?
+                    .build_sketches_in_parallel(documents, num_chunks)?;
+                let build_sec = start.elapsed().as_secs_f64();
+                let start = Instant::now();
+                let results = searcher.search_similar_pairs(args.radius);
+                (results, build_sec, start.elapsed().as_secs_f64())
+            }
+            Metric::Cosine => {
+                let start = Instant::now();
+                let searcher = CosineSearcher::new(window_size, args.delimiter, Some(seed))?
+                    .lowercase(args.lowercase)
+                    .normalize_nfkc(args.nfkc)
+                    .strip_punctuation(args.strip_punct)
+                    .delimiter_regex(args.delimiter_regex.as_deref())?
+                    .window_range(window_size..=max_window_size)?
+                    .tf_weighter(tf.clone())
+                    .idf_weighter(idf.clone())
+                    .build_sketches_in_parallel(documents, num_chunks)?;
+                let build_sec = start.elapsed().as_secs_f64();
+                let start = Instant::now();
+                let results = searcher.search_similar_pairs(args.radius);
+                (results, build_sec, start.elapsed().as_secs_f64())
+            }
+            Metric::WeightedJaccard | Metric::Containment => unreachable!(),
+        };
+        Ok(Trial {
+            results,
+            build_sec,
+            join_sec,
+        })
+    };
+
+    tracing::info!("Sweeping #chunks...");
+    let mut recommended = None;
+    for num_chunks in 1..=args.max_chunks {
+        let (recall, precision) = accuracy(&run(&texts, num_chunks)?.results, &truth);
+        tracing::info!("#chunks={num_chunks}: recall={recall:.4}, precision={precision:.4}");
+        if args.target_recall <= recall {
+            recommended = Some(num_chunks);
+            break;
+        }
+    }
+    let num_chunks = recommended.unwrap_or_else(|| {
+        tracing::warn!(
+            "The target recall is not achieved within {} chunks, so the maximum is recommended.",
+            args.max_chunks
+        );
+        args.max_chunks
+    });
+    let num_chunks = match args.max_memory {
+        Some(ByteSize(max_memory)) => fit_num_chunks::<u64>(num_docs, num_chunks, max_memory)?,
+        None => num_chunks,
+    };
+
+    let full = run(&texts, num_chunks)?;
+    let (recall, _) = accuracy(&full.results, &truth);
+
+    // The join time is extrapolated with the growth rate observed between
+    // the half and full samples, which is between linear and quadratic.
+    let expected_sec = {
+        let half = run(&texts[..texts.len() / 2], num_chunks)?;
+        let ratio = full.join_sec / half.join_sec;
+        let exponent = if ratio.is_finite() && 0. < ratio {
+            ratio.log2().clamp(1., 2.)
+        } else {
+            2.
+        };
+        let scale = num_docs as f64 / texts.len() as f64;
+        full.build_sec * scale + full.join_sec * scale.powf(exponent)
+    };
+    let expected_memory = ChunkedJoiner::<u64>::estimate_memory_in_bytes(num_docs, num_chunks);
+
+    println!("num_chunks: {num_chunks}");
+    println!("dimensions: {}", num_chunks * 64);
+    println!("recall: {recall:.4} (on {} sampled documents)", texts.len());
+    println!("expected memory: {}", human(expected_memory));
+    println!("expected runtime: {expected_sec:.1} sec");
+
+    Ok(())
+}
+
+/// Computes the recall and precision of results against the true pairs.
+fn accuracy(results: &[(usize, usize, f64)], truth: &HashSet<(usize, usize)>) -> (f64, f64) {
+    let true_positive = results
+        .iter()
+        .filter(|&&(i, j, _)| truth.contains(&(i.min(j), i.max(j))))
+        .count() as f64;
+    let recall = true_positive / truth.len() as f64;
+    let precision = if results.is_empty() {
+        1.
+    } else {
+        true_positive / results.len() as f64
+    };
+    (recall, precision)
+}
@@ -0,0 +1,360 @@
+mod element_hasher;
+mod filter;
+mod input;
+mod logging;
+mod memory;
+mod progress;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Instant;
+
+use clap::Parser;
+
+use element_hasher::ElementHasherArg;
+use filter::min_length_filter;
+use find_simdoc::feature::ShortDocumentPolicy;
+use find_simdoc::JaccardSearcher;
+use input::{DocumentReader, ErrorSlot, InputFormat};
+use logging::init_logger;
+use memory::{fit_num_chunks, ByteSize};
+use progress::progress_hook;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeepPolicy {
+    First,
+    Longest,
+    Shortest,
+}
+
+impl FromStr for KeepPolicy {
+    type Err = &'static str;
+    fn from_str(w: &str) -> Result<Self, Self::Err> {
+        match w {
+            "first" => Ok(Self::First),
+            "longest" => Ok(Self::Longest),
+            "shortest" => Ok(Self::Shortest),
+            _ => Err("Could not parse a keep policy"),
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "find-simdoc-dedup",
+    about = "A program to remove near-duplicate documents in the Jaccard space."
+)]
+struct Args {
+    /// File paths to document files to be deduplicated, or directories of them.
+    /// Multiple files are concatenated in the given order.
+    /// Empty lines are skipped with --min-chars or --min-tokens, and rejected otherwise.
+    #[clap(short = 'i', long, required = true, multiple_values = true)]
+    document_path: Vec<PathBuf>,
+
+    /// File path to write the documents retained, in the same format as the input.
+    #[clap(short = 'o', long)]
+    output_path: PathBuf,
+
+    /// File path to write the mapping of removed documents to kept ones,
+    /// in CSV of zero-origin line numbers (or ids if the id field is given).
+    #[clap(short = 'm', long)]
+    mapping_path: Option<PathBuf>,
+
+    /// Search radius in the range of [0,1].
+    #[clap(short = 'r', long)]
+    radius: f64,
+
+    /// Policy to decide the document kept among near-duplicates.
+    /// "first" keeps the earliest document in the input.
+    /// "longest" keeps the longest document.
+    /// "shortest" keeps the shortest document.
+    #[clap(short = 'k', long, default_value = "first")]
+    keep: KeepPolicy,
+
+    /// Format of the document file.
+    /// "text" is a document per line.
+    /// "jsonl" is a JSON object per line, whose text is given by the text field.
+    #[clap(long, default_value = "text")]
+    input_format: InputFormat,
+
+    /// Field of texts in JSON objects.
+    #[clap(long, default_value = "text")]
+    text_field: String,
+
+    /// Field of document ids in JSON objects, which are written in the mapping.
+    #[clap(long)]
+    id_field: Option<String>,
+
+    /// Delimiter for recognizing words as tokens in feature extraction.
+    /// If None, characters are used for tokens.
+    #[clap(short = 'd', long)]
+    delimiter: Option<char>,
+
+    /// Window size for w-shingling in feature extraction (must be more than 0).
+    #[clap(short = 'w', long, default_value = "1")]
+    window_size: usize,
+
+    /// Converts texts into lowercase before feature extraction.
+    #[clap(long)]
+    lowercase: bool,
+
+    /// Applies the NFKC normalization to texts before feature extraction,
+    /// which unifies full-width and half-width forms of characters, for example.
+    #[clap(long)]
+    nfkc: bool,
+
+    /// Removes punctuation characters from texts before feature extraction.
+    #[clap(long)]
+    strip_punct: bool,
+
+    /// Skips documents with fewer characters than this value, which are not searched.
+    /// The line numbers of the other documents are kept as in the input.
+    #[clap(long, default_value = "0")]
+    min_chars: usize,
+
+    /// Skips documents with fewer whitespace-separated tokens than this value, which are not searched.
+    /// The line numbers of the other documents are kept as in the input.
+    #[clap(long, default_value = "0")]
+    min_tokens: usize,
+
+    /// Number of chunks in sketches, indicating that the number of dimensions in the Hamming space
+    /// will be 64*#chunks. The larger this value, the more accurate the approximation,
+    /// but the more time and memory it takes to search.
+    #[clap(short = 'c', long, default_value = "8")]
+    num_chunks: usize,
+
+    /// Memory budget for sketches and their join (e.g., 512M or 16G).
+    /// If given, #chunks is reduced to fit the budget, and
+    /// the program aborts if even a single chunk cannot fit.
+    #[clap(long)]
+    max_memory: Option<ByteSize>,
+
+    /// Hash function of elements in sketching.
+    /// "splitmix64" is the default element mixer.
+    /// "wymix" is a faster wyhash-style mixer generating different sketches.
+    #[clap(long, default_value = "splitmix64")]
+    element_hasher: ElementHasherArg,
+
+    /// Seed value for random values.
+    #[clap(short = 's', long)]
+    seed: Option<u64>,
+
+    /// Number of threads used in parallel processing.
+    /// If not given or 0, the number of threads is determined automatically.
+    #[clap(short = 't', long)]
+    threads: Option<usize>,
+
+    /// Directory to save the progress of the join, from which an interrupted run resumes.
+    /// The same documents and options, including the seed, must be given to resume.
+    #[clap(long, requires = "seed")]
+    checkpoint_dir: Option<PathBuf>,
+
+    /// Increases the verbosity of logs to stderr (-v for debug and -vv for trace).
+    #[clap(short = 'v', long, parse(from_occurrences), conflicts_with = "quiet")]
+    verbose: u64,
+
+    /// Suppresses the progress and messages to stderr except warnings.
+    #[clap(short = 'q', long)]
+    quiet: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let quiet = args.quiet;
+    init_logger(args.verbose, quiet);
+
+    let reader = DocumentReader::new(&args.document_path, args.input_format, &args.text_field)?;
+    let ids = match &args.id_field {
+        Some(id_field) => Some(reader.read_field(id_field)?),
+        None => None,
+    };
+
+    let num_chunks = match args.max_memory {
+        Some(ByteSize(max_memory)) => {
+            let num_docs = reader.num_documents()?;
+            fit_num_chunks::<u64>(num_docs, args.num_chunks, max_memory)?
+        }
+        None => args.num_chunks,
+    };
+
+    let mut searcher = JaccardSearcher::new(args.window_size, args.delimiter, args.seed)?
+        .lowercase(args.lowercase)
+        .normalize_nfkc(args.nfkc)
+        .strip_punctuation(args.strip_punct)
+        .element_hasher(args.element_hasher.build())
+        .progress_hook(progress_hook(quiet));
+    if let Some(threads) = args.threads {
+        searcher = searcher.num_threads(threads)?;
+    }
+    if let Some(filter) = min_length_filter(args.min_chars, args.min_tokens) {
+        searcher = searcher
+            .preprocessor(Some(filter))
+            .short_document_policy(ShortDocumentPolicy::Skip);
+    }
+
+    {
+        tracing::info!("Converting documents into sketches...");
+        let start = Instant::now();
+        let errors = ErrorSlot::default();
+        let documents = errors.until_error(reader.texts_iter());
+        searcher = searcher.build_sketches_in_parallel(documents, num_chunks)?;
+        errors.check()?;
+        tracing::info!(
+            "Produced {} sketches in {} sec",
+            searcher.len(),
+            start.elapsed().as_secs_f64()
+        );
+        let num_skipped = searcher.skipped_documents().len();
+        if num_skipped != 0 {
+            tracing::info!("Skipped {num_skipped} short documents");
+        }
+    }
+
+    tracing::info!("Finding all similar pairs in sketches...");
+    let start = Instant::now();
+    let results = match &args.checkpoint_dir {
+        Some(checkpoint_dir) => {
+            std::fs::create_dir_all(checkpoint_dir)?;
+            searcher.search_similar_pairs_with_checkpoint(
+                args.radius,
+                checkpoint_dir.join("join.ckpt"),
+            )?
+        }
+        None => searcher.search_similar_pairs(args.radius),
+    };
+    tracing::info!("Done in {} sec", start.elapsed().as_secs_f64());
+
+    let lengths = reader
+        .texts_iter()
+        .map(|text| text.map(|text| text.chars().count()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let kept_by = decide_kept(&lengths, &results, args.keep);
+
+    let mut wtr = BufWriter::new(File::create(&args.output_path)?);
+    let mut num_kept = 0;
+    for (line, kept) in reader.lines_iter().zip(&kept_by) {
+        let line = line?;
+        if kept.is_none() {
+            writeln!(wtr, "{line}")?;
+            num_kept += 1;
+        }
+    }
+    wtr.flush()?;
+    tracing::info!(
+        "Kept {num_kept} documents and removed {}",
+        kept_by.len() - num_kept
+    );
+
+    if let Some(mapping_path) = args.mapping_path {
+        let sources = reader.sources()?;
+        let mut wtr = csv::Writer::from_path(mapping_path)?;
+        let mut header = vec!["removed", "kept"];
+        if sources.is_some() {
+            header.extend(["removed_source", "removed_line", "kept_source", "kept_line"]);
+        }
+        wtr.write_record(&header)?;
+        for (i, kept) in kept_by.iter().enumerate() {
+            if let Some(j) = *kept {
+                let mut record = match &ids {
+                    Some(ids) => vec![ids[i].clone(), ids[j].clone()],
+                    None => vec![i.to_string(), j.to_string()],
+                };
+                if let Some(sources) = &sources {
+                    for k in [i, j] {
+                        let (source, line) = sources.locate(k);
+                        record.extend([source.to_string(), line.to_string()]);
+                    }
+                }
+                wtr.write_record(&record)?;
+            }
+        }
+        wtr.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Decides the documents to be removed, returning the id of the kept document similar to
+/// each removed one, or `None` for documents kept.
+///
+/// Documents are visited in the order of priority, and a document is removed if it is
+/// similar to a document kept already. Thus, a kept document is never similar to another kept one,
+/// and removed documents are not chained through other removed ones.
+fn decide_kept(
+    lengths: &[usize],
+    results: &[(usize, usize, f64)],
+    policy: KeepPolicy,
+) -> Vec<Option<usize>> {
+    let mut neighbors = vec![vec![]; lengths.len()];
+    for &(i, j, _) in results {
+        neighbors[i].push(j);
+        neighbors[j].push(i);
+    }
+    let mut order: Vec<_> = (0..lengths.len()).collect();
+    match policy {
+        KeepPolicy::First => {}
+        KeepPolicy::Longest => order.sort_by_key(|&i| std::cmp::Reverse(lengths[i])),
+        KeepPolicy::Shortest => order.sort_by_key(|&i| lengths[i]),
+    }
+    let mut kept = vec![false; lengths.len()];
+    let mut kept_by = vec![None; lengths.len()];
+    for i in order {
+        match neighbors[i].iter().find(|&&j| kept[j]) {
+            Some(&j) => kept_by[i] = Some(j),
+            None => kept[i] = true,
+        }
+    }
+    kept_by
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decide_kept() {
+        let lengths = [3, 5, 4, 1];
+        let results = [(0, 1, 0.1), (0, 2, 0.2), (2, 3, 0.3)];
+        assert_eq!(
+            decide_kept(&lengths, &results, KeepPolicy::First),
+            vec![None, Some(0), Some(0), None]
+        );
+        assert_eq!(
+            decide_kept(&lengths, &results, KeepPolicy::Longest),
+            vec![Some(1), None, None, Some(2)]
+        );
+        assert_eq!(
+            decide_kept(&lengths, &results, KeepPolicy::Shortest),
+            vec![None, Some(0), Some(0), None]
+        );
+    }
+
+    #[test]
+    fn test_decide_kept_chain() {
+        // A chain 0-1-2-3, where a removed document never keeps another one.
+        let lengths = [1, 1, 1, 1];
+        let results = [(0, 1, 0.1), (1, 2, 0.1), (2, 3, 0.1)];
+        assert_eq!(
+            decide_kept(&lengths, &results, KeepPolicy::First),
+            vec![None, Some(0), None, Some(2)]
+        );
+        // Ties of lengths are broken by the line numbers.
+        assert_eq!(
+            decide_kept(&lengths, &results, KeepPolicy::Longest),
+            vec![None, Some(0), None, Some(2)]
+        );
+    }
+
+    #[test]
+    fn test_decide_kept_no_pairs() {
+        assert_eq!(
+            decide_kept(&[2, 1], &[], KeepPolicy::Longest),
+            vec![None, None]
+        );
+        assert_eq!(decide_kept(&[], &[], KeepPolicy::First), vec![]);
+    }
+}
@@ -1,3 +1,5 @@
+mod pairs;
+
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -5,14 +7,42 @@ use std::path::PathBuf;
 
 use clap::Parser;
 
+use pairs::{clusters, overlaps, read_pairs};
+
+const HIGHLIGHT_BEGIN: &str = "\x1b[1;31m";
+const HIGHLIGHT_END: &str = "\x1b[0m";
+
 #[derive(Parser, Debug)]
 #[clap(name = "find-simdoc-dump", about = "A program to dump similar texts.")]
 struct Args {
     #[clap(short = 'i', long)]
     text_path: PathBuf,
 
+    /// File path to similar pairs in CSV or NDJSON, written by jaccard or cosine.
     #[clap(short = 's', long)]
     simpair_path: PathBuf,
+
+    /// Sorts pairs in the ascending order of distances.
+    #[clap(long)]
+    sort: bool,
+
+    /// Dumps only the first N pairs (after sorting if specified).
+    #[clap(long)]
+    top: Option<usize>,
+
+    /// Groups documents into clusters connected by similar pairs, and dumps each cluster
+    /// instead of pairs.
+    #[clap(long)]
+    cluster: bool,
+
+    /// Highlights the regions of each text overlapping with the other text of the pair
+    /// using ANSI colors.
+    #[clap(long)]
+    color: bool,
+
+    /// Minimum number of characters of overlapping regions to be highlighted.
+    #[clap(long, default_value = "5")]
+    highlight_width: usize,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -26,22 +56,58 @@ fn main() -> Result<(), Box<dyn Error>> {
         .map(|line| line.unwrap())
         .collect();
 
-    for (i, row) in BufReader::new(File::open(simpair_path)?)
-        .lines()
-        .enumerate()
+    let mut pairs = read_pairs(&simpair_path)?;
+    if let Some(&(i, j, _)) = pairs
+        .iter()
+        .find(|&&(i, j, _)| texts.len() <= i || texts.len() <= j)
     {
-        if i == 0 {
-            continue;
+        return Err(format!("The pair ({i},{j}) is out of the range of the texts.").into());
+    }
+    if args.sort {
+        pairs.sort_by(|a, b| a.2.total_cmp(&b.2));
+    }
+    if let Some(top) = args.top {
+        pairs.truncate(top);
+    }
+
+    if args.cluster {
+        for (k, cluster) in clusters(texts.len(), &pairs).iter().enumerate() {
+            println!("[cluster={k},size={}]", cluster.len());
+            for &i in cluster {
+                println!("{i}\t{}", texts[i]);
+            }
         }
-        let row = row?;
-        let cols: Vec<_> = row.split(',').collect();
-        let i = cols[0].parse::<usize>()?;
-        let j = cols[1].parse::<usize>()?;
-        let dist = cols[2].parse::<f64>()?;
+        return Ok(());
+    }
+
+    for (i, j, dist) in pairs {
         println!("[i={i},j={j},dist={dist}]");
-        println!("{}", texts[i]);
-        println!("{}", texts[j]);
+        if args.color {
+            let width = args.highlight_width.max(1);
+            println!("{}", highlight(&texts[i], &texts[j], width));
+            println!("{}", highlight(&texts[j], &texts[i], width));
+        } else {
+            println!("{}", texts[i]);
+            println!("{}", texts[j]);
+        }
     }
 
     Ok(())
 }
+
+/// Highlights the regions of `text` covered by character `width`-grams also appearing in `other`.
+fn highlight(text: &str, other: &str, width: usize) -> String {
+    let chars: Vec<_> = text.chars().collect();
+    let covered = overlaps(text, other, width);
+    let mut highlighted = String::with_capacity(text.len());
+    for (k, &c) in chars.iter().enumerate() {
+        if covered[k] && (k == 0 || !covered[k - 1]) {
+            highlighted.push_str(HIGHLIGHT_BEGIN);
+        }
+        highlighted.push(c);
+        if covered[k] && (k + 1 == chars.len() || !covered[k + 1]) {
+            highlighted.push_str(HIGHLIGHT_END);
+        }
+    }
+    highlighted
+}
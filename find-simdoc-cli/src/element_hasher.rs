@@ -0,0 +1,40 @@
+use std::fmt;
+use std::str::FromStr;
+
+use find_simdoc::lsh::ElementHasher;
+
+/// Hash function of elements in sketching, corresponding to [`ElementHasher`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElementHasherArg {
+    SplitMix64,
+    WyMix,
+}
+
+impl ElementHasherArg {
+    pub const fn build(self) -> ElementHasher {
+        match self {
+            Self::SplitMix64 => ElementHasher::SplitMix64,
+            Self::WyMix => ElementHasher::WyMix,
+        }
+    }
+}
+
+impl FromStr for ElementHasherArg {
+    type Err = &'static str;
+    fn from_str(w: &str) -> Result<Self, Self::Err> {
+        match w {
+            "splitmix64" => Ok(Self::SplitMix64),
+            "wymix" => Ok(Self::WyMix),
+            _ => Err("Could not parse an element hash function"),
+        }
+    }
+}
+
+impl fmt::Display for ElementHasherArg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::SplitMix64 => write!(f, "splitmix64"),
+            Self::WyMix => write!(f, "wymix"),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+mod input;
+mod logging;
+mod metric;
+mod output;
+mod truth;
+mod weight;
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use clap::Parser;
+
+use input::{DocumentReader, ErrorSlot, InputFormat};
+use logging::init_logger;
+use metric::Metric;
+use output::{write_pairs, OutputFormatArg};
+use truth::{cosine_pairs, jaccard_pairs, sample_documents};
+use weight::{IdfWeights, TfWeights};
+
+use find_simdoc::feature::{FeatureConfig, FeatureExtractor};
+use find_simdoc::tfidf::TermWeighter;
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "find-simdoc-exact",
+    about = "A program to find similar documents with exact distances, producing ground truth for the approximate search."
+)]
+struct Args {
+    /// File paths to document files to be searched, or directories of them.
+    /// Multiple files are concatenated in the given order.
+    /// Empty lines are rejected with an error.
+    #[clap(short = 'i', long, required = true, multiple_values = true)]
+    document_path: Vec<PathBuf>,
+
+    /// Format of the document file.
+    /// "text" is a document per line.
+    /// "jsonl" is a JSON object per line, whose text is given by the text field.
+    #[clap(long, default_value = "text")]
+    input_format: InputFormat,
+
+    /// Field of texts in JSON objects.
+    #[clap(long, default_value = "text")]
+    text_field: String,
+
+    /// Field of document ids in JSON objects, which are output along with the pairs.
+    #[clap(long)]
+    id_field: Option<String>,
+
+    /// Format of the output.
+    /// "csv" is a pair per row with the header.
+    /// "ndjson" is a JSON object per pair.
+    #[clap(long, default_value = "csv")]
+    output_format: OutputFormatArg,
+
+    /// Distance metric.
+    /// "jaccard" is the Jaccard distance as in the jaccard program.
+    /// "cosine" is the angular distance as in the cosine program.
+    #[clap(short = 'M', long, default_value = "jaccard", possible_values = ["jaccard", "cosine"])]
+    metric: Metric,
+
+    /// Search radius in the range of [0,1].
+    #[clap(short = 'r', long)]
+    radius: f64,
+
+    /// Number of documents sampled uniformly at random from the input,
+    /// among which all pairs are verified. If not given, all documents are verified.
+    /// The line numbers of the sampled documents are kept as in the input.
+    #[clap(long)]
+    sample_size: Option<usize>,
+
+    /// Delimiter for recognizing words as tokens in feature extraction.
+    /// If None, characters are used for tokens.
+    #[clap(short = 'd', long)]
+    delimiter: Option<char>,
+
+    /// Regular expression for recognizing words as tokens in feature extraction,
+    /// used instead of the delimiter character (e.g., "[\s\p{P}]+").
+    #[clap(long, conflicts_with = "delimiter")]
+    delimiter_regex: Option<String>,
+
+    /// Window size for w-shingling in feature extraction (must be more than 0).
+    #[clap(short = 'w', long, default_value = "1")]
+    window_size: usize,
+
+    /// Maximum window size for w-shingling, with which features combine shingles
+    /// of all the window sizes from window_size to max_window_size.
+    #[clap(long)]
+    max_window_size: Option<usize>,
+
+    /// Converts texts into lowercase before feature extraction.
+    #[clap(long)]
+    lowercase: bool,
+
+    /// Applies the NFKC normalization to texts before feature extraction,
+    /// which unifies full-width and half-width forms of characters, for example.
+    #[clap(long)]
+    nfkc: bool,
+
+    /// Removes punctuation characters from texts before feature extraction.
+    #[clap(long)]
+    strip_punct: bool,
+
+    /// Weighting variant of term frequency for the Cosine distance.
+    /// "binary" is the boolean value.
+    /// "standard" is the raw count.
+    /// "sublinear" is the logarithmically scaled frequency.
+    #[clap(short = 'T', long, default_value = "standard")]
+    tf: TfWeights,
+
+    /// Weighting variant of inverse document frequency for the Cosine distance.
+    /// "unary" is always 1.
+    /// "standard" is the standard idf.
+    /// "smooth" is the smoothed idf.
+    /// The idf is built from all the documents even if they are sampled.
+    #[clap(short = 'I', long, default_value = "smooth")]
+    idf: IdfWeights,
+
+    /// Seed value for random values.
+    #[clap(short = 's', long)]
+    seed: Option<u64>,
+
+    /// Number of threads used in parallel processing.
+    /// If not given or 0, the number of threads is determined automatically.
+    #[clap(short = 't', long)]
+    threads: Option<usize>,
+
+    /// Increases the verbosity of logs to stderr (-v for debug and -vv for trace).
+    #[clap(short = 'v', long, parse(from_occurrences), conflicts_with = "quiet")]
+    verbose: u64,
+
+    /// Suppresses the progress and messages to stderr except warnings.
+    #[clap(short = 'q', long)]
+    quiet: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    init_logger(args.verbose, args.quiet);
+
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+
+    let reader = DocumentReader::new(&args.document_path, args.input_format, &args.text_field)?;
+    let ids = match &args.id_field {
+        Some(id_field) => Some(reader.read_field(id_field)?),
+        None => None,
+    };
+    let window_size = args.window_size;
+    let max_window_size = args.max_window_size.unwrap_or(window_size);
+    let seed = args.seed.unwrap_or_else(rand::random::<u64>);
+
+    // The features are compared with each other without sketching,
+    // so the seed of hashing tokens does not matter.
+    let config = FeatureConfig::new(window_size, args.delimiter, seed)?
+        .lowercase(args.lowercase)
+        .normalize_nfkc(args.nfkc)
+        .strip_punctuation(args.strip_punct)
+        .delimiter_regex(args.delimiter_regex.as_deref())?
+        .window_range(window_size..=max_window_size)?;
+    let extractor = FeatureExtractor::new(&config);
+
+    let (doc_ids, texts) = sample_documents(&reader, args.sample_size, seed)?;
+
+    tracing::info!("Finding all similar pairs with exact distances...");
+    let start = Instant::now();
+    let results = match args.metric {
+        Metric::Jaccard => jaccard_pairs(&extractor, &texts, args.radius),
+        Metric::Cosine => {
+            let tf = args.tf.build();
+            let errors = ErrorSlot::default();
+            let idf = args
+                .idf
+                .build(errors.until_error(reader.texts_iter()), &config)?;
+            errors.check()?;
+            cosine_pairs(
+                &extractor,
+                &texts,
+                tf.as_ref().map(|tf| tf as &dyn TermWeighter),
+                idf.as_ref().map(|idf| idf as &dyn TermWeighter),
+                args.radius,
+            )
+        }
+        Metric::WeightedJaccard | Metric::Containment => unreachable!(),
+    };
+    tracing::info!("Done in {} sec", start.elapsed().as_secs_f64());
+    tracing::info!("Found {} pairs", results.len());
+
+    // Maps the indices of sampled documents back to the line numbers.
+    let results: Vec<_> = results
+        .into_iter()
+        .map(|(i, j, dist)| (doc_ids[i], doc_ids[j], dist))
+        .collect();
+
+    let sources = reader.sources()?;
+    write_pairs(
+        std::io::stdout().lock(),
+        args.output_format,
+        &results,
+        None,
+        (ids.as_deref(), ids.as_deref()),
+        (sources.as_ref(), sources.as_ref()),
+        (None, None),
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use find_simdoc::feature::Preprocessor;
+
+/// Creates a preprocessor emptying texts with fewer than `min_chars` characters or
+/// fewer than `min_tokens` whitespace-separated tokens, or returns `None` if no text is emptied.
+///
+/// The emptied documents are skipped by the searcher with [`ShortDocumentPolicy::Skip`],
+/// keeping the ids of the other documents.
+///
+/// [`ShortDocumentPolicy::Skip`]: find_simdoc::feature::ShortDocumentPolicy::Skip
+pub fn min_length_filter(min_chars: usize, min_tokens: usize) -> Option<Preprocessor> {
+    if min_chars == 0 && min_tokens == 0 {
+        return None;
+    }
+    Some(Arc::new(move |text: &str| {
+        if text.chars().take(min_chars).count() < min_chars
+            || text.split_whitespace().take(min_tokens).count() < min_tokens
+        {
+            Cow::Borrowed("")
+        } else {
+            Cow::Borrowed(text)
+        }
+    }))
+}
@@ -0,0 +1,276 @@
+mod element_hasher;
+mod input;
+mod logging;
+mod memory;
+mod metric;
+mod progress;
+// The TF weighting is built from the settings of the index.
+#[allow(dead_code)]
+mod weight;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use clap::Parser;
+use serde_json::Value;
+
+use element_hasher::ElementHasherArg;
+use find_simdoc::index_settings::IndexSettings;
+use input::{DocumentReader, ErrorSlot, InputFormat};
+use logging::init_logger;
+use memory::{fit_num_chunks, ByteSize};
+use metric::Metric;
+use progress::progress_hook;
+use weight::{IdfWeights, TfWeights};
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "find-simdoc-index",
+    about = "A program to build an index of sketches of documents, which is searched by query."
+)]
+struct Args {
+    /// File paths to document files to be indexed, or directories of them.
+    /// Multiple files are concatenated in the given order.
+    /// Empty lines are rejected with an error.
+    #[clap(short = 'i', long, required = true, multiple_values = true)]
+    document_path: Vec<PathBuf>,
+
+    /// File path to write the index.
+    #[clap(short = 'o', long)]
+    output_path: PathBuf,
+
+    /// Format of the document file.
+    /// "text" is a document per line.
+    /// "jsonl" is a JSON object per line, whose text is given by the text field.
+    #[clap(long, default_value = "text")]
+    input_format: InputFormat,
+
+    /// Field of texts in JSON objects.
+    #[clap(long, default_value = "text")]
+    text_field: String,
+
+    /// Field of document ids in JSON objects, which are stored in the index.
+    #[clap(long)]
+    id_field: Option<String>,
+
+    /// Distance metric of the index.
+    /// "jaccard" is the Jaccard distance.
+    /// "cosine" is the Cosine distance.
+    /// "wjac" is the weighted Jaccard distance of tfidf-weighted features.
+    /// "containment" is one minus the containment |X∩Y|/|X| of a document X in Y,
+    /// where X is the left side (or the query) and Y is the right side of each result.
+    #[clap(short = 'M', long, default_value = "jaccard")]
+    metric: Metric,
+
+    /// Delimiter for recognizing words as tokens in feature extraction.
+    /// If None, characters are used for tokens.
+    #[clap(short = 'd', long)]
+    delimiter: Option<char>,
+
+    /// Regular expression for recognizing words as tokens in feature extraction,
+    /// used instead of the delimiter character (e.g., "[\s\p{P}]+").
+    #[clap(long, conflicts_with = "delimiter")]
+    delimiter_regex: Option<String>,
+
+    /// Window size for w-shingling in feature extraction (must be more than 0).
+    #[clap(short = 'w', long, default_value = "1")]
+    window_size: usize,
+
+    /// Maximum window size for w-shingling, with which features combine shingles
+    /// of all the window sizes from window_size to max_window_size.
+    #[clap(long)]
+    max_window_size: Option<usize>,
+
+    /// Converts texts into lowercase before feature extraction.
+    #[clap(long)]
+    lowercase: bool,
+
+    /// Applies the NFKC normalization to texts before feature extraction,
+    /// which unifies full-width and half-width forms of characters, for example.
+    #[clap(long)]
+    nfkc: bool,
+
+    /// Removes punctuation characters from texts before feature extraction.
+    #[clap(long)]
+    strip_punct: bool,
+
+    /// Number of chunks in sketches, indicating that the number of dimensions in the Hamming space
+    /// will be 64*#chunks. The larger this value, the more accurate the approximation,
+    /// but the more time and memory it takes to search.
+    #[clap(short = 'c', long, default_value = "8")]
+    num_chunks: usize,
+
+    /// Memory budget for sketches and their join (e.g., 512M or 16G).
+    /// If given, #chunks is reduced to fit the budget, and
+    /// the program aborts if even a single chunk cannot fit.
+    #[clap(long)]
+    max_memory: Option<ByteSize>,
+
+    /// Weighting variant of term frequency for the Cosine and weighted Jaccard distances.
+    /// "binary" is the boolean frequency.
+    /// "standard" is the standard term frequency.
+    /// "sublinear" is the logarithmically scaled frequency.
+    #[clap(short = 'T', long, default_value = "standard")]
+    tf: TfWeights,
+
+    /// Weighting variant of inverse document frequency
+    /// for the Cosine and weighted Jaccard distances.
+    /// "unary" is always 1.
+    /// "standard" is the standard inverse document frequency.
+    /// "smooth" is the smoothed inverse document frequency.
+    #[clap(short = 'I', long, default_value = "smooth")]
+    idf: IdfWeights,
+
+    /// Hash function of elements in sketching for the Jaccard and Cosine distances.
+    /// "splitmix64" is the default element mixer.
+    /// "wymix" is a faster wyhash-style mixer generating different sketches.
+    #[clap(long, default_value = "splitmix64")]
+    element_hasher: ElementHasherArg,
+
+    /// Seed value for random values.
+    #[clap(short = 's', long)]
+    seed: Option<u64>,
+
+    /// Number of threads used in parallel processing.
+    /// If not given or 0, the number of threads is determined automatically.
+    #[clap(short = 't', long)]
+    threads: Option<usize>,
+
+    /// Increases the verbosity of logs to stderr (-v for debug and -vv for trace).
+    #[clap(short = 'v', long, parse(from_occurrences), conflicts_with = "quiet")]
+    verbose: u64,
+
+    /// Suppresses the progress and messages to stderr except warnings.
+    #[clap(short = 'q', long)]
+    quiet: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let quiet = args.quiet;
+    init_logger(args.verbose, quiet);
+
+    let reader = DocumentReader::new(&args.document_path, args.input_format, &args.text_field)?;
+    let ids = match &args.id_field {
+        Some(id_field) => Some(reader.read_field(id_field)?),
+        None => None,
+    };
+    let num_chunks = match args.max_memory {
+        Some(ByteSize(max_memory)) => {
+            let num_docs = reader.num_documents()?;
+            fit_num_chunks::<u64>(num_docs, args.num_chunks, max_memory)?
+        }
+        None => args.num_chunks,
+    };
+    // The seed is fixed in advance to be written in the header.
+    let mut settings = IndexSettings::new(
+        args.metric.to_string(),
+        args.window_size,
+        args.seed.unwrap_or_else(rand::random),
+    );
+    settings.max_window_size = args.max_window_size.unwrap_or(args.window_size);
+    settings.delimiter = args.delimiter;
+    settings.delimiter_regex = args.delimiter_regex.clone();
+    settings.lowercase = args.lowercase;
+    settings.nfkc = args.nfkc;
+    settings.strip_punct = args.strip_punct;
+    settings.element_hasher = args.element_hasher.build();
+    settings.tf = args.tf.to_string();
+    settings.idf = args.idf.to_string();
+
+    // The sections are described in the find_simdoc::index_format module.
+    let mut sections = vec![];
+    if let Some(ids) = &ids {
+        sections.push(("ids", serde_json::to_vec(ids)?));
+    }
+    if let Some(sources) = reader.sources()? {
+        sections.push(("sources", serde_json::to_vec(&Value::from(&sources))?));
+    }
+    // The file is created in advance to fail before building sketches.
+    let mut wtr = BufWriter::new(File::create(&args.output_path)?);
+
+    tracing::info!("Converting documents into sketches...");
+    let start = Instant::now();
+    let errors = ErrorSlot::default();
+    let documents = errors.until_error(reader.texts_iter());
+    let mut file = match args.metric {
+        Metric::Jaccard => {
+            let mut searcher = settings
+                .jaccard_searcher()?
+                .progress_hook(progress_hook(quiet));
+            if let Some(threads) = args.threads {
+                searcher = searcher.num_threads(threads)?;
+            }
+            let searcher = searcher.build_sketches_in_parallel(documents, num_chunks)?;
+            searcher.export_sketches()?
+        }
+        Metric::Cosine => {
+            let mut searcher = settings
+                .cosine_searcher()?
+                .progress_hook(progress_hook(quiet));
+            if let Some(threads) = args.threads {
+                searcher = searcher.num_threads(threads)?;
+            }
+            let idf = args
+                .idf
+                .build(errors.until_error(reader.texts_iter()), searcher.config())?;
+            if let Some(idf) = &idf {
+                let mut data = vec![];
+                idf.serialize_into(&mut data)?;
+                sections.push(("idf", data));
+            }
+            let searcher = searcher
+                .idf(idf)
+                .build_sketches_in_parallel(documents, num_chunks)?;
+            searcher.export_sketches()?
+        }
+        Metric::WeightedJaccard => {
+            let mut searcher = settings
+                .weighted_jaccard_searcher()?
+                .progress_hook(progress_hook(quiet));
+            if let Some(threads) = args.threads {
+                searcher = searcher.num_threads(threads)?;
+            }
+            let idf = args
+                .idf
+                .build(errors.until_error(reader.texts_iter()), searcher.config())?;
+            if let Some(idf) = &idf {
+                let mut data = vec![];
+                idf.serialize_into(&mut data)?;
+                sections.push(("idf", data));
+            }
+            let searcher = searcher
+                .idf(idf)
+                .build_sketches_in_parallel(documents, num_chunks)?;
+            searcher.export_sketches()?
+        }
+        Metric::Containment => {
+            let mut searcher = settings
+                .containment_searcher()?
+                .progress_hook(progress_hook(quiet));
+            if let Some(threads) = args.threads {
+                searcher = searcher.num_threads(threads)?;
+            }
+            let searcher = searcher.build_sketches_in_parallel(documents, num_chunks)?;
+            searcher.export_sketches()?
+        }
+    };
+    errors.check()?;
+    file.set_header(settings.header())?;
+    for (name, data) in sections {
+        file.push_section(name, data)?;
+    }
+    file.serialize_into(&mut wtr)?;
+    wtr.flush()?;
+    let num_docs = file.num_sketches();
+    tracing::info!(
+        "Indexed {num_docs} documents in {} sec",
+        start.elapsed().as_secs_f64()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use all_pairs_hamming::progress::ProgressHook;
+use find_simdoc::index_format::IndexFile;
+use find_simdoc::index_settings::IndexSettings;
+use find_simdoc::tfidf::Idf;
+use find_simdoc::{ContainmentSearcher, CosineSearcher, JaccardSearcher, WeightedJaccardSearcher};
+use serde_json::Value;
+
+use crate::input::Sources;
+use crate::metric::Metric;
+
+// An index file is in the format of find_simdoc::index_format, whose header holds the settings
+// in find_simdoc::index_settings. The ids and sources of documents and the IDF model are stored
+// in the sections.
+
+pub enum Searcher {
+    Jaccard(JaccardSearcher),
+    Cosine(CosineSearcher),
+    WeightedJaccard(WeightedJaccardSearcher),
+    Containment(ContainmentSearcher),
+}
+
+impl Searcher {
+    pub fn search_similar_documents(
+        &self,
+        query: &str,
+        radius: f64,
+    ) -> find_simdoc::errors::Result<Vec<(usize, f64)>> {
+        match self {
+            Self::Jaccard(searcher) => searcher.search_similar_documents(query, radius),
+            Self::Cosine(searcher) => searcher.search_similar_documents(query, radius),
+            Self::WeightedJaccard(searcher) => searcher.search_similar_documents(query, radius),
+            Self::Containment(searcher) => searcher.search_similar_documents(query, radius),
+        }
+    }
+}
+
+/// Searcher, ids, and sources of documents restored from an index file.
+pub struct Index {
+    pub searcher: Searcher,
+    pub ids: Option<Vec<String>>,
+    pub sources: Option<Sources>,
+}
+
+/// Loads the index from a file.
+pub fn load_index<P>(
+    path: P,
+    threads: Option<usize>,
+    progress: Option<ProgressHook>,
+) -> Result<Index, Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    let file = IndexFile::deserialize_from(BufReader::new(File::open(path)?))?;
+    let settings = IndexSettings::from_index_file(&file)?;
+    let metric: Metric = settings.metric.parse()?;
+    let ids = match file.section("ids") {
+        Some(ids) => Some(serde_json::from_slice(ids).map_err(|_| "Invalid ids of documents.")?),
+        None => None,
+    };
+    let sources = match file.section("sources") {
+        Some(sources) => {
+            let sources: Value =
+                serde_json::from_slice(sources).map_err(|_| "Invalid sources of documents.")?;
+            Some(Sources::try_from(&sources)?)
+        }
+        None => None,
+    };
+    let searcher = match metric {
+        Metric::Jaccard => {
+            let mut searcher = settings.jaccard_searcher()?.progress_hook(progress.clone());
+            if let Some(threads) = threads {
+                searcher = searcher.num_threads(threads)?;
+            }
+            Searcher::Jaccard(searcher.import_sketches(&file)?)
+        }
+        Metric::Cosine => {
+            let mut searcher = settings.cosine_searcher()?.progress_hook(progress.clone());
+            if let Some(threads) = threads {
+                searcher = searcher.num_threads(threads)?;
+            }
+            let idf = load_idf(&file, &settings)?;
+            Searcher::Cosine(searcher.idf(idf).import_sketches(&file)?)
+        }
+        Metric::WeightedJaccard => {
+            let mut searcher = settings
+                .weighted_jaccard_searcher()?
+                .progress_hook(progress.clone());
+            if let Some(threads) = threads {
+                searcher = searcher.num_threads(threads)?;
+            }
+            let idf = load_idf(&file, &settings)?;
+            Searcher::WeightedJaccard(searcher.idf(idf).import_sketches(&file)?)
+        }
+        Metric::Containment => {
+            let mut searcher = settings.containment_searcher()?;
+            if let Some(threads) = threads {
+                searcher = searcher.num_threads(threads)?;
+            }
+            Searcher::Containment(searcher.import_sketches(&file)?)
+        }
+    };
+    Ok(Index {
+        searcher,
+        ids,
+        sources,
+    })
+}
+
+/// Loads the IDF model from the idf section if used in the Cosine or weighted Jaccard space.
+fn load_idf(
+    file: &IndexFile,
+    settings: &IndexSettings,
+) -> Result<Option<Idf<u64>>, Box<dyn Error>> {
+    if !settings.uses_idf()? {
+        return Ok(None);
+    }
+    let idf = file.section("idf").ok_or("The IDF model is not stored.")?;
+    Ok(Some(Idf::deserialize_from(idf)?))
+}
@@ -0,0 +1,259 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// Error in reading documents, which can be sent across threads.
+#[derive(Debug)]
+pub struct InputError(String);
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for InputError {}
+
+impl From<io::Error> for InputError {
+    fn from(e: io::Error) -> Self {
+        Self(e.to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+    Text,
+    Jsonl,
+}
+
+impl FromStr for InputFormat {
+    type Err = &'static str;
+    fn from_str(w: &str) -> Result<Self, Self::Err> {
+        match w {
+            "text" => Ok(Self::Text),
+            "jsonl" => Ok(Self::Jsonl),
+            _ => Err("Could not parse an input format"),
+        }
+    }
+}
+
+/// Reader of documents in the input formats, concatenating those of multiple files.
+pub struct DocumentReader {
+    paths: Vec<PathBuf>,
+    format: InputFormat,
+    text_field: String,
+}
+
+impl DocumentReader {
+    /// Creates an instance reading the files in the given order,
+    /// where a directory is expanded into its files sorted by their names.
+    pub fn new(
+        paths: &[PathBuf],
+        format: InputFormat,
+        text_field: &str,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut expanded = vec![];
+        for path in paths {
+            if path.is_dir() {
+                let mut files = vec![];
+                for entry in fs::read_dir(path)? {
+                    let entry = entry?;
+                    let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                    if entry.file_type()?.is_file() && !hidden {
+                        files.push(entry.path());
+                    }
+                }
+                files.sort();
+                expanded.extend(files);
+            } else {
+                expanded.push(path.clone());
+            }
+        }
+        if expanded.is_empty() {
+            return Err("No document file is found.".into());
+        }
+        Ok(Self {
+            paths: expanded,
+            format,
+            text_field: text_field.to_string(),
+        })
+    }
+
+    /// Iterates raw lines of documents in the files, where each file is opened
+    /// only when the lines of the previous one are consumed.
+    pub fn lines_iter(&self) -> impl Iterator<Item = Result<String, InputError>> + '_ {
+        self.paths.iter().flat_map(|path| {
+            let (reader, error) = match File::open(path) {
+                Ok(file) => (Some(BufReader::new(file)), None),
+                Err(e) => (
+                    None,
+                    Some(Err(InputError(format!("{}: {e}", path.display())))),
+                ),
+            };
+            error.into_iter().chain(
+                reader
+                    .into_iter()
+                    .flat_map(|reader| reader.lines().map(|line| Ok(line?))),
+            )
+        })
+    }
+
+    /// Iterates texts of documents in the files.
+    ///
+    /// For JSONL, each line must be an object with the string field of texts,
+    /// and an error is yielded otherwise.
+    pub fn texts_iter(&self) -> impl Iterator<Item = Result<String, InputError>> + '_ {
+        self.lines_iter()
+            .enumerate()
+            .map(move |(i, line)| match self.format {
+                InputFormat::Text => line,
+                InputFormat::Jsonl => string_field(&line?, &self.text_field)
+                    .map_err(|e| InputError(format!("{}: {e}", self.locate(i)))),
+            })
+    }
+
+    /// Counts the documents in the files, i.e., the number of lines.
+    pub fn num_documents(&self) -> Result<usize, Box<dyn Error>> {
+        Ok(self
+            .lines_iter()
+            .try_fold(0, |n, line| line.map(|_| n + 1))?)
+    }
+
+    /// Reads the values of a field of documents in the JSONL files, where numbers are
+    /// converted into strings.
+    pub fn read_field(&self, field: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        if self.format != InputFormat::Jsonl {
+            return Err("The id field is available only for the jsonl format.".into());
+        }
+        let mut values = vec![];
+        for (i, line) in self.lines_iter().enumerate() {
+            let value =
+                string_field(&line?, field).map_err(|e| format!("{}: {e}", self.locate(i)))?;
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    /// Describes the position of the `i`-th line in the files as the source file
+    /// and the one-origin line number, e.g., for error messages.
+    fn locate(&self, i: usize) -> String {
+        match self.sources() {
+            Ok(Some(sources)) => {
+                let (source, line) = sources.locate(i);
+                format!("{source}:{line}")
+            }
+            Ok(None) => format!("{}:{}", self.paths[0].display(), i + 1),
+            Err(_) => format!("Line {}", i + 1),
+        }
+    }
+
+    /// Reads the source files of documents, or returns `None` for a single file.
+    pub fn sources(&self) -> Result<Option<Sources>, Box<dyn Error>> {
+        if self.paths.len() == 1 {
+            return Ok(None);
+        }
+        let mut offsets = vec![0];
+        for path in &self.paths {
+            let num_lines = BufReader::new(File::open(path)?)
+                .lines()
+                .try_fold(0, |n, line| line.map(|_| n + 1))?;
+            offsets.push(offsets.last().unwrap() + num_lines);
+        }
+        Ok(Some(Sources {
+            files: self
+                .paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect(),
+            offsets,
+        }))
+    }
+}
+
+/// Slot keeping the first error in reading documents consumed by the library,
+/// which takes iterators of texts instead of results.
+#[derive(Clone, Default)]
+pub struct ErrorSlot(Arc<Mutex<Option<InputError>>>);
+
+impl ErrorSlot {
+    /// Passes the texts of `iter` until the first error, which is kept in the slot.
+    pub fn until_error<I>(&self, iter: I) -> impl Iterator<Item = String> + Send
+    where
+        I: Iterator<Item = Result<String, InputError>> + Send,
+    {
+        let slot = self.clone();
+        iter.map_while(move |item| {
+            item.map_err(|e| {
+                slot.0.lock().unwrap().get_or_insert(e);
+            })
+            .ok()
+        })
+    }
+
+    /// Returns the kept error if any, which must be checked after the iteration.
+    pub fn check(&self) -> Result<(), Box<dyn Error>> {
+        match self.0.lock().unwrap().take() {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Source files of documents concatenated from multiple files.
+pub struct Sources {
+    files: Vec<String>,
+    // The documents of the k-th file have ids from offsets[k] to offsets[k+1].
+    offsets: Vec<usize>,
+}
+
+impl Sources {
+    /// Gets the source file and the one-origin line number of the `i`-th document.
+    pub fn locate(&self, i: usize) -> (&str, usize) {
+        let k = self.offsets.partition_point(|&offset| offset <= i) - 1;
+        (&self.files[k], i - self.offsets[k] + 1)
+    }
+}
+
+impl From<&Sources> for serde_json::Value {
+    fn from(sources: &Sources) -> Self {
+        serde_json::json!({ "files": sources.files, "offsets": sources.offsets })
+    }
+}
+
+impl TryFrom<&serde_json::Value> for Sources {
+    type Error = &'static str;
+    fn try_from(value: &serde_json::Value) -> Result<Self, Self::Error> {
+        let files: Option<Vec<_>> = value["files"].as_array().and_then(|files| {
+            files
+                .iter()
+                .map(|file| file.as_str().map(String::from))
+                .collect()
+        });
+        let offsets: Option<Vec<_>> = value["offsets"].as_array().and_then(|offsets| {
+            offsets
+                .iter()
+                .map(|offset| offset.as_u64().map(|n| n as usize))
+                .collect()
+        });
+        match (files, offsets) {
+            (Some(files), Some(offsets)) if files.len() + 1 == offsets.len() => {
+                Ok(Self { files, offsets })
+            }
+            _ => Err("Invalid sources of documents"),
+        }
+    }
+}
+
+fn string_field(line: &str, field: &str) -> Result<String, Box<dyn Error>> {
+    let obj: serde_json::Value = serde_json::from_str(line)?;
+    match obj.get(field) {
+        Some(serde_json::Value::String(s)) => Ok(s.clone()),
+        Some(serde_json::Value::Number(n)) => Ok(n.to_string()),
+        Some(_) => Err(format!("The field {field} must be a string or a number.").into()),
+        None => Err(format!("The field {field} is not found.").into()),
+    }
+}
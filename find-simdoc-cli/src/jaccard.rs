@@ -1,11 +1,31 @@
+mod element_hasher;
+mod filter;
+mod input;
+mod logging;
+mod memory;
+mod output;
+mod progress;
+
 use std::error::Error;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
+use std::io::BufWriter;
 use std::path::PathBuf;
 use std::time::Instant;
 
+use all_pairs_hamming::sketch::Sketch;
 use clap::Parser;
+use rayon::prelude::*;
+
+use element_hasher::ElementHasherArg;
+use filter::min_length_filter;
+use input::{DocumentReader, ErrorSlot, InputFormat};
+use logging::init_logger;
+use memory::{fit_num_chunks, ByteSize};
+use output::{write_pairs, OutputFormatArg};
+use progress::progress_hook;
 
+use find_simdoc::feature::ShortDocumentPolicy;
+use find_simdoc::label::LabelFilter;
 use find_simdoc::JaccardSearcher;
 
 #[derive(Parser, Debug)]
@@ -14,10 +34,59 @@ use find_simdoc::JaccardSearcher;
     about = "A program to find similar documents in the Jaccard space."
 )]
 struct Args {
-    /// File path to a document file to be searched.
-    /// Empty lines must not be included.
-    #[clap(short = 'i', long)]
-    document_path: PathBuf,
+    /// File paths to document files to be searched, or directories of them.
+    /// Multiple files are concatenated in the given order.
+    /// Empty lines are skipped with --min-chars or --min-tokens, and rejected otherwise.
+    #[clap(short = 'i', long, required = true, multiple_values = true)]
+    document_path: Vec<PathBuf>,
+
+    /// File paths to other document files, or directories of them, in the same format.
+    /// If given, only pairs spanning a document in document_path (as i) and
+    /// one in these files (as j) are reported, where j is the line number in these files.
+    #[clap(long, multiple_values = true, conflicts_with = "checkpoint-dir")]
+    against: Vec<PathBuf>,
+
+    /// Format of the document file.
+    /// "text" is a document per line.
+    /// "jsonl" is a JSON object per line, whose text is given by the text field.
+    #[clap(long, default_value = "text")]
+    input_format: InputFormat,
+
+    /// Field of texts in JSON objects.
+    #[clap(long, default_value = "text")]
+    text_field: String,
+
+    /// Field of document ids in JSON objects, which are output along with the pairs.
+    #[clap(long)]
+    id_field: Option<String>,
+
+    /// Format of the output.
+    /// "csv" is a pair per row with the header.
+    /// "ndjson" is a JSON object per pair.
+    #[clap(long, default_value = "csv")]
+    output_format: OutputFormatArg,
+
+    /// Outputs the texts of documents along with the pairs.
+    #[clap(long)]
+    output_texts: bool,
+
+    /// Recomputes the exact Jaccard distance of each pair from the features of documents,
+    /// and outputs it as an additional column of exact_dist.
+    /// The features are kept in memory during the search.
+    #[clap(long)]
+    exact_verify: bool,
+
+    /// File path to export the generated sketches, whose header holds the settings and seed,
+    /// in the index format documented in the find_simdoc::index_format module.
+    /// The file can be joined again with the join_sketches program.
+    /// Only 64-bit sketches (i.e., "--sketch-bits 64") can be exported.
+    #[clap(long)]
+    export_sketches: Option<PathBuf>,
+
+    /// Stores the texts of documents along with the exported sketches,
+    /// so that the file is self-contained.
+    #[clap(long)]
+    export_documents: bool,
 
     /// Search radius in the range of [0,1].
     #[clap(short = 'r', long)]
@@ -28,16 +97,67 @@ struct Args {
     #[clap(short = 'd', long)]
     delimiter: Option<char>,
 
+    /// Regular expression for recognizing words as tokens in feature extraction,
+    /// used instead of the delimiter character (e.g., "[\s\p{P}]+").
+    #[clap(long, conflicts_with = "delimiter")]
+    delimiter_regex: Option<String>,
+
     /// Window size for w-shingling in feature extraction (must be more than 0).
     #[clap(short = 'w', long, default_value = "1")]
     window_size: usize,
 
+    /// Maximum window size for w-shingling, with which features combine shingles
+    /// of all the window sizes from window_size to max_window_size.
+    #[clap(long)]
+    max_window_size: Option<usize>,
+
+    /// Converts texts into lowercase before feature extraction.
+    #[clap(long)]
+    lowercase: bool,
+
+    /// Applies the NFKC normalization to texts before feature extraction,
+    /// which unifies full-width and half-width forms of characters, for example.
+    #[clap(long)]
+    nfkc: bool,
+
+    /// Removes punctuation characters from texts before feature extraction.
+    #[clap(long)]
+    strip_punct: bool,
+
+    /// Skips documents with fewer characters than this value, which are not searched.
+    /// The line numbers of the other documents are kept as in the input.
+    #[clap(long, default_value = "0")]
+    min_chars: usize,
+
+    /// Skips documents with fewer whitespace-separated tokens than this value, which are not searched.
+    /// The line numbers of the other documents are kept as in the input.
+    #[clap(long, default_value = "0")]
+    min_tokens: usize,
+
     /// Number of chunks in sketches, indicating that the number of dimensions in the Hamming space
-    /// will be 64*#chunks. The larger this value, the more accurate the approximation,
+    /// will be sketch_bits*#chunks. The larger this value, the more accurate the approximation,
     /// but the more time and memory it takes to search.
     #[clap(short = 'c', long, default_value = "8")]
     num_chunks: usize,
 
+    /// Number of bits in each chunk of sketches.
+    /// Smaller words allow for more and shorter chunks in the same number of dimensions,
+    /// which lowers the memory usage with fewer dimensions, e.g., "-c 3 --sketch-bits 16".
+    #[clap(long, default_value = "64", possible_values = ["8", "16", "32", "64", "128"])]
+    sketch_bits: usize,
+
+    /// Memory budget for sketches and their join (e.g., 512M or 16G).
+    /// If given, #chunks is reduced to fit the budget, and
+    /// the program aborts if even a single chunk cannot fit.
+    #[clap(long)]
+    max_memory: Option<ByteSize>,
+
+    /// Hash function of elements in sketching.
+    /// "splitmix64" is the default element mixer.
+    /// "wymix" is a faster wyhash-style mixer generating different sketches.
+    #[clap(long, default_value = "splitmix64")]
+    element_hasher: ElementHasherArg,
+
     /// Seed value for random values.
     #[clap(short = 's', long)]
     seed: Option<u64>,
@@ -45,56 +165,240 @@ struct Args {
     /// Disables parallel construction.
     #[clap(short = 'p', long)]
     disable_parallel: bool,
+
+    /// Number of threads used in parallel processing.
+    /// If not given or 0, the number of threads is determined automatically.
+    #[clap(short = 't', long)]
+    threads: Option<usize>,
+
+    /// Directory to save the progress of the join, from which an interrupted run resumes.
+    /// The same documents and options, including the seed, must be given to resume.
+    #[clap(long, requires = "seed")]
+    checkpoint_dir: Option<PathBuf>,
+
+    /// Increases the verbosity of logs to stderr (-v for debug and -vv for trace).
+    #[clap(short = 'v', long, parse(from_occurrences), conflicts_with = "quiet")]
+    verbose: u64,
+
+    /// Suppresses the progress and messages to stderr except warnings.
+    #[clap(short = 'q', long)]
+    quiet: bool,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
 
-    let document_path = args.document_path;
-    let radius = args.radius;
+    let quiet = args.quiet;
+    init_logger(args.verbose, quiet);
+
+    let reader = DocumentReader::new(&args.document_path, args.input_format, &args.text_field)?;
+    let against = if args.against.is_empty() {
+        None
+    } else {
+        Some(DocumentReader::new(
+            &args.against,
+            args.input_format,
+            &args.text_field,
+        )?)
+    };
     let delimiter = args.delimiter;
+    let delimiter_regex = &args.delimiter_regex;
     let window_size = args.window_size;
-    let num_chunks = args.num_chunks;
+    let max_window_size = args.max_window_size.unwrap_or(window_size);
     let seed = args.seed;
-    let disable_parallel = args.disable_parallel;
 
-    let mut searcher = JaccardSearcher::new(window_size, delimiter, seed)?.shows_progress(true);
+    let mut searcher = JaccardSearcher::new(window_size, delimiter, seed)?
+        .lowercase(args.lowercase)
+        .normalize_nfkc(args.nfkc)
+        .strip_punctuation(args.strip_punct)
+        .delimiter_regex(delimiter_regex.as_deref())?
+        .window_range(window_size..=max_window_size)?
+        .stores_features(args.exact_verify)
+        .element_hasher(args.element_hasher.build())
+        .progress_hook(progress_hook(quiet));
+    if let Some(threads) = args.threads {
+        searcher = searcher.num_threads(threads)?;
+    }
+    if let Some(filter) = min_length_filter(args.min_chars, args.min_tokens) {
+        searcher = searcher
+            .preprocessor(Some(filter))
+            .short_document_policy(ShortDocumentPolicy::Skip);
+    }
+
+    if args.export_sketches.is_some() && args.sketch_bits != 64 {
+        return Err("Only 64-bit sketches can be exported.".into());
+    }
+
+    // The type of sketches is determined at runtime, so the search is monomorphized for each.
+    match args.sketch_bits {
+        8 => search(
+            &args,
+            &reader,
+            against.as_ref(),
+            searcher.sketch_type::<u8>(),
+        ),
+        16 => search(
+            &args,
+            &reader,
+            against.as_ref(),
+            searcher.sketch_type::<u16>(),
+        ),
+        32 => search(
+            &args,
+            &reader,
+            against.as_ref(),
+            searcher.sketch_type::<u32>(),
+        ),
+        64 => search(&args, &reader, against.as_ref(), searcher),
+        128 => search(
+            &args,
+            &reader,
+            against.as_ref(),
+            searcher.sketch_type::<u128>(),
+        ),
+        _ => unreachable!(),
+    }
+}
+
+fn search<S>(
+    args: &Args,
+    reader: &DocumentReader,
+    against: Option<&DocumentReader>,
+    mut searcher: JaccardSearcher<S>,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Sketch + Send + Sync,
+{
+    let num_primary = reader.num_documents()?;
+    let num_docs = match against {
+        Some(against) => {
+            // The documents are labeled with their sides to report only cross pairs.
+            let num_docs = num_primary + against.num_documents()?;
+            let mut labels = vec![0; num_primary];
+            labels.resize(num_docs, 1);
+            searcher = searcher.labels(Some(labels));
+            num_docs
+        }
+        None => num_primary,
+    };
+    let num_chunks = match args.max_memory {
+        Some(ByteSize(max_memory)) => fit_num_chunks::<S>(num_docs, args.num_chunks, max_memory)?,
+        None => args.num_chunks,
+    };
+
+    let texts = || {
+        reader
+            .texts_iter()
+            .chain(against.into_iter().flat_map(|against| against.texts_iter()))
+    };
 
     {
-        eprintln!("Converting documents into sketches...");
+        tracing::info!("Converting documents into sketches...");
         let start = Instant::now();
-        let documents = texts_iter(File::open(&document_path)?);
-        searcher = if disable_parallel {
+        let errors = ErrorSlot::default();
+        let documents = errors.until_error(texts());
+        searcher = if args.disable_parallel {
             searcher.build_sketches(documents, num_chunks)?
         } else {
             searcher.build_sketches_in_parallel(documents, num_chunks)?
         };
+        errors.check()?;
         let duration = start.elapsed();
         let memory_in_bytes = searcher.memory_in_bytes() as f64;
-        eprintln!(
+        tracing::info!(
             "Produced {} sketches in {} sec, consuming {} MiB",
             searcher.len(),
             duration.as_secs_f64(),
             memory_in_bytes / (1024. * 1024.)
         );
+        let num_skipped = searcher.skipped_documents().len();
+        if num_skipped != 0 {
+            tracing::info!("Skipped {num_skipped} short documents");
+        }
+    }
+
+    if let Some(path) = &args.export_sketches {
+        tracing::info!("Exporting sketches...");
+        let mut file = searcher.export_sketches()?;
+        if args.export_documents {
+            file.set_documents(texts().collect::<Result<Vec<_>, _>>()?);
+        }
+        file.serialize_into(BufWriter::new(File::create(path)?))?;
+        tracing::info!("Exported {} sketches to {path:?}", file.num_sketches());
     }
 
-    eprintln!("Finding all similar pairs in sketches...");
+    tracing::info!("Finding all similar pairs in sketches...");
     let start = Instant::now();
-    let results = searcher.search_similar_pairs(radius);
-    eprintln!("Done in {} sec", start.elapsed().as_secs_f64());
+    let results = match &args.checkpoint_dir {
+        Some(checkpoint_dir) => {
+            std::fs::create_dir_all(checkpoint_dir)?;
+            searcher.search_similar_pairs_with_checkpoint(
+                args.radius,
+                checkpoint_dir.join("join.ckpt"),
+            )?
+        }
+        None if against.is_some() => {
+            searcher.search_similar_pairs_with_filter(args.radius, LabelFilter::CrossLabelOnly)?
+        }
+        None => searcher.search_similar_pairs(args.radius),
+    };
+    tracing::info!("Done in {} sec", start.elapsed().as_secs_f64());
 
-    println!("i,j,dist");
-    for (i, j, dist) in results {
-        println!("{i},{j},{dist}");
-    }
+    let exact_dists: Option<Vec<_>> = if args.exact_verify {
+        tracing::info!("Computing exact distances of the pairs...");
+        let start = Instant::now();
+        let exact_dists = results
+            .par_iter()
+            .map(|&(i, j, _)| searcher.exact_distance(i, j))
+            .collect::<Result<_, _>>()?;
+        tracing::info!("Done in {} sec", start.elapsed().as_secs_f64());
+        Some(exact_dists)
+    } else {
+        None
+    };
 
-    Ok(())
-}
+    let read_ids = |reader: &DocumentReader| match &args.id_field {
+        Some(id_field) => reader.read_field(id_field).map(Some),
+        None => Ok(None),
+    };
+    let read_texts = |reader: &DocumentReader| -> Result<Option<Vec<_>>, Box<dyn Error>> {
+        if args.output_texts {
+            Ok(Some(reader.texts_iter().collect::<Result<_, _>>()?))
+        } else {
+            Ok(None)
+        }
+    };
+    let ids = read_ids(reader)?;
+    let texts = read_texts(reader)?;
+    let sources = reader.sources()?;
+    let (results, other_ids, other_texts, other_sources) = match against {
+        // Right-side ids are converted into the line numbers in the other files.
+        Some(against) => (
+            results
+                .into_iter()
+                .map(|(i, j, dist)| (i.min(j), i.max(j) - num_primary, dist))
+                .collect(),
+            read_ids(against)?,
+            read_texts(against)?,
+            against.sources()?,
+        ),
+        None => (results, None, None, None),
+    };
+    write_pairs(
+        std::io::stdout().lock(),
+        args.output_format,
+        &results,
+        exact_dists.as_deref(),
+        (ids.as_deref(), other_ids.as_deref().or(ids.as_deref())),
+        (
+            sources.as_ref(),
+            against.map_or(sources.as_ref(), |_| other_sources.as_ref()),
+        ),
+        (
+            texts.as_deref(),
+            other_texts.as_deref().or(texts.as_deref()),
+        ),
+    )?;
 
-fn texts_iter<R>(rdr: R) -> impl Iterator<Item = String>
-where
-    R: Read,
-{
-    BufReader::new(rdr).lines().map(|line| line.unwrap())
+    Ok(())
 }
@@ -0,0 +1,103 @@
+// Sources of documents are not given with sketches.
+#[allow(dead_code)]
+mod input;
+mod logging;
+mod output;
+mod progress;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use clap::Parser;
+
+use logging::init_logger;
+use output::{write_pairs, OutputFormatArg};
+use progress::progress_hook;
+
+use find_simdoc::index_format::IndexFile;
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "find-simdoc-join_sketches",
+    about = "A program to find similar pairs of precomputed 64-bit sketches in the Hamming space."
+)]
+struct Args {
+    /// File path to precomputed sketches, such as simhash or minhash fingerprints,
+    /// in the index format documented in the find_simdoc::index_format module.
+    /// The i-th sketch is output as the i-th document.
+    #[clap(short = 'i', long)]
+    sketch_path: PathBuf,
+
+    /// Format of the output.
+    /// "csv" is a pair per row with the header.
+    /// "ndjson" is a JSON object per pair.
+    #[clap(long, default_value = "csv")]
+    output_format: OutputFormatArg,
+
+    /// Search radius of the normalized Hamming distance in the range of [0,1].
+    #[clap(short = 'r', long)]
+    radius: f64,
+
+    /// Number of chunks used in the join, which must be no more than that of the sketches.
+    /// If not given, all the chunks are used.
+    #[clap(short = 'c', long)]
+    num_chunks: Option<usize>,
+
+    /// Increases the verbosity of logs to stderr (-v for debug and -vv for trace).
+    #[clap(short = 'v', long, parse(from_occurrences), conflicts_with = "quiet")]
+    verbose: u64,
+
+    /// Suppresses the progress and messages to stderr except warnings.
+    #[clap(short = 'q', long)]
+    quiet: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    init_logger(args.verbose, args.quiet);
+
+    if !(0.0..=1.0).contains(&args.radius) {
+        return Err("radius must be in [0,1].".into());
+    }
+
+    tracing::info!("Loading sketches...");
+    let start = Instant::now();
+    let file = IndexFile::deserialize_from(BufReader::new(File::open(&args.sketch_path)?))?;
+    for (key, value) in file.header() {
+        tracing::info!("{key}={value}");
+    }
+    let mut joiner = file.to_joiner()?.progress_hook(progress_hook(args.quiet));
+    if let Some(num_chunks) = args.num_chunks {
+        joiner = joiner.prefix(num_chunks)?;
+    }
+    tracing::info!(
+        "Loaded {} sketches of {} chunks in {} sec",
+        joiner.num_sketches(),
+        joiner.num_chunks(),
+        start.elapsed().as_secs_f64()
+    );
+
+    tracing::info!("Finding all similar pairs in sketches...");
+    let start = Instant::now();
+    let results = joiner.similar_pairs(args.radius);
+    tracing::info!(
+        "Found {} pairs in {} sec",
+        results.len(),
+        start.elapsed().as_secs_f64()
+    );
+
+    write_pairs(
+        std::io::stdout().lock(),
+        args.output_format,
+        &results,
+        None,
+        (None, None),
+        (None, None),
+        (None, None),
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use std::io::{self, IsTerminal, Write};
+use std::sync::LazyLock;
+
+use indicatif::MultiProgress;
+use tracing_subscriber::filter::LevelFilter;
+
+/// Progress bars drawn on stderr, which are cleared while the logger writes a line.
+pub static PROGRESS_BARS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// Writer to stderr suspending the progress bars.
+struct StderrWriter;
+
+impl Write for StderrWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        PROGRESS_BARS.suspend(|| io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// Initializes the logger writing to stderr.
+///
+/// The level is WARN if `quiet` is set, and otherwise INFO, DEBUG, or TRACE
+/// according to the number of `-v` given as `verbose`.
+/// Colors are disabled unless stderr is a terminal so that redirected logs can be parsed.
+/// Lines are written without breaking the progress bars in [`PROGRESS_BARS`].
+pub fn init_logger(verbose: u64, quiet: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::WARN,
+        (false, 0) => LevelFilter::INFO,
+        (false, 1) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    let stderr = io::stderr();
+    tracing_subscriber::fmt()
+        .with_writer(|| StderrWriter)
+        .with_ansi(stderr.is_terminal())
+        .with_max_level(level)
+        .init();
+}
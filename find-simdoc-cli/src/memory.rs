@@ -0,0 +1,74 @@
+use std::str::FromStr;
+
+use all_pairs_hamming::sketch::Sketch;
+use all_pairs_hamming::ChunkedJoiner;
+
+/// Size in bytes with an optional suffix of K, M, G, or T (in powers of 1024).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ByteSize(pub usize);
+
+impl FromStr for ByteSize {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (digits, shift) = match s.chars().last().map(|c| c.to_ascii_uppercase()) {
+            Some('K') => (&s[..s.len() - 1], 10),
+            Some('M') => (&s[..s.len() - 1], 20),
+            Some('G') => (&s[..s.len() - 1], 30),
+            Some('T') => (&s[..s.len() - 1], 40),
+            _ => (s, 0),
+        };
+        let n = digits
+            .parse::<f64>()
+            .map_err(|_| "Could not parse a memory size")?;
+        if !n.is_finite() || n <= 0. {
+            return Err("Memory size must be positive");
+        }
+        Ok(Self((n * (1u64 << shift) as f64) as usize))
+    }
+}
+
+/// Chooses the largest number of chunks up to `num_chunks` such that sketches of type `S` of
+/// `num_docs` documents can be joined within `max_memory` bytes.
+///
+/// A warning is logged if the number of chunks is reduced, and an error is returned
+/// if even a single chunk cannot fit.
+pub fn fit_num_chunks<S>(
+    num_docs: usize,
+    num_chunks: usize,
+    max_memory: usize,
+) -> Result<usize, String>
+where
+    S: Sketch,
+{
+    let estimate = |c| ChunkedJoiner::<S>::estimate_memory_in_bytes(num_docs, c);
+    if max_memory < estimate(1) {
+        return Err(format!(
+            "The join of {num_docs} documents needs at least {}, exceeding the memory budget of {}.",
+            human(estimate(1)),
+            human(max_memory),
+        ));
+    }
+    let fitted = (1..=num_chunks)
+        .rev()
+        .find(|&c| estimate(c) <= max_memory)
+        .unwrap_or(1);
+    if fitted < num_chunks {
+        tracing::warn!(
+            "#chunks is reduced from {num_chunks} to {fitted} to fit the memory budget of {}, which lowers the accuracy of estimated distances.",
+            human(max_memory),
+        );
+    }
+    Ok(fitted)
+}
+
+pub fn human(bytes: usize) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if size < 1024. {
+            return format!("{size:.1} {unit}");
+        }
+        size /= 1024.;
+    }
+    format!("{size:.1} TiB")
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Metric {
+    Jaccard,
+    Cosine,
+    WeightedJaccard,
+    Containment,
+}
+
+impl FromStr for Metric {
+    type Err = &'static str;
+    fn from_str(w: &str) -> Result<Self, Self::Err> {
+        match w {
+            "jaccard" => Ok(Self::Jaccard),
+            "cosine" => Ok(Self::Cosine),
+            "wjac" => Ok(Self::WeightedJaccard),
+            "containment" => Ok(Self::Containment),
+            _ => Err("Could not parse a metric"),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Jaccard => write!(f, "jaccard"),
+            Self::Cosine => write!(f, "cosine"),
+            Self::WeightedJaccard => write!(f, "wjac"),
+            Self::Containment => write!(f, "containment"),
+        }
+    }
+}
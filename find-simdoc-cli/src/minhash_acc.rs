@@ -1,5 +1,7 @@
 #![allow(clippy::mutex_atomic)]
 
+mod logging;
+
 use std::env;
 use std::error::Error;
 use std::fmt::Write as _;
@@ -15,6 +17,7 @@ use clap::Parser;
 use find_simdoc::feature::{FeatureConfig, FeatureExtractor};
 use find_simdoc::lsh::minhash::MinHasher;
 use hashbrown::HashSet;
+use logging::init_logger;
 use positioned_io::WriteAt;
 use rand::{RngCore, SeedableRng};
 use rayon::prelude::*;
@@ -28,7 +31,7 @@ const MAX_CHUNKS: usize = 100;
 )]
 struct Args {
     /// File path to a document file to be searched.
-    /// Empty lines must not be included.
+    /// Empty lines are rejected with an error.
     #[clap(short = 'i', long)]
     document_path: PathBuf,
 
@@ -41,6 +44,19 @@ struct Args {
     #[clap(short = 'w', long, default_value = "1")]
     window_size: usize,
 
+    /// Converts texts into lowercase before feature extraction.
+    #[clap(long)]
+    lowercase: bool,
+
+    /// Applies the NFKC normalization to texts before feature extraction,
+    /// which unifies full-width and half-width forms of characters, for example.
+    #[clap(long)]
+    nfkc: bool,
+
+    /// Removes punctuation characters from texts before feature extraction.
+    #[clap(long)]
+    strip_punct: bool,
+
     /// Seed value for random values.
     #[clap(short = 's', long)]
     seed: Option<u64>,
@@ -48,10 +64,19 @@ struct Args {
     /// Directory path to write a tmp file.
     #[clap(short = 't', long)]
     tmp_dir: Option<PathBuf>,
+
+    /// Increases the verbosity of logs to stderr (-v for debug and -vv for trace).
+    #[clap(short = 'v', long, parse(from_occurrences), conflicts_with = "quiet")]
+    verbose: u64,
+
+    /// Suppresses the progress and messages to stderr except warnings.
+    #[clap(short = 'q', long)]
+    quiet: bool,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
+    init_logger(args.verbose, args.quiet);
 
     let document_path = args.document_path;
     let delimiter = args.delimiter;
@@ -70,11 +95,14 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut seeder =
         rand_xoshiro::SplitMix64::seed_from_u64(seed.unwrap_or_else(rand::random::<u64>));
 
-    let config = FeatureConfig::new(window_size, delimiter, seeder.next_u64())?;
+    let config = FeatureConfig::new(window_size, delimiter, seeder.next_u64())?
+        .lowercase(args.lowercase)
+        .normalize_nfkc(args.nfkc)
+        .strip_punctuation(args.strip_punct);
     let extractor = FeatureExtractor::new(&config);
 
     let features = {
-        eprintln!("Loading documents and extracting features...");
+        tracing::info!("Loading documents and extracting features...");
         let start = Instant::now();
         let mut features = vec![];
         for document in documents {
@@ -88,7 +116,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         let duration = start.elapsed();
         let total_bytes =
             features.iter().fold(0, |acc, f| acc + f.len()) * std::mem::size_of::<u64>();
-        eprintln!(
+        tracing::info!(
             "Extracted {} features in {} sec, consuming {} MiB",
             features.len(),
             duration.as_secs_f64(),
@@ -98,7 +126,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     };
 
     let sketches = {
-        eprintln!("Producing binary sketches...");
+        tracing::info!("Producing binary sketches...");
         let start = Instant::now();
         let hasher = MinHasher::new(seeder.next_u64());
 
@@ -109,23 +137,20 @@ fn main() -> Result<(), Box<dyn Error>> {
             .par_iter()
             .map(|feature| {
                 {
-                    // Mutex::lock also locks eprintln.
+                    // Mutex::lock also serializes the messages.
                     let mut cnt = processed.lock().unwrap();
                     *cnt += 1;
-                    if *cnt % 1000 == 0 {
-                        eprintln!("Processed {} features...", *cnt);
+                    if cnt.is_multiple_of(1000) {
+                        tracing::info!("Processed {} features...", *cnt);
                     }
                 }
-                let mut iter = hasher.iter(feature);
-                let mut sketch = Vec::with_capacity(MAX_CHUNKS);
-                (0..MAX_CHUNKS).for_each(|_| sketch.push(iter.next().unwrap()));
-                sketch
+                hasher.sketch(feature, MAX_CHUNKS).unwrap()
             })
             .collect_into_vec(&mut sketches);
 
         let duration = start.elapsed();
         let total_bytes = sketches.len() * MAX_CHUNKS * std::mem::size_of::<u64>();
-        eprintln!(
+        tracing::info!(
             "Produced in {} sec, consuming {} MiB",
             duration.as_secs_f64(),
             total_bytes as f64 / (1024. * 1024.)
@@ -143,7 +168,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         let start = Instant::now();
 
         let possible_pairs = features.len() * (features.len() - 1) / 2;
-        eprintln!("Computing exact Jaccard distances for {possible_pairs} pairs...");
+        tracing::info!("Computing exact Jaccard distances for {possible_pairs} pairs...");
 
         let tmp_file_size = possible_pairs * mem::size_of::<f64>();
         let offsets = {
@@ -167,7 +192,7 @@ fn main() -> Result<(), Box<dyn Error>> {
                 w.write_at(tmp_file_size as u64 - 1, &[0])?;
             }
 
-            eprintln!(
+            tracing::info!(
                 "Created a tmp file of {} GiB, at {:?}",
                 tmp_file_size as f64 / (1024. * 1024. * 1024.),
                 &tmp_path
@@ -175,11 +200,11 @@ fn main() -> Result<(), Box<dyn Error>> {
 
             (0..features.len()).into_par_iter().for_each(|i| {
                 {
-                    // Mutex::lock also locks eprintln.
+                    // Mutex::lock also serializes the messages.
                     let mut cnt = processed.lock().unwrap();
                     *cnt += 1;
-                    if *cnt % 1000 == 0 {
-                        eprintln!("Processed {} features...", *cnt);
+                    if cnt.is_multiple_of(1000) {
+                        tracing::info!("Processed {} features...", *cnt);
                     }
                 }
 
@@ -188,8 +213,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
                 let x = &features[i];
                 for y in features.iter().skip(i + 1) {
-                    let dist =
-                        find_simdoc::lsh::jaccard_distance(x.iter().clone(), y.iter().clone());
+                    let dist = find_simdoc::exact::jaccard_distance(x.iter(), y.iter());
                     jac_dists.extend_from_slice(&dist.to_le_bytes());
                 }
 
@@ -203,7 +227,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
 
         let duration = start.elapsed();
-        eprintln!("Computed in {} sec", duration.as_secs_f64());
+        tracing::info!("Computed in {} sec", duration.as_secs_f64());
         possible_pairs
     };
 
@@ -217,7 +241,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
     println!("{header}");
 
-    eprintln!("Computing accuracy...");
+    tracing::info!("Computing accuracy...");
     let start = Instant::now();
 
     let results = {
@@ -226,11 +250,11 @@ fn main() -> Result<(), Box<dyn Error>> {
             .into_par_iter()
             .map(|num_chunks| {
                 {
-                    // Mutex::lock also locks eprintln.
+                    // Mutex::lock also serializes the messages.
                     let mut cnt = processed.lock().unwrap();
                     *cnt += 1;
-                    if *cnt % 10 == 0 {
-                        eprintln!("Processed {} chunks...", *cnt);
+                    if cnt.is_multiple_of(10) {
+                        tracing::info!("Processed {} chunks...", *cnt);
                     }
                 }
 
@@ -286,7 +310,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         results
     };
     let duration = start.elapsed();
-    eprintln!("Computed in {} sec", duration.as_secs_f64());
+    tracing::info!("Computed in {} sec", duration.as_secs_f64());
 
     for (_, body) in results {
         println!("{body}");
@@ -0,0 +1,96 @@
+use std::error::Error;
+use std::io::Write;
+use std::str::FromStr;
+
+use find_simdoc::output::{OutputFormat, PairWriter, Value};
+
+use crate::input::Sources;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormatArg {
+    Csv,
+    Ndjson,
+}
+
+impl FromStr for OutputFormatArg {
+    type Err = &'static str;
+    fn from_str(w: &str) -> Result<Self, Self::Err> {
+        match w {
+            "csv" => Ok(Self::Csv),
+            "ndjson" => Ok(Self::Ndjson),
+            _ => Err("Could not parse an output format"),
+        }
+    }
+}
+
+/// Writes similar pairs, optionally with exact distances of the pairs, and ids, sources, and
+/// texts of documents.
+///
+/// The ids, sources, and texts are given for the left and right sides, respectively, since they
+/// can be different collections of documents, and their columns are written only for the given sides.
+pub fn write_pairs<W>(
+    wtr: W,
+    format: OutputFormatArg,
+    results: &[(usize, usize, f64)],
+    exact_dists: Option<&[f64]>,
+    ids: (Option<&[String]>, Option<&[String]>),
+    sources: (Option<&Sources>, Option<&Sources>),
+    texts: (Option<&[String]>, Option<&[String]>),
+) -> Result<(), Box<dyn Error>>
+where
+    W: Write,
+{
+    let format = match format {
+        OutputFormatArg::Csv => OutputFormat::Csv,
+        OutputFormatArg::Ndjson => OutputFormat::JsonLines,
+    };
+    let mut columns = vec![];
+    if exact_dists.is_some() {
+        columns.push("exact_dist");
+    }
+    if ids.0.is_some() {
+        columns.push("id_i");
+    }
+    if ids.1.is_some() {
+        columns.push("id_j");
+    }
+    if sources.0.is_some() {
+        columns.extend(["source_i", "line_i"]);
+    }
+    if sources.1.is_some() {
+        columns.extend(["source_j", "line_j"]);
+    }
+    if texts.0.is_some() {
+        columns.push("text_i");
+    }
+    if texts.1.is_some() {
+        columns.push("text_j");
+    }
+    let mut writer = PairWriter::with_columns(wtr, format, &columns)?;
+    let mut values = Vec::with_capacity(columns.len());
+    for (k, &(i, j, dist)) in results.iter().enumerate() {
+        values.clear();
+        if let Some(exact_dists) = exact_dists {
+            values.push(Value::Num(exact_dists[k]));
+        }
+        for (ids, k) in [(ids.0, i), (ids.1, j)] {
+            if let Some(ids) = ids {
+                values.push(Value::Str(&ids[k]));
+            }
+        }
+        for (sources, k) in [(sources.0, i), (sources.1, j)] {
+            if let Some(sources) = sources {
+                let (source, line) = sources.locate(k);
+                values.extend([Value::Str(source), Value::Num(line as f64)]);
+            }
+        }
+        for (texts, k) in [(texts.0, i), (texts.1, j)] {
+            if let Some(texts) = texts {
+                values.push(Value::Str(&texts[k]));
+            }
+        }
+        writer.write_with_values(i, j, dist, &values)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use hashbrown::HashSet;
+
+/// Triplet of the left-side id, the right-side id, and their distance.
+pub type SimPair = (usize, usize, f64);
+
+/// Reads similar pairs from the CSV or NDJSON file, ignoring additional columns.
+/// The mapping of removed documents written by dedup (without ids) is also accepted,
+/// whose distances are NaN since they are not written.
+pub fn read_pairs(path: &Path) -> Result<Vec<SimPair>, Box<dyn Error>> {
+    let mut pairs = vec![];
+    let mut has_dist = true;
+    for row in BufReader::new(File::open(path)?).lines() {
+        let row = row?;
+        if row.starts_with('{') {
+            let obj: serde_json::Value = serde_json::from_str(&row)?;
+            let (Some(i), Some(j), Some(dist)) =
+                (obj["i"].as_u64(), obj["j"].as_u64(), obj["dist"].as_f64())
+            else {
+                return Err(format!("Invalid pair: {row}").into());
+            };
+            pairs.push((i as usize, j as usize, dist));
+        } else if row.starts_with("removed,kept") {
+            has_dist = false;
+        } else if !row.starts_with("i,j,dist") {
+            let mut cols = row.split(',');
+            let (Some(i), Some(j)) = (cols.next(), cols.next()) else {
+                return Err(format!("Invalid pair: {row}").into());
+            };
+            let dist = match (has_dist, cols.next()) {
+                (true, Some(dist)) => dist.parse()?,
+                (true, None) => return Err(format!("Invalid pair: {row}").into()),
+                (false, _) => f64::NAN,
+            };
+            pairs.push((i.parse()?, j.parse()?, dist));
+        }
+    }
+    Ok(pairs)
+}
+
+/// Groups documents into connected components of similar pairs,
+/// omitting documents in no pair.
+pub fn clusters(num_docs: usize, pairs: &[SimPair]) -> Vec<Vec<usize>> {
+    fn find(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+    let mut parents: Vec<_> = (0..num_docs).collect();
+    let mut in_pair = vec![false; num_docs];
+    for &(i, j, _) in pairs {
+        let (ri, rj) = (find(&mut parents, i), find(&mut parents, j));
+        parents[ri.max(rj)] = ri.min(rj);
+        in_pair[i] = true;
+        in_pair[j] = true;
+    }
+    let mut cluster_ids = vec![usize::MAX; num_docs];
+    let mut clusters = vec![];
+    for i in (0..num_docs).filter(|&i| in_pair[i]) {
+        let root = find(&mut parents, i);
+        if cluster_ids[root] == usize::MAX {
+            cluster_ids[root] = clusters.len();
+            clusters.push(vec![]);
+        }
+        clusters[cluster_ids[root]].push(i);
+    }
+    clusters
+}
+
+/// Checks if each character of `text` is covered by character `width`-grams also appearing in `other`.
+pub fn overlaps(text: &str, other: &str, width: usize) -> Vec<bool> {
+    let chars: Vec<_> = text.chars().collect();
+    let others: Vec<_> = other.chars().collect();
+    let grams: HashSet<_> = others.windows(width).collect();
+    let mut covered = vec![false; chars.len()];
+    for (k, gram) in chars.windows(width).enumerate() {
+        if grams.contains(gram) {
+            covered[k..k + width].fill(true);
+        }
+    }
+    covered
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::sync::Mutex;
+
+use all_pairs_hamming::progress::{ProgressHook, Stage};
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::logging::PROGRESS_BARS;
+
+/// Creates a hook drawing a progress bar with the rate and ETA for each stage on stderr,
+/// or returns `None` if `quiet` is set.
+///
+/// If stderr is not a terminal, the progress is logged as `tracing` events instead,
+/// so that redirected logs do not include the control sequences of bars.
+pub fn progress_hook(quiet: bool) -> Option<ProgressHook> {
+    if quiet {
+        return None;
+    }
+    if !std::io::stderr().is_terminal() {
+        return Some(ProgressHook::tracing());
+    }
+    let bars = Mutex::new(HashMap::new());
+    Some(ProgressHook::new(move |stage, done, total| {
+        let mut bars = bars.lock().unwrap();
+        let bar = bars.entry(stage).or_insert_with(|| {
+            let bar = PROGRESS_BARS.add(ProgressBar::new_spinner());
+            bar.set_style(spinner_style());
+            bar.set_message(stage.to_string());
+            bar
+        });
+        if let Some(total) = total {
+            if bar.length() != Some(total as u64) {
+                bar.set_length(total as u64);
+                bar.set_style(bar_style(stage));
+            }
+        }
+        bar.set_position(done as u64);
+        if total == Some(done) {
+            bar.finish();
+        }
+    }))
+}
+
+fn spinner_style() -> ProgressStyle {
+    ProgressStyle::with_template("{spinner} {msg:>14} {human_pos} ({per_sec})").unwrap()
+}
+
+fn bar_style(stage: Stage) -> ProgressStyle {
+    let template = format!(
+        "{{msg:>16}} [{{bar:40}}] {{human_pos}}/{{human_len}} {} ({{per_sec}}, ETA {{eta}})",
+        stage.unit()
+    );
+    ProgressStyle::with_template(&template)
+        .unwrap()
+        .progress_chars("=> ")
+}
@@ -0,0 +1,135 @@
+mod index_file;
+// Queries are searched one by one, so their read errors need not be kept in a slot.
+#[allow(dead_code)]
+mod input;
+mod logging;
+mod metric;
+mod output;
+mod progress;
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use clap::Parser;
+use rayon::prelude::*;
+
+use index_file::load_index;
+use input::{DocumentReader, InputFormat};
+use logging::init_logger;
+use output::{write_pairs, OutputFormatArg};
+use progress::progress_hook;
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "find-simdoc-query",
+    about = "A program to search an index for documents similar to queries."
+)]
+struct Args {
+    /// File path to the index built by the index executable.
+    #[clap(short = 'x', long)]
+    index_path: PathBuf,
+
+    /// File paths to query files, or directories of them.
+    /// Multiple files are concatenated in the given order.
+    /// Empty lines are rejected with an error.
+    #[clap(short = 'i', long, required = true, multiple_values = true)]
+    query_path: Vec<PathBuf>,
+
+    /// Format of the query file.
+    /// "text" is a query per line.
+    /// "jsonl" is a JSON object per line, whose text is given by the text field.
+    #[clap(long, default_value = "text")]
+    input_format: InputFormat,
+
+    /// Field of texts in JSON objects.
+    #[clap(long, default_value = "text")]
+    text_field: String,
+
+    /// Field of query ids in JSON objects, which are output along with the pairs.
+    #[clap(long)]
+    id_field: Option<String>,
+
+    /// Format of the output.
+    /// "csv" is a pair per row with the header.
+    /// "ndjson" is a JSON object per pair.
+    #[clap(long, default_value = "csv")]
+    output_format: OutputFormatArg,
+
+    /// Search radius in the range of [0,1].
+    #[clap(short = 'r', long)]
+    radius: f64,
+
+    /// Number of threads used in parallel processing.
+    /// If not given or 0, the number of threads is determined automatically.
+    #[clap(short = 't', long)]
+    threads: Option<usize>,
+
+    /// Increases the verbosity of logs to stderr (-v for debug and -vv for trace).
+    #[clap(short = 'v', long, parse(from_occurrences), conflicts_with = "quiet")]
+    verbose: u64,
+
+    /// Suppresses the progress and messages to stderr except warnings.
+    #[clap(short = 'q', long)]
+    quiet: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let quiet = args.quiet;
+    init_logger(args.verbose, quiet);
+
+    tracing::info!("Loading the index...");
+    let start = Instant::now();
+    let index = load_index(&args.index_path, args.threads, progress_hook(quiet))?;
+    tracing::info!("Done in {} sec", start.elapsed().as_secs_f64());
+
+    let reader = DocumentReader::new(&args.query_path, args.input_format, &args.text_field)?;
+    let query_ids = match &args.id_field {
+        Some(id_field) => Some(reader.read_field(id_field)?),
+        None => None,
+    };
+
+    tracing::info!(
+        "Searching for documents similar to {} queries...",
+        reader.num_documents()?
+    );
+    let start = Instant::now();
+    let mut results: Vec<_> = reader
+        .texts_iter()
+        .enumerate()
+        .par_bridge()
+        .map(|(i, query)| {
+            let results = index
+                .searcher
+                .search_similar_documents(&query?, args.radius)?;
+            Ok(results.into_iter().map(move |(j, dist)| (i, j, dist)))
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()
+        .map_err(|e| e as Box<dyn Error>)?
+        .into_iter()
+        .flatten()
+        .collect();
+    // The stable sort keeps the results of each query in the ascending order of distances.
+    results.sort_by_key(|&(i, _, _)| i);
+    tracing::info!(
+        "Found {} pairs in {} sec",
+        results.len(),
+        start.elapsed().as_secs_f64()
+    );
+
+    // The left and right sides of pairs are queries and documents, respectively.
+    let sources = reader.sources()?;
+    write_pairs(
+        std::io::stdout().lock(),
+        args.output_format,
+        &results,
+        None,
+        (query_ids.as_deref(), index.ids.as_deref()),
+        (sources.as_ref(), index.sources.as_ref()),
+        (None, None),
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,177 @@
+mod pairs;
+
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use pairs::{clusters, overlaps, read_pairs, SimPair};
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;width:100%;margin-bottom:1em}\
+th,td{border:1px solid #ccc;padding:4px 8px;text-align:left;vertical-align:top}\
+th{background:#f4f4f4}td.num{white-space:nowrap;text-align:right}\
+mark{background:#ffd54f}section{margin-bottom:2em}";
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "find-simdoc-report",
+    about = "A program to render similar documents into a standalone HTML report."
+)]
+struct Args {
+    /// File path to the texts of documents, a document per line.
+    #[clap(short = 'i', long)]
+    text_path: PathBuf,
+
+    /// File path to similar pairs in CSV or NDJSON, written by jaccard or cosine,
+    /// or the mapping of removed documents written by dedup without ids.
+    #[clap(short = 's', long)]
+    simpair_path: PathBuf,
+
+    /// Reports only the N largest groups of similar documents.
+    #[clap(long)]
+    top: Option<usize>,
+
+    /// Minimum number of characters of common regions, with which the other regions
+    /// are highlighted as differences.
+    #[clap(long, default_value = "5")]
+    highlight_width: usize,
+
+    /// Title of the report.
+    #[clap(long, default_value = "Similar documents")]
+    title: String,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let texts: Vec<_> = BufReader::new(File::open(&args.text_path)?)
+        .lines()
+        .collect::<Result<_, _>>()?;
+
+    let pairs = read_pairs(&args.simpair_path)?;
+    if let Some(&(i, j, _)) = pairs
+        .iter()
+        .find(|&&(i, j, _)| texts.len() <= i || texts.len() <= j)
+    {
+        return Err(format!("The pair ({i},{j}) is out of the range of the texts.").into());
+    }
+
+    // Larger groups come first since they are more likely to be worth reviewing.
+    let mut groups = clusters(texts.len(), &pairs);
+    groups.sort_by_key(|group| std::cmp::Reverse(group.len()));
+    let num_groups = groups.len();
+    if let Some(top) = args.top {
+        groups.truncate(top);
+    }
+
+    // Pairs are assigned to the group containing their left-side documents.
+    let mut group_ids = vec![usize::MAX; texts.len()];
+    for (g, group) in groups.iter().enumerate() {
+        for &i in group {
+            group_ids[i] = g;
+        }
+    }
+    let mut group_pairs: Vec<Vec<SimPair>> = vec![vec![]; groups.len()];
+    for &(i, j, dist) in &pairs {
+        if let Some(pairs) = group_pairs.get_mut(group_ids[i]) {
+            pairs.push((i, j, dist));
+        }
+    }
+
+    let width = args.highlight_width.max(1);
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html><head><meta charset=\"utf-8\">")?;
+    writeln!(html, "<title>{}</title>", escape(&args.title))?;
+    writeln!(html, "<style>{STYLE}</style></head><body>")?;
+    writeln!(html, "<h1>{}</h1>", escape(&args.title))?;
+    writeln!(
+        html,
+        "<p>{} pairs in {num_groups} groups of {} documents. Showing {} groups.</p>",
+        pairs.len(),
+        texts.len(),
+        groups.len()
+    )?;
+    writeln!(
+        html,
+        "<p>In each group, the first document is the reference, and \
+         the regions of the others not appearing in it are <mark>highlighted</mark>.</p>"
+    )?;
+
+    for (g, (group, pairs)) in groups.iter().zip(group_pairs.iter()).enumerate() {
+        writeln!(html, "<section>")?;
+        writeln!(html, "<h2>Group {} ({} documents)</h2>", g + 1, group.len())?;
+        writeln!(html, "<table><tr><th>line</th><th>text</th></tr>")?;
+        let reference = &texts[group[0]];
+        for (k, &i) in group.iter().enumerate() {
+            let text = if k == 0 {
+                escape(reference)
+            } else {
+                highlight_differences(&texts[i], reference, width)
+            };
+            writeln!(html, "<tr><td class=\"num\">{i}</td><td>{text}</td></tr>")?;
+        }
+        writeln!(html, "</table>")?;
+        writeln!(
+            html,
+            "<details><summary>{} pairs</summary><table><tr><th>i</th><th>j</th><th>dist</th></tr>",
+            pairs.len()
+        )?;
+        for &(i, j, dist) in pairs {
+            let dist = if dist.is_nan() {
+                "-".to_string()
+            } else {
+                format!("{dist:.4}")
+            };
+            writeln!(
+                html,
+                "<tr><td class=\"num\">{i}</td><td class=\"num\">{j}</td><td class=\"num\">{dist}</td></tr>"
+            )?;
+        }
+        writeln!(html, "</table></details>")?;
+        writeln!(html, "</section>")?;
+    }
+    writeln!(html, "</body></html>")?;
+
+    std::io::stdout().lock().write_all(html.as_bytes())?;
+    Ok(())
+}
+
+/// Marks the regions of `text` not covered by character `width`-grams appearing in `other`.
+fn highlight_differences(text: &str, other: &str, width: usize) -> String {
+    let covered = overlaps(text, other, width);
+    let mut highlighted = String::with_capacity(text.len());
+    let mut marked = false;
+    for (c, covered) in text.chars().zip(covered) {
+        if marked == covered {
+            highlighted.push_str(if covered { "</mark>" } else { "<mark>" });
+            marked = !covered;
+        }
+        push_escaped(&mut highlighted, c);
+    }
+    if marked {
+        highlighted.push_str("</mark>");
+    }
+    highlighted
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    text.chars().for_each(|c| push_escaped(&mut escaped, c));
+    escaped
+}
+
+fn push_escaped(s: &mut String, c: char) {
+    match c {
+        '&' => s.push_str("&amp;"),
+        '<' => s.push_str("&lt;"),
+        '>' => s.push_str("&gt;"),
+        '"' => s.push_str("&quot;"),
+        '\'' => s.push_str("&#39;"),
+        _ => s.push(c),
+    }
+}
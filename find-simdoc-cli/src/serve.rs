@@ -0,0 +1,461 @@
+mod element_hasher;
+mod index_file;
+mod input;
+mod logging;
+mod memory;
+mod metric;
+mod progress;
+mod weight;
+
+use std::error::Error;
+use std::io::Read;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Instant;
+
+use clap::Parser;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use element_hasher::ElementHasherArg;
+use find_simdoc::{ContainmentSearcher, CosineSearcher, JaccardSearcher, WeightedJaccardSearcher};
+use index_file::{load_index, Searcher};
+use input::{DocumentReader, ErrorSlot, InputFormat, Sources};
+use logging::init_logger;
+use memory::{fit_num_chunks, ByteSize};
+use metric::Metric;
+use progress::progress_hook;
+use weight::{IdfWeights, TfWeights};
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "find-simdoc-serve",
+    about = "A program to serve a searcher of similar documents over HTTP."
+)]
+struct Args {
+    /// File paths to document files to be searched, or directories of them.
+    /// Multiple files are concatenated in the given order.
+    /// Empty lines are rejected with an error.
+    #[clap(
+        short = 'i',
+        long,
+        required_unless_present = "index-path",
+        conflicts_with = "index-path",
+        multiple_values = true
+    )]
+    document_path: Vec<PathBuf>,
+
+    /// File path to an index built by the index executable, which is served
+    /// instead of documents. The options of feature extraction and sketching are ignored.
+    #[clap(short = 'x', long)]
+    index_path: Option<PathBuf>,
+
+    /// Format of the document file.
+    /// "text" is a document per line.
+    /// "jsonl" is a JSON object per line, whose text is given by the text field.
+    #[clap(long, default_value = "text")]
+    input_format: InputFormat,
+
+    /// Field of texts in JSON objects.
+    #[clap(long, default_value = "text")]
+    text_field: String,
+
+    /// Field of document ids in JSON objects, which are returned along with the results.
+    #[clap(long)]
+    id_field: Option<String>,
+
+    /// Distance metric of the searcher.
+    /// "jaccard" is the Jaccard distance.
+    /// "cosine" is the Cosine distance.
+    /// "wjac" is the weighted Jaccard distance of tfidf-weighted features.
+    /// "containment" is one minus the containment |X∩Y|/|X| of a document X in Y,
+    /// where X is the left side (or the query) and Y is the right side of each result.
+    #[clap(short = 'M', long, default_value = "jaccard")]
+    metric: Metric,
+
+    /// Address to listen on.
+    #[clap(short = 'a', long, default_value = "127.0.0.1:8080")]
+    addr: String,
+
+    /// Number of worker threads handling requests concurrently (must be more than 0).
+    #[clap(long, default_value = "4")]
+    workers: usize,
+
+    /// Maximum size of the request body of /similar (e.g., 64K or 1M).
+    /// Larger requests are rejected with the status 413.
+    #[clap(long, default_value = "1M")]
+    max_body_size: ByteSize,
+
+    /// Maximum number of pairs returned by /pairs, which is also the default of its limit parameter.
+    #[clap(long, default_value = "10000")]
+    max_pairs: usize,
+
+    /// Delimiter for recognizing words as tokens in feature extraction.
+    /// If None, characters are used for tokens.
+    #[clap(short = 'd', long)]
+    delimiter: Option<char>,
+
+    /// Window size for w-shingling in feature extraction (must be more than 0).
+    #[clap(short = 'w', long, default_value = "1")]
+    window_size: usize,
+
+    /// Converts texts into lowercase before feature extraction.
+    #[clap(long)]
+    lowercase: bool,
+
+    /// Applies the NFKC normalization to texts before feature extraction,
+    /// which unifies full-width and half-width forms of characters, for example.
+    #[clap(long)]
+    nfkc: bool,
+
+    /// Removes punctuation characters from texts before feature extraction.
+    #[clap(long)]
+    strip_punct: bool,
+
+    /// Number of chunks in sketches, indicating that the number of dimensions in the Hamming space
+    /// will be 64*#chunks. The larger this value, the more accurate the approximation,
+    /// but the more time and memory it takes to search.
+    #[clap(short = 'c', long, default_value = "8")]
+    num_chunks: usize,
+
+    /// Memory budget for sketches and their join (e.g., 512M or 16G).
+    /// If given, #chunks is reduced to fit the budget, and
+    /// the program aborts if even a single chunk cannot fit.
+    #[clap(long)]
+    max_memory: Option<ByteSize>,
+
+    /// Weighting variant of term frequency for the Cosine and weighted Jaccard distances.
+    /// "binary" is the boolean frequency.
+    /// "standard" is the standard term frequency.
+    /// "sublinear" is the logarithmically scaled frequency.
+    #[clap(short = 'T', long, default_value = "standard")]
+    tf: TfWeights,
+
+    /// Weighting variant of inverse document frequency
+    /// for the Cosine and weighted Jaccard distances.
+    /// "unary" is always 1.
+    /// "standard" is the standard inverse document frequency.
+    /// "smooth" is the smoothed inverse document frequency.
+    #[clap(short = 'I', long, default_value = "smooth")]
+    idf: IdfWeights,
+
+    /// Hash function of elements in sketching for the Jaccard and Cosine distances.
+    /// "splitmix64" is the default element mixer.
+    /// "wymix" is a faster wyhash-style mixer generating different sketches.
+    #[clap(long, default_value = "splitmix64")]
+    element_hasher: ElementHasherArg,
+
+    /// Seed value for random values.
+    #[clap(short = 's', long)]
+    seed: Option<u64>,
+
+    /// Number of threads used in parallel processing.
+    /// If not given or 0, the number of threads is determined automatically.
+    #[clap(short = 't', long)]
+    threads: Option<usize>,
+
+    /// Increases the verbosity of logs to stderr (-v for debug and -vv for trace).
+    #[clap(short = 'v', long, parse(from_occurrences), conflicts_with = "quiet")]
+    verbose: u64,
+
+    /// Suppresses the progress and messages to stderr except warnings.
+    #[clap(short = 'q', long)]
+    quiet: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let quiet = args.quiet;
+    init_logger(args.verbose, quiet);
+
+    if args.workers == 0 {
+        return Err("The number of workers must be more than 0.".into());
+    }
+    let limits = Limits {
+        workers: args.workers,
+        max_body_size: args.max_body_size.0,
+        max_pairs: args.max_pairs,
+    };
+
+    if let Some(index_path) = &args.index_path {
+        tracing::info!("Loading the index...");
+        let start = Instant::now();
+        let index = load_index(index_path, args.threads, progress_hook(quiet))?;
+        tracing::info!("Done in {} sec", start.elapsed().as_secs_f64());
+        return serve(
+            &args.addr,
+            &limits,
+            &index.searcher,
+            index.ids.as_deref(),
+            index.sources.as_ref(),
+        );
+    }
+
+    let reader = DocumentReader::new(&args.document_path, args.input_format, &args.text_field)?;
+    let ids = match &args.id_field {
+        Some(id_field) => Some(reader.read_field(id_field)?),
+        None => None,
+    };
+
+    let num_chunks = match args.max_memory {
+        Some(ByteSize(max_memory)) => {
+            let num_docs = reader.num_documents()?;
+            fit_num_chunks::<u64>(num_docs, args.num_chunks, max_memory)?
+        }
+        None => args.num_chunks,
+    };
+
+    tracing::info!("Converting documents into sketches...");
+    let start = Instant::now();
+    let errors = ErrorSlot::default();
+    let documents = errors.until_error(reader.texts_iter());
+    let searcher = match args.metric {
+        Metric::Jaccard => {
+            let mut searcher = JaccardSearcher::new(args.window_size, args.delimiter, args.seed)?
+                .lowercase(args.lowercase)
+                .normalize_nfkc(args.nfkc)
+                .strip_punctuation(args.strip_punct)
+                .element_hasher(args.element_hasher.build())
+                .progress_hook(progress_hook(quiet));
+            if let Some(threads) = args.threads {
+                searcher = searcher.num_threads(threads)?;
+            }
+            Searcher::Jaccard(searcher.build_sketches_in_parallel(documents, num_chunks)?)
+        }
+        Metric::Cosine => {
+            let mut searcher = CosineSearcher::new(args.window_size, args.delimiter, args.seed)?
+                .lowercase(args.lowercase)
+                .normalize_nfkc(args.nfkc)
+                .strip_punctuation(args.strip_punct)
+                .element_hasher(args.element_hasher.build())
+                .progress_hook(progress_hook(quiet));
+            if let Some(threads) = args.threads {
+                searcher = searcher.num_threads(threads)?;
+            }
+            let tf = args.tf.build();
+            let idf = args
+                .idf
+                .build(errors.until_error(reader.texts_iter()), searcher.config())?;
+            searcher = searcher.tf(tf).idf(idf);
+            Searcher::Cosine(searcher.build_sketches_in_parallel(documents, num_chunks)?)
+        }
+        Metric::WeightedJaccard => {
+            let mut searcher =
+                WeightedJaccardSearcher::new(args.window_size, args.delimiter, args.seed)?
+                    .lowercase(args.lowercase)
+                    .normalize_nfkc(args.nfkc)
+                    .strip_punctuation(args.strip_punct)
+                    .progress_hook(progress_hook(quiet));
+            if let Some(threads) = args.threads {
+                searcher = searcher.num_threads(threads)?;
+            }
+            let tf = args.tf.build();
+            let idf = args
+                .idf
+                .build(errors.until_error(reader.texts_iter()), searcher.config())?;
+            searcher = searcher.tf(tf).idf(idf);
+            Searcher::WeightedJaccard(searcher.build_sketches_in_parallel(documents, num_chunks)?)
+        }
+        Metric::Containment => {
+            let mut searcher =
+                ContainmentSearcher::new(args.window_size, args.delimiter, args.seed)?
+                    .lowercase(args.lowercase)
+                    .normalize_nfkc(args.nfkc)
+                    .strip_punctuation(args.strip_punct)
+                    .progress_hook(progress_hook(quiet));
+            if let Some(threads) = args.threads {
+                searcher = searcher.num_threads(threads)?;
+            }
+            Searcher::Containment(searcher.build_sketches_in_parallel(documents, num_chunks)?)
+        }
+    };
+    errors.check()?;
+    tracing::info!("Done in {} sec", start.elapsed().as_secs_f64());
+
+    serve(
+        &args.addr,
+        &limits,
+        &searcher,
+        ids.as_deref(),
+        reader.sources()?.as_ref(),
+    )
+}
+
+/// Limits of the server to bound the threads and memory consumed by requests.
+struct Limits {
+    workers: usize,
+    max_body_size: usize,
+    max_pairs: usize,
+}
+
+/// Serves the searcher on the address with a pool of worker threads
+/// until the process is terminated.
+fn serve(
+    addr: &str,
+    limits: &Limits,
+    searcher: &Searcher,
+    ids: Option<&[String]>,
+    sources: Option<&Sources>,
+) -> Result<(), Box<dyn Error>> {
+    let server = Server::http(addr).map_err(|e| e.to_string())?;
+    tracing::info!("Listening on http://{addr} with {} workers", limits.workers);
+    thread::scope(|s| {
+        for _ in 0..limits.workers {
+            s.spawn(|| loop {
+                match server.recv() {
+                    Ok(request) => handle(request, limits, searcher, ids, sources),
+                    Err(e) => {
+                        tracing::warn!("Failed to receive a request: {e}");
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+/// Handles a request to the endpoints.
+///
+/// - `POST /similar?radius=R` searches for documents similar to the text of the request body.
+/// - `GET /pairs?radius=R&limit=N` searches for all pairs of similar documents, returning at most
+///   `N` pairs in the ascending order of distances. `truncated` is set if more pairs are found.
+fn handle(
+    mut request: Request,
+    limits: &Limits,
+    searcher: &Searcher,
+    ids: Option<&[String]>,
+    sources: Option<&Sources>,
+) {
+    let (path, query) = split_url(request.url());
+    let radius = parse_radius(query);
+    let result = match (request.method(), path) {
+        (Method::Post, "/similar") => radius.and_then(|radius| {
+            let query = read_body(&mut request, limits.max_body_size)?;
+            let results = searcher
+                .search_similar_documents(&query, radius)
+                .map_err(|e| (400, e.to_string()))?;
+            let results: Vec<_> = results
+                .into_iter()
+                .map(|(i, dist)| {
+                    let mut obj = json!({"i": i, "dist": dist});
+                    if let Some(ids) = ids {
+                        obj["id"] = Value::from(ids[i].as_str());
+                    }
+                    if let Some(sources) = sources {
+                        let (source, line) = sources.locate(i);
+                        obj["source"] = Value::from(source);
+                        obj["line"] = Value::from(line);
+                    }
+                    obj
+                })
+                .collect();
+            Ok(json!({ "results": results }))
+        }),
+        (Method::Get, "/pairs") => radius.and_then(|radius| {
+            let limit = parse_limit(query, limits.max_pairs)?;
+            let mut pairs = match searcher {
+                Searcher::Jaccard(searcher) => searcher.search_similar_pairs(radius),
+                Searcher::Cosine(searcher) => searcher.search_similar_pairs(radius),
+                Searcher::WeightedJaccard(searcher) => searcher.search_similar_pairs(radius),
+                Searcher::Containment(searcher) => searcher.search_similar_pairs(radius),
+            };
+            pairs.sort_by(|a, b| a.2.total_cmp(&b.2));
+            let truncated = pairs.len() > limit;
+            pairs.truncate(limit);
+            let pairs: Vec<_> = pairs
+                .into_iter()
+                .map(|(i, j, dist)| {
+                    let mut obj = json!({"i": i, "j": j, "dist": dist});
+                    if let Some(ids) = ids {
+                        obj["id_i"] = Value::from(ids[i].as_str());
+                        obj["id_j"] = Value::from(ids[j].as_str());
+                    }
+                    if let Some(sources) = sources {
+                        let (source_i, line_i) = sources.locate(i);
+                        let (source_j, line_j) = sources.locate(j);
+                        obj["source_i"] = Value::from(source_i);
+                        obj["line_i"] = Value::from(line_i);
+                        obj["source_j"] = Value::from(source_j);
+                        obj["line_j"] = Value::from(line_j);
+                    }
+                    obj
+                })
+                .collect();
+            Ok(json!({ "pairs": pairs, "truncated": truncated }))
+        }),
+        _ => Err((404, "Not found".to_string())),
+    };
+    let (status, body) = match result {
+        Ok(body) => (200, body),
+        Err((status, msg)) => (status, json!({ "error": msg })),
+    };
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header);
+    if let Err(e) = request.respond(response) {
+        tracing::warn!("Failed to respond: {e}");
+    }
+}
+
+/// Reads the request body as a string of at most `max_size` bytes.
+fn read_body(request: &mut Request, max_size: usize) -> Result<String, (u16, String)> {
+    let too_large = || {
+        (
+            413,
+            format!("The request body must be at most {max_size} bytes."),
+        )
+    };
+    if request.body_length().is_some_and(|len| len > max_size) {
+        return Err(too_large());
+    }
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(max_size as u64 + 1)
+        .read_to_string(&mut body)
+        .map_err(|e| (400, e.to_string()))?;
+    if body.len() > max_size {
+        return Err(too_large());
+    }
+    Ok(body)
+}
+
+/// Splits a URL into the path and the query string.
+fn split_url(url: &str) -> (&str, &str) {
+    url.split_once('?').unwrap_or((url, ""))
+}
+
+/// Gets the value of a parameter in the query string.
+fn param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .find_map(|param| param.strip_prefix(name)?.strip_prefix('='))
+}
+
+/// Parses the required radius in the query string.
+fn parse_radius(query: &str) -> Result<f64, (u16, String)> {
+    param(query, "radius")
+        .ok_or_else(|| (400, "The parameter radius is required.".to_string()))
+        .and_then(|radius| {
+            radius
+                .parse::<f64>()
+                .ok()
+                .filter(|r| (0. ..=1.).contains(r))
+                .ok_or_else(|| (400, "The radius must be a number in [0,1].".to_string()))
+        })
+}
+
+/// Parses the optional limit in the query string, which defaults to and must not exceed `max`.
+fn parse_limit(query: &str, max: usize) -> Result<usize, (u16, String)> {
+    param(query, "limit").map_or(Ok(max), |limit| {
+        limit
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n <= max)
+            .ok_or_else(|| (400, format!("The limit must be an integer in [0,{max}].")))
+    })
+}
@@ -0,0 +1,365 @@
+#![allow(clippy::mutex_atomic)]
+
+mod logging;
+mod weight;
+
+use std::env;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::mem;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use all_pairs_hamming::sketch::Sketch;
+use clap::Parser;
+use find_simdoc::feature::{FeatureConfig, FeatureExtractor};
+use find_simdoc::lsh::simhash::SimHasher;
+use find_simdoc::tfidf::TermWeighter;
+use hashbrown::HashSet;
+use logging::init_logger;
+use positioned_io::WriteAt;
+use rand::{RngCore, SeedableRng};
+use rayon::prelude::*;
+use weight::{IdfWeights, TfWeights};
+
+const MAX_CHUNKS: usize = 100;
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "find-simdoc-simhash_acc",
+    about = "A program to test accuracy in simplified simhash."
+)]
+struct Args {
+    /// File path to a document file to be searched.
+    /// Empty lines are rejected with an error.
+    #[clap(short = 'i', long)]
+    document_path: PathBuf,
+
+    /// Delimiter for recognizing words as tokens in feature extraction.
+    /// If None, characters are used for tokens.
+    #[clap(short = 'd', long)]
+    delimiter: Option<char>,
+
+    /// Window size for w-shingling in feature extraction (must to be more than 0).
+    #[clap(short = 'w', long, default_value = "1")]
+    window_size: usize,
+
+    /// Converts texts into lowercase before feature extraction.
+    #[clap(long)]
+    lowercase: bool,
+
+    /// Applies the NFKC normalization to texts before feature extraction,
+    /// which unifies full-width and half-width forms of characters, for example.
+    #[clap(long)]
+    nfkc: bool,
+
+    /// Removes punctuation characters from texts before feature extraction.
+    #[clap(long)]
+    strip_punct: bool,
+
+    /// Weighting variant of term frequency.
+    /// "binary" is the boolean value.
+    /// "standard" is the raw count.
+    /// "sublinear" is the logarithmically scaled frequency.
+    #[clap(short = 'T', long, default_value = "standard")]
+    tf: TfWeights,
+
+    /// Weighting variant of inverse document frequency.
+    /// "unary" is always 1.
+    /// "standard" is the standard idf.
+    /// "smooth" is the smoothed idf.
+    #[clap(short = 'I', long, default_value = "smooth")]
+    idf: IdfWeights,
+
+    /// Seed value for random values.
+    #[clap(short = 's', long)]
+    seed: Option<u64>,
+
+    /// Directory path to write a tmp file.
+    #[clap(short = 't', long)]
+    tmp_dir: Option<PathBuf>,
+
+    /// Increases the verbosity of logs to stderr (-v for debug and -vv for trace).
+    #[clap(short = 'v', long, parse(from_occurrences), conflicts_with = "quiet")]
+    verbose: u64,
+
+    /// Suppresses the progress and messages to stderr except warnings.
+    #[clap(short = 'q', long)]
+    quiet: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    init_logger(args.verbose, args.quiet);
+
+    let document_path = args.document_path;
+    let delimiter = args.delimiter;
+    let window_size = args.window_size;
+    let seed = args.seed;
+    let tmp_dir = args.tmp_dir;
+
+    if window_size == 0 {
+        return Err("window_size must not be 0.".into());
+    }
+
+    let documents: Vec<_> = BufReader::new(File::open(document_path)?)
+        .lines()
+        .map(|line| line.unwrap())
+        .collect();
+
+    let mut seeder =
+        rand_xoshiro::SplitMix64::seed_from_u64(seed.unwrap_or_else(rand::random::<u64>));
+
+    let config = FeatureConfig::new(window_size, delimiter, seeder.next_u64())?
+        .lowercase(args.lowercase)
+        .normalize_nfkc(args.nfkc)
+        .strip_punctuation(args.strip_punct);
+    let extractor = FeatureExtractor::new(&config);
+
+    let tf = args.tf.build();
+    let idf = {
+        let start = Instant::now();
+        let idf = args.idf.build(documents.iter(), &config)?;
+        if idf.is_some() {
+            tracing::info!("Produced IDF in {} sec", start.elapsed().as_secs_f64());
+        }
+        idf
+    };
+
+    let features = {
+        tracing::info!("Loading documents and extracting features...");
+        let start = Instant::now();
+        let mut features = vec![];
+        for document in documents {
+            if document.is_empty() {
+                return Err("Input document must not be empty.".into());
+            }
+            let mut feature = vec![];
+            extractor.extract_with_weights(document, &mut feature);
+            if let Some(tf) = tf.as_ref() {
+                tf.weight(&mut feature);
+            }
+            if let Some(idf) = idf.as_ref() {
+                idf.weight(&mut feature);
+            }
+            features.push(feature);
+        }
+        let duration = start.elapsed();
+        let total_bytes =
+            features.iter().fold(0, |acc, f| acc + f.len()) * std::mem::size_of::<(u64, f64)>();
+        tracing::info!(
+            "Extracted {} features in {} sec, consuming {} MiB",
+            features.len(),
+            duration.as_secs_f64(),
+            total_bytes as f64 / (1024. * 1024.)
+        );
+        features
+    };
+
+    let sketches = {
+        tracing::info!("Producing binary sketches...");
+        let start = Instant::now();
+        let hasher = SimHasher::new(seeder.next_u64());
+
+        let processed = Mutex::new(0usize);
+
+        let mut sketches = vec![vec![]; features.len()];
+        features
+            .par_iter()
+            .map(|feature| {
+                {
+                    // Mutex::lock also serializes the messages.
+                    let mut cnt = processed.lock().unwrap();
+                    *cnt += 1;
+                    if cnt.is_multiple_of(1000) {
+                        tracing::info!("Processed {} features...", *cnt);
+                    }
+                }
+                hasher.sketch(feature, MAX_CHUNKS)
+            })
+            .collect_into_vec(&mut sketches);
+
+        let duration = start.elapsed();
+        let total_bytes = sketches.len() * MAX_CHUNKS * std::mem::size_of::<u64>();
+        tracing::info!(
+            "Produced in {} sec, consuming {} MiB",
+            duration.as_secs_f64(),
+            total_bytes as f64 / (1024. * 1024.)
+        );
+        sketches
+    };
+
+    let tmp_path = {
+        let mut tmp_path = tmp_dir.unwrap_or_else(env::temp_dir);
+        tmp_path.push("tmp.ang_dist");
+        tmp_path
+    };
+
+    let possible_pairs = {
+        let start = Instant::now();
+
+        let possible_pairs = features.len() * (features.len() - 1) / 2;
+        tracing::info!("Computing exact angular distances for {possible_pairs} pairs...");
+
+        let tmp_file_size = possible_pairs * mem::size_of::<f64>();
+        let offsets = {
+            let mut offset = 0;
+            let mut offsets = Vec::with_capacity(features.len());
+            for i in 0..features.len() {
+                offsets.push(offset);
+                offset += features.len() - i - 1;
+            }
+            assert_eq!(offset, possible_pairs);
+            offsets
+        };
+
+        {
+            let processed = Mutex::new(0usize);
+            let writer = Mutex::new(File::create(&tmp_path)?);
+
+            // Creates a file object of size tmp_file_size bytes.
+            {
+                let mut w = writer.lock().unwrap();
+                w.write_at(tmp_file_size as u64 - 1, &[0])?;
+            }
+
+            tracing::info!(
+                "Created a tmp file of {} GiB, at {:?}",
+                tmp_file_size as f64 / (1024. * 1024. * 1024.),
+                &tmp_path
+            );
+
+            (0..features.len()).into_par_iter().for_each(|i| {
+                {
+                    // Mutex::lock also serializes the messages.
+                    let mut cnt = processed.lock().unwrap();
+                    *cnt += 1;
+                    if cnt.is_multiple_of(1000) {
+                        tracing::info!("Processed {} features...", *cnt);
+                    }
+                }
+
+                let mut ang_dists =
+                    Vec::with_capacity((features.len() - i) * mem::size_of::<f64>());
+
+                let x = &features[i];
+                for y in features.iter().skip(i + 1) {
+                    let dist =
+                        find_simdoc::exact::angular_distance(x.iter().copied(), y.iter().copied());
+                    ang_dists.extend_from_slice(&dist.to_le_bytes());
+                }
+
+                // Writes distances with random access on a file stream.
+                let offset = offsets[i] * mem::size_of::<f64>();
+                {
+                    let mut w = writer.lock().unwrap();
+                    w.write_at(offset as u64, &ang_dists).unwrap();
+                }
+            });
+        }
+
+        let duration = start.elapsed();
+        tracing::info!("Computed in {} sec", duration.as_secs_f64());
+        possible_pairs
+    };
+
+    let radii = vec![0.01, 0.02, 0.05, 0.1, 0.2, 0.5];
+    let mut header = "num_chunks,dimensions,mean_absolute_error".to_string();
+    for &r in &radii {
+        write!(header, ",results_{r}")?;
+        write!(header, ",precision_{r}")?;
+        write!(header, ",recall_{r}")?;
+        write!(header, ",f1_{r}")?;
+    }
+    println!("{header}");
+
+    tracing::info!("Computing accuracy...");
+    let start = Instant::now();
+
+    let results = {
+        let processed = Mutex::new(0usize);
+        let mut results: Vec<_> = (1..=MAX_CHUNKS)
+            .into_par_iter()
+            .map(|num_chunks| {
+                {
+                    // Mutex::lock also serializes the messages.
+                    let mut cnt = processed.lock().unwrap();
+                    *cnt += 1;
+                    if cnt.is_multiple_of(10) {
+                        tracing::info!("Processed {} chunks...", *cnt);
+                    }
+                }
+
+                let mut sum_error = 0.;
+                let mut true_results: Vec<_> = (0..radii.len()).map(|_| HashSet::new()).collect();
+                let mut appx_results: Vec<_> = (0..radii.len()).map(|_| HashSet::new()).collect();
+
+                let mut reader = BufReader::new(File::open(&tmp_path).unwrap());
+
+                for i in 0..sketches.len() {
+                    let x = &sketches[i];
+                    for (j, y) in sketches.iter().enumerate().skip(i + 1) {
+                        let mut buf = [0; mem::size_of::<f64>()];
+                        reader.read_exact(&mut buf).unwrap();
+
+                        let ang_dist = f64::from_le_bytes(buf);
+                        let ham_dist = hamming_distance(&x[..num_chunks], &y[..num_chunks]);
+                        sum_error += (ang_dist - ham_dist).abs();
+
+                        for (k, &r) in radii.iter().enumerate() {
+                            if ang_dist <= r {
+                                true_results[k].insert((i, j));
+                            }
+                            if ham_dist <= r {
+                                appx_results[k].insert((i, j));
+                            }
+                        }
+                    }
+                }
+
+                let dim = num_chunks * 64;
+                let mae = sum_error / possible_pairs as f64;
+
+                let mut prf = vec![];
+                for (tr, ar) in true_results.iter().zip(appx_results.iter()) {
+                    let true_positive = tr.intersection(ar).count() as f64;
+                    let false_positive = ar.len() as f64 - true_positive;
+                    let false_negative = tr.len() as f64 - true_positive;
+                    let precision = true_positive / (true_positive + false_positive);
+                    let recall = true_positive / (true_positive + false_negative);
+                    let f1 = (2. * precision * recall) / (precision + recall);
+                    prf.push((tr.len(), precision, recall, f1));
+                }
+
+                let mut body = format!("{num_chunks},{dim},{mae}");
+                for (t, p, r, f) in prf {
+                    write!(body, ",{t},{p},{r},{f}").unwrap();
+                }
+                (num_chunks, body)
+            })
+            .collect();
+        results.sort_by_key(|r| r.0);
+        results
+    };
+    let duration = start.elapsed();
+    tracing::info!("Computed in {} sec", duration.as_secs_f64());
+
+    for (_, body) in results {
+        println!("{body}");
+    }
+
+    Ok(())
+}
+
+fn hamming_distance(xs: &[u64], ys: &[u64]) -> f64 {
+    assert_eq!(xs.len(), ys.len());
+    let mut dist = 0;
+    for (&x, &y) in xs.iter().zip(ys.iter()) {
+        dist += x.hamdist(y);
+    }
+    // In simhash, the collision probability is one minus the angle divided by π.
+    dist as f64 / (xs.len() * 64) as f64
+}
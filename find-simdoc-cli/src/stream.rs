@@ -0,0 +1,160 @@
+mod logging;
+
+use std::error::Error;
+use std::io::{BufRead, BufWriter, Write};
+use std::str::FromStr;
+use std::time::Instant;
+
+use clap::Parser;
+
+use find_simdoc::stream::StreamDeduplicator;
+use logging::init_logger;
+
+/// Number of documents with an optional suffix of K, M, or G in powers of 1000.
+#[derive(Clone, Copy, Debug)]
+struct Count(usize);
+
+impl FromStr for Count {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (digits, scale) = match s.chars().last().map(|c| c.to_ascii_uppercase()) {
+            Some('K') => (&s[..s.len() - 1], 1e3),
+            Some('M') => (&s[..s.len() - 1], 1e6),
+            Some('G') => (&s[..s.len() - 1], 1e9),
+            _ => (s, 1.),
+        };
+        let n = digits
+            .parse::<f64>()
+            .map_err(|_| "Could not parse a number of documents")?;
+        if !n.is_finite() || n < 1. {
+            return Err("Number of documents must be positive");
+        }
+        Ok(Self((n * scale) as usize))
+    }
+}
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "find-simdoc-stream",
+    about = "A program to filter out lines near-duplicate to recent ones from stdin in the Jaccard space."
+)]
+struct Args {
+    /// Search radius in the range of [0,1].
+    #[clap(short = 'r', long)]
+    radius: f64,
+
+    /// Number of the last distinct lines kept in the rolling window (e.g., 100K or 1M),
+    /// against which each line is compared. The memory usage is bounded by
+    /// 8*#chunks bytes per line in the window.
+    #[clap(long, default_value = "1M")]
+    window: Count,
+
+    /// Delimiter for recognizing words as tokens in feature extraction.
+    /// If None, characters are used for tokens.
+    #[clap(short = 'd', long)]
+    delimiter: Option<char>,
+
+    /// Regular expression for recognizing words as tokens in feature extraction,
+    /// used instead of the delimiter character (e.g., "[\s\p{P}]+").
+    #[clap(long, conflicts_with = "delimiter")]
+    delimiter_regex: Option<String>,
+
+    /// Window size for w-shingling in feature extraction (must be more than 0).
+    #[clap(short = 'w', long, default_value = "1")]
+    window_size: usize,
+
+    /// Maximum window size for w-shingling, with which features combine shingles
+    /// of all the window sizes from window_size to max_window_size.
+    #[clap(long)]
+    max_window_size: Option<usize>,
+
+    /// Converts texts into lowercase before feature extraction.
+    #[clap(long)]
+    lowercase: bool,
+
+    /// Applies the NFKC normalization to texts before feature extraction,
+    /// which unifies full-width and half-width forms of characters, for example.
+    #[clap(long)]
+    nfkc: bool,
+
+    /// Removes punctuation characters from texts before feature extraction.
+    #[clap(long)]
+    strip_punct: bool,
+
+    /// Number of chunks in sketches, indicating that the number of dimensions in the Hamming space
+    /// will be 64*#chunks. The larger this value, the more accurate the approximation,
+    /// but the more time and memory it takes to search.
+    #[clap(short = 'c', long, default_value = "8")]
+    num_chunks: usize,
+
+    /// Seed value for random values.
+    #[clap(short = 's', long)]
+    seed: Option<u64>,
+
+    /// Number of threads used in parallel processing.
+    /// If not given or 0, the number of threads is determined automatically.
+    #[clap(short = 't', long)]
+    threads: Option<usize>,
+
+    /// Increases the verbosity of logs to stderr (-v for debug and -vv for trace).
+    #[clap(short = 'v', long, parse(from_occurrences), conflicts_with = "quiet")]
+    verbose: u64,
+
+    /// Suppresses the progress and messages to stderr except warnings.
+    #[clap(short = 'q', long)]
+    quiet: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    init_logger(args.verbose, args.quiet);
+
+    if !(0. ..=1.).contains(&args.radius) {
+        return Err("radius must be in [0,1].".into());
+    }
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+
+    let window_size = args.window_size;
+    let max_window_size = args.max_window_size.unwrap_or(window_size);
+    let mut dedup = StreamDeduplicator::new(window_size, args.delimiter, args.seed)?
+        .lowercase(args.lowercase)
+        .normalize_nfkc(args.nfkc)
+        .strip_punctuation(args.strip_punct)
+        .delimiter_regex(args.delimiter_regex.as_deref())?
+        .window_range(window_size..=max_window_size)?
+        .num_chunks(args.num_chunks)?
+        .capacity(args.window.0)?;
+    tracing::info!(
+        "Keeping up to {} lines in the window ({} bytes)",
+        args.window.0,
+        dedup.memory_in_bytes()
+    );
+
+    let start = Instant::now();
+    let (mut num_lines, mut num_kept) = (0, 0);
+    let stdin = std::io::stdin();
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    for line in stdin.lock().lines() {
+        let line = line?;
+        num_lines += 1;
+        if dedup.insert(&line, args.radius)? {
+            num_kept += 1;
+            writeln!(out, "{line}")?;
+        }
+        if num_lines % 100_000 == 0 {
+            tracing::debug!("Processed {num_lines} lines, kept {num_kept} lines");
+        }
+    }
+    out.flush()?;
+    tracing::info!(
+        "Kept {num_kept} of {num_lines} lines in {} sec",
+        start.elapsed().as_secs_f64()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use std::error::Error;
+
+use find_simdoc::exact::{angular_distance, jaccard_distance, similar_pairs};
+use find_simdoc::feature::FeatureExtractor;
+use find_simdoc::tfidf::TermWeighter;
+use rand::SeedableRng;
+use rayon::prelude::*;
+
+use crate::input::DocumentReader;
+
+/// Samples `sample_size` documents uniformly at random, returning their line numbers
+/// in ascending order and their texts. All the documents are returned if `sample_size`
+/// is not given or not less than the number of documents.
+pub fn sample_documents(
+    reader: &DocumentReader,
+    sample_size: Option<usize>,
+    seed: u64,
+) -> Result<(Vec<usize>, Vec<String>), Box<dyn Error>> {
+    let num_docs = reader.num_documents()?;
+    let doc_ids: Vec<_> = match sample_size {
+        Some(sample_size) if sample_size < num_docs => {
+            let mut rng = rand_xoshiro::SplitMix64::seed_from_u64(seed);
+            let mut doc_ids = rand::seq::index::sample(&mut rng, num_docs, sample_size).into_vec();
+            doc_ids.sort_unstable();
+            tracing::info!("Sampled {sample_size} of {num_docs} documents");
+            doc_ids
+        }
+        _ => (0..num_docs).collect(),
+    };
+    let texts = {
+        let mut sampled = doc_ids.iter().peekable();
+        reader
+            .texts_iter()
+            .enumerate()
+            .filter(|&(i, _)| sampled.next_if_eq(&&i).is_some())
+            .map(|(_, text)| text)
+            .collect::<Result<_, _>>()?
+    };
+    Ok((doc_ids, texts))
+}
+
+/// Finds all pairs of documents within the exact Jaccard distance of `radius`,
+/// as the ground truth of the jaccard program.
+pub fn jaccard_pairs(
+    extractor: &FeatureExtractor,
+    texts: &[String],
+    radius: f64,
+) -> Vec<(usize, usize, f64)> {
+    let features = extractor.extract_batch_parallel(texts);
+    similar_pairs(&features, radius, |x, y| {
+        jaccard_distance(x.iter(), y.iter())
+    })
+}
+
+/// Finds all pairs of documents within the exact angular distance of `radius`
+/// between the weighted features, as the ground truth of the cosine program.
+pub fn cosine_pairs(
+    extractor: &FeatureExtractor,
+    texts: &[String],
+    tf: Option<&dyn TermWeighter>,
+    idf: Option<&dyn TermWeighter>,
+    radius: f64,
+) -> Vec<(usize, usize, f64)> {
+    let features: Vec<_> = texts
+        .par_iter()
+        .map(|text| {
+            let mut feature = vec![];
+            extractor.extract_with_weights(text, &mut feature);
+            if let Some(tf) = tf {
+                tf.weight(&mut feature);
+            }
+            if let Some(idf) = idf {
+                idf.weight(&mut feature);
+            }
+            feature
+        })
+        .collect();
+    similar_pairs(&features, radius, |x, y| {
+        angular_distance(x.iter().copied(), y.iter().copied())
+    })
+}
@@ -0,0 +1,97 @@
+use std::fmt;
+use std::str::FromStr;
+
+use find_simdoc::errors::FindSimdocError;
+use find_simdoc::feature::FeatureConfig;
+use find_simdoc::tfidf::{Idf, Tf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TfWeights {
+    Binary,
+    Standard,
+    Sublinear,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdfWeights {
+    Unary,
+    Standard,
+    Smooth,
+}
+
+impl TfWeights {
+    /// Creates the TF weighting, or returns `None` for the binary one.
+    pub fn build(self) -> Option<Tf> {
+        match self {
+            Self::Binary => None,
+            Self::Standard | Self::Sublinear => Some(Tf::new().sublinear(self == Self::Sublinear)),
+        }
+    }
+}
+
+impl IdfWeights {
+    /// Builds the IDF weighting from documents, or returns `None` for the unary one
+    /// without consuming the documents.
+    pub fn build<I, D>(
+        self,
+        documents: I,
+        config: &FeatureConfig,
+    ) -> Result<Option<Idf<u64>>, FindSimdocError>
+    where
+        I: IntoIterator<Item = D>,
+        D: AsRef<str>,
+    {
+        match self {
+            Self::Unary => Ok(None),
+            Self::Standard | Self::Smooth => Ok(Some(
+                Idf::new()
+                    .smooth(self == Self::Smooth)
+                    .build(documents, config)?,
+            )),
+        }
+    }
+}
+
+impl FromStr for TfWeights {
+    type Err = &'static str;
+    fn from_str(w: &str) -> Result<Self, Self::Err> {
+        match w {
+            "binary" => Ok(Self::Binary),
+            "standard" => Ok(Self::Standard),
+            "sublinear" => Ok(Self::Sublinear),
+            _ => Err("Could not parse a tf-weighting value"),
+        }
+    }
+}
+
+impl FromStr for IdfWeights {
+    type Err = &'static str;
+    fn from_str(w: &str) -> Result<Self, Self::Err> {
+        match w {
+            "unary" => Ok(Self::Unary),
+            "standard" => Ok(Self::Standard),
+            "smooth" => Ok(Self::Smooth),
+            _ => Err("Could not parse a idf-weighting value"),
+        }
+    }
+}
+
+impl fmt::Display for TfWeights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Binary => write!(f, "binary"),
+            Self::Standard => write!(f, "standard"),
+            Self::Sublinear => write!(f, "sublinear"),
+        }
+    }
+}
+
+impl fmt::Display for IdfWeights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unary => write!(f, "unary"),
+            Self::Standard => write!(f, "standard"),
+            Self::Smooth => write!(f, "smooth"),
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! Node.js bindings of find-simdoc.
+//!
+//! The searchers are saved into the same index file format as the `index` executable,
+//! so indexes can be shared between Node.js, Python, and the CLI.
+//! The methods run synchronously on the calling thread, while building and searching
+//! are parallelized with rayon internally.
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+
+use find_simdoc::index_format::IndexFile;
+use find_simdoc::index_settings::IndexSettings;
+use find_simdoc::tfidf::Idf;
+use find_simdoc::{CosineSearcher, JaccardSearcher};
+use napi::bindgen_prelude::{BigInt, Float64Array, Uint32Array};
+use napi::{Error, Result, Status};
+use napi_derive::napi;
+
+fn invalid_arg<E: Display>(e: E) -> Error {
+    Error::new(Status::InvalidArg, e.to_string())
+}
+
+fn not_built() -> Error {
+    Error::new(Status::GenericFailure, "Sketches must be built.")
+}
+
+/// Options of feature extraction.
+#[napi(object)]
+#[derive(Default)]
+pub struct SearcherOptions {
+    /// Delimiter for recognizing words as tokens. If not given, characters are used for tokens.
+    pub delimiter: Option<String>,
+    /// Seed value for random values. If not given, a random one is used.
+    pub seed: Option<BigInt>,
+    /// Maximum window size for w-shingling, combining shingles of all the window sizes.
+    pub max_window_size: Option<u32>,
+    /// Regular expression for recognizing words as tokens, used instead of the delimiter.
+    pub delimiter_regex: Option<String>,
+    /// Converts texts into lowercase.
+    pub lowercase: Option<bool>,
+    /// Applies the NFKC normalization to texts.
+    pub nfkc: Option<bool>,
+    /// Removes punctuation characters from texts.
+    pub strip_punct: Option<bool>,
+}
+
+/// Weighting of features in the Cosine space.
+#[napi(object)]
+#[derive(Default)]
+pub struct Weighting {
+    /// TF weighting: `binary`, `standard` (default), or `sublinear`.
+    pub tf: Option<String>,
+    /// IDF weighting: `unary`, `standard`, or `smooth` (default).
+    pub idf: Option<String>,
+}
+
+/// Arrays of the left ids, right ids, and distances of similar pairs.
+#[napi(object)]
+pub struct SimilarPairs {
+    /// Left-side ids.
+    pub i: Uint32Array,
+    /// Right-side ids.
+    pub j: Uint32Array,
+    /// Distances.
+    pub dist: Float64Array,
+}
+
+/// Arrays of the ids and distances of similar documents.
+#[napi(object)]
+pub struct SimilarDocuments {
+    /// Document ids.
+    pub ids: Uint32Array,
+    /// Distances.
+    pub dists: Float64Array,
+}
+
+/// Creates the settings of feature extraction, which are written in the header of index files.
+fn settings(
+    metric: &str,
+    window_size: Option<u32>,
+    options: Option<SearcherOptions>,
+) -> Result<IndexSettings> {
+    let options = options.unwrap_or_default();
+    let window_size = window_size.unwrap_or(1) as usize;
+    let seed = match options.seed {
+        Some(seed) => match seed.get_u64() {
+            (false, seed, true) => seed,
+            _ => return Err(invalid_arg("seed must be a 64-bit unsigned integer.")),
+        },
+        // The seed is fixed in advance to write it in the header of index files.
+        None => rand::random::<u64>(),
+    };
+    let mut settings = IndexSettings::new(metric, window_size, seed);
+    settings.max_window_size = options
+        .max_window_size
+        .map_or(window_size, |size| size as usize);
+    settings.delimiter = options.delimiter.and_then(|s| s.chars().next());
+    settings.delimiter_regex = options.delimiter_regex;
+    settings.lowercase = options.lowercase.unwrap_or(false);
+    settings.nfkc = options.nfkc.unwrap_or(false);
+    settings.strip_punct = options.strip_punct.unwrap_or(false);
+    Ok(settings)
+}
+
+fn similar_pairs(results: Vec<(usize, usize, f64)>) -> SimilarPairs {
+    let mut is = Vec::with_capacity(results.len());
+    let mut js = Vec::with_capacity(results.len());
+    let mut dists = Vec::with_capacity(results.len());
+    for (i, j, dist) in results {
+        is.push(i as u32);
+        js.push(j as u32);
+        dists.push(dist);
+    }
+    SimilarPairs {
+        i: is.into(),
+        j: js.into(),
+        dist: dists.into(),
+    }
+}
+
+fn similar_documents(results: Vec<(usize, f64)>) -> SimilarDocuments {
+    let (ids, dists): (Vec<_>, Vec<_>) = results.into_iter().map(|(i, d)| (i as u32, d)).unzip();
+    SimilarDocuments {
+        ids: ids.into(),
+        dists: dists.into(),
+    }
+}
+
+fn save_index(path: &str, settings: &IndexSettings, mut file: IndexFile) -> Result<()> {
+    file.set_header(settings.header()).map_err(invalid_arg)?;
+    let mut wtr = BufWriter::new(File::create(path)?);
+    file.serialize_into(&mut wtr).map_err(invalid_arg)?;
+    wtr.flush()?;
+    Ok(())
+}
+
+fn load_index(path: &str, metric: &str) -> Result<(IndexSettings, IndexFile)> {
+    let file =
+        IndexFile::deserialize_from(BufReader::new(File::open(path)?)).map_err(invalid_arg)?;
+    let settings = IndexSettings::from_index_file(&file).map_err(invalid_arg)?;
+    if settings.metric != metric {
+        return Err(invalid_arg(format!(
+            "The index file is not of the {metric} metric."
+        )));
+    }
+    Ok((settings, file))
+}
+
+/// Searcher for all pairs of similar documents in the Jaccard space.
+#[napi(js_name = "JaccardSearcher")]
+pub struct JsJaccardSearcher {
+    settings: IndexSettings,
+    searcher: Option<JaccardSearcher>,
+}
+
+impl JsJaccardSearcher {
+    fn built(&self) -> Result<&JaccardSearcher> {
+        self.searcher.as_ref().ok_or_else(not_built)
+    }
+}
+
+#[napi]
+impl JsJaccardSearcher {
+    /// Creates a searcher of w-shingling with `windowSize` (1 by default).
+    #[napi(constructor)]
+    pub fn new(window_size: Option<u32>, options: Option<SearcherOptions>) -> Result<Self> {
+        let settings = settings("jaccard", window_size, options)?;
+        // Validates the settings in advance.
+        settings.jaccard_searcher().map_err(invalid_arg)?;
+        Ok(Self {
+            settings,
+            searcher: None,
+        })
+    }
+
+    /// Builds the database of sketches of `64*numChunks` dimensions from documents,
+    /// replacing the existing one.
+    #[napi]
+    pub fn build(&mut self, documents: Vec<String>, num_chunks: Option<u32>) -> Result<()> {
+        let num_chunks = num_chunks.unwrap_or(8) as usize;
+        let searcher = self
+            .settings
+            .jaccard_searcher()
+            .and_then(|searcher| searcher.build_sketches_in_parallel(documents.iter(), num_chunks))
+            .map_err(invalid_arg)?;
+        self.searcher = Some(searcher);
+        Ok(())
+    }
+
+    /// Searches for all pairs of similar documents within `radius`,
+    /// returning the arrays of `i`, `j`, and distances.
+    #[napi]
+    pub fn search_similar_pairs(&self, radius: f64) -> Result<SimilarPairs> {
+        let searcher = self.built()?;
+        Ok(similar_pairs(searcher.search_similar_pairs(radius)))
+    }
+
+    /// Searches for documents similar to a query within `radius`,
+    /// returning the arrays of ids and distances.
+    #[napi]
+    pub fn query(&self, text: String, radius: f64) -> Result<SimilarDocuments> {
+        let searcher = self.built()?;
+        let results = searcher
+            .search_similar_documents(&text, radius)
+            .map_err(invalid_arg)?;
+        Ok(similar_documents(results))
+    }
+
+    /// Saves the settings and sketches into an index file, which can be also
+    /// read by the query executable.
+    #[napi]
+    pub fn save(&self, path: String) -> Result<()> {
+        let file = self.built()?.export_sketches().map_err(invalid_arg)?;
+        save_index(&path, &self.settings, file)
+    }
+
+    /// Loads a searcher from an index file of the Jaccard space.
+    #[napi(factory)]
+    pub fn load(path: String) -> Result<Self> {
+        let (settings, file) = load_index(&path, "jaccard")?;
+        let searcher = settings
+            .jaccard_searcher()
+            .and_then(|searcher| searcher.import_sketches(&file))
+            .map_err(invalid_arg)?;
+        Ok(Self {
+            settings,
+            searcher: Some(searcher),
+        })
+    }
+
+    /// Seed value used in the searcher.
+    #[napi(getter)]
+    pub fn seed(&self) -> BigInt {
+        BigInt::from(self.settings.seed)
+    }
+
+    /// Number of the documents in the database.
+    #[napi(getter)]
+    pub fn length(&self) -> u32 {
+        self.searcher.as_ref().map_or(0, JaccardSearcher::len) as u32
+    }
+}
+
+/// Searcher for all pairs of similar documents in the Cosine space.
+#[napi(js_name = "CosineSearcher")]
+pub struct JsCosineSearcher {
+    settings: IndexSettings,
+    // Serialized IDF model written in the idf section of index files.
+    idf_data: Vec<u8>,
+    searcher: Option<CosineSearcher>,
+}
+
+impl JsCosineSearcher {
+    fn built(&self) -> Result<&CosineSearcher> {
+        self.searcher.as_ref().ok_or_else(not_built)
+    }
+}
+
+#[napi]
+impl JsCosineSearcher {
+    /// Creates a searcher of w-shingling with `windowSize` (1 by default).
+    #[napi(constructor)]
+    pub fn new(
+        window_size: Option<u32>,
+        options: Option<SearcherOptions>,
+        weighting: Option<Weighting>,
+    ) -> Result<Self> {
+        let mut settings = settings("cosine", window_size, options)?;
+        let weighting = weighting.unwrap_or_default();
+        if let Some(tf) = weighting.tf {
+            settings.tf = tf;
+        }
+        if let Some(idf) = weighting.idf {
+            settings.idf = idf;
+        }
+        settings.cosine_searcher().map_err(invalid_arg)?;
+        settings.uses_idf().map_err(invalid_arg)?;
+        Ok(Self {
+            settings,
+            idf_data: vec![],
+            searcher: None,
+        })
+    }
+
+    /// Builds the database of sketches of `64*numChunks` dimensions from documents,
+    /// replacing the existing one. The IDF model is built from the documents.
+    #[napi]
+    pub fn build(&mut self, documents: Vec<String>, num_chunks: Option<u32>) -> Result<()> {
+        let num_chunks = num_chunks.unwrap_or(8) as usize;
+        let searcher = self.settings.cosine_searcher().map_err(invalid_arg)?;
+        let idf = if self.settings.uses_idf().map_err(invalid_arg)? {
+            Some(Idf::new().smooth(self.settings.idf == "smooth"))
+        } else {
+            None
+        };
+        let (searcher, idf_data) = (|| -> find_simdoc::errors::Result<_> {
+            let mut idf_data = vec![];
+            let idf = match idf {
+                Some(idf) => {
+                    let idf = idf.build(documents.iter(), searcher.config())?;
+                    idf.serialize_into(&mut idf_data)?;
+                    Some(idf)
+                }
+                None => None,
+            };
+            let searcher = searcher
+                .idf(idf)
+                .build_sketches_in_parallel(documents.iter(), num_chunks)?;
+            Ok((searcher, idf_data))
+        })()
+        .map_err(invalid_arg)?;
+        self.searcher = Some(searcher);
+        self.idf_data = idf_data;
+        Ok(())
+    }
+
+    /// Searches for all pairs of similar documents within `radius`,
+    /// returning the arrays of `i`, `j`, and distances.
+    #[napi]
+    pub fn search_similar_pairs(&self, radius: f64) -> Result<SimilarPairs> {
+        let searcher = self.built()?;
+        Ok(similar_pairs(searcher.search_similar_pairs(radius)))
+    }
+
+    /// Searches for documents similar to a query within `radius`,
+    /// returning the arrays of ids and distances.
+    #[napi]
+    pub fn query(&self, text: String, radius: f64) -> Result<SimilarDocuments> {
+        let searcher = self.built()?;
+        let results = searcher
+            .search_similar_documents(&text, radius)
+            .map_err(invalid_arg)?;
+        Ok(similar_documents(results))
+    }
+
+    /// Saves the settings, IDF model, and sketches into an index file, which can be also
+    /// read by the query executable.
+    #[napi]
+    pub fn save(&self, path: String) -> Result<()> {
+        let mut file = self.built()?.export_sketches().map_err(invalid_arg)?;
+        if !self.idf_data.is_empty() {
+            file.push_section("idf", self.idf_data.clone())
+                .map_err(invalid_arg)?;
+        }
+        save_index(&path, &self.settings, file)
+    }
+
+    /// Loads a searcher from an index file of the Cosine space.
+    #[napi(factory)]
+    pub fn load(path: String) -> Result<Self> {
+        let (settings, file) = load_index(&path, "cosine")?;
+        let (idf_model, idf_data) = if !settings.uses_idf().map_err(invalid_arg)? {
+            (None, vec![])
+        } else {
+            let idf_data = file
+                .section("idf")
+                .ok_or_else(|| invalid_arg("The IDF model is not stored."))?;
+            let idf_model = Idf::deserialize_from(idf_data).map_err(invalid_arg)?;
+            (Some(idf_model), idf_data.to_vec())
+        };
+        let searcher = settings
+            .cosine_searcher()
+            .and_then(|searcher| searcher.idf(idf_model).import_sketches(&file))
+            .map_err(invalid_arg)?;
+        Ok(Self {
+            settings,
+            idf_data,
+            searcher: Some(searcher),
+        })
+    }
+
+    /// Seed value used in the searcher.
+    #[napi(getter)]
+    pub fn seed(&self) -> BigInt {
+        BigInt::from(self.settings.seed)
+    }
+
+    /// Number of the documents in the database.
+    #[napi(getter)]
+    pub fn length(&self) -> u32 {
+        self.searcher.as_ref().map_or(0, CosineSearcher::len) as u32
+    }
+}
@@ -0,0 +1,395 @@
+//! Python bindings of find-simdoc.
+//!
+//! The searchers are saved into the same index file format as the `index` executable,
+//! so indexes can be shared between Python and the CLI.
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+
+use find_simdoc::index_format::IndexFile;
+use find_simdoc::index_settings::IndexSettings;
+use find_simdoc::tfidf::Idf;
+use find_simdoc::{CosineSearcher, JaccardSearcher};
+use numpy::{IntoPyArray, PyArray1};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+
+/// Arrays of the left ids, right ids, and distances of similar pairs.
+type PairArrays<'py> = (
+    Bound<'py, PyArray1<u64>>,
+    Bound<'py, PyArray1<u64>>,
+    Bound<'py, PyArray1<f64>>,
+);
+
+/// Arrays of the ids and distances of similar documents.
+type QueryArrays<'py> = (Bound<'py, PyArray1<u64>>, Bound<'py, PyArray1<f64>>);
+
+fn value_error<E: Display>(e: E) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn settings(
+    metric: &str,
+    window_size: usize,
+    delimiter: Option<char>,
+    seed: Option<u64>,
+    max_window_size: Option<usize>,
+    delimiter_regex: Option<String>,
+    lowercase: bool,
+    nfkc: bool,
+    strip_punct: bool,
+) -> IndexSettings {
+    // The seed is fixed in advance to write it in the header of index files.
+    let mut settings = IndexSettings::new(
+        metric,
+        window_size,
+        seed.unwrap_or_else(rand::random::<u64>),
+    );
+    settings.max_window_size = max_window_size.unwrap_or(window_size);
+    settings.delimiter = delimiter;
+    settings.delimiter_regex = delimiter_regex;
+    settings.lowercase = lowercase;
+    settings.nfkc = nfkc;
+    settings.strip_punct = strip_punct;
+    settings
+}
+
+fn pair_arrays(py: Python<'_>, results: Vec<(usize, usize, f64)>) -> PairArrays<'_> {
+    let mut is = Vec::with_capacity(results.len());
+    let mut js = Vec::with_capacity(results.len());
+    let mut dists = Vec::with_capacity(results.len());
+    for (i, j, dist) in results {
+        is.push(i as u64);
+        js.push(j as u64);
+        dists.push(dist);
+    }
+    (
+        is.into_pyarray(py),
+        js.into_pyarray(py),
+        dists.into_pyarray(py),
+    )
+}
+
+fn query_arrays(py: Python<'_>, results: Vec<(usize, f64)>) -> QueryArrays<'_> {
+    let (ids, dists): (Vec<_>, Vec<_>) = results.into_iter().map(|(i, d)| (i as u64, d)).unzip();
+    (ids.into_pyarray(py), dists.into_pyarray(py))
+}
+
+fn save_index(path: &str, settings: &IndexSettings, mut file: IndexFile) -> PyResult<()> {
+    file.set_header(settings.header()).map_err(value_error)?;
+    let mut wtr = BufWriter::new(File::create(path)?);
+    file.serialize_into(&mut wtr).map_err(value_error)?;
+    wtr.flush()?;
+    Ok(())
+}
+
+fn load_index(path: &str, metric: &str) -> PyResult<(IndexSettings, IndexFile)> {
+    let file =
+        IndexFile::deserialize_from(BufReader::new(File::open(path)?)).map_err(value_error)?;
+    let settings = IndexSettings::from_index_file(&file).map_err(value_error)?;
+    if settings.metric != metric {
+        return Err(value_error(format!(
+            "The index file is not of the {metric} metric."
+        )));
+    }
+    Ok((settings, file))
+}
+
+/// Searcher for all pairs of similar documents in the Jaccard space.
+///
+/// Results are returned as numpy arrays.
+#[pyclass(name = "JaccardSearcher", module = "find_simdoc")]
+struct PyJaccardSearcher {
+    settings: IndexSettings,
+    searcher: Option<JaccardSearcher>,
+}
+
+impl PyJaccardSearcher {
+    fn built(&self) -> PyResult<&JaccardSearcher> {
+        self.searcher
+            .as_ref()
+            .ok_or_else(|| PyRuntimeError::new_err("Sketches must be built."))
+    }
+}
+
+#[pymethods]
+impl PyJaccardSearcher {
+    #[new]
+    #[pyo3(signature = (
+        window_size = 1,
+        delimiter = None,
+        seed = None,
+        *,
+        max_window_size = None,
+        delimiter_regex = None,
+        lowercase = false,
+        nfkc = false,
+        strip_punct = false,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        window_size: usize,
+        delimiter: Option<char>,
+        seed: Option<u64>,
+        max_window_size: Option<usize>,
+        delimiter_regex: Option<String>,
+        lowercase: bool,
+        nfkc: bool,
+        strip_punct: bool,
+    ) -> PyResult<Self> {
+        let settings = settings(
+            "jaccard",
+            window_size,
+            delimiter,
+            seed,
+            max_window_size,
+            delimiter_regex,
+            lowercase,
+            nfkc,
+            strip_punct,
+        );
+        // Validates the settings in advance.
+        settings.jaccard_searcher().map_err(value_error)?;
+        Ok(Self {
+            settings,
+            searcher: None,
+        })
+    }
+
+    /// Builds the database of sketches of `64*num_chunks` dimensions from documents,
+    /// replacing the existing one.
+    #[pyo3(signature = (documents, num_chunks = 8))]
+    fn build(&mut self, py: Python<'_>, documents: Vec<String>, num_chunks: usize) -> PyResult<()> {
+        let searcher = self.settings.jaccard_searcher().map_err(value_error)?;
+        let searcher = py
+            .detach(|| searcher.build_sketches_in_parallel(documents.iter(), num_chunks))
+            .map_err(value_error)?;
+        self.searcher = Some(searcher);
+        Ok(())
+    }
+
+    /// Searches for all pairs of similar documents within `radius`,
+    /// returning the arrays of `i`, `j`, and distances.
+    fn search_similar_pairs<'py>(&self, py: Python<'py>, radius: f64) -> PyResult<PairArrays<'py>> {
+        let searcher = self.built()?;
+        let results = py.detach(|| searcher.search_similar_pairs(radius));
+        Ok(pair_arrays(py, results))
+    }
+
+    /// Searches for documents similar to a query within `radius`,
+    /// returning the arrays of ids and distances.
+    fn query<'py>(&self, py: Python<'py>, text: &str, radius: f64) -> PyResult<QueryArrays<'py>> {
+        let searcher = self.built()?;
+        let results = py
+            .detach(|| searcher.search_similar_documents(text, radius))
+            .map_err(value_error)?;
+        Ok(query_arrays(py, results))
+    }
+
+    /// Saves the settings and sketches into an index file, which can be also
+    /// read by the query executable.
+    fn save(&self, path: &str) -> PyResult<()> {
+        let file = self.built()?.export_sketches().map_err(value_error)?;
+        save_index(path, &self.settings, file)
+    }
+
+    /// Loads a searcher from an index file of the Jaccard space.
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        let (settings, file) = load_index(path, "jaccard")?;
+        let searcher = settings
+            .jaccard_searcher()
+            .and_then(|searcher| searcher.import_sketches(&file))
+            .map_err(value_error)?;
+        Ok(Self {
+            settings,
+            searcher: Some(searcher),
+        })
+    }
+
+    /// Seed value used in the searcher.
+    #[getter]
+    const fn seed(&self) -> u64 {
+        self.settings.seed
+    }
+
+    fn __len__(&self) -> usize {
+        self.searcher.as_ref().map_or(0, JaccardSearcher::len)
+    }
+}
+
+/// Searcher for all pairs of similar documents in the Cosine space.
+///
+/// Results are returned as numpy arrays.
+#[pyclass(name = "CosineSearcher", module = "find_simdoc")]
+struct PyCosineSearcher {
+    settings: IndexSettings,
+    // Serialized IDF model written in the idf section of index files.
+    idf_data: Vec<u8>,
+    searcher: Option<CosineSearcher>,
+}
+
+impl PyCosineSearcher {
+    fn built(&self) -> PyResult<&CosineSearcher> {
+        self.searcher
+            .as_ref()
+            .ok_or_else(|| PyRuntimeError::new_err("Sketches must be built."))
+    }
+}
+
+#[pymethods]
+impl PyCosineSearcher {
+    #[new]
+    #[pyo3(signature = (
+        window_size = 1,
+        delimiter = None,
+        seed = None,
+        *,
+        max_window_size = None,
+        delimiter_regex = None,
+        lowercase = false,
+        nfkc = false,
+        strip_punct = false,
+        tf = "standard",
+        idf = "smooth",
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        window_size: usize,
+        delimiter: Option<char>,
+        seed: Option<u64>,
+        max_window_size: Option<usize>,
+        delimiter_regex: Option<String>,
+        lowercase: bool,
+        nfkc: bool,
+        strip_punct: bool,
+        tf: &str,
+        idf: &str,
+    ) -> PyResult<Self> {
+        let mut settings = settings(
+            "cosine",
+            window_size,
+            delimiter,
+            seed,
+            max_window_size,
+            delimiter_regex,
+            lowercase,
+            nfkc,
+            strip_punct,
+        );
+        settings.tf = tf.to_string();
+        settings.idf = idf.to_string();
+        settings.cosine_searcher().map_err(value_error)?;
+        settings.uses_idf().map_err(value_error)?;
+        Ok(Self {
+            settings,
+            idf_data: vec![],
+            searcher: None,
+        })
+    }
+
+    /// Builds the database of sketches of `64*num_chunks` dimensions from documents,
+    /// replacing the existing one. The IDF model is built from the documents.
+    #[pyo3(signature = (documents, num_chunks = 8))]
+    fn build(&mut self, py: Python<'_>, documents: Vec<String>, num_chunks: usize) -> PyResult<()> {
+        let searcher = self.settings.cosine_searcher().map_err(value_error)?;
+        let idf = if self.settings.uses_idf().map_err(value_error)? {
+            Some(Idf::new().smooth(self.settings.idf == "smooth"))
+        } else {
+            None
+        };
+        let (searcher, idf_data) = py
+            .detach(|| -> find_simdoc::errors::Result<_> {
+                let mut idf_data = vec![];
+                let idf = match idf {
+                    Some(idf) => {
+                        let idf = idf.build(documents.iter(), searcher.config())?;
+                        idf.serialize_into(&mut idf_data)?;
+                        Some(idf)
+                    }
+                    None => None,
+                };
+                let searcher = searcher
+                    .idf(idf)
+                    .build_sketches_in_parallel(documents.iter(), num_chunks)?;
+                Ok((searcher, idf_data))
+            })
+            .map_err(value_error)?;
+        self.searcher = Some(searcher);
+        self.idf_data = idf_data;
+        Ok(())
+    }
+
+    /// Searches for all pairs of similar documents within `radius`,
+    /// returning the arrays of `i`, `j`, and distances.
+    fn search_similar_pairs<'py>(&self, py: Python<'py>, radius: f64) -> PyResult<PairArrays<'py>> {
+        let searcher = self.built()?;
+        let results = py.detach(|| searcher.search_similar_pairs(radius));
+        Ok(pair_arrays(py, results))
+    }
+
+    /// Searches for documents similar to a query within `radius`,
+    /// returning the arrays of ids and distances.
+    fn query<'py>(&self, py: Python<'py>, text: &str, radius: f64) -> PyResult<QueryArrays<'py>> {
+        let searcher = self.built()?;
+        let results = py
+            .detach(|| searcher.search_similar_documents(text, radius))
+            .map_err(value_error)?;
+        Ok(query_arrays(py, results))
+    }
+
+    /// Saves the settings, IDF model, and sketches into an index file, which can be also
+    /// read by the query executable.
+    fn save(&self, path: &str) -> PyResult<()> {
+        let mut file = self.built()?.export_sketches().map_err(value_error)?;
+        if !self.idf_data.is_empty() {
+            file.push_section("idf", self.idf_data.clone())
+                .map_err(value_error)?;
+        }
+        save_index(path, &self.settings, file)
+    }
+
+    /// Loads a searcher from an index file of the Cosine space.
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        let (settings, file) = load_index(path, "cosine")?;
+        let (idf_model, idf_data) = if !settings.uses_idf().map_err(value_error)? {
+            (None, vec![])
+        } else {
+            let idf_data = file
+                .section("idf")
+                .ok_or_else(|| value_error("The IDF model is not stored."))?;
+            let idf_model = Idf::deserialize_from(idf_data).map_err(value_error)?;
+            (Some(idf_model), idf_data.to_vec())
+        };
+        let searcher = settings
+            .cosine_searcher()
+            .and_then(|searcher| searcher.idf(idf_model).import_sketches(&file))
+            .map_err(value_error)?;
+        Ok(Self {
+            settings,
+            idf_data,
+            searcher: Some(searcher),
+        })
+    }
+
+    /// Seed value used in the searcher.
+    #[getter]
+    const fn seed(&self) -> u64 {
+        self.settings.seed
+    }
+
+    fn __len__(&self) -> usize {
+        self.searcher.as_ref().map_or(0, CosineSearcher::len)
+    }
+}
+
+/// Time- and memory-efficient all pairs similarity searches in documents.
+#[pymodule]
+#[pyo3(name = "find_simdoc")]
+fn find_simdoc_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyJaccardSearcher>()?;
+    m.add_class::<PyCosineSearcher>()?;
+    Ok(())
+}
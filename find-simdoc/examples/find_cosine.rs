@@ -2,7 +2,7 @@ use find_simdoc::tfidf::{Idf, Tf};
 use find_simdoc::CosineSearcher;
 
 fn main() {
-    let documents = vec![
+    let documents = [
         "Welcome to Jimbocho, the town of books and curry!",
         "Welcome to Jimbocho, the city of books and curry!",
         "We welcome you to Jimbocho, the town of books and curry.",
@@ -1,7 +1,7 @@
 use find_simdoc::JaccardSearcher;
 
 fn main() {
-    let documents = vec![
+    let documents = [
         "Welcome to Jimbocho, the town of books and curry!",
         "Welcome to Jimbocho, the city of books and curry!",
         "We welcome you to Jimbocho, the town of books and curry.",
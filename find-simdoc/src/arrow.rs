@@ -0,0 +1,154 @@
+//! Integration with [Arrow](https://arrow.apache.org/) record batches, enabled with the `arrow` feature.
+//!
+//! Searchers can be built from a string column of a [`RecordBatch`] without copying texts,
+//! and similar pairs can be converted into a [`RecordBatch`] of `(i, j, dist)`.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use arrow_array::{RecordBatch, StringArray};
+//! use find_simdoc::arrow::pairs_to_record_batch;
+//! use find_simdoc::JaccardSearcher;
+//!
+//! let texts = StringArray::from(vec![
+//!     "Welcome to Jimbocho, the town of books and curry!",
+//!     "Welcome to Jimbocho, the city of books and curry!",
+//!     "We welcome you to Jimbocho, the town of books and curry.",
+//! ]);
+//! let batch = RecordBatch::try_from_iter([("text", Arc::new(texts) as _)]).unwrap();
+//!
+//! let searcher = JaccardSearcher::new(3, None, Some(42))
+//!     .unwrap()
+//!     .build_sketches_from_record_batch(&batch, "text", 20)
+//!     .unwrap();
+//! let results = pairs_to_record_batch(&searcher.search_similar_pairs(0.25)).unwrap();
+//! assert_eq!(results.schema().field(2).name(), "dist");
+//! ```
+use std::sync::Arc;
+
+use all_pairs_hamming::sketch::Sketch;
+use arrow_array::cast::AsArray;
+use arrow_array::{Float64Array, RecordBatch, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+
+use crate::errors::{FindSimdocError, Result};
+use crate::{CosineSearcher, JaccardSearcher};
+
+/// Iterator over the texts of a string column.
+type Texts<'a> = Box<dyn Iterator<Item = &'a str> + Send + 'a>;
+
+/// Gets the texts of a string column, which must be of `Utf8`, `LargeUtf8`, or `Utf8View`
+/// without nulls.
+///
+/// # Errors
+///
+/// An error is returned if the column is not found, is not of a string type, or includes nulls.
+pub fn string_column<'a>(batch: &'a RecordBatch, column: &str) -> Result<Texts<'a>> {
+    let array = batch
+        .column_by_name(column)
+        .ok_or_else(|| FindSimdocError::input(format!("Column not found: {column}")))?;
+    if array.null_count() != 0 {
+        return Err(FindSimdocError::input(format!(
+            "Column {column} must not include nulls."
+        )));
+    }
+    // Nulls are checked above, so the texts are always given.
+    let texts: Texts = match array.data_type() {
+        DataType::Utf8 => Box::new(array.as_string::<i32>().iter().flatten()),
+        DataType::LargeUtf8 => Box::new(array.as_string::<i64>().iter().flatten()),
+        DataType::Utf8View => Box::new(array.as_string_view().iter().flatten()),
+        data_type => {
+            return Err(FindSimdocError::input(format!(
+                "Column {column} must be of a string type, but {data_type}."
+            )))
+        }
+    };
+    Ok(texts)
+}
+
+/// Converts similar pairs into a record batch of the columns `i` and `j` of `UInt64`
+/// and `dist` of `Float64`.
+pub fn pairs_to_record_batch(results: &[(usize, usize, f64)]) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("i", DataType::UInt64, false),
+        Field::new("j", DataType::UInt64, false),
+        Field::new("dist", DataType::Float64, false),
+    ]);
+    let is: UInt64Array = results.iter().map(|&(i, _, _)| i as u64).collect();
+    let js: UInt64Array = results.iter().map(|&(_, j, _)| j as u64).collect();
+    let dists: Float64Array = results.iter().map(|&(_, _, dist)| dist).collect();
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![Arc::new(is), Arc::new(js), Arc::new(dists)],
+    )
+    .map_err(|e| FindSimdocError::input(e.to_string()))
+}
+
+impl<S> JaccardSearcher<S>
+where
+    S: Sketch + Send + Sync,
+{
+    /// Builds the database of sketches from a string column of a record batch in parallel.
+    /// See [`Self::build_sketches_in_parallel()`] and [`string_column()`].
+    pub fn build_sketches_from_record_batch(
+        self,
+        batch: &RecordBatch,
+        column: &str,
+        num_chunks: usize,
+    ) -> Result<Self> {
+        self.build_sketches_in_parallel(string_column(batch, column)?, num_chunks)
+    }
+}
+
+impl<S> CosineSearcher<S>
+where
+    S: Sketch + Send + Sync,
+{
+    /// Builds the database of sketches from a string column of a record batch in parallel.
+    /// See [`Self::build_sketches_in_parallel()`] and [`string_column()`].
+    pub fn build_sketches_from_record_batch(
+        self,
+        batch: &RecordBatch,
+        column: &str,
+        num_chunks: usize,
+    ) -> Result<Self> {
+        self.build_sketches_in_parallel(string_column(batch, column)?, num_chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::{ArrayRef, Int32Array, LargeStringArray, StringArray};
+
+    #[test]
+    fn test_string_column() {
+        let texts = LargeStringArray::from(vec!["abc", "abd"]);
+        let numbers = Int32Array::from(vec![1, 2]);
+        let nulls = StringArray::from(vec![Some("abc"), None]);
+        let batch = RecordBatch::try_from_iter([
+            ("text", Arc::new(texts) as ArrayRef),
+            ("number", Arc::new(numbers) as ArrayRef),
+            ("null", Arc::new(nulls) as ArrayRef),
+        ])
+        .unwrap();
+        let texts: Vec<_> = string_column(&batch, "text").unwrap().collect();
+        assert_eq!(texts, vec!["abc", "abd"]);
+        assert!(string_column(&batch, "number").is_err());
+        assert!(string_column(&batch, "null").is_err());
+        assert!(string_column(&batch, "missing").is_err());
+    }
+
+    #[test]
+    fn test_pairs_to_record_batch() {
+        let batch = pairs_to_record_batch(&[(0, 1, 0.5), (2, 3, 0.25)]).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        let js: &UInt64Array = batch.column(1).as_primitive();
+        assert_eq!(js.values(), &[1, 3]);
+        let dists: &Float64Array = batch.column(2).as_primitive();
+        assert_eq!(dists.values(), &[0.5, 0.25]);
+    }
+}
@@ -0,0 +1,449 @@
+//! Searcher for all pairs of documents contained in others.
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::errors::{FindSimdocError, Result};
+use crate::feature::{FeatureConfig, FeatureExtractor, TokenBuffer};
+use crate::index_format::IndexFile;
+use crate::lsh::containment::SizedSketch;
+use crate::lsh::minhash::MinHasher;
+
+use all_pairs_hamming::progress::{ProgressHook, Stage};
+use rand::{RngCore, SeedableRng};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+/// Searcher for all pairs of documents that are near-subsets of others,
+/// e.g., quotes embedded in articles.
+///
+/// The distance of a document `X` to a document `Y` is `1 - |X ∩ Y| / |X|`,
+/// i.e., one minus the containment of the features of `X` in those of `Y`.
+/// The distance is asymmetric, and a pair `(i, j, dist)` in the results
+/// always indicates that the left-side document `i` is contained in the right-side one `j`.
+///
+/// # Approach
+///
+/// The search steps consist of
+///
+/// 1. Extracts features from documents,
+///    where a feature is a set representation of character or word ngrams.
+/// 2. Convert the features into binary sketches through 1-bit minwise hashing,
+///    storing the number of distinct ngrams along with them (see [`SizedSketch`]).
+/// 3. Estimates the containment from the Jaccard similarity of sketches and the set sizes.
+///
+/// Since documents with a large containment can have a small Jaccard similarity,
+/// the similarity join in the Hamming space cannot be used to find candidates,
+/// and all pairs are compared in parallel except for those pruned with the set sizes.
+/// That is, the time complexity is quadratic over the number of documents.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::ContainmentSearcher;
+///
+/// let documents = vec![
+///     "Welcome to Jimbocho, the town",
+///     "Welcome to Jimbocho, the town of books and curry!",
+///     "We welcome you to the city of books.",
+/// ];
+///
+/// // Creates a searcher for word unigrams (with random seed value 42).
+/// let searcher = ContainmentSearcher::new(1, Some(' '), Some(42))
+///     .unwrap()
+///     // where binary sketches are in the Hamming space of 20*64 dimensions.
+///     .build_sketches_in_parallel(documents.iter(), 20)
+///     .unwrap();
+///
+/// // Searches all pairs of contained documents within radius 0.2.
+/// let results = searcher.search_similar_pairs(0.2);
+/// assert!(results.iter().any(|&(i, j, _)| (i, j) == (0, 1)));
+/// ```
+pub struct ContainmentSearcher {
+    seed: u64,
+    config: FeatureConfig,
+    hasher: MinHasher,
+    sketches: Option<Vec<SizedSketch>>,
+    num_chunks: usize,
+    thread_pool: Option<Arc<ThreadPool>>,
+    progress: Option<ProgressHook>,
+}
+
+impl ContainmentSearcher {
+    /// Creates an instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `window_size` - Window size for w-shingling in feature extraction (must be more than 0).
+    /// * `delimiter` - Delimiter for recognizing words as tokens in feature extraction.
+    ///   If `None`, characters are used for tokens.
+    /// * `seed` - Seed value for random values.
+    pub fn new(window_size: usize, delimiter: Option<char>, seed: Option<u64>) -> Result<Self> {
+        let seed = seed.unwrap_or_else(rand::random::<u64>);
+        let mut seeder = rand_xoshiro::SplitMix64::seed_from_u64(seed);
+        let config = FeatureConfig::new(window_size, delimiter, seeder.next_u64())?;
+        let hasher = MinHasher::new(seeder.next_u64());
+        Ok(Self {
+            seed,
+            config,
+            hasher,
+            sketches: None,
+            num_chunks: 0,
+            thread_pool: None,
+            progress: None,
+        })
+    }
+
+    /// Sets a regular expression for recognizing words as tokens in feature extraction,
+    /// used instead of the delimiter character.
+    /// See [`FeatureConfig::delimiter_regex()`].
+    pub fn delimiter_regex(mut self, pattern: Option<&str>) -> Result<Self> {
+        self.config = self.config.delimiter_regex(pattern)?;
+        Ok(self)
+    }
+
+    /// Sets the range of window sizes for w-shingling in feature extraction.
+    /// See [`FeatureConfig::window_range()`].
+    pub fn window_range(mut self, range: RangeInclusive<usize>) -> Result<Self> {
+        self.config = self.config.window_range(range)?;
+        Ok(self)
+    }
+
+    /// Applies the Unicode NFKC normalization to documents before tokenization?
+    /// See [`FeatureConfig::normalize_nfkc()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn normalize_nfkc(mut self, yes: bool) -> Self {
+        self.config = self.config.normalize_nfkc(yes);
+        self
+    }
+
+    /// Converts documents into lowercase before tokenization?
+    /// See [`FeatureConfig::lowercase()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn lowercase(mut self, yes: bool) -> Self {
+        self.config = self.config.lowercase(yes);
+        self
+    }
+
+    /// Removes punctuation characters before tokenization?
+    /// See [`FeatureConfig::strip_punctuation()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn strip_punctuation(mut self, yes: bool) -> Self {
+        self.config = self.config.strip_punctuation(yes);
+        self
+    }
+
+    /// Sets the thread pool used in parallel processing.
+    /// If `None`, the global thread pool of [`rayon`] is used.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn thread_pool(mut self, thread_pool: Option<Arc<ThreadPool>>) -> Self {
+        self.thread_pool = thread_pool;
+        self
+    }
+
+    /// Sets a new thread pool of `num_threads` threads used in parallel processing.
+    /// If `num_threads` is 0, the number of threads is determined by [`rayon`].
+    pub fn num_threads(self, num_threads: usize) -> Result<Self> {
+        let thread_pool = ThreadPoolBuilder::new().num_threads(num_threads).build()?;
+        Ok(self.thread_pool(Some(Arc::new(thread_pool))))
+    }
+
+    /// Sets a hook receiving the progress of reading documents and building sketches,
+    /// so that applications can show it in their own way.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn progress_hook(mut self, hook: Option<ProgressHook>) -> Self {
+        self.progress = hook;
+        self
+    }
+
+    /// Builds the database of sketches from input documents in parallel.
+    ///
+    /// # Arguments
+    ///
+    /// * `documents` - List of documents, each of which must include at least one feature.
+    /// * `num_chunks` - Number of chunks of sketches, indicating that
+    ///   the number of dimensions in the Hamming space is `num_chunks*64`.
+    pub fn build_sketches_in_parallel<I, D>(
+        mut self,
+        documents: I,
+        num_chunks: usize,
+    ) -> Result<Self>
+    where
+        I: Iterator<Item = D> + Send,
+        D: AsRef<str> + Send,
+    {
+        let _span = tracing::info_span!("build_sketches_in_parallel", num_chunks).entered();
+        let extractor = FeatureExtractor::new(&self.config);
+        let processed = AtomicUsize::new(0);
+        self.report_progress(Stage::DocumentsRead, 0, false);
+        self.report_progress(Stage::SketchesBuilt, 0, false);
+        let mut sketches = self.install(|| -> Result<Vec<_>> {
+            documents
+                .into_iter()
+                .enumerate()
+                .inspect(|&(i, _)| self.report_progress(Stage::DocumentsRead, i + 1, false))
+                .par_bridge()
+                .map_init(TokenBuffer::new, |buffer, (i, doc)| {
+                    let cnt = processed.fetch_add(1, Ordering::Relaxed) + 1;
+                    self.report_progress(Stage::SketchesBuilt, cnt, false);
+                    let sketch = self.sketch(&extractor, doc.as_ref(), num_chunks, buffer)?;
+                    Ok((i, sketch))
+                })
+                .collect()
+        })?;
+        self.report_progress(Stage::DocumentsRead, sketches.len(), true);
+        self.report_progress(Stage::SketchesBuilt, sketches.len(), true);
+        sketches.par_sort_by_key(|&(i, _)| i);
+        self.sketches = Some(sketches.into_iter().map(|(_, sketch)| sketch).collect());
+        self.num_chunks = num_chunks;
+        Ok(self)
+    }
+
+    /// Exports the sketches into an [`IndexFile`], with which the database can be restored by
+    /// [`Self::import_sketches()`] without re-hashing documents.
+    /// The header holds the seed value, the `sizes` section holds the numbers of features,
+    /// and the `i`-th sketch in the file corresponds to the `i`-th document.
+    ///
+    /// The settings of the searcher are not restored except for checking the seed value,
+    /// so the same settings must be given to the searcher importing the sketches.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the sketches are not built.
+    pub fn export_sketches(&self) -> Result<IndexFile> {
+        let sketches = self
+            .sketches
+            .as_ref()
+            .ok_or_else(|| FindSimdocError::input("Sketches must be built."))?;
+        let mut file = IndexFile::new(self.num_chunks);
+        file.push_header("seed", self.seed.to_string())?;
+        let mut sizes = Vec::with_capacity(sketches.len() * 8);
+        for sketch in sketches {
+            file.add(sketch.sketches().iter().copied())?;
+            sizes.extend_from_slice(&(sketch.size() as u64).to_le_bytes());
+        }
+        file.push_section("sizes", sizes)?;
+        Ok(file)
+    }
+
+    /// Restores the database from the sketches exported with [`Self::export_sketches()`],
+    /// replacing the existing one.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the seed value in the header is mismatched or
+    /// the `sizes` section is not valid.
+    pub fn import_sketches(mut self, file: &IndexFile) -> Result<Self> {
+        file.check_seed(self.seed)?;
+        let sizes = file
+            .section("sizes")
+            .filter(|sizes| sizes.len() == file.num_sketches() * 8)
+            .ok_or_else(|| FindSimdocError::serialization("Invalid sizes of sketches."))?;
+        let sketches = sizes
+            .chunks_exact(8)
+            .enumerate()
+            .map(|(i, size)| {
+                let size = u64::from_le_bytes(size.try_into().unwrap()) as usize;
+                SizedSketch::from_parts(file.sketch(i), size)
+                    .map_err(|_| FindSimdocError::serialization("Invalid sketches."))
+            })
+            .collect::<Result<_>>()?;
+        self.sketches = Some(sketches);
+        self.num_chunks = file.num_chunks();
+        Ok(self)
+    }
+
+    /// Searches for all pairs of documents within an input radius, returning triplets of
+    /// the id of the contained document, the id of the containing one, and their distance.
+    pub fn search_similar_pairs(&self, radius: f64) -> Vec<(usize, usize, f64)> {
+        let _span = tracing::info_span!("search_similar_pairs", radius).entered();
+        let Some(sketches) = self.sketches.as_ref() else {
+            return vec![];
+        };
+        let results: Vec<_> = self.install(|| {
+            (0..sketches.len())
+                .into_par_iter()
+                .flat_map_iter(|i| {
+                    (0..sketches.len())
+                        .filter(move |&j| i != j)
+                        .filter_map(move |j| {
+                            self.distance(&sketches[i], &sketches[j], radius)
+                                .map(|dist| (i, j, dist))
+                        })
+                })
+                .collect()
+        });
+        tracing::info!("Found {} similar pairs", results.len());
+        results
+    }
+
+    /// Searches for documents containing a query within an input radius, returning
+    /// pairs of the document id and the distance in the ascending order of distances,
+    /// where the distance is one minus the containment of the query in the document.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the sketches are not built or the query has no feature.
+    pub fn search_similar_documents(&self, query: &str, radius: f64) -> Result<Vec<(usize, f64)>> {
+        let _span = tracing::info_span!("search_similar_documents", radius).entered();
+        let sketches = self
+            .sketches
+            .as_ref()
+            .ok_or_else(|| FindSimdocError::input("Sketches must be built."))?;
+        let extractor = FeatureExtractor::new(&self.config);
+        let query = self
+            .sketch(&extractor, query, self.num_chunks, &mut TokenBuffer::new())
+            .map_err(|_| FindSimdocError::input("The query must include at least one feature."))?;
+        let mut results: Vec<_> = self.install(|| {
+            sketches
+                .par_iter()
+                .enumerate()
+                .filter_map(|(k, sketch)| {
+                    self.distance(&query, sketch, radius).map(|dist| (k, dist))
+                })
+                .collect()
+        });
+        results.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        Ok(results)
+    }
+
+    /// Computes the distance of `x` contained in `y` if it is within the radius.
+    fn distance(&self, x: &SizedSketch, y: &SizedSketch, radius: f64) -> Option<f64> {
+        // The containment never exceeds |Y|/|X|, which prunes pairs without comparing sketches.
+        if (y.size() as f64) < (1. - radius) * x.size() as f64 {
+            return None;
+        }
+        let dist = 1. - x.containment_in(y, &self.hasher).ok()?;
+        (dist <= radius).then_some(dist)
+    }
+
+    fn sketch(
+        &self,
+        extractor: &FeatureExtractor,
+        doc: &str,
+        num_chunks: usize,
+        buffer: &mut TokenBuffer,
+    ) -> Result<SizedSketch> {
+        if doc.is_empty() {
+            return Err(FindSimdocError::input("Input document must not be empty."));
+        }
+        let mut feature = vec![];
+        extractor.extract_buffered(doc, &mut feature, buffer);
+        if feature.is_empty() {
+            return Err(FindSimdocError::input(
+                "Input document must include at least one feature.",
+            ));
+        }
+        SizedSketch::new(&self.hasher, &feature, num_chunks)
+    }
+
+    fn report_progress(&self, stage: Stage, done: usize, finished: bool) {
+        if let Some(progress) = &self.progress {
+            if finished {
+                progress.report(stage, done, Some(done));
+            } else if done.is_multiple_of(10000) {
+                progress.report(stage, done, None);
+            }
+        }
+    }
+
+    fn install<OP, R>(&self, op: OP) -> R
+    where
+        OP: FnOnce() -> R + Send,
+        R: Send,
+    {
+        match self.thread_pool.as_ref() {
+            Some(thread_pool) => thread_pool.install(op),
+            None => op(),
+        }
+    }
+
+    /// Gets the number of input documents.
+    pub fn len(&self) -> usize {
+        self.sketches.as_ref().map_or(0, |sketches| sketches.len())
+    }
+
+    /// Checks if the database is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the memory usage in bytes.
+    pub fn memory_in_bytes(&self) -> usize {
+        self.len() * (self.num_chunks + 1) * std::mem::size_of::<u64>()
+    }
+
+    /// Gets the configuration of feature extraction.
+    pub const fn config(&self) -> &FeatureConfig {
+        &self.config
+    }
+
+    /// Gets the seed value for random values.
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_similar_pairs() {
+        let documents = [
+            "a b c d",
+            "a b c d e f g h",
+            "x y z w v u t s",
+            "a b c d e f g h i j k l",
+        ];
+        let searcher = ContainmentSearcher::new(1, Some(' '), Some(42))
+            .unwrap()
+            .build_sketches_in_parallel(documents.iter(), 16)
+            .unwrap();
+        let mut results: Vec<_> = searcher
+            .search_similar_pairs(0.1)
+            .into_iter()
+            .map(|(i, j, _)| (i, j))
+            .collect();
+        results.sort_unstable();
+        assert_eq!(results, [(0, 1), (0, 3), (1, 3)]);
+
+        let results = searcher
+            .search_similar_documents("a b c d e f", 0.15)
+            .unwrap();
+        let mut ids: Vec<_> = results.into_iter().map(|(i, _)| i).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, [1, 3]);
+    }
+
+    #[test]
+    fn test_import_sketches() {
+        let documents = ["abcdefg", "abcdefh", "abcxyzw", "hijklmn"];
+        let searcher = ContainmentSearcher::new(2, None, Some(42))
+            .unwrap()
+            .build_sketches_in_parallel(documents.iter(), 4)
+            .unwrap();
+        let mut data = vec![];
+        searcher
+            .export_sketches()
+            .unwrap()
+            .serialize_into(&mut data)
+            .unwrap();
+        let file = IndexFile::deserialize_from(&data[..]).unwrap();
+
+        let other = ContainmentSearcher::new(2, None, Some(42))
+            .unwrap()
+            .import_sketches(&file)
+            .unwrap();
+        assert_eq!(other.len(), searcher.len());
+        assert_eq!(
+            other.search_similar_pairs(0.5),
+            searcher.search_similar_pairs(0.5)
+        );
+
+        assert!(ContainmentSearcher::new(2, None, Some(43))
+            .unwrap()
+            .import_sketches(&file)
+            .is_err());
+    }
+}
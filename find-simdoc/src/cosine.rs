@@ -1,15 +1,40 @@
 //! Searcher for all pairs of similar documents in the Cosine space.
-use std::sync::Mutex;
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
+use crate::duplicate::DuplicateDetector;
 use crate::errors::{FindSimdocError, Result};
-use crate::feature::{FeatureConfig, FeatureExtractor};
+use crate::estimate::Estimate;
+use crate::exact::{angular_distance, l2_norm};
+use crate::feature::{
+    FeatureConfig, FeatureExtractor, Preprocessor, ShortDocumentPolicy, TokenBuffer, TokenUnit,
+    TokenWeighter,
+};
+use crate::fingerprint::{Fingerprint, Metric};
+use crate::index_format::IndexFile;
+use crate::label::LabelFilter;
 use crate::lsh::simhash::SimHasher;
-use crate::tfidf::{Idf, Tf};
+use crate::lsh::superbit::SuperBitHasher;
+use crate::lsh::ElementHasher;
+use crate::output::{OutputFormat, PairWriter};
+use crate::pruning::DfPruner;
+use crate::skip::SkipMap;
+use crate::spool::FeatureSpool;
+use crate::tfidf::{Bm25, Idf, TermWeighter, Tf};
+use crate::tokenizer::Tokenizer;
 
 use all_pairs_hamming::chunked_join::ChunkedJoiner;
+use all_pairs_hamming::progress::{ProgressHook, Stage};
+use all_pairs_hamming::sketch::Sketch;
 use rand::{RngCore, SeedableRng};
 use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 
+/// Sketch, digest, feature, and norm of a document.
+type SketchEntry<S> = (Vec<S>, Option<u64>, Option<Vec<(u64, f64)>>, Option<f64>);
 /// Searcher for all pairs of similar documents in the Cosine space.
 ///
 /// # Approach
@@ -55,13 +80,32 @@ use rayon::prelude::*;
 /// // Searches all similar pairs within radius 0.25.
 /// let results = searcher.search_similar_pairs(0.25);
 /// ```
-pub struct CosineSearcher {
+pub struct CosineSearcher<S = u64> {
+    seed: u64,
     config: FeatureConfig,
+    df_pruner: Option<DfPruner>,
     hasher: SimHasher,
-    tf: Option<Tf>,
-    idf: Option<Idf<u64>>,
-    joiner: Option<ChunkedJoiner<u64>>,
-    shows_progress: bool,
+    superbit_seed: u64,
+    superbit_params: Option<(usize, usize)>,
+    superbit: Option<SuperBitHasher>,
+    tf: Option<Arc<dyn TermWeighter>>,
+    idf: Option<Arc<dyn TermWeighter>>,
+    joiner: Option<ChunkedJoiner<S>>,
+    // Retained chunks following those of the joiner, moved between them in re-slicing.
+    pool: Option<ChunkedJoiner<S>>,
+    num_pooled_chunks: usize,
+    labels: Option<Vec<usize>>,
+    duplicates: Option<DuplicateDetector>,
+    separates_duplicates: bool,
+    short_document_policy: ShortDocumentPolicy,
+    skips: Option<SkipMap>,
+    features: Option<Vec<Vec<(u64, f64)>>>,
+    stores_features: bool,
+    norms: Option<Vec<f64>>,
+    normalizes_l2: bool,
+    spill_dir: Option<PathBuf>,
+    thread_pool: Option<Arc<ThreadPool>>,
+    progress: Option<ProgressHook>,
 }
 
 impl CosineSearcher {
@@ -71,7 +115,7 @@ impl CosineSearcher {
     ///
     /// * `window_size` - Window size for w-shingling in feature extraction (must be more than 0).
     /// * `delimiter` - Delimiter for recognizing words as tokens in feature extraction.
-    ///                 If `None`, characters are used for tokens.
+    ///   If `None`, characters are used for tokens.
     /// * `seed` - Seed value for random values.
     pub fn new(window_size: usize, delimiter: Option<char>, seed: Option<u64>) -> Result<Self> {
         let seed = seed.unwrap_or_else(rand::random::<u64>);
@@ -79,32 +123,377 @@ impl CosineSearcher {
         let config = FeatureConfig::new(window_size, delimiter, seeder.next_u64())?;
         let hasher = SimHasher::new(seeder.next_u64());
         Ok(Self {
+            seed,
             config,
+            df_pruner: None,
             hasher,
+            superbit_seed: seeder.next_u64(),
+            superbit_params: None,
+            superbit: None,
             tf: None,
             idf: None,
             joiner: None,
-            shows_progress: false,
+            pool: None,
+            num_pooled_chunks: 0,
+            labels: None,
+            duplicates: None,
+            separates_duplicates: false,
+            short_document_policy: ShortDocumentPolicy::Error,
+            skips: None,
+            features: None,
+            stores_features: false,
+            norms: None,
+            normalizes_l2: false,
+            spill_dir: None,
+            thread_pool: None,
+            progress: None,
         })
     }
+}
+
+impl<S> CosineSearcher<S>
+where
+    S: Sketch + Send + Sync,
+{
+    /// Changes the type of words in sketches into `T`, i.e., each chunk of sketches
+    /// has [`Sketch::dim()`] bits of `T`, e.g., 8 bits for [`u8`].
+    /// Smaller words allow for a finer number of dimensions, i.e., more and shorter chunks.
+    ///
+    /// The database is discarded, so call it before building sketches.
+    /// Note that the super-bit LSH supports only [`u64`].
+    pub fn sketch_type<T>(self) -> CosineSearcher<T>
+    where
+        T: Sketch + Send + Sync,
+    {
+        CosineSearcher {
+            seed: self.seed,
+            config: self.config,
+            df_pruner: self.df_pruner,
+            hasher: self.hasher,
+            superbit_seed: self.superbit_seed,
+            superbit_params: self.superbit_params,
+            superbit: None,
+            tf: self.tf,
+            idf: self.idf,
+            joiner: None,
+            pool: None,
+            num_pooled_chunks: self.num_pooled_chunks,
+            labels: self.labels,
+            duplicates: None,
+            separates_duplicates: self.separates_duplicates,
+            short_document_policy: self.short_document_policy,
+            skips: None,
+            features: None,
+            stores_features: self.stores_features,
+            norms: None,
+            normalizes_l2: self.normalizes_l2,
+            spill_dir: self.spill_dir,
+            thread_pool: self.thread_pool,
+            progress: self.progress,
+        }
+    }
+
+    /// Sets a regular expression for recognizing words as tokens in feature extraction,
+    /// used instead of the delimiter character.
+    /// See [`FeatureConfig::delimiter_regex()`].
+    pub fn delimiter_regex(mut self, pattern: Option<&str>) -> Result<Self> {
+        self.config = self.config.delimiter_regex(pattern)?;
+        Ok(self)
+    }
 
-    /// Shows the progress via the standard error output?
-    pub const fn shows_progress(mut self, yes: bool) -> Self {
-        self.shows_progress = yes;
+    /// Sets multiple delimiter strings for recognizing words as tokens in feature extraction,
+    /// used instead of the delimiter character.
+    /// See [`FeatureConfig::delimiters()`].
+    pub fn delimiters<I, D>(mut self, delimiters: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = D>,
+        D: AsRef<str>,
+    {
+        self.config = self.config.delimiters(delimiters)?;
+        Ok(self)
+    }
+
+    /// Sets the range of window sizes for w-shingling in feature extraction.
+    /// See [`FeatureConfig::window_range()`].
+    pub fn window_range(mut self, range: RangeInclusive<usize>) -> Result<Self> {
+        self.config = self.config.window_range(range)?;
+        Ok(self)
+    }
+
+    /// Sets a function preprocessing documents before feature extraction.
+    /// See [`FeatureConfig::preprocessor()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn preprocessor(mut self, preprocessor: Option<Preprocessor>) -> Self {
+        self.config = self.config.preprocessor(preprocessor);
         self
     }
 
-    /// Sets the scheme of TF weighting.
+    /// Sets a function weighting tokens, which is combined with the TF-IDF weighting.
+    /// See [`FeatureConfig::token_weighter()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn token_weighter(mut self, token_weighter: Option<TokenWeighter>) -> Self {
+        self.config = self.config.token_weighter(token_weighter);
+        self
+    }
+
+    /// Sets a tokenizer for recognizing tokens in feature extraction.
+    /// See [`FeatureConfig::tokenizer()`].
     #[allow(clippy::missing_const_for_fn)]
-    pub fn tf(mut self, tf: Option<Tf>) -> Self {
-        self.tf = tf;
+    pub fn tokenizer(mut self, tokenizer: Option<Arc<dyn Tokenizer>>) -> Self {
+        self.config = self.config.tokenizer(tokenizer);
         self
     }
 
+    /// Sets the unit of tokens recognized when no delimiter is specified.
+    /// See [`FeatureConfig::token_unit()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn token_unit(mut self, unit: TokenUnit) -> Self {
+        self.config = self.config.token_unit(unit);
+        self
+    }
+
+    /// Applies the Unicode NFKC normalization to documents before tokenization?
+    /// See [`FeatureConfig::normalize_nfkc()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn normalize_nfkc(mut self, yes: bool) -> Self {
+        self.config = self.config.normalize_nfkc(yes);
+        self
+    }
+
+    /// Identifies k-mers with their reverse complements in nucleotide sequences?
+    /// See [`FeatureConfig::canonical_kmers()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn canonical_kmers(mut self, yes: bool) -> Self {
+        self.config = self.config.canonical_kmers(yes);
+        self
+    }
+
+    /// Hashes shingles by rolling the hash values of their tokens?
+    /// See [`FeatureConfig::rolling_hash()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn rolling_hash(mut self, yes: bool) -> Self {
+        self.config = self.config.rolling_hash(yes);
+        self
+    }
+
+    /// Converts documents into lowercase before tokenization?
+    /// See [`FeatureConfig::lowercase()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn lowercase(mut self, yes: bool) -> Self {
+        self.config = self.config.lowercase(yes);
+        self
+    }
+
+    /// Sets the maximum number of distinct features extracted from a document.
+    /// See [`FeatureConfig::max_features_per_doc()`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the maximum number is 0.
+    pub fn max_features_per_doc(mut self, max_features: Option<usize>) -> Result<Self> {
+        self.config = self.config.max_features_per_doc(max_features)?;
+        Ok(self)
+    }
+
+    /// Sets the number of buckets of positions combined with features,
+    /// with which similarities are sensitive to the order of large blocks.
+    /// See [`FeatureConfig::position_buckets()`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the number of buckets is 0.
+    pub fn position_buckets(mut self, num_buckets: Option<usize>) -> Result<Self> {
+        self.config = self.config.position_buckets(num_buckets)?;
+        Ok(self)
+    }
+
+    /// Replaces numeric characters with `'0'` before tokenization?
+    /// See [`FeatureConfig::fold_digits()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn fold_digits(mut self, yes: bool) -> Self {
+        self.config = self.config.fold_digits(yes);
+        self
+    }
+
+    /// Removes punctuation characters before tokenization?
+    /// See [`FeatureConfig::strip_punctuation()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn strip_punctuation(mut self, yes: bool) -> Self {
+        self.config = self.config.strip_punctuation(yes);
+        self
+    }
+
+    /// Replaces each run of whitespace with a single space before tokenization?
+    /// See [`FeatureConfig::collapse_whitespace()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn collapse_whitespace(mut self, yes: bool) -> Self {
+        self.config = self.config.collapse_whitespace(yes);
+        self
+    }
+
+    /// Sets stopwords removed from tokens in feature extraction.
+    /// See [`FeatureConfig::stopwords()`].
+    pub fn stopwords<I, W>(mut self, stopwords: I) -> Self
+    where
+        I: IntoIterator<Item = W>,
+        W: AsRef<str>,
+    {
+        self.config = self.config.stopwords(stopwords);
+        self
+    }
+
+    /// Sets the pruner of features based on document frequencies,
+    /// which is applied to features before sketching.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn df_pruner(mut self, df_pruner: Option<DfPruner>) -> Self {
+        self.df_pruner = df_pruner;
+        self
+    }
+
+    /// Emits the progress of building sketches and the similarity join as [`tracing`] events
+    /// with [`ProgressHook::tracing()`]?
+    /// This replaces the hook set with [`Self::progress_hook()`].
+    pub fn shows_progress(mut self, yes: bool) -> Self {
+        self.progress = yes.then(ProgressHook::tracing);
+        self
+    }
+
+    /// Sets a hook receiving the progress of reading documents, building sketches,
+    /// and the similarity join, so that applications can show it in their own way.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn progress_hook(mut self, hook: Option<ProgressHook>) -> Self {
+        self.progress = hook;
+        self
+    }
+
+    /// Sets the scheme of TF weighting.
+    pub fn tf(self, tf: Option<Tf>) -> Self {
+        self.tf_weighter(tf.map(|tf| Arc::new(tf) as Arc<dyn TermWeighter>))
+    }
+
+    /// Sets the scheme of BM25 TF weighting, which replaces that set with [`Self::tf()`].
+    pub fn bm25(self, bm25: Option<Bm25>) -> Self {
+        self.tf_weighter(bm25.map(|bm25| Arc::new(bm25) as Arc<dyn TermWeighter>))
+    }
+
     /// Sets the scheme of IDF weighting.
+    pub fn idf(self, idf: Option<Idf<u64>>) -> Self {
+        self.idf_weighter(idf.map(|idf| Arc::new(idf) as Arc<dyn TermWeighter>))
+    }
+
+    /// Sets a user-supplied weighter applied in place of the TF weighting,
+    /// which replaces that set with [`Self::tf()`] or [`Self::bm25()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn tf_weighter(mut self, weighter: Option<Arc<dyn TermWeighter>>) -> Self {
+        self.tf = weighter;
+        self
+    }
+
+    /// Sets a user-supplied weighter applied in place of the IDF weighting,
+    /// which replaces that set with [`Self::idf()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn idf_weighter(mut self, weighter: Option<Arc<dyn TermWeighter>>) -> Self {
+        self.idf = weighter;
+        self
+    }
+
+    /// Separates exact duplicate documents (i.e., those with identical features) from the search?
+    /// If enabled, only the first document in each group of duplicates is searched,
+    /// and the groups can be obtained with [`Self::duplicate_groups()`].
+    /// If labels are attached with [`Self::labels()`] before building, duplicates are grouped
+    /// only within a label, so that the pairs spanning labels are still searched.
+    pub const fn separates_duplicates(mut self, yes: bool) -> Self {
+        self.separates_duplicates = yes;
+        self
+    }
+
+    /// Sets the policy for documents from which no feature is extracted.
+    /// The default value is [`ShortDocumentPolicy::Error`].
+    ///
+    /// With [`ShortDocumentPolicy::Skip`], skipped documents are listed by [`Self::skipped_documents()`].
+    pub const fn short_document_policy(mut self, policy: ShortDocumentPolicy) -> Self {
+        self.short_document_policy = policy;
+        self
+    }
+
+    /// Sets the number of chunks of sketches generated and retained in building,
+    /// with which [`Self::with_num_chunks()`] can re-slice the sketches without re-hashing documents.
+    /// The setting is ignored if it is not more than the number of chunks specified in building.
+    pub const fn num_pooled_chunks(mut self, num_chunks: usize) -> Self {
+        self.num_pooled_chunks = num_chunks;
+        self
+    }
+
+    /// Stores features of documents in building?
+    /// If enabled, [`Self::exact_distance()`] can be used at the cost of memory.
+    pub const fn stores_features(mut self, yes: bool) -> Self {
+        self.stores_features = yes;
+        self
+    }
+
+    /// Normalizes weighted features to unit L2 norms before sketching?
+    /// If enabled, the stored features (see [`Self::stores_features()`]) are comparable
+    /// across documents of different lengths, and the norms before normalization
+    /// are stored and obtained with [`Self::norm()`].
+    pub const fn l2_normalize(mut self, yes: bool) -> Self {
+        self.normalizes_l2 = yes;
+        self
+    }
+
+    /// Sets a directory in which a temporary file is created to spill features
+    /// in [`Self::build_sketches_with_idf()`], instead of holding them in memory.
+    /// The file is newly created without overwriting any file and removed after the build,
+    /// even if it fails.
     #[allow(clippy::missing_const_for_fn)]
-    pub fn idf(mut self, idf: Option<Idf<u64>>) -> Self {
-        self.idf = idf;
+    pub fn spill_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.spill_dir = dir;
+        self
+    }
+
+    /// Sets the hash function of elements in the simplified simhash and the super-bit LSH.
+    /// The default value is [`ElementHasher::SplitMix64`], and
+    /// the sketches differ between the functions even with the same seed.
+    pub fn element_hasher(mut self, element_hasher: ElementHasher) -> Self {
+        self.hasher = self.hasher.element_hasher(element_hasher);
+        self
+    }
+
+    /// Uses the [`SuperBitHasher`] instead of the simplified simhash, which reduces
+    /// the variance of estimates at the same number of bits, with parameters `(dim, depth)`.
+    /// If `None`, the simplified simhash is used.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `dim` is 0 or `depth` is not in the range of `[1, dim]`.
+    pub fn superbit(mut self, params: Option<(usize, usize)>) -> Result<Self> {
+        if let Some((dim, depth)) = params {
+            // Validates the parameters without materializing projections.
+            SuperBitHasher::new(self.superbit_seed, dim, depth, 0)?;
+        }
+        self.superbit_params = params;
+        Ok(self)
+    }
+
+    /// Sets the thread pool used in parallel processing.
+    /// If `None`, the global thread pool of [`rayon`] is used.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn thread_pool(mut self, thread_pool: Option<Arc<ThreadPool>>) -> Self {
+        self.thread_pool = thread_pool;
+        self
+    }
+
+    /// Sets a new thread pool of `num_threads` threads used in parallel processing.
+    /// If `num_threads` is 0, the number of threads is determined by [`rayon`].
+    pub fn num_threads(self, num_threads: usize) -> Result<Self> {
+        let thread_pool = ThreadPoolBuilder::new().num_threads(num_threads).build()?;
+        Ok(self.thread_pool(Some(Arc::new(thread_pool))))
+    }
+
+    /// Attaches labels to documents, where the `i`-th label is for the `i`-th document.
+    /// The labels are used to filter search results with [`LabelFilter`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn labels(mut self, labels: Option<Vec<usize>>) -> Self {
+        self.labels = labels;
         self
     }
 
@@ -112,117 +501,906 @@ impl CosineSearcher {
     ///
     /// # Arguments
     ///
-    /// * `documents` - List of documents (must not include an empty string).
+    /// * `documents` - List of documents. Documents from which no feature is extracted,
+    ///   including empty strings, are handled according to [`Self::short_document_policy()`].
     /// * `num_chunks` - Number of chunks of sketches, indicating that
-    ///                  the number of dimensions in the Hamming space is `num_chunks*64`.
+    ///   the number of dimensions in the Hamming space is `num_chunks*S::dim()`.
     pub fn build_sketches<I, D>(mut self, documents: I, num_chunks: usize) -> Result<Self>
     where
         I: IntoIterator<Item = D>,
         D: AsRef<str>,
     {
-        let mut joiner = ChunkedJoiner::<u64>::new(num_chunks).shows_progress(self.shows_progress);
+        let _span = tracing::info_span!("build_sketches", num_chunks).entered();
+        self.prepare_superbit(num_chunks)?;
+        self.init_database(num_chunks);
+        self.add_documents(documents)
+    }
+
+    /// Builds the database of sketches from input documents in parallel.
+    ///
+    /// # Arguments
+    ///
+    /// * `documents` - List of documents. Documents from which no feature is extracted,
+    ///   including empty strings, are handled according to [`Self::short_document_policy()`].
+    /// * `num_chunks` - Number of chunks of sketches, indicating that
+    ///   the number of dimensions in the Hamming space is `num_chunks*S::dim()`.
+    pub fn build_sketches_in_parallel<I, D>(
+        mut self,
+        documents: I,
+        num_chunks: usize,
+    ) -> Result<Self>
+    where
+        I: Iterator<Item = D> + Send,
+        D: AsRef<str> + Send,
+    {
+        let span = tracing::info_span!("build_sketches_in_parallel", num_chunks);
+        let _enter = span.enter();
+        self.prepare_superbit(num_chunks)?;
         let extractor = FeatureExtractor::new(&self.config);
+        let processed = AtomicUsize::new(0);
+        self.report_progress(Stage::DocumentsRead, 0, false);
+        self.report_progress(Stage::SketchesBuilt, 0, false);
+        let sketches = self.install(|| -> Result<Vec<_>> {
+            let mut sketches: Vec<_> = documents
+                .into_iter()
+                .enumerate()
+                .inspect(|&(i, _)| self.report_progress(Stage::DocumentsRead, i + 1, false))
+                .par_bridge()
+                .map_init(TokenBuffer::new, |buffer, (i, doc)| {
+                    // Spans in worker threads are nested under the calling one.
+                    let _enter = span.enter();
+                    let cnt = processed.fetch_add(1, Ordering::Relaxed) + 1;
+                    self.report_progress(Stage::SketchesBuilt, cnt, false);
+                    let doc = doc.as_ref();
+                    self.check_document(doc)?;
+                    let mut feature = vec![];
+                    tracing::trace_span!("extract")
+                        .in_scope(|| self.extract(&extractor, doc, &mut feature, buffer));
+                    Ok((i, self.sketch_feature(Some(doc), feature, num_chunks)?))
+                })
+                .collect::<Result<_>>()?;
+            sketches.par_sort_by_key(|&(i, _)| i);
+            Ok(sketches)
+        })?;
+        self.report_progress(Stage::DocumentsRead, sketches.len(), true);
+        self.report_progress(Stage::SketchesBuilt, sketches.len(), true);
 
+        let sketches = sketches.into_iter().map(|(_, entry)| entry);
+        self.set_sketches(sketches, num_chunks)?;
+        Ok(self)
+    }
+
+    /// Builds the database of sketches from input documents, training the IDF weighting
+    /// in the same pass over the documents, so that the corpus is read only once.
+    ///
+    /// The extracted features are buffered in memory, or spilled to a file
+    /// if [`Self::spill_dir()`] is set, until the IDF weighting is trained.
+    /// The trained weighting replaces that set with [`Self::idf()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `documents` - List of documents. Documents from which no feature is extracted,
+    ///   including empty strings, are handled according to [`Self::short_document_policy()`].
+    /// * `idf` - IDF weighter to be trained, which may be trained on other documents in advance
+    ///   but must not be pruned.
+    /// * `num_chunks` - Number of chunks of sketches, indicating that
+    ///   the number of dimensions in the Hamming space is `num_chunks*S::dim()`.
+    pub fn build_sketches_with_idf<I, D>(
+        mut self,
+        documents: I,
+        mut idf: Idf<u64>,
+        num_chunks: usize,
+    ) -> Result<Self>
+    where
+        I: IntoIterator<Item = D>,
+        D: AsRef<str>,
+    {
+        let _span = tracing::info_span!("build_sketches_with_idf", num_chunks).entered();
+        self.prepare_superbit(num_chunks)?;
+        let extractor = FeatureExtractor::new(&self.config);
+        let mut spool = FeatureSpool::new(self.spill_dir.as_deref())?;
         let mut feature = vec![];
+        let mut terms = vec![];
+        let mut buffer = TokenBuffer::new();
+        let mut num_docs = 0;
+        self.report_progress(Stage::DocumentsRead, 0, false);
         for (i, doc) in documents.into_iter().enumerate() {
-            if self.shows_progress && (i + 1) % 10000 == 0 {
-                eprintln!("Processed {} documents...", i + 1);
-            }
+            self.report_progress(Stage::DocumentsRead, i + 1, false);
+            num_docs = i + 1;
             let doc = doc.as_ref();
-            if doc.is_empty() {
-                return Err(FindSimdocError::input("Input document must not be empty."));
-            }
-            extractor.extract_with_weights(doc, &mut feature);
-            if let Some(tf) = self.tf.as_ref() {
-                tf.tf(&mut feature);
-            }
-            if let Some(idf) = self.idf.as_ref() {
-                for (term, weight) in feature.iter_mut() {
-                    *weight *= idf.idf(*term);
-                }
+            self.check_document(doc)?;
+            extractor.extract_with_weights_buffered(doc, &mut feature, &mut buffer);
+            // A skipped document is buffered as an empty feature.
+            if self.fill_short_feature(Some(doc), &mut feature)? {
+                terms.clear();
+                terms.extend(feature.iter().map(|&(term, _)| term));
+                idf.add(&terms)?;
             }
-            joiner.add(self.hasher.iter(&feature)).unwrap();
+            spool.push(&feature)?;
         }
-        self.joiner = Some(joiner);
+
+        self.report_progress(Stage::DocumentsRead, num_docs, true);
+
+        self = self.idf(Some(idf));
+        let mut sketches = vec![];
+        self.report_progress(Stage::SketchesBuilt, 0, false);
+        spool.for_each(|mut feature| {
+            if feature.is_empty() {
+                sketches.push(None);
+            } else {
+                self.weight(&mut feature);
+                sketches.push(self.sketch_feature(None, feature, num_chunks)?);
+            }
+            self.report_progress(Stage::SketchesBuilt, sketches.len(), false);
+            Ok(())
+        })?;
+        self.report_progress(Stage::SketchesBuilt, num_docs, true);
+        self.set_sketches(sketches, num_chunks)?;
         Ok(self)
     }
 
-    /// Builds the database of sketches from input documents in parallel.
+    /// Builds the database of sketches from features extracted in advance, in parallel.
+    /// The features can be extracted once per corpus with
+    /// [`FeatureExtractor::extract_batch_parallel()`] using [`Self::config()`],
+    /// and reused for multiple searchers.
     ///
     /// # Arguments
     ///
-    /// * `documents` - List of documents (must not include an empty string).
+    /// * `features` - List of features. Empty features are handled according to
+    ///   [`Self::short_document_policy()`].
     /// * `num_chunks` - Number of chunks of sketches, indicating that
-    ///                  the number of dimensions in the Hamming space is `num_chunks*64`.
-    ///
-    /// # Notes
-    ///
-    /// The progress is not printed even if `shows_progress = true`.
-    pub fn build_sketches_in_parallel<I, D>(
+    ///   the number of dimensions in the Hamming space is `num_chunks*S::dim()`.
+    pub fn build_sketches_from_features<F>(
         mut self,
-        documents: I,
+        features: &[F],
         num_chunks: usize,
     ) -> Result<Self>
     where
-        I: Iterator<Item = D> + Send,
-        D: AsRef<str> + Send,
+        F: AsRef<[u64]> + Sync,
     {
-        let extractor = FeatureExtractor::new(&self.config);
-        #[allow(clippy::mutex_atomic)]
-        let processed = Mutex::new(0usize);
-        let mut sketches: Vec<_> = documents
-            .into_iter()
-            .enumerate()
-            .par_bridge()
-            .map(|(i, doc)| {
-                #[allow(clippy::mutex_atomic)]
-                {
-                    // Mutex::lock also locks eprintln.
-                    let mut cnt = processed.lock().unwrap();
-                    *cnt += 1;
-                    if self.shows_progress && *cnt % 10000 == 0 {
-                        eprintln!("Processed {} documents...", *cnt);
-                    }
-                }
-                let doc = doc.as_ref();
-                // TODO: Returns the error value (but I dont know the manner).
-                assert!(!doc.is_empty(), "Input document must not be empty.");
-                let mut feature = vec![];
-                extractor.extract_with_weights(doc, &mut feature);
-                if let Some(tf) = self.tf.as_ref() {
-                    tf.tf(&mut feature);
-                }
-                if let Some(idf) = self.idf.as_ref() {
-                    for (term, weight) in feature.iter_mut() {
-                        *weight *= idf.idf(*term);
-                    }
+        let span = tracing::info_span!("build_sketches_from_features", num_chunks);
+        let _enter = span.enter();
+        self.prepare_superbit(num_chunks)?;
+        let sketches = self.install(|| {
+            features
+                .par_iter()
+                .map(|feature| {
+                    let _enter = span.enter();
+                    let mut feature: Vec<_> =
+                        feature.as_ref().iter().map(|&term| (term, 1.)).collect();
+                    self.weight(&mut feature);
+                    self.sketch_feature(None, feature, num_chunks)
+                })
+                .collect::<Result<Vec<_>>>()
+        })?;
+        self.set_sketches(sketches, num_chunks)?;
+        Ok(self)
+    }
+
+    /// Appends sketches of input documents to the database built in advance,
+    /// so that a growing corpus can be searched without rebuilding the whole database.
+    /// The sketches are generated with the same seed as the existing ones, and
+    /// the appended documents are assigned ids following the existing documents.
+    ///
+    /// If labels are attached with [`Self::labels()`], re-attach them for all the documents.
+    ///
+    /// # Arguments
+    ///
+    /// * `documents` - List of documents. Documents from which no feature is extracted,
+    ///   including empty strings, are handled according to [`Self::short_document_policy()`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the sketches are not built or an input document is rejected
+    /// with the short document policy.
+    pub fn add_documents<I, D>(mut self, documents: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = D>,
+        D: AsRef<str>,
+    {
+        let _span = tracing::info_span!("add_documents").entered();
+        let num_chunks = self
+            .num_retained_chunks()
+            .ok_or_else(|| FindSimdocError::input("Sketches must be built."))?;
+        // The config is cloned so that the database can be updated during extraction.
+        let config = self.config.clone();
+        let extractor = FeatureExtractor::new(&config);
+
+        let mut feature = vec![];
+        let mut buffer = TokenBuffer::new();
+        // The progress counts the documents added before, e.g., in preceding batches.
+        let mut num_docs = self.len();
+        self.report_progress(Stage::DocumentsRead, num_docs, false);
+        for (i, doc) in (num_docs..).zip(documents) {
+            // The sketches of the preceding documents have been built.
+            self.report_progress(Stage::DocumentsRead, i + 1, false);
+            self.report_progress(Stage::SketchesBuilt, i, false);
+            num_docs = i + 1;
+            let doc = doc.as_ref();
+            self.check_document(doc)?;
+            tracing::trace_span!("extract")
+                .in_scope(|| self.extract(&extractor, doc, &mut feature, &mut buffer));
+            let retained = self.fill_short_feature(Some(doc), &mut feature)?;
+            self.register_document(retained);
+            if !retained {
+                continue;
+            }
+            let digest = self.separates_duplicates.then(|| self.digest(&feature));
+            if !self.is_distinct(i, digest) {
+                continue;
+            }
+            let norm = self.normalize(&mut feature);
+            let sketch =
+                tracing::trace_span!("hash").in_scope(|| self.sketch(&feature, num_chunks));
+            self.push_sketch(sketch, self.stores_features.then(|| feature.clone()), norm)?;
+        }
+        self.report_progress(Stage::DocumentsRead, num_docs, true);
+        self.report_progress(Stage::SketchesBuilt, num_docs, true);
+        Ok(self)
+    }
+
+    fn sketch_feature(
+        &self,
+        doc: Option<&str>,
+        mut feature: Vec<(u64, f64)>,
+        num_chunks: usize,
+    ) -> Result<Option<SketchEntry<S>>> {
+        if !self.fill_short_feature(doc, &mut feature)? {
+            return Ok(None);
+        }
+        let norm = self.normalize(&mut feature);
+        let sketch = tracing::trace_span!("hash")
+            .in_scope(|| self.sketch(&feature, num_chunks.max(self.num_pooled_chunks)));
+        let digest = self.separates_duplicates.then(|| self.digest(&feature));
+        let feature = self.stores_features.then_some(feature);
+        Ok(Some((sketch, digest, feature, norm)))
+    }
+
+    fn sketch(&self, feature: &[(u64, f64)], num_chunks: usize) -> Vec<S> {
+        match self.superbit.as_ref() {
+            // The sketch type is checked to be u64 in preparing the super-bit LSH.
+            Some(superbit) => superbit
+                .sketch(feature, num_chunks)
+                .into_iter()
+                .map(|x| S::from_u64(x).unwrap())
+                .collect(),
+            None => self.hasher.sketch_as(feature, num_chunks),
+        }
+    }
+
+    /// Materializes the projections of the super-bit LSH for `num_chunks` chunks if enabled.
+    fn prepare_superbit(&mut self, num_chunks: usize) -> Result<()> {
+        if self.superbit_params.is_some() && S::dim() != 64 {
+            return Err(FindSimdocError::input(
+                "The super-bit LSH supports only 64-bit sketches.",
+            ));
+        }
+        self.superbit = match self.superbit_params {
+            Some((dim, depth)) => Some(
+                SuperBitHasher::new(
+                    self.superbit_seed,
+                    dim,
+                    depth,
+                    num_chunks.max(self.num_pooled_chunks),
+                )?
+                .element_hasher(self.hasher.element_hasher_value()),
+            ),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Normalizes a weighted feature to the unit L2 norm if enabled,
+    /// returning the norm before normalization.
+    fn normalize(&self, feature: &mut [(u64, f64)]) -> Option<f64> {
+        if !self.normalizes_l2 {
+            return None;
+        }
+        let norm = l2_norm(feature.iter().cloned());
+        if norm > 0. {
+            feature.iter_mut().for_each(|(_, w)| *w /= norm);
+        }
+        Some(norm)
+    }
+
+    fn check_document(&self, doc: &str) -> Result<()> {
+        if doc.is_empty() && self.short_document_policy == ShortDocumentPolicy::Error {
+            return Err(FindSimdocError::input("Input document must not be empty."));
+        }
+        Ok(())
+    }
+
+    /// Handles a feature of a document according to the short document policy,
+    /// returning `false` if the document is skipped.
+    fn fill_short_feature(&self, doc: Option<&str>, feature: &mut Vec<(u64, f64)>) -> Result<bool> {
+        if !feature.is_empty() {
+            return Ok(true);
+        }
+        match self.short_document_policy {
+            ShortDocumentPolicy::Error => Err(FindSimdocError::input(
+                "Input document must include at least one feature.",
+            )),
+            ShortDocumentPolicy::Pad => {
+                // Documents given as features share the same padding.
+                feature.push((self.config.hash(doc), 1.));
+                Ok(true)
+            }
+            ShortDocumentPolicy::Skip => Ok(false),
+        }
+    }
+
+    fn register_document(&mut self, retained: bool) {
+        if let Some(skips) = self.skips.as_mut() {
+            skips.push(retained);
+        }
+    }
+
+    fn set_sketches<I>(&mut self, sketches: I, num_chunks: usize) -> Result<()>
+    where
+        I: IntoIterator<Item = Option<SketchEntry<S>>>,
+    {
+        self.init_database(num_chunks);
+        for (i, entry) in sketches.into_iter().enumerate() {
+            self.register_document(entry.is_some());
+            if let Some((sketch, digest, feature, norm)) = entry {
+                if self.is_distinct(i, digest) {
+                    self.push_sketch(sketch, feature, norm)?;
                 }
-                let mut gen = self.hasher.iter(&feature);
-                let sketch: Vec<_> = (0..num_chunks).map(|_| gen.next().unwrap()).collect();
-                (i, sketch)
-            })
-            .collect();
-        sketches.par_sort_by_key(|&(i, _)| i);
+            }
+        }
+        Ok(())
+    }
 
-        let mut joiner = ChunkedJoiner::<u64>::new(num_chunks).shows_progress(self.shows_progress);
-        for (_, sketch) in sketches {
-            joiner.add(sketch).unwrap();
+    /// Reports the progress of a stage per 10000 documents,
+    /// or the final number of documents if `finished`.
+    fn report_progress(&self, stage: Stage, done: usize, finished: bool) {
+        if let Some(progress) = &self.progress {
+            if finished {
+                progress.report(stage, done, Some(done));
+            } else if done.is_multiple_of(10000) {
+                progress.report(stage, done, None);
+            }
         }
-        self.joiner = Some(joiner);
+    }
+
+    fn init_database(&mut self, num_chunks: usize) {
+        let joiner = ChunkedJoiner::<S>::new(num_chunks.max(self.num_pooled_chunks))
+            .progress_hook(self.progress.clone());
+        self.set_joiner(joiner, num_chunks);
+        self.duplicates = self.separates_duplicates.then(DuplicateDetector::default);
+        self.skips =
+            (self.short_document_policy == ShortDocumentPolicy::Skip).then(SkipMap::default);
+        self.features = self.stores_features.then(Vec::new);
+        self.norms = self.normalizes_l2.then(Vec::new);
+    }
+
+    /// Checks if the `i`-th input document is distinct from the preceding ones,
+    /// where duplicates are collapsed only within a label if labels are attached.
+    fn is_distinct(&mut self, i: usize, digest: Option<u64>) -> bool {
+        let label = self
+            .labels
+            .as_ref()
+            .and_then(|labels| labels.get(i).cloned());
+        match (self.duplicates.as_mut(), digest) {
+            (Some(duplicates), Some(digest)) => duplicates.insert(digest, label),
+            _ => true,
+        }
+    }
+
+    fn push_sketch(
+        &mut self,
+        sketch: Vec<S>,
+        feature: Option<Vec<(u64, f64)>>,
+        norm: Option<f64>,
+    ) -> Result<()> {
+        let joiner = self.joiner.as_mut().unwrap();
+        let num_chunks = joiner.num_chunks();
+        joiner.add(sketch[..num_chunks].iter().cloned())?;
+        if let Some(pool) = self.pool.as_mut() {
+            pool.add(sketch[num_chunks..].iter().cloned())?;
+        }
+        if let (Some(features), Some(feature)) = (self.features.as_mut(), feature) {
+            features.push(feature);
+        }
+        if let (Some(norms), Some(norm)) = (self.norms.as_mut(), norm) {
+            norms.push(norm);
+        }
+        Ok(())
+    }
+
+    /// Re-slices the sketches into `num_chunks` chunks from the pool of sketches
+    /// retained in building, without re-hashing documents.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the sketches are not built, or
+    /// `num_chunks` is 0 or more than the number of retained chunks.
+    pub fn with_num_chunks(mut self, num_chunks: usize) -> Result<Self> {
+        let num_retained_chunks = self
+            .num_retained_chunks()
+            .ok_or_else(|| FindSimdocError::input("Sketches must be built."))?;
+        if num_chunks == 0 || num_retained_chunks < num_chunks {
+            return Err(FindSimdocError::input(
+                "The number of chunks must be in the range of [1, #retained chunks].",
+            ));
+        }
+        let mut joiner = self.joiner.take().unwrap();
+        if let Some(pool) = self.pool.take() {
+            joiner.append(pool)?;
+        }
+        self.set_joiner(joiner, num_chunks);
         Ok(self)
     }
 
+    /// Gets the number of chunks in the joiner and the pool, or `None` if not built.
+    fn num_retained_chunks(&self) -> Option<usize> {
+        let joiner = self.joiner.as_ref()?;
+        Some(joiner.num_chunks() + self.pool.as_ref().map_or(0, |pool| pool.num_chunks()))
+    }
+
+    /// Exports the sketches into an [`IndexFile`], with which the database can be restored by
+    /// [`Self::import_sketches()`] without re-hashing documents.
+    /// The header holds the entries of [`Self::fingerprint()`], and
+    /// the `i`-th sketch in the file corresponds to the `i`-th document.
+    ///
+    /// The settings of the searcher are not restored except for checking the seed value,
+    /// so the same settings must be given to the searcher importing the sketches.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the sketches are not built or not of 64 bits, or the database
+    /// holds states other than the sketches, i.e., separated duplicates, skipped documents, or L2 normalization.
+    pub fn export_sketches(&self) -> Result<IndexFile> {
+        self.check_exportable()?;
+        if S::dim() != 64 {
+            return Err(FindSimdocError::input(
+                "Only 64-bit sketches can be exported.",
+            ));
+        }
+        let joiner = self
+            .joiner
+            .as_ref()
+            .ok_or_else(|| FindSimdocError::input("Sketches must be built."))?;
+        let mut file = IndexFile::new(joiner.num_chunks());
+        for (key, value) in self.fingerprint().entries() {
+            file.push_header(key, value)?;
+        }
+        for i in 0..joiner.num_sketches() {
+            file.add(joiner.sketch(i).map(|x| x.to_u64().unwrap()))?;
+        }
+        Ok(file)
+    }
+
+    /// Restores the database from the sketches exported with [`Self::export_sketches()`],
+    /// replacing the existing one.
+    /// The features of documents are not restored even if [`Self::stores_features()`] is enabled.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the seed value in the header is mismatched, the sketches are
+    /// not of 64 bits, or the settings of the searcher are not supported in serialization.
+    pub fn import_sketches(mut self, file: &IndexFile) -> Result<Self> {
+        self.check_exportable()?;
+        if S::dim() != 64 {
+            return Err(FindSimdocError::input(
+                "Only 64-bit sketches can be imported.",
+            ));
+        }
+        file.check_seed(self.seed)?;
+        let mut joiner = ChunkedJoiner::new(file.num_chunks()).progress_hook(self.progress.clone());
+        for i in 0..file.num_sketches() {
+            joiner.add(file.sketch(i).into_iter().map(|x| S::from_u64(x).unwrap()))?;
+        }
+        let num_chunks = joiner.num_chunks();
+        self.init_database(num_chunks);
+        self.features = None;
+        self.norms = None;
+        self.set_joiner(joiner, num_chunks);
+        Ok(self)
+    }
+
+    fn check_exportable(&self) -> Result<()> {
+        if self.separates_duplicates
+            || self.short_document_policy == ShortDocumentPolicy::Skip
+            || self.normalizes_l2
+        {
+            return Err(FindSimdocError::input(
+                "Sketches cannot be exported with separated duplicates, skipped documents, or L2 normalization.",
+            ));
+        }
+        Ok(())
+    }
+
+    fn install<OP, R>(&self, op: OP) -> R
+    where
+        OP: FnOnce() -> R + Send,
+        R: Send,
+    {
+        match self.thread_pool.as_ref() {
+            Some(thread_pool) => thread_pool.install(op),
+            None => op(),
+        }
+    }
+
+    /// Sets the first `num_chunks` chunks of `joiner` to the join and retains the rest,
+    /// moving the sketches instead of copying them.
+    fn set_joiner(&mut self, mut joiner: ChunkedJoiner<S>, num_chunks: usize) {
+        self.pool =
+            (num_chunks < joiner.num_chunks()).then(|| joiner.split_off(num_chunks).unwrap());
+        self.joiner = Some(joiner);
+    }
+
     /// Searches for all pairs of similar documents within an input radius, returning
     /// triplets of the left-side id, the right-side id, and their distance.
     pub fn search_similar_pairs(&self, radius: f64) -> Vec<(usize, usize, f64)> {
-        self.joiner.as_ref().unwrap().similar_pairs(radius)
+        let _span = tracing::info_span!("search_similar_pairs", radius).entered();
+        let mut results = vec![];
+        self.for_each_similar_pair(radius, |i, j, dist| results.push((i, j, dist)));
+        tracing::info!("Found {} similar pairs", results.len());
+        results
+    }
+
+    /// Searches for all pairs of similar documents within an input radius
+    /// in the same manner as [`Self::search_similar_pairs()`], persisting the progress of
+    /// the join to the checkpoint file at `path`.
+    ///
+    /// If the checkpoint file exists, the join resumes from the last completed chunk.
+    /// The sketches must be built from the same documents with the same seed as those
+    /// of the interrupted search. The file is removed after the search is completed.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the checkpoint file cannot be read or written, or
+    /// it was written for different sketches or a different radius.
+    pub fn search_similar_pairs_with_checkpoint<P>(
+        &self,
+        radius: f64,
+        path: P,
+    ) -> Result<Vec<(usize, usize, f64)>>
+    where
+        P: AsRef<Path>,
+    {
+        let _span = tracing::info_span!("search_similar_pairs_with_checkpoint", radius).entered();
+        let mut results = vec![];
+        self.for_each_similar_pair_in(radius, Some(path.as_ref()), |i, j, dist| {
+            results.push((i, j, dist))
+        })?;
+        tracing::info!("Found {} similar pairs", results.len());
+        Ok(results)
+    }
+
+    /// Searches for all pairs of similar documents within an input radius, and
+    /// writes triplets of the left-side id, the right-side id, and their distance to `writer`
+    /// one by one instead of collecting them, returning the number of written pairs.
+    ///
+    /// The candidate pairs are also verified chunk by chunk without being collected,
+    /// so the memory usage stays bounded however many pairs are found.
+    /// Instead, the pairs are written in no particular order.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned as soon as writing a pair fails.
+    pub fn search_similar_pairs_to<W>(
+        &self,
+        radius: f64,
+        writer: W,
+        format: OutputFormat,
+    ) -> Result<usize>
+    where
+        W: Write + Send,
+    {
+        let _span = tracing::info_span!("search_similar_pairs_to", radius).entered();
+        let mut writer = PairWriter::new(writer, format)?;
+        let mut num_pairs = 0;
+        self.try_for_each_similar_pair(radius, |i, j, dist| {
+            writer.write(i, j, dist)?;
+            num_pairs += 1;
+            Ok(())
+        })?;
+        writer.finish()?;
+        tracing::info!("Wrote {} similar pairs", num_pairs);
+        Ok(num_pairs)
+    }
+
+    /// Searches for documents similar to an input query document within an input radius,
+    /// returning pairs of the document id and the distance in ascending order of distances.
+    ///
+    /// The query is converted into a sketch in the same manner as the stored documents and
+    /// compared with all of them, so it takes time linear to the number of documents.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the sketches are not built or the query has no feature.
+    pub fn search_similar_documents(&self, query: &str, radius: f64) -> Result<Vec<(usize, f64)>> {
+        let _span = tracing::info_span!("search_similar_documents", radius).entered();
+        let joiner = self
+            .joiner
+            .as_ref()
+            .ok_or_else(|| FindSimdocError::input("Sketches must be built."))?;
+        let extractor = FeatureExtractor::new(&self.config);
+        let mut feature = vec![];
+        self.extract(&extractor, query, &mut feature, &mut TokenBuffer::new());
+        if feature.is_empty() {
+            return Err(FindSimdocError::input(
+                "The query must include at least one feature.",
+            ));
+        }
+        self.normalize(&mut feature);
+        let sketch = self.sketch(&feature, joiner.num_chunks());
+        let mut results: Vec<_> = self.install(|| {
+            (0..joiner.num_sketches())
+                .into_par_iter()
+                .filter_map(|k| {
+                    let hamdists = joiner
+                        .sketch(k)
+                        .zip(sketch.iter())
+                        .map(|(x, &y)| x.hamdist(y));
+                    let dist = Estimate::from_chunks(hamdists, S::dim(), 1.).distance;
+                    (dist <= radius).then(|| (self.input_id(k), dist))
+                })
+                .collect()
+        });
+        results.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        Ok(results)
+    }
+
+    fn for_each_similar_pair<F>(&self, radius: f64, f: F)
+    where
+        F: FnMut(usize, usize, f64) + Send,
+    {
+        // Never fails without checkpoints.
+        self.for_each_similar_pair_in(radius, None, f).unwrap();
+    }
+
+    fn for_each_similar_pair_in<F>(
+        &self,
+        radius: f64,
+        checkpoint: Option<&Path>,
+        mut f: F,
+    ) -> Result<()>
+    where
+        F: FnMut(usize, usize, f64) + Send,
+    {
+        let Some(joiner) = self.joiner.as_ref() else {
+            return Ok(());
+        };
+        self.install(|| {
+            let g = |i, j, dist| {
+                f(self.input_id(i), self.input_id(j), dist);
+            };
+            match checkpoint {
+                Some(path) => joiner.for_each_similar_pair_with_checkpoint(radius, path, g)?,
+                None => joiner.for_each_similar_pair(radius, g),
+            }
+            Ok(())
+        })
+    }
+
+    fn try_for_each_similar_pair<F>(&self, radius: f64, mut f: F) -> Result<()>
+    where
+        F: FnMut(usize, usize, f64) -> Result<()> + Send,
+    {
+        let Some(joiner) = self.joiner.as_ref() else {
+            return Ok(());
+        };
+        self.install(|| {
+            joiner.try_for_each_similar_pair(radius, |i, j, dist| {
+                f(self.input_id(i), self.input_id(j), dist)
+            })
+        })
+    }
+
+    /// Converts the id of a sketch into that of the input document.
+    fn input_id(&self, k: usize) -> usize {
+        let k = self
+            .duplicates
+            .as_ref()
+            .map_or(k, |duplicates| duplicates.representative(k));
+        self.skips.as_ref().map_or(k, |skips| skips.input_id(k))
+    }
+
+    fn digest(&self, feature: &[(u64, f64)]) -> u64 {
+        DuplicateDetector::digest(&self.config, feature.iter().map(|&(term, _)| term))
+    }
+
+    fn extract(
+        &self,
+        extractor: &FeatureExtractor,
+        doc: &str,
+        feature: &mut Vec<(u64, f64)>,
+        buffer: &mut TokenBuffer,
+    ) {
+        extractor.extract_with_weights_buffered(doc, feature, buffer);
+        self.weight(feature);
+    }
+
+    fn weight(&self, feature: &mut Vec<(u64, f64)>) {
+        if let Some(pruner) = self.df_pruner.as_ref() {
+            feature.retain(|&(term, _)| pruner.accepts(term));
+        }
+        // The weighters multiply the token weights, so they are combined.
+        if let Some(tf) = self.tf.as_ref() {
+            tf.weight(feature);
+        }
+        if let Some(idf) = self.idf.as_ref() {
+            idf.weight(feature);
+        }
+    }
+
+    /// Searches for all pairs of similar documents within an input radius, returning
+    /// triplets of the left-side id, the right-side id, and their estimated distance
+    /// with the standard error.
+    ///
+    /// Use [`Estimate::confidence_interval()`] to threshold on the confidence interval
+    /// rather than the point estimate.
+    pub fn search_similar_pairs_with_estimates(
+        &self,
+        radius: f64,
+    ) -> Vec<(usize, usize, Estimate)> {
+        self.search_similar_pairs(radius)
+            .into_iter()
+            .map(|(i, j, _)| (i, j, self.estimate(i, j)))
+            .collect()
+    }
+
+    /// Computes the distance between the `i`-th and `j`-th documents estimated from their sketches.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the sketches are not built or the ids are out of range.
+    pub fn distance(&self, i: usize, j: usize) -> Result<f64> {
+        self.check_ids(i, j)?;
+        Ok(self.estimate(i, j).distance)
+    }
+
+    /// Checks if the distance between the `i`-th and `j`-th documents estimated from their sketches
+    /// is within an input radius.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the sketches are not built or the ids are out of range.
+    pub fn is_similar(&self, i: usize, j: usize, radius: f64) -> Result<bool> {
+        Ok(self.distance(i, j)? <= radius)
+    }
+
+    /// Computes the exact angular distance (approximated by the sketches) of weighted features
+    /// between the `i`-th and `j`-th documents from their stored features.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the features are not stored (see [`Self::stores_features()`])
+    /// or the ids are out of range.
+    pub fn exact_distance(&self, i: usize, j: usize) -> Result<f64> {
+        self.check_ids(i, j)?;
+        let features = self
+            .features
+            .as_ref()
+            .ok_or_else(|| FindSimdocError::input("Features must be stored."))?;
+        let (i, j) = self.sketch_ids(i, j);
+        Ok(angular_distance(
+            features[i].iter().cloned(),
+            features[j].iter().cloned(),
+        ))
+    }
+
+    /// Gets the L2 norm of the weighted feature of the `i`-th document before normalization,
+    /// with which the exact Cosine similarity can be re-scored from the stored features.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the features are not normalized (see [`Self::l2_normalize()`])
+    /// or the id is out of range.
+    pub fn norm(&self, i: usize) -> Result<f64> {
+        self.check_ids(i, i)?;
+        let norms = self
+            .norms
+            .as_ref()
+            .ok_or_else(|| FindSimdocError::input("Features must be normalized."))?;
+        let (i, _) = self.sketch_ids(i, i);
+        Ok(norms[i])
+    }
+
+    fn check_ids(&self, i: usize, j: usize) -> Result<()> {
+        if self.joiner.is_none() {
+            return Err(FindSimdocError::input("Sketches must be built."));
+        }
+        if self.len() <= i || self.len() <= j {
+            return Err(FindSimdocError::input(
+                "Document ids must be less than the number of documents.",
+            ));
+        }
+        if let Some(skips) = self.skips.as_ref() {
+            if skips.retained_id(i).is_none() || skips.retained_id(j).is_none() {
+                return Err(FindSimdocError::input(
+                    "Skipped documents must not be given.",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn sketch_ids(&self, i: usize, j: usize) -> (usize, usize) {
+        let (i, j) = self.skips.as_ref().map_or((i, j), |skips| {
+            (skips.retained_id(i).unwrap(), skips.retained_id(j).unwrap())
+        });
+        self.duplicates.as_ref().map_or((i, j), |duplicates| {
+            (duplicates.group(i), duplicates.group(j))
+        })
+    }
+
+    fn estimate(&self, i: usize, j: usize) -> Estimate {
+        let joiner = self.joiner.as_ref().unwrap();
+        let (i, j) = self.sketch_ids(i, j);
+        let hamdists = joiner
+            .sketch(i)
+            .zip(joiner.sketch(j))
+            .map(|(x, y)| x.hamdist(y));
+        Estimate::from_chunks(hamdists, S::dim(), 1.)
+    }
+
+    /// Searches for all pairs of similar documents within an input radius
+    /// such that their labels satisfy an input filter, returning
+    /// triplets of the left-side id, the right-side id, and their distance.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the labels are not attached to all the documents.
+    pub fn search_similar_pairs_with_filter(
+        &self,
+        radius: f64,
+        filter: LabelFilter,
+    ) -> Result<Vec<(usize, usize, f64)>> {
+        let mut results = self.search_similar_pairs(radius);
+        if filter != LabelFilter::All {
+            let labels = self.labels.as_ref().ok_or_else(|| {
+                FindSimdocError::input("Labels must be attached to filter results.")
+            })?;
+            if labels.len() != self.len() {
+                return Err(FindSimdocError::input(
+                    "The number of labels must be the same as that of documents.",
+                ));
+            }
+            filter.retain(labels, &mut results);
+        }
+        Ok(results)
+    }
+
+    /// Gets the groups of exact duplicate documents, where each group consists of
+    /// two or more ids of documents with identical features.
+    /// The groups are empty unless [`Self::separates_duplicates()`] is enabled.
+    pub fn duplicate_groups(&self) -> Vec<Vec<usize>> {
+        let mut groups = self
+            .duplicates
+            .as_ref()
+            .map_or_else(Vec::new, |duplicates| duplicates.duplicate_groups());
+        if let Some(skips) = self.skips.as_ref() {
+            for i in groups.iter_mut().flatten() {
+                *i = skips.input_id(*i);
+            }
+        }
+        groups
+    }
+
+    /// Gets the ids of documents skipped with [`ShortDocumentPolicy::Skip`] in ascending order.
+    pub fn skipped_documents(&self) -> Vec<usize> {
+        self.skips
+            .as_ref()
+            .map_or_else(Vec::new, |skips| skips.skipped())
     }
 
     /// Gets the number of input documents.
     pub fn len(&self) -> usize {
-        self.joiner
-            .as_ref()
-            .map_or(0, |joiner| joiner.num_sketches())
+        if let Some(skips) = self.skips.as_ref() {
+            return skips.num_docs();
+        }
+        self.duplicates.as_ref().map_or_else(
+            || {
+                self.joiner
+                    .as_ref()
+                    .map_or(0, |joiner| joiner.num_sketches())
+            },
+            |duplicates| duplicates.num_docs(),
+        )
     }
 
     /// Checks if the database is empty.
@@ -232,13 +1410,230 @@ impl CosineSearcher {
 
     /// Gets the memory usage in bytes.
     pub fn memory_in_bytes(&self) -> usize {
-        self.joiner
+        let sketches: usize = self
+            .joiner
+            .iter()
+            .chain(self.pool.iter())
+            .map(|joiner| joiner.memory_in_bytes())
+            .sum();
+        let features = self.features.as_ref().map_or(0, |features| {
+            features.iter().map(|f| f.len()).sum::<usize>() * std::mem::size_of::<(u64, f64)>()
+        });
+        let norms = self
+            .norms
             .as_ref()
-            .map_or(0, |joiner| joiner.memory_in_bytes())
+            .map_or(0, |norms| norms.len() * std::mem::size_of::<f64>());
+        sketches + features + norms
     }
 
     /// Gets the configure of feature extraction.
     pub const fn config(&self) -> &FeatureConfig {
         &self.config
     }
+
+    /// Gets the fingerprint of all the settings used to build the database,
+    /// with which the search results can be reproduced.
+    pub fn fingerprint(&self) -> Fingerprint {
+        let mut fingerprint = Fingerprint::new(Metric::Cosine, self.seed);
+        fingerprint.window_size = self.config.window_size();
+        fingerprint.max_window_size = self.config.max_window_size();
+        fingerprint.delimiter = self.config.delimiter();
+        fingerprint.delimiter_regex = self.config.delimiter_regex_pattern().map(String::from);
+        fingerprint.token_unit = self.config.unit_of_tokens();
+        fingerprint.canonical_kmers = self.config.is_canonical_kmers();
+        fingerprint.rolling_hash = self.config.is_rolling_hash();
+        fingerprint.tokenizer = self.config.tokenizer_name().map(String::from);
+        fingerprint.preprocesses = self.config.has_preprocessor();
+        fingerprint.weights_tokens = self.config.has_token_weighter();
+        fingerprint.normalizes_nfkc = self.config.is_nfkc_normalized();
+        fingerprint.lowercases = self.config.is_lowercased();
+        fingerprint.folds_digits = self.config.folds_digits();
+        fingerprint.strips_punctuation = self.config.strips_punctuation();
+        fingerprint.collapses_whitespace = self.config.collapses_whitespace();
+        fingerprint.df_range = self
+            .df_pruner
+            .as_ref()
+            .map(|pruner| (pruner.min_df_count(), pruner.max_df_count()));
+        fingerprint.max_features_per_doc = self.config.max_features_per_doc_count();
+        fingerprint.position_buckets = self.config.num_position_buckets();
+        fingerprint.stopwords = self
+            .config
+            .stopwords_sorted()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        fingerprint.num_chunks = self.joiner.as_ref().map_or(0, |j| j.num_chunks());
+        fingerprint.separates_duplicates = self.separates_duplicates;
+        fingerprint.sketch_bits = S::dim();
+        fingerprint.superbit = self.superbit_params;
+        fingerprint.element_hasher = self.hasher.element_hasher_value();
+        fingerprint.short_document_policy = self.short_document_policy;
+        fingerprint.tf_weighter = self.tf.as_ref().map(|tf| tf.description());
+        fingerprint.idf_weighter = self.idf.as_ref().map(|idf| idf.description());
+        fingerprint
+    }
+
+    /// Checks if the database was built with the same settings as an input fingerprint,
+    /// returning an error if mismatched.
+    pub fn verify_fingerprint(&self, fingerprint: &Fingerprint) -> Result<()> {
+        self.fingerprint().verify(fingerprint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_sketches_with_idf() {
+        let documents = ["a b c a", "a b d", "a b c a e", "x y z"];
+        let idf = Idf::new()
+            .build(
+                documents.iter(),
+                CosineSearcher::new(1, Some(' '), Some(42))
+                    .unwrap()
+                    .config(),
+            )
+            .unwrap();
+        let expected = CosineSearcher::new(1, Some(' '), Some(42))
+            .unwrap()
+            .idf(Some(idf))
+            .build_sketches(documents.iter(), 4)
+            .unwrap();
+
+        for dir in [None, Some(std::env::temp_dir())] {
+            let searcher = CosineSearcher::new(1, Some(' '), Some(42))
+                .unwrap()
+                .spill_dir(dir)
+                .build_sketches_with_idf(documents.iter(), Idf::new(), 4)
+                .unwrap();
+            assert_eq!(searcher.fingerprint(), expected.fingerprint());
+            assert_eq!(
+                searcher.search_similar_pairs(0.5),
+                expected.search_similar_pairs(0.5)
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_similar_documents() {
+        let documents = ["a b c d e f", "a b c d e g", "u v w x y z"];
+        let searcher = CosineSearcher::new(1, Some(' '), Some(42))
+            .unwrap()
+            .build_sketches(documents.iter(), 8)
+            .unwrap();
+        let results = searcher
+            .search_similar_documents("a b c d e f", 0.3)
+            .unwrap();
+        assert_eq!(results[0], (0, 0.));
+        assert!(results.iter().all(|&(i, dist)| i != 2 && dist <= 0.3));
+        assert!(searcher.search_similar_documents("", 0.3).is_err());
+    }
+
+    #[test]
+    fn test_superbit() {
+        let documents = ["a b c d e f", "a b c d e g", "u v w x y z"];
+        let searcher = CosineSearcher::new(1, Some(' '), Some(42))
+            .unwrap()
+            .superbit(Some((64, 32)))
+            .unwrap()
+            .build_sketches_in_parallel(documents.iter(), 8)
+            .unwrap();
+        assert_eq!(searcher.fingerprint().superbit, Some((64, 32)));
+        let results = searcher.search_similar_pairs(0.3);
+        assert_eq!(results.len(), 1);
+        assert_eq!((results[0].0, results[0].1), (0, 1));
+
+        assert!(CosineSearcher::new(1, Some(' '), Some(42))
+            .unwrap()
+            .superbit(Some((8, 16)))
+            .is_err());
+    }
+
+    #[test]
+    fn test_sketch_type() {
+        let documents = ["a b c d e f", "a b c d e g", "u v w x y z"];
+        let expected = CosineSearcher::new(1, Some(' '), Some(42))
+            .unwrap()
+            .build_sketches(documents.iter(), 8)
+            .unwrap();
+        // 4 chunks of u128 are the same as 8 chunks of u64.
+        let searcher = CosineSearcher::new(1, Some(' '), Some(42))
+            .unwrap()
+            .sketch_type::<u128>()
+            .build_sketches_in_parallel(documents.iter(), 4)
+            .unwrap();
+        assert_eq!(searcher.fingerprint().sketch_bits, 128);
+        assert_eq!(
+            searcher.search_similar_pairs(0.3),
+            expected.search_similar_pairs(0.3)
+        );
+
+        assert!(CosineSearcher::new(1, Some(' '), Some(42))
+            .unwrap()
+            .superbit(Some((64, 32)))
+            .unwrap()
+            .sketch_type::<u32>()
+            .build_sketches(documents.iter(), 8)
+            .is_err());
+    }
+
+    #[test]
+    fn test_l2_normalize() {
+        let documents = ["a b a", "a b c d", "x y"];
+        let searcher = CosineSearcher::new(1, Some(' '), Some(42))
+            .unwrap()
+            .l2_normalize(true)
+            .stores_features(true)
+            .build_sketches(documents.iter(), 2)
+            .unwrap();
+        assert!((searcher.norm(0).unwrap() - 5f64.sqrt()).abs() < 1e-9);
+        assert!((searcher.norm(1).unwrap() - 2.).abs() < 1e-9);
+        for feature in searcher.features.as_ref().unwrap() {
+            assert!((l2_norm(feature.iter().cloned()) - 1.).abs() < 1e-9);
+        }
+        assert!(searcher.norm(3).is_err());
+
+        let searcher = CosineSearcher::new(1, Some(' '), Some(42))
+            .unwrap()
+            .build_sketches(documents.iter(), 2)
+            .unwrap();
+        assert!(searcher.norm(0).is_err());
+    }
+
+    #[test]
+    fn test_import_sketches() {
+        let documents = ["abcdefg", "abcdefh", "abcxyzw", "hijklmn"];
+        let searcher = CosineSearcher::new(2, None, Some(42))
+            .unwrap()
+            .build_sketches(documents.iter(), 4)
+            .unwrap();
+        let mut data = vec![];
+        searcher
+            .export_sketches()
+            .unwrap()
+            .serialize_into(&mut data)
+            .unwrap();
+        let file = IndexFile::deserialize_from(&data[..]).unwrap();
+
+        let other = CosineSearcher::new(2, None, Some(42))
+            .unwrap()
+            .import_sketches(&file)
+            .unwrap();
+        assert_eq!(other.len(), searcher.len());
+        assert_eq!(
+            other.search_similar_pairs(0.5),
+            searcher.search_similar_pairs(0.5)
+        );
+
+        assert!(CosineSearcher::new(2, None, Some(43))
+            .unwrap()
+            .import_sketches(&file)
+            .is_err());
+        assert!(CosineSearcher::new(2, None, Some(42))
+            .unwrap()
+            .l2_normalize(true)
+            .import_sketches(&file)
+            .is_err());
+    }
 }
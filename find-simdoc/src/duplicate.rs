@@ -0,0 +1,170 @@
+//! Detector of exact duplicate documents.
+use hashbrown::hash_map::Entry;
+use hashbrown::HashMap;
+
+use crate::feature::FeatureConfig;
+
+/// Detector of exact duplicate documents, i.e., documents with identical feature multisets.
+///
+/// Documents are registered in order, and those identical to a registered one are grouped.
+/// The `k`-th group corresponds to the `k`-th distinct document.
+#[derive(Default)]
+pub struct DuplicateDetector {
+    digests: HashMap<(u64, Option<usize>), usize>,
+    groups: Vec<Vec<usize>>,
+    assignments: Vec<usize>,
+}
+
+impl DuplicateDetector {
+    /// Computes the digest of a feature multiset.
+    pub fn digest<I>(config: &FeatureConfig, terms: I) -> u64
+    where
+        I: IntoIterator<Item = u64>,
+    {
+        let mut terms: Vec<_> = terms.into_iter().collect();
+        terms.sort_unstable();
+        config.hash(terms)
+    }
+
+    /// Registers the next document with its label if any, returning `true` if it is distinct
+    /// from the registered ones with the same label.
+    /// Documents with different labels are never grouped, so that the pairs spanning labels
+    /// are still searched.
+    pub fn insert(&mut self, digest: u64, label: Option<usize>) -> bool {
+        let doc_id = self.assignments.len();
+        match self.digests.entry((digest, label)) {
+            Entry::Occupied(e) => {
+                self.groups[*e.get()].push(doc_id);
+                self.assignments.push(*e.get());
+                false
+            }
+            Entry::Vacant(e) => {
+                e.insert(self.groups.len());
+                self.assignments.push(self.groups.len());
+                self.groups.push(vec![doc_id]);
+                true
+            }
+        }
+    }
+
+    /// Gets the id of the first document in the `k`-th group.
+    pub fn representative(&self, k: usize) -> usize {
+        self.groups[k][0]
+    }
+
+    /// Gets the id of the group including the `i`-th document.
+    pub fn group(&self, i: usize) -> usize {
+        self.assignments[i]
+    }
+
+    /// Gets the number of registered documents.
+    pub fn num_docs(&self) -> usize {
+        self.assignments.len()
+    }
+
+    /// Gets the groups of two or more identical documents.
+    pub fn duplicate_groups(&self) -> Vec<Vec<usize>> {
+        self.groups
+            .iter()
+            .filter(|g| 2 <= g.len())
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::label::LabelFilter;
+    use crate::JaccardSearcher;
+
+    #[test]
+    fn test_insert() {
+        let mut detector = DuplicateDetector::default();
+        assert!(detector.insert(3, None));
+        assert!(detector.insert(1, None));
+        assert!(!detector.insert(3, None));
+        assert!(detector.insert(2, None));
+        assert!(!detector.insert(1, None));
+        assert!(!detector.insert(3, None));
+
+        assert_eq!(detector.num_docs(), 6);
+        assert_eq!(detector.representative(0), 0);
+        assert_eq!(detector.representative(1), 1);
+        assert_eq!(detector.representative(2), 3);
+        assert_eq!(detector.group(4), 1);
+        assert_eq!(detector.group(5), 0);
+        assert_eq!(detector.duplicate_groups(), vec![vec![0, 2, 5], vec![1, 4]]);
+    }
+
+    #[test]
+    fn test_insert_with_label() {
+        let mut detector = DuplicateDetector::default();
+        assert!(detector.insert(3, Some(0)));
+        assert!(detector.insert(3, Some(1)));
+        assert!(!detector.insert(3, Some(0)));
+        assert!(detector.insert(3, None));
+        assert_eq!(detector.duplicate_groups(), vec![vec![0, 2]]);
+    }
+
+    #[test]
+    fn test_digest() {
+        let config = FeatureConfig::new(1, None, 42).unwrap();
+        assert_eq!(
+            DuplicateDetector::digest(&config, [1, 2, 2, 3]),
+            DuplicateDetector::digest(&config, [2, 3, 2, 1])
+        );
+        assert_ne!(
+            DuplicateDetector::digest(&config, [1, 2, 3]),
+            DuplicateDetector::digest(&config, [1, 2, 2, 3])
+        );
+    }
+
+    #[test]
+    fn test_searcher() {
+        let documents = ["abcd", "wxyz", "abcd", "abce", "wxyz", "abcd"];
+        for parallel in [false, true] {
+            let searcher = JaccardSearcher::new(2, None, Some(42))
+                .unwrap()
+                .separates_duplicates(true);
+            let searcher = if parallel {
+                searcher.build_sketches_in_parallel(documents.iter(), 4)
+            } else {
+                searcher.build_sketches(documents.iter(), 4)
+            }
+            .unwrap();
+            assert_eq!(searcher.len(), 6);
+            assert_eq!(searcher.duplicate_groups(), vec![vec![0, 2, 5], vec![1, 4]]);
+            let results = searcher.search_similar_pairs(0.9);
+            assert!(results.iter().any(|&(i, j, _)| (i, j) == (0, 3)));
+            assert!(results
+                .iter()
+                .all(|&(i, j, _)| [0, 1, 3].contains(&i) && [0, 1, 3].contains(&j)));
+        }
+    }
+
+    #[test]
+    fn test_searcher_with_labels() {
+        let documents = ["abcd", "wxyz", "abcd", "abce", "wxyz", "abcd"];
+        let labels = vec![0, 0, 1, 0, 0, 1];
+        for parallel in [false, true] {
+            let searcher = JaccardSearcher::new(2, None, Some(42))
+                .unwrap()
+                .separates_duplicates(true)
+                .labels(Some(labels.clone()));
+            let searcher = if parallel {
+                searcher.build_sketches_in_parallel(documents.iter(), 4)
+            } else {
+                searcher.build_sketches(documents.iter(), 4)
+            }
+            .unwrap();
+            // The copies of "abcd" with different labels are not collapsed.
+            assert_eq!(searcher.duplicate_groups(), vec![vec![1, 4], vec![2, 5]]);
+            let results = searcher
+                .search_similar_pairs_with_filter(0.1, LabelFilter::CrossLabelOnly)
+                .unwrap();
+            assert!(results.iter().any(|&(i, j, _)| (i, j) == (0, 2)));
+        }
+    }
+}
@@ -1,6 +1,10 @@
 //! Error definitions.
 use std::error::Error;
-use std::{fmt, result};
+use std::{fmt, io, result};
+
+use all_pairs_hamming::errors::AllPairsHammingError;
+#[cfg(feature = "parallel")]
+use rayon::ThreadPoolBuildError;
 
 /// A specialized Result type for this library.
 pub type Result<T, E = FindSimdocError> = result::Result<T, E>;
@@ -10,28 +14,83 @@ pub type Result<T, E = FindSimdocError> = result::Result<T, E>;
 pub enum FindSimdocError {
     /// Contains [`InputError`].
     Input(InputError),
+
+    /// Contains [`io::Error`].
+    Io(io::Error),
+
+    /// Contains [`BuildError`].
+    Build(BuildError),
+
+    /// Contains [`SerializationError`].
+    Serialization(SerializationError),
 }
 
 impl fmt::Display for FindSimdocError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Input(e) => e.fmt(f),
+            Self::Io(e) => write!(f, "IoError: {e}"),
+            Self::Build(e) => e.fmt(f),
+            Self::Serialization(e) => e.fmt(f),
         }
     }
 }
 
-impl Error for FindSimdocError {}
+impl Error for FindSimdocError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl FindSimdocError {
-    pub(crate) const fn input(msg: &'static str) -> Self {
-        Self::Input(InputError { msg })
+    pub(crate) fn input<S>(msg: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Input(InputError { msg: msg.into() })
+    }
+
+    pub(crate) fn build<S>(msg: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Build(BuildError { msg: msg.into() })
+    }
+
+    pub(crate) fn serialization<S>(msg: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Serialization(SerializationError { msg: msg.into() })
+    }
+}
+
+impl From<io::Error> for FindSimdocError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<AllPairsHammingError> for FindSimdocError {
+    fn from(e: AllPairsHammingError) -> Self {
+        Self::build(e.to_string())
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl From<ThreadPoolBuildError> for FindSimdocError {
+    fn from(e: ThreadPoolBuildError) -> Self {
+        Self::build(e.to_string())
     }
 }
 
 /// Error used when the input argument is invalid.
 #[derive(Debug)]
 pub struct InputError {
-    msg: &'static str,
+    msg: String,
 }
 
 impl fmt::Display for InputError {
@@ -39,3 +98,27 @@ impl fmt::Display for InputError {
         write!(f, "InputError: {}", self.msg)
     }
 }
+
+/// Error used when building a component fails.
+#[derive(Debug)]
+pub struct BuildError {
+    msg: String,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BuildError: {}", self.msg)
+    }
+}
+
+/// Error used when serializing or deserializing data fails.
+#[derive(Debug)]
+pub struct SerializationError {
+    msg: String,
+}
+
+impl fmt::Display for SerializationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SerializationError: {}", self.msg)
+    }
+}
@@ -0,0 +1,237 @@
+//! Estimated distances with their uncertainty.
+use crate::errors::{FindSimdocError, Result};
+
+/// Distance estimated from binary sketches, along with its standard error.
+///
+/// Each chunk of 64 dimensions provides an independent estimate of the distance,
+/// and the standard error is derived from the dispersion of the estimates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Estimate {
+    /// Estimated distance, i.e., the mean of the estimates over chunks.
+    pub distance: f64,
+    /// Standard error of the estimated distance.
+    pub std_error: f64,
+}
+
+impl Estimate {
+    /// Computes the estimate from the Hamming distances of chunks in `dim` dimensions,
+    /// where each normalized distance is multiplied by `scale`.
+    #[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+    pub(crate) fn from_chunks<I>(hamdists: I, dim: usize, scale: f64) -> Self
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        let dists: Vec<_> = hamdists
+            .into_iter()
+            .map(|d| d as f64 / dim as f64 * scale)
+            .collect();
+        let m = dists.len() as f64;
+        let distance = dists.iter().sum::<f64>() / m;
+        let std_error = if dists.len() < 2 {
+            // Falls back to the binomial variance over dimensions.
+            let p = distance / scale;
+            scale * (p * (1. - p) / dim as f64).sqrt()
+        } else {
+            let var = dists.iter().map(|d| (d - distance).powi(2)).sum::<f64>() / (m - 1.);
+            (var / m).sqrt()
+        };
+        Self {
+            distance,
+            std_error,
+        }
+    }
+
+    /// Gets the confidence interval of the distance with a given z-score
+    /// (e.g., 1.96 for the 95% confidence), clamped into the range of [0,1].
+    pub fn confidence_interval(&self, z: f64) -> (f64, f64) {
+        let lower = (self.distance - z * self.std_error).clamp(0., 1.);
+        let upper = (self.distance + z * self.std_error).clamp(0., 1.);
+        (lower, upper)
+    }
+}
+
+/// Computes the probability that a sample of the b-bit minwise hashing collides
+/// for a Jaccard similarity, i.e., `J + (1 - J) 2^-b`.
+pub fn minhash_collision_probability(jaccard: f64, bits: usize) -> f64 {
+    jaccard + (1. - jaccard) * 0.5f64.powi(bits as i32)
+}
+
+/// Computes the probability that a bit of the simhash collides for a Cosine similarity,
+/// i.e., `1 - arccos(cos) / π`.
+pub fn simhash_collision_probability(cosine: f64) -> f64 {
+    1. - cosine.clamp(-1., 1.).acos() / std::f64::consts::PI
+}
+
+/// Computes the expected standard error of the Jaccard distance estimated
+/// from `num_chunks` sketches of the b-bit minwise hashing.
+///
+/// # Arguments
+///
+/// * `distance` - Jaccard distance.
+/// * `bits` - Number of bits retained from each minimum hash value (1, 2, 4, or 8).
+/// * `num_chunks` - Number of chunks of sketches (must be more than 0).
+///
+/// # Errors
+///
+/// An error is returned if `bits` or `num_chunks` is invalid.
+pub fn minhash_std_error(distance: f64, bits: usize, num_chunks: usize) -> Result<f64> {
+    check_bits(bits)?;
+    check_num_chunks(num_chunks)?;
+    let scale = minhash_scale(bits);
+    Ok(binomial_std_error(distance / scale, num_chunks * 64 / bits) * scale)
+}
+
+/// Computes the expected standard error of the angular distance estimated
+/// from `num_chunks` sketches of the simhash.
+///
+/// # Errors
+///
+/// An error is returned if `num_chunks` is 0.
+pub fn simhash_std_error(distance: f64, num_chunks: usize) -> Result<f64> {
+    check_num_chunks(num_chunks)?;
+    Ok(binomial_std_error(distance, num_chunks * 64))
+}
+
+/// Computes the minimum number of chunks of sketches of the b-bit minwise hashing
+/// such that the standard error of the Jaccard distance is at most `std_error`.
+///
+/// # Arguments
+///
+/// * `distance` - Jaccard distance of interest, e.g., the search radius.
+/// * `bits` - Number of bits retained from each minimum hash value (1, 2, 4, or 8).
+/// * `std_error` - Required standard error (must be positive).
+///
+/// # Errors
+///
+/// An error is returned if `bits` or `std_error` is invalid.
+pub fn minhash_num_chunks(distance: f64, bits: usize, std_error: f64) -> Result<usize> {
+    check_bits(bits)?;
+    check_std_error(std_error)?;
+    let scale = minhash_scale(bits);
+    Ok(required_samples(distance / scale, std_error / scale).div_ceil(64 / bits))
+}
+
+/// Computes the minimum number of chunks of sketches of the simhash
+/// such that the standard error of the angular distance is at most `std_error`.
+///
+/// # Arguments
+///
+/// * `distance` - Angular distance of interest, e.g., the search radius.
+/// * `std_error` - Required standard error (must be positive).
+///
+/// # Errors
+///
+/// An error is returned if `std_error` is not positive.
+pub fn simhash_num_chunks(distance: f64, std_error: f64) -> Result<usize> {
+    check_std_error(std_error)?;
+    Ok(required_samples(distance, std_error).div_ceil(64))
+}
+
+fn check_bits(bits: usize) -> Result<()> {
+    if !matches!(bits, 1 | 2 | 4 | 8) {
+        return Err(FindSimdocError::input(
+            "The number of bits must be 1, 2, 4, or 8.",
+        ));
+    }
+    Ok(())
+}
+
+fn check_num_chunks(num_chunks: usize) -> Result<()> {
+    if num_chunks == 0 {
+        return Err(FindSimdocError::input(
+            "The number of chunks must be more than 0.",
+        ));
+    }
+    Ok(())
+}
+
+fn check_std_error(std_error: f64) -> Result<()> {
+    if std_error.is_nan() || std_error <= 0. {
+        return Err(FindSimdocError::input(
+            "The standard error must be positive.",
+        ));
+    }
+    Ok(())
+}
+
+fn minhash_scale(bits: usize) -> f64 {
+    1. / (1. - 0.5f64.powi(bits as i32))
+}
+
+/// Standard error of the ratio of `n` samples with the probability `p`.
+fn binomial_std_error(p: f64, n: usize) -> f64 {
+    let p = p.clamp(0., 1.);
+    (p * (1. - p) / n as f64).sqrt()
+}
+
+/// Number of samples such that the standard error of the ratio with the probability `p`
+/// is at most `std_error`, which is at least one.
+fn required_samples(p: f64, std_error: f64) -> usize {
+    let p = p.clamp(0., 1.);
+    ((p * (1. - p) / (std_error * std_error)).ceil() as usize).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_chunks() {
+        let est = Estimate::from_chunks([8, 16, 8, 16], 64, 1.);
+        assert_eq!(est.distance, 0.1875);
+        // The sample standard deviation is 0.0721..., divided by sqrt(4).
+        let sd = ((4. * 0.0625f64.powi(2)) / 3.).sqrt();
+        assert!((est.std_error - sd / 2.).abs() < 1e-12);
+
+        let est = Estimate::from_chunks([16], 64, 2.);
+        assert_eq!(est.distance, 0.5);
+        assert!((est.std_error - 2. * (0.25f64 * 0.75 / 64.).sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_collision_probability() {
+        assert_eq!(minhash_collision_probability(0.5, 1), 0.75);
+        assert_eq!(minhash_collision_probability(1., 8), 1.);
+        assert!((simhash_collision_probability(0.) - 0.5).abs() < 1e-12);
+        assert_eq!(simhash_collision_probability(1.), 1.);
+    }
+
+    #[test]
+    fn test_std_error() {
+        // Consistent with the fallback of a single chunk.
+        let est = Estimate::from_chunks([16], 64, 2.);
+        assert!((minhash_std_error(est.distance, 1, 1).unwrap() - est.std_error).abs() < 1e-12);
+        assert!(
+            (simhash_std_error(0.25, 4).unwrap() - (0.25f64 * 0.75 / 256.).sqrt()).abs() < 1e-12
+        );
+
+        for bits in [1, 2, 4, 8] {
+            let num_chunks = minhash_num_chunks(0.3, bits, 0.01).unwrap();
+            assert!(minhash_std_error(0.3, bits, num_chunks).unwrap() <= 0.01);
+            assert!(minhash_std_error(0.3, bits, num_chunks - 1).unwrap() > 0.01);
+        }
+        let num_chunks = simhash_num_chunks(0.1, 0.005).unwrap();
+        assert!(simhash_std_error(0.1, num_chunks).unwrap() <= 0.005);
+        assert!(simhash_std_error(0.1, num_chunks - 1).unwrap() > 0.005);
+        assert_eq!(simhash_num_chunks(0., 0.01).unwrap(), 1);
+
+        assert!(minhash_std_error(0.3, 0, 1).is_err());
+        assert!(minhash_std_error(0.3, 3, 1).is_err());
+        assert!(minhash_std_error(0.3, 1, 0).is_err());
+        assert!(simhash_std_error(0.3, 0).is_err());
+        assert!(minhash_num_chunks(0.3, 0, 0.01).is_err());
+        assert!(minhash_num_chunks(0.3, 1, 0.).is_err());
+        assert!(simhash_num_chunks(0.3, -0.1).is_err());
+        assert!(simhash_num_chunks(0.3, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_confidence_interval() {
+        let est = Estimate {
+            distance: 0.1,
+            std_error: 0.1,
+        };
+        assert_eq!(est.confidence_interval(2.), (0., 0.30000000000000004));
+    }
+}
@@ -0,0 +1,272 @@
+//! Exact distance functions and all pairs similarity search on features.
+use std::hash::Hash;
+
+use hashbrown::{HashMap, HashSet};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Computes the Jaccard distance.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::exact::jaccard_distance;
+///
+/// let x = vec![1, 2, 4];
+/// let y = vec![1, 2, 5, 7];
+/// assert_eq!(jaccard_distance(x, y), 0.6);
+/// ```
+pub fn jaccard_distance<I, T>(lhs: I, rhs: I) -> f64
+where
+    I: IntoIterator<Item = T>,
+    T: Hash + Eq,
+{
+    let a = HashSet::<T>::from_iter(lhs);
+    let b = HashSet::<T>::from_iter(rhs);
+    1. - (a.intersection(&b).count() as f64) / (a.union(&b).count() as f64)
+}
+
+/// Computes the bag (or multiset) Jaccard distance, i.e., one minus
+/// `Σ min(x_i, y_i) / Σ max(x_i, y_i)` for the multiplicities `x_i` and `y_i` of element `i`.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::exact::bag_jaccard_distance;
+///
+/// let x = vec![1, 1, 2];
+/// let y = vec![1, 2, 2];
+/// assert_eq!(bag_jaccard_distance(x, y), 0.5);
+/// ```
+pub fn bag_jaccard_distance<I, T>(lhs: I, rhs: I) -> f64
+where
+    I: IntoIterator<Item = T>,
+    T: Hash + Eq,
+{
+    let mut counts = HashMap::<T, (usize, usize)>::new();
+    for x in lhs {
+        counts.entry(x).or_default().0 += 1;
+    }
+    for y in rhs {
+        counts.entry(y).or_default().1 += 1;
+    }
+    let (min, max) = counts.values().fold((0, 0), |(min, max), &(a, b)| {
+        (min + a.min(b), max + a.max(b))
+    });
+    if max == 0 {
+        return 0.;
+    }
+    1. - min as f64 / max as f64
+}
+
+/// Computes the Dice distance, i.e., one minus `2|X ∩ Y| / (|X| + |Y|)`.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::exact::dice_distance;
+///
+/// let x = vec![1, 2, 4];
+/// let y = vec![1, 2, 5, 7];
+/// assert!((dice_distance(x, y) - 3. / 7.).abs() < 1e-9);
+/// ```
+pub fn dice_distance<I, T>(lhs: I, rhs: I) -> f64
+where
+    I: IntoIterator<Item = T>,
+    T: Hash + Eq,
+{
+    let a = HashSet::<T>::from_iter(lhs);
+    let b = HashSet::<T>::from_iter(rhs);
+    let total = a.len() + b.len();
+    if total == 0 {
+        return 0.;
+    }
+    1. - (2 * a.intersection(&b).count()) as f64 / total as f64
+}
+
+/// Computes the overlap distance, i.e., one minus `|X ∩ Y| / min(|X|, |Y|)`,
+/// which is zero if either set contains the other.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::exact::overlap_distance;
+///
+/// let x = vec![1, 2];
+/// let y = vec![1, 2, 5, 7];
+/// assert_eq!(overlap_distance(x, y), 0.);
+/// ```
+pub fn overlap_distance<I, T>(lhs: I, rhs: I) -> f64
+where
+    I: IntoIterator<Item = T>,
+    T: Hash + Eq,
+{
+    let a = HashSet::<T>::from_iter(lhs);
+    let b = HashSet::<T>::from_iter(rhs);
+    let min = a.len().min(b.len());
+    if min == 0 {
+        return 0.;
+    }
+    1. - a.intersection(&b).count() as f64 / min as f64
+}
+
+/// Computes the Cosine distance, i.e., one minus the Cosine similarity,
+/// of weighted features, where weights of the same term are summed up.
+///
+/// Note that [`crate::CosineSearcher`] approximates [`angular_distance`] instead.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::exact::cosine_distance;
+///
+/// let x = vec![(1, 1.), (2, 1.)];
+/// let y = vec![(1, 1.), (3, 1.)];
+/// assert!((cosine_distance(x, y) - 0.5).abs() < 1e-9);
+/// ```
+pub fn cosine_distance<I, T>(lhs: I, rhs: I) -> f64
+where
+    I: IntoIterator<Item = (T, f64)>,
+    T: Hash + Eq,
+{
+    1. - cosine_similarity(lhs, rhs)
+}
+
+/// Computes the angular distance, i.e., the angle between weighted features divided by π,
+/// where weights of the same term are summed up.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::exact::angular_distance;
+///
+/// let x = vec![(1, 1.), (2, 1.)];
+/// let y = vec![(1, 1.), (3, 1.)];
+/// assert!((angular_distance(x, y) - 1. / 3.).abs() < 1e-9);
+/// ```
+pub fn angular_distance<I, T>(lhs: I, rhs: I) -> f64
+where
+    I: IntoIterator<Item = (T, f64)>,
+    T: Hash + Eq,
+{
+    cosine_similarity(lhs, rhs).clamp(-1., 1.).acos() / std::f64::consts::PI
+}
+
+/// Computes the L2 norm of weighted features, where weights of the same term are summed up.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::exact::l2_norm;
+///
+/// let x = vec![(1, 1.), (2, 2.), (1, 1.)];
+/// assert!((l2_norm(x) - 8f64.sqrt()).abs() < 1e-9);
+/// ```
+pub fn l2_norm<I, T>(feature: I) -> f64
+where
+    I: IntoIterator<Item = (T, f64)>,
+    T: Hash + Eq,
+{
+    accumulate(feature)
+        .values()
+        .map(|x| x * x)
+        .sum::<f64>()
+        .sqrt()
+}
+
+fn cosine_similarity<I, T>(lhs: I, rhs: I) -> f64
+where
+    I: IntoIterator<Item = (T, f64)>,
+    T: Hash + Eq,
+{
+    let a = accumulate(lhs);
+    let b = accumulate(rhs);
+    let dot: f64 = a.iter().filter_map(|(t, x)| b.get(t).map(|y| x * y)).sum();
+    let norm_a = a.values().map(|x| x * x).sum::<f64>().sqrt();
+    let norm_b = b.values().map(|x| x * x).sum::<f64>().sqrt();
+    if norm_a == 0. || norm_b == 0. {
+        return 0.;
+    }
+    dot / (norm_a * norm_b)
+}
+
+fn accumulate<I, T>(feature: I) -> HashMap<T, f64>
+where
+    I: IntoIterator<Item = (T, f64)>,
+    T: Hash + Eq,
+{
+    let mut weights = HashMap::new();
+    for (term, weight) in feature {
+        *weights.entry(term).or_insert(0.) += weight;
+    }
+    weights
+}
+
+/// Finds all pairs of similar features within an input radius in parallel, returning
+/// triplets of the left-side id, the right-side id, and their exact distance.
+///
+/// This takes a quadratic time, so use it for ground truth on small samples.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::exact::{jaccard_distance, similar_pairs};
+///
+/// let features = vec![vec![1, 2, 3], vec![1, 2, 4], vec![5, 6]];
+/// let results = similar_pairs(&features, 0.5, |x, y| jaccard_distance(x, y));
+/// assert_eq!(results, vec![(0, 1, 0.5)]);
+/// ```
+pub fn similar_pairs<T, F>(features: &[T], radius: f64, distance: F) -> Vec<(usize, usize, f64)>
+where
+    T: Sync,
+    F: Fn(&T, &T) -> f64 + Sync,
+{
+    let distance = &distance;
+    let pairs = |i: usize| {
+        let x = &features[i];
+        features[i + 1..]
+            .iter()
+            .enumerate()
+            .filter_map(move |(k, y)| {
+                let dist = distance(x, y);
+                (dist <= radius).then_some((i, i + 1 + k, dist))
+            })
+    };
+    #[cfg(feature = "parallel")]
+    let results = (0..features.len())
+        .into_par_iter()
+        .flat_map_iter(pairs)
+        .collect();
+    #[cfg(not(feature = "parallel"))]
+    let results = (0..features.len()).flat_map(pairs).collect();
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_similar_pairs() {
+        let features = vec![
+            vec![(1, 1.), (2, 1.)],
+            vec![(1, 1.), (2, 1.), (2, 1.)],
+            vec![(3, 1.)],
+            vec![(1, 1.), (2, 1.)],
+        ];
+        let mut expected = vec![];
+        for i in 0..features.len() {
+            for j in i + 1..features.len() {
+                let dist = cosine_distance(features[i].clone(), features[j].clone());
+                if dist <= 0.2 {
+                    expected.push((i, j, dist));
+                }
+            }
+        }
+        let results = similar_pairs(&features, 0.2, |x, y| {
+            cosine_distance(x.iter().cloned(), y.iter().cloned())
+        });
+        assert_eq!(results, expected);
+        assert_eq!(results.len(), 3);
+    }
+}
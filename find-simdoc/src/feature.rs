@@ -1,18 +1,133 @@
 //! Feature extractor.
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::hash::{BuildHasher, Hash, Hasher};
-use std::ops::Range;
+use std::iter;
+use std::ops::{Range, RangeInclusive};
+use std::sync::{Arc, Mutex};
 
 use ahash::RandomState;
+use hashbrown::{HashMap, HashSet};
 use rand::{RngCore, SeedableRng};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use regex::Regex;
+use unicode_general_category::{get_general_category, GeneralCategory};
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::errors::{FindSimdocError, Result};
-use crate::shingling::ShingleIter;
+use crate::shingling::{finish_rolling_hash, RollingHasher, ShingleIter, ROLLING_BASE};
+use crate::tokenizer::Tokenizer;
+
+/// Unit of tokens recognized when no delimiter is specified.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum TokenUnit {
+    /// Unicode scalar values (i.e., [`char`]).
+    #[default]
+    Char,
+    /// Extended grapheme clusters, with which emoji sequences and combining marks
+    /// are handled as single tokens.
+    Grapheme,
+    /// Words split at the Unicode word boundaries ([UAX #29](https://www.unicode.org/reports/tr29/)),
+    /// where whitespace and punctuation are ignored.
+    Word,
+    /// Bytes, with which texts are shingled without decoding UTF-8 sequences.
+    /// Inputs with broken encodings can be handled after a lossy conversion
+    /// such as [`String::from_utf8_lossy()`].
+    Byte,
+    /// Nucleotides of DNA or RNA sequences (i.e., `A`, `C`, `G`, and `T` or `U`, case-insensitive),
+    /// with which shingles are k-mers packed into 2-bit-encoded values without hashing
+    /// if the window size is fixed and at most 32.
+    /// K-mers including other characters (e.g., `N`) are skipped.
+    Nucleotide,
+}
+
+/// Policy for documents from which no feature is extracted, such as those
+/// shorter than the window size of k-mers or consisting only of stopwords.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ShortDocumentPolicy {
+    /// Returns an error.
+    #[default]
+    Error,
+    /// Uses the hash value of the whole document as the only feature.
+    Pad,
+    /// Skips the document, which is not searched.
+    /// The ids of the other documents are kept as they are.
+    Skip,
+}
+
+/// User-supplied function preprocessing texts before normalization and tokenization,
+/// such as stripping comments of source codes.
+pub type Preprocessor = Arc<dyn Fn(&str) -> Cow<'_, str> + Send + Sync>;
+
+/// Shared reference to a user-supplied preprocessor.
+#[derive(Clone)]
+struct SharedPreprocessor(Preprocessor);
+
+impl fmt::Debug for SharedPreprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Preprocessor")
+    }
+}
+
+/// User-supplied function weighting tokens (e.g., boosting domain terms),
+/// which is used for weighted features.
+pub type TokenWeighter = Arc<dyn Fn(&str) -> f64 + Send + Sync>;
+
+/// Shared reference to a user-supplied token weighter.
+#[derive(Clone)]
+struct SharedTokenWeighter(TokenWeighter);
+
+impl fmt::Debug for SharedTokenWeighter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TokenWeighter")
+    }
+}
+
+/// Shared reference to a user-supplied tokenizer.
+#[derive(Clone)]
+struct SharedTokenizer(Arc<dyn Tokenizer>);
+
+impl fmt::Debug for SharedTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Tokenizer({})", self.0.name())
+    }
+}
 
 /// Configuration of feature extraction.
 #[derive(Clone, Debug)]
 pub struct FeatureConfig {
     window_size: usize,
+    max_window_size: usize,
     delimiter: Option<char>,
+    token_unit: TokenUnit,
+    tokenizer: Option<SharedTokenizer>,
+    preprocessor: Option<SharedPreprocessor>,
+    token_weighter: Option<SharedTokenWeighter>,
+    delimiter_regex: Option<Regex>,
+    normalize_nfkc: bool,
+    lowercase: bool,
+    fold_digits: bool,
+    strip_punctuation: bool,
+    collapse_whitespace: bool,
+    stopwords: HashSet<String>,
+    canonical_kmers: bool,
+    max_features_per_doc: Option<usize>,
+    position_buckets: Option<usize>,
+    rolling_hash: bool,
     build_hasher: RandomState,
 }
 
@@ -23,7 +138,7 @@ impl FeatureConfig {
     ///
     /// * `window_size` - Window size for w-shingling in feature extraction (must be more than 0).
     /// * `delimiter` - Delimiter for recognizing words as tokens in feature extraction.
-    ///                 If `None`, characters are used for tokens.
+    ///   If `None`, characters are used for tokens.
     /// * `seed` - Seed value for random values.
     pub fn new(window_size: usize, delimiter: Option<char>, seed: u64) -> Result<Self> {
         if window_size == 0 {
@@ -38,33 +153,536 @@ impl FeatureConfig {
         );
         Ok(Self {
             window_size,
+            max_window_size: window_size,
             delimiter,
+            token_unit: TokenUnit::Char,
+            tokenizer: None,
+            preprocessor: None,
+            token_weighter: None,
+            delimiter_regex: None,
+            normalize_nfkc: false,
+            lowercase: false,
+            fold_digits: false,
+            strip_punctuation: false,
+            collapse_whitespace: false,
+            stopwords: HashSet::new(),
+            canonical_kmers: false,
+            max_features_per_doc: None,
+            position_buckets: None,
+            rolling_hash: false,
             build_hasher,
         })
     }
 
-    fn hash<I, T>(&self, iter: I) -> u64
+    /// Sets the range of window sizes for w-shingling, with which
+    /// features combine shingles of multiple lengths (e.g., `2..=4`) in one set.
+    /// This overrides the window size specified in [`Self::new()`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the range is empty or starts with 0.
+    pub fn window_range(mut self, range: RangeInclusive<usize>) -> Result<Self> {
+        let (&min, &max) = (range.start(), range.end());
+        if min == 0 {
+            return Err(FindSimdocError::input("Window size must not be 0."));
+        }
+        if max < min {
+            return Err(FindSimdocError::input("Window range must not be empty."));
+        }
+        self.window_size = min;
+        self.max_window_size = max;
+        Ok(self)
+    }
+
+    /// Sets the unit of tokens recognized when no delimiter is specified.
+    pub const fn token_unit(mut self, unit: TokenUnit) -> Self {
+        self.token_unit = unit;
+        self
+    }
+
+    /// Sets a tokenizer for recognizing tokens in feature extraction,
+    /// which is prioritized over delimiters and the unit of tokens.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn tokenizer(mut self, tokenizer: Option<Arc<dyn Tokenizer>>) -> Self {
+        self.tokenizer = tokenizer.map(SharedTokenizer);
+        self
+    }
+
+    /// Sets a function preprocessing texts before the normalization and tokenization,
+    /// with which documents can be cleaned up (e.g., comments and whitespace in source codes
+    /// are removed for code clone detection) without copying the corpus in advance.
+    /// Texts are borrowed as they are if the function returns [`Cow::Borrowed`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn preprocessor(mut self, preprocessor: Option<Preprocessor>) -> Self {
+        self.preprocessor = preprocessor.map(SharedPreprocessor);
+        self
+    }
+
+    /// Sets a function weighting tokens, with which
+    /// [`FeatureExtractor::extract_with_weights()`] gives each feature
+    /// the average weight of the tokens in the originating shingle instead of 1.0.
+    /// BOS, EOS, and empty tokens are ignored in the average.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn token_weighter(mut self, token_weighter: Option<TokenWeighter>) -> Self {
+        self.token_weighter = token_weighter.map(SharedTokenWeighter);
+        self
+    }
+
+    /// Applies the Unicode NFKC normalization to texts before tokenization?
+    /// If enabled, texts differing only in width or compatibility forms
+    /// (e.g., `"ｆｉｎｄ"` and `"find"`) are treated as identical.
+    pub const fn normalize_nfkc(mut self, yes: bool) -> Self {
+        self.normalize_nfkc = yes;
+        self
+    }
+
+    /// Converts texts into lowercase before tokenization?
+    /// The conversion is applied after the NFKC normalization.
+    pub const fn lowercase(mut self, yes: bool) -> Self {
+        self.lowercase = yes;
+        self
+    }
+
+    /// Replaces numeric characters with `'0'` before tokenization?
+    /// If enabled, templated texts differing only in numbers or dates
+    /// (e.g., `"Invoice 2022-10-01"` and `"Invoice 2023-04-15"`) are treated as identical.
+    /// The replacement is applied after the lowercase conversion.
+    pub const fn fold_digits(mut self, yes: bool) -> Self {
+        self.fold_digits = yes;
+        self
+    }
+
+    /// Removes punctuation characters (i.e., those in the Unicode category `P`
+    /// and ASCII punctuation) before tokenization?
+    /// The removal is applied after the lowercase conversion.
+    pub const fn strip_punctuation(mut self, yes: bool) -> Self {
+        self.strip_punctuation = yes;
+        self
+    }
+
+    /// Replaces each run of whitespace with a single space before tokenization?
+    /// The replacement is applied after the removal of punctuation.
+    pub const fn collapse_whitespace(mut self, yes: bool) -> Self {
+        self.collapse_whitespace = yes;
+        self
+    }
+
+    /// Identifies k-mers with their reverse complements in [`TokenUnit::Nucleotide`]?
+    /// If enabled, the lexicographically smaller one is used as the feature,
+    /// so that sequences read from either strand are treated as identical.
+    pub const fn canonical_kmers(mut self, yes: bool) -> Self {
+        self.canonical_kmers = yes;
+        self
+    }
+
+    /// Hashes shingles by rolling the hash values of their tokens?
+    /// If enabled, each token is hashed only once even if it appears in overlapping windows,
+    /// which speeds up the extraction for large window sizes.
+    /// Note that the resulting features differ from those of the default hashing
+    /// of token strings, so sketches built with different settings are incompatible.
+    pub const fn rolling_hash(mut self, yes: bool) -> Self {
+        self.rolling_hash = yes;
+        self
+    }
+
+    /// Sets the maximum number of distinct features extracted from a document,
+    /// bounding the cost of sketching extremely long documents.
+    ///
+    /// Features are sampled with priorities given by their hash values (i.e., bottom-k sampling),
+    /// so that the same features are consistently retained across documents
+    /// and the similarities estimated from sampled features remain unbiased.
+    /// For weighted features, all the occurrences of sampled features are retained.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the maximum number is 0.
+    pub fn max_features_per_doc(mut self, max_features: Option<usize>) -> Result<Self> {
+        if max_features == Some(0) {
+            return Err(FindSimdocError::input(
+                "Maximum number of features must not be 0.",
+            ));
+        }
+        self.max_features_per_doc = max_features;
+        Ok(self)
+    }
+
+    /// Sets the number of buckets of positions combined with features, with which
+    /// features are sensitive to the order of tokens beyond the window of w-shingling.
+    ///
+    /// A document is evenly divided into the buckets by byte offsets, and each shingle is
+    /// identified with the bucket in which it starts. Thus, reordering large blocks
+    /// of a document reduces the similarity, unlike ordinary w-shingling.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the number of buckets is 0.
+    pub fn position_buckets(mut self, num_buckets: Option<usize>) -> Result<Self> {
+        if num_buckets == Some(0) {
+            return Err(FindSimdocError::input(
+                "Number of position buckets must not be 0.",
+            ));
+        }
+        self.position_buckets = num_buckets;
+        Ok(self)
+    }
+
+    /// Sets stopwords removed from tokens before w-shingling,
+    /// so that extremely common words (e.g., `"the"` and `"of"`) do not dominate similarities.
+    /// Empty strings are ignored.
+    pub fn stopwords<I, S>(mut self, stopwords: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.stopwords = stopwords
+            .into_iter()
+            .map(|s| s.as_ref().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        self
+    }
+
+    /// Sets a regular expression for recognizing words as tokens in feature extraction,
+    /// such as `[\s\p{P}]+` splitting on whitespace or punctuation.
+    /// If specified, it is used instead of the delimiter character.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the pattern is not a valid regular expression
+    /// or can match the empty string, e.g., `\s*`.
+    pub fn delimiter_regex(mut self, pattern: Option<&str>) -> Result<Self> {
+        if let Some(pattern) = pattern {
+            let hir =
+                regex_syntax::parse(pattern).map_err(|e| FindSimdocError::input(format!("{e}")))?;
+            if hir.properties().minimum_len() == Some(0) {
+                return Err(FindSimdocError::input(
+                    "The delimiter regex must not match the empty string.",
+                ));
+            }
+        }
+        self.delimiter_regex = pattern
+            .map(|pattern| Regex::new(pattern).map_err(|e| FindSimdocError::input(format!("{e}"))))
+            .transpose()?;
+        Ok(self)
+    }
+
+    /// Sets multiple delimiter strings for recognizing words as tokens in feature extraction,
+    /// such as `[" ", "\t", "、", "。"]`, where texts are split on any of them.
+    /// If specified, they are used instead of the delimiter character.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if no delimiter is given or a delimiter is empty.
+    pub fn delimiters<I, S>(self, delimiters: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut delimiters: Vec<_> = delimiters
+            .into_iter()
+            .map(|d| d.as_ref().to_string())
+            .collect();
+        if delimiters.is_empty() || delimiters.iter().any(|d| d.is_empty()) {
+            return Err(FindSimdocError::input("Delimiters must not be empty."));
+        }
+        // Longer delimiters are preferred as the alternation matches the first one.
+        delimiters.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        let pattern: Vec<_> = delimiters.iter().map(|d| regex::escape(d)).collect();
+        self.delimiter_regex(Some(&pattern.join("|")))
+    }
+
+    /// Gets the window size for w-shingling.
+    pub const fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Gets the maximum window size for w-shingling,
+    /// which is larger than [`Self::window_size()`] if a range is set.
+    pub const fn max_window_size(&self) -> usize {
+        self.max_window_size
+    }
+
+    /// Gets the delimiter for recognizing words as tokens.
+    pub const fn delimiter(&self) -> Option<char> {
+        self.delimiter
+    }
+
+    /// Gets the unit of tokens recognized when no delimiter is specified.
+    pub const fn unit_of_tokens(&self) -> TokenUnit {
+        self.token_unit
+    }
+
+    /// Checks if a token weighter is set.
+    pub const fn has_token_weighter(&self) -> bool {
+        self.token_weighter.is_some()
+    }
+
+    /// Checks if a preprocessor is set.
+    pub const fn has_preprocessor(&self) -> bool {
+        self.preprocessor.is_some()
+    }
+
+    /// Gets the name of the tokenizer, or `None` if not set.
+    pub fn tokenizer_name(&self) -> Option<&str> {
+        self.tokenizer.as_ref().map(|t| t.0.name())
+    }
+
+    /// Gets the pattern of the regular expression for recognizing words as tokens.
+    pub fn delimiter_regex_pattern(&self) -> Option<&str> {
+        self.delimiter_regex.as_ref().map(|re| re.as_str())
+    }
+
+    /// Checks if texts are normalized with NFKC before tokenization.
+    pub const fn is_nfkc_normalized(&self) -> bool {
+        self.normalize_nfkc
+    }
+
+    /// Checks if texts are converted into lowercase before tokenization.
+    pub const fn is_lowercased(&self) -> bool {
+        self.lowercase
+    }
+
+    /// Checks if numeric characters are replaced with `'0'` before tokenization.
+    pub const fn folds_digits(&self) -> bool {
+        self.fold_digits
+    }
+
+    /// Checks if punctuation characters are removed before tokenization.
+    pub const fn strips_punctuation(&self) -> bool {
+        self.strip_punctuation
+    }
+
+    /// Checks if runs of whitespace are collapsed before tokenization.
+    pub const fn collapses_whitespace(&self) -> bool {
+        self.collapse_whitespace
+    }
+
+    const fn folds_chars(&self) -> bool {
+        self.fold_digits || self.strip_punctuation || self.collapse_whitespace
+    }
+
+    /// Checks if k-mers are identified with their reverse complements.
+    pub const fn is_canonical_kmers(&self) -> bool {
+        self.canonical_kmers
+    }
+
+    /// Checks if shingles are hashed by rolling the hash values of their tokens.
+    pub const fn is_rolling_hash(&self) -> bool {
+        self.rolling_hash
+    }
+
+    /// Gets the maximum number of distinct features extracted from a document,
+    /// or `None` if unlimited.
+    pub const fn max_features_per_doc_count(&self) -> Option<usize> {
+        self.max_features_per_doc
+    }
+
+    /// Gets the number of buckets of positions combined with features, or `None` if disabled.
+    pub const fn num_position_buckets(&self) -> Option<usize> {
+        self.position_buckets
+    }
+
+    /// Gets the stopwords in lexicographic order.
+    pub fn stopwords_sorted(&self) -> Vec<&str> {
+        let mut stopwords: Vec<_> = self.stopwords.iter().map(|s| s.as_str()).collect();
+        stopwords.sort_unstable();
+        stopwords
+    }
+
+    fn is_char_unigram(&self) -> bool {
+        self.delimiter.is_none()
+            && self.tokenizer.is_none()
+            && self.delimiter_regex.is_none()
+            && self.stopwords.is_empty()
+            && self.token_unit == TokenUnit::Char
+            && self.max_window_size == 1
+    }
+
+    fn is_nucleotide(&self) -> bool {
+        self.delimiter.is_none()
+            && self.tokenizer.is_none()
+            && self.delimiter_regex.is_none()
+            && self.token_unit == TokenUnit::Nucleotide
+    }
+
+    /// Hashes a sequence of values in the same manner as features of shingles,
+    /// i.e., by feeding each element into a hasher, or by rolling the hash values of
+    /// each element if [`Self::rolling_hash()`] is enabled.
+    pub(crate) fn hash<I, T>(&self, iter: I) -> u64
     where
         I: IntoIterator<Item = T>,
         T: Hash,
     {
-        let mut s = self.build_hasher.build_hasher();
+        if !self.rolling_hash {
+            let mut s = self.build_hasher.build_hasher();
+            for t in iter {
+                t.hash(&mut s);
+            }
+            return s.finish();
+        }
+        let mut hash = 0u64;
+        let mut len = 0;
         for t in iter {
-            t.hash(&mut s);
+            hash = hash
+                .wrapping_mul(ROLLING_BASE)
+                .wrapping_add(self.hash_token(t));
+            len += 1;
+        }
+        finish_rolling_hash(hash, len)
+    }
+
+    fn hash_token<T>(&self, t: T) -> u64
+    where
+        T: Hash,
+    {
+        self.build_hasher.hash_one(t)
+    }
+}
+
+/// Vocabulary mapping features to their originating shingles, for inspecting
+/// what content drives a reported similarity.
+///
+/// The memory usage is bounded by a given capacity,
+/// and shingles are no longer recorded when it is reached.
+#[derive(Clone, Debug, Default)]
+pub struct Vocabulary {
+    shingles: HashMap<u64, Vec<String>>,
+    memory_in_bytes: usize,
+    max_memory_in_bytes: usize,
+}
+
+impl Vocabulary {
+    /// Creates an instance with the capacity in bytes.
+    pub fn new(max_memory_in_bytes: usize) -> Self {
+        Self {
+            max_memory_in_bytes,
+            ..Self::default()
+        }
+    }
+
+    /// Gets the tokens of the shingle from which an input feature originates,
+    /// where BOS and EOS are represented as empty strings.
+    pub fn get(&self, feature: u64) -> Option<&[String]> {
+        self.shingles.get(&feature).map(|tokens| tokens.as_slice())
+    }
+
+    /// Creates an iterator to visit pairs of a feature and its originating shingle.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &[String])> {
+        self.shingles
+            .iter()
+            .map(|(&feature, tokens)| (feature, tokens.as_slice()))
+    }
+
+    /// Gets the number of recorded features.
+    pub fn len(&self) -> usize {
+        self.shingles.len()
+    }
+
+    /// Checks if no feature is recorded.
+    pub fn is_empty(&self) -> bool {
+        self.shingles.is_empty()
+    }
+
+    /// Gets the approximate memory usage in bytes.
+    pub const fn memory_in_bytes(&self) -> usize {
+        self.memory_in_bytes
+    }
+
+    fn insert<'t, I>(&mut self, feature: u64, tokens: I)
+    where
+        I: IntoIterator<Item = &'t [u8]>,
+    {
+        if self.shingles.contains_key(&feature) {
+            return;
+        }
+        let tokens: Vec<_> = tokens
+            .into_iter()
+            .map(|t| String::from_utf8_lossy(t).into_owned())
+            .collect();
+        let memory_in_bytes = std::mem::size_of::<(u64, Vec<String>)>()
+            + tokens
+                .iter()
+                .map(|t| std::mem::size_of::<String>() + t.len())
+                .sum::<usize>();
+        if self.memory_in_bytes + memory_in_bytes <= self.max_memory_in_bytes {
+            self.shingles.insert(feature, tokens);
+            self.memory_in_bytes += memory_in_bytes;
+        }
+    }
+}
+
+/// Statistics of collisions of feature hash values, where distinct shingles
+/// mapped to the same feature are counted as collisions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CollisionStats {
+    /// Number of distinct shingles.
+    pub num_shingles: usize,
+    /// Number of distinct features.
+    pub num_features: usize,
+}
+
+impl CollisionStats {
+    /// Gets the number of shingles colliding with another one.
+    pub const fn num_collisions(&self) -> usize {
+        self.num_shingles - self.num_features
+    }
+
+    /// Gets the ratio of shingles colliding with another one.
+    pub fn collision_rate(&self) -> f64 {
+        if self.num_shingles == 0 {
+            return 0.;
         }
-        s.finish()
+        self.num_collisions() as f64 / self.num_shingles as f64
+    }
+}
+
+/// Reusable buffer for feature extraction, with which extracting features
+/// from many documents does not allocate memory per document.
+#[derive(Default)]
+pub struct TokenBuffer {
+    normalized: String,
+    token_ranges: Vec<Range<usize>>,
+    token_hashes: Vec<u64>,
+    padded_ranges: Vec<Range<usize>>,
+    samples: BTreeMap<(u64, u64), (usize, f64)>,
+}
+
+impl TokenBuffer {
+    /// Creates an instance.
+    pub fn new() -> Self {
+        Self::default()
     }
 }
 
 /// Extractor of feature vectors.
 pub struct FeatureExtractor<'a> {
     config: &'a FeatureConfig,
+    vocabulary: Option<Mutex<Vocabulary>>,
 }
 
 impl<'a> FeatureExtractor<'a> {
     /// Creates an instance.
     pub const fn new(config: &'a FeatureConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            vocabulary: None,
+        }
+    }
+
+    /// Records the originating shingles of extracted features in a [`Vocabulary`]
+    /// for debugging, where the memory usage is bounded by `max_memory_in_bytes`.
+    pub fn records_vocabulary(mut self, max_memory_in_bytes: usize) -> Self {
+        self.vocabulary = Some(Mutex::new(Vocabulary::new(max_memory_in_bytes)));
+        self
+    }
+
+    /// Gets a copy of the vocabulary recorded so far,
+    /// or `None` if [`Self::records_vocabulary()`] is not enabled.
+    pub fn vocabulary(&self) -> Option<Vocabulary> {
+        self.vocabulary
+            .as_ref()
+            .map(|vocabulary| vocabulary.lock().unwrap().clone())
     }
 
     /// Extracts a feature vector from an input text.
@@ -72,117 +690,666 @@ impl<'a> FeatureExtractor<'a> {
     where
         S: AsRef<str>,
     {
-        let text = text.as_ref();
+        self.extract_buffered(text, feature, &mut TokenBuffer::new());
+    }
 
+    /// Extracts a feature vector from an input text, reusing a buffer for tokens.
+    pub fn extract_buffered<S>(&self, text: S, feature: &mut Vec<u64>, buffer: &mut TokenBuffer)
+    where
+        S: AsRef<str>,
+    {
         feature.clear();
-        if self.config.delimiter.is_none() && self.config.window_size == 1 {
-            // The simplest case.
-            text.chars().for_each(|c| feature.push(c as u64));
+        if let Some(max_features) = self.config.max_features_per_doc {
+            let samples = self.sample(text.as_ref(), buffer, max_features);
+            feature.extend(samples.keys().map(|&(_, f)| f));
+            buffer.samples = samples;
         } else {
-            let token_ranges = self.tokenize(text);
-            for ranges in ShingleIter::new(&token_ranges, self.config.window_size) {
-                feature.push(self.config.hash(ranges.iter().cloned().map(|r| &text[r])));
-            }
+            self.for_each_feature(text.as_ref(), buffer, |f, _, _| feature.push(f));
         }
     }
 
-    /// Extracts a feature vector from an input text with weights of 1.0.
+    /// Extracts a feature vector from an input text with weights of 1.0,
+    /// or those given by [`FeatureConfig::token_weighter()`].
     pub fn extract_with_weights<S>(&self, text: S, feature: &mut Vec<(u64, f64)>)
     where
         S: AsRef<str>,
     {
-        let text = text.as_ref();
+        self.extract_with_weights_buffered(text, feature, &mut TokenBuffer::new());
+    }
 
+    /// Extracts a feature vector from an input text with weights of 1.0,
+    /// or those given by [`FeatureConfig::token_weighter()`], reusing a buffer for tokens.
+    pub fn extract_with_weights_buffered<S>(
+        &self,
+        text: S,
+        feature: &mut Vec<(u64, f64)>,
+        buffer: &mut TokenBuffer,
+    ) where
+        S: AsRef<str>,
+    {
         feature.clear();
-        if self.config.delimiter.is_none() && self.config.window_size == 1 {
-            // The simplest case.
-            text.chars().for_each(|c| {
-                let f = c as u64;
-                let w = 1.;
-                feature.push((f, w))
-            });
+        if let Some(max_features) = self.config.max_features_per_doc {
+            let samples = self.sample(text.as_ref(), buffer, max_features);
+            for (&(_, f), &(cnt, w)) in &samples {
+                feature.extend(std::iter::repeat_n((f, w), cnt));
+            }
+            buffer.samples = samples;
         } else {
-            let token_ranges = self.tokenize(text);
-            for ranges in ShingleIter::new(&token_ranges, self.config.window_size) {
-                let f = self.config.hash(ranges.iter().cloned().map(|r| &text[r]));
-                let w = 1.;
+            self.for_each_feature(text.as_ref(), buffer, |f, text, ranges| {
+                let w = self.weight(text, ranges);
                 feature.push((f, w))
-            }
+            });
         }
     }
 
-    fn tokenize(&self, text: &str) -> Vec<Range<usize>> {
-        let mut token_ranges = vec![];
-        for _ in 1..self.config.window_size {
-            token_ranges.push(0..0); // BOS
-        }
-        let mut offset = 0;
-        if let Some(delim) = self.config.delimiter {
-            while offset < text.len() {
-                let len = text[offset..].find(delim);
-                if let Some(len) = len {
-                    token_ranges.push(offset..offset + len);
-                    offset += len + 1;
-                } else {
-                    token_ranges.push(offset..text.len());
-                    break;
+    /// Extracts feature vectors from input documents in parallel,
+    /// so that the features can be cached and reused independently of sketching.
+    /// Without the `parallel` feature, the documents are processed sequentially.
+    pub fn extract_batch_parallel<D>(&self, documents: &[D]) -> Vec<Vec<u64>>
+    where
+        D: AsRef<str> + Sync,
+    {
+        let extract = |buffer: &mut TokenBuffer, doc: &D| {
+            let mut feature = vec![];
+            self.extract_buffered(doc, &mut feature, buffer);
+            feature
+        };
+        #[cfg(feature = "parallel")]
+        let features = documents
+            .par_iter()
+            .map_init(TokenBuffer::new, extract)
+            .collect();
+        #[cfg(not(feature = "parallel"))]
+        let features = {
+            let mut buffer = TokenBuffer::new();
+            documents
+                .iter()
+                .map(|doc| extract(&mut buffer, doc))
+                .collect()
+        };
+        features
+    }
+
+    /// Extracts a feature vector from an input text with the byte span of each element,
+    /// which covers the tokens of the originating shingle.
+    /// The spans can be used to highlight overlapping shingles between similar documents.
+    ///
+    /// If the preprocessing or normalization of texts is enabled,
+    /// the spans are positions in the preprocessed and normalized text.
+    pub fn extract_with_spans<S>(&self, text: S, feature: &mut Vec<(u64, Range<usize>)>)
+    where
+        S: AsRef<str>,
+    {
+        feature.clear();
+        self.for_each_feature(text.as_ref(), &mut TokenBuffer::new(), |f, _, ranges| {
+            feature.push((f, Self::span(ranges)))
+        });
+    }
+
+    /// Counts distinct shingles mapped to the same feature in input documents,
+    /// with which the collision rate of 64-bit feature hash values can be examined.
+    ///
+    /// All the distinct shingles are kept in memory, so use a sample of the corpus.
+    /// Note that k-mers identified with their reverse complements
+    /// (see [`FeatureConfig::canonical_kmers()`]) are also counted as collisions.
+    pub fn collision_stats<I, D>(&self, documents: I) -> CollisionStats
+    where
+        I: IntoIterator<Item = D>,
+        D: AsRef<str>,
+    {
+        let mut shingles: HashMap<u64, HashSet<Vec<u8>>> = HashMap::new();
+        let mut buffer = TokenBuffer::new();
+        for doc in documents {
+            self.for_each_feature(doc.as_ref(), &mut buffer, |f, text, ranges| {
+                // Tokens are prefixed with their lengths so that shingles are uniquely encoded.
+                let mut shingle = vec![];
+                for r in ranges {
+                    shingle.extend_from_slice(&(r.len() as u64).to_le_bytes());
+                    shingle.extend_from_slice(&text.as_bytes()[r.clone()]);
                 }
-            }
-        } else {
-            for c in text.chars() {
-                let len = c.len_utf8();
-                token_ranges.push(offset..offset + len);
-                offset += len;
-            }
+                shingles.entry(f).or_default().insert(shingle);
+            });
         }
-        for _ in 1..self.config.window_size {
-            token_ranges.push(text.len()..text.len()); // EOS
+        CollisionStats {
+            num_shingles: shingles.values().map(|s| s.len()).sum(),
+            num_features: shingles.len(),
         }
-        token_ranges
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Samples at most `max_features` distinct features with the smallest priorities,
+    /// returning the map from pairs of priorities and features to their occurrences and weights.
+    fn sample(
+        &self,
+        text: &str,
+        buffer: &mut TokenBuffer,
+        max_features: usize,
+    ) -> BTreeMap<(u64, u64), (usize, f64)> {
+        let mut samples = std::mem::take(&mut buffer.samples);
+        samples.clear();
+        self.for_each_feature(text, buffer, |f, text, ranges| {
+            let key = (self.config.hash_token(f), f);
+            if samples.len() == max_features
+                && samples
+                    .last_key_value()
+                    .is_some_and(|(&last, _)| key > last)
+            {
+                return;
+            }
+            samples
+                .entry(key)
+                .or_insert_with(|| (0, self.weight(text, ranges)))
+                .0 += 1;
+            if samples.len() > max_features {
+                samples.pop_last();
+            }
+        });
+        samples
+    }
 
-    #[test]
-    fn test_char_unigram() {
-        let config = FeatureConfig::new(1, None, 42).unwrap();
-        let extractor = FeatureExtractor::new(&config);
+    fn for_each_feature<F>(&self, text: &str, buffer: &mut TokenBuffer, mut f: F)
+    where
+        F: FnMut(u64, &str, &[Range<usize>]),
+    {
+        let mut f = |feature: u64, text: &str, ranges: &[Range<usize>]| {
+            let feature = match self.config.position_buckets {
+                Some(num_buckets) => {
+                    let bucket = Self::span(ranges).start * num_buckets / text.len().max(1);
+                    self.config.hash([feature, bucket as u64])
+                }
+                None => feature,
+            };
+            self.record(feature, text, ranges);
+            f(feature, text, ranges)
+        };
+        let TokenBuffer {
+            normalized,
+            token_ranges,
+            token_hashes,
+            padded_ranges,
+            ..
+        } = buffer;
+        let preprocessed = self.config.preprocessor.as_ref().map(|p| (p.0)(text));
+        let text = self.normalize(preprocessed.as_deref().unwrap_or(text), normalized);
 
-        let text = "abcd";
-        let mut feature = vec![];
+        if self.config.is_char_unigram() {
+            // The simplest case.
+            text.char_indices().for_each(|(offset, c)| {
+                let span = offset..offset + c.len_utf8();
+                let ranges = std::slice::from_ref(&span);
+                f(c as u64, text, ranges)
+            });
+            return;
+        }
 
-        extractor.extract(text, &mut feature);
-        assert_eq!(
-            feature,
-            vec!['a' as u64, 'b' as u64, 'c' as u64, 'd' as u64]
-        )
+        if self.config.is_nucleotide() {
+            self.for_each_kmer(text, token_ranges, token_hashes, f);
+            return;
+        }
+
+        let (min_w, max_w) = (self.config.window_size, self.config.max_window_size);
+        if min_w != max_w {
+            self.for_each_shingle_in_range(text, token_ranges, token_hashes, padded_ranges, f);
+            return;
+        }
+
+        // Enumerates shingles from the stream of tokens padded with BOS and EOS,
+        // without materializing all the tokens.
+        let num_tokens = Cell::new(0);
+        let tokens = self
+            .tokens(text, token_ranges)
+            .inspect(|_| num_tokens.set(num_tokens.get() + 1));
+        let pad = min_w - 1;
+        let padded = iter::repeat_n(0..0, pad) // BOS
+            .chain(tokens)
+            .chain(iter::repeat_n(text.len()..text.len(), pad)); // EOS
+        let mut shingles = ShingleIter::new(padded, min_w);
+        let mut hasher = self.config.rolling_hash.then(|| RollingHasher::new(min_w));
+        let mut filled = false;
+        while let Some(ranges) = shingles.next_window() {
+            if let Some(hasher) = hasher.as_mut() {
+                // Only the last token is new except for the first window.
+                let new_tokens = if filled { &ranges[pad..] } else { ranges };
+                for r in new_tokens {
+                    hasher.push(self.token_hash(text, r));
+                }
+            }
+            filled = true;
+            if num_tokens.get() == 0 {
+                // No token is found except BOS and EOS.
+                break;
+            }
+            let feature = match hasher.as_ref() {
+                Some(hasher) => hasher.finish(),
+                None => self.shingle_hash(text, ranges),
+            };
+            f(feature, text, ranges);
+        }
     }
 
-    #[test]
-    fn test_char_bigram() {
-        let config = FeatureConfig::new(2, None, 42).unwrap();
-        let extractor = FeatureExtractor::new(&config);
+    /// Enumerates shingles of every window size in the range,
+    /// tokenizing the text only once into the tokens padded with BOS and EOS.
+    fn for_each_shingle_in_range<F>(
+        &self,
+        text: &str,
+        token_ranges: &mut Vec<Range<usize>>,
+        hashes: &mut Vec<u64>,
+        padded: &mut Vec<Range<usize>>,
+        mut f: F,
+    ) where
+        F: FnMut(u64, &str, &[Range<usize>]),
+    {
+        let (min_w, max_w) = (self.config.window_size, self.config.max_window_size);
+        let max_pad = max_w - 1;
+        padded.clear();
+        padded.extend(iter::repeat_n(0..0, max_pad)); // BOS
+        padded.extend(self.tokens(text, token_ranges));
+        if padded.len() == max_pad {
+            // No token is found except BOS and EOS.
+            return;
+        }
+        padded.extend(iter::repeat_n(text.len()..text.len(), max_pad)); // EOS
 
-        let text = "abcd";
-        let mut feature = vec![];
+        if !self.config.rolling_hash {
+            for window_size in min_w..=max_w {
+                // Strips the padding exceeding the window size.
+                let skip = max_w - window_size;
+                for ranges in padded[skip..padded.len() - skip].windows(window_size) {
+                    f(self.shingle_hash(text, ranges), text, ranges);
+                }
+            }
+            return;
+        }
 
-        extractor.extract(text, &mut feature);
-        assert_eq!(
-            feature,
-            vec![
-                config.hash(&["", "a"]),
-                config.hash(&["a", "b"]),
-                config.hash(&["b", "c"]),
-                config.hash(&["c", "d"]),
-                config.hash(&["d", ""]),
-            ]
-        )
+        hashes.clear();
+        hashes.extend(padded.iter().map(|r| self.token_hash(text, r)));
+        for window_size in min_w..=max_w {
+            let skip = max_w - window_size;
+            let ranges = &padded[skip..padded.len() - skip];
+            let mut hasher = RollingHasher::new(window_size);
+            for (i, &h) in hashes[skip..hashes.len() - skip].iter().enumerate() {
+                hasher.push(h);
+                if i + 1 >= window_size {
+                    f(hasher.finish(), text, &ranges[i + 1 - window_size..=i]);
+                }
+            }
+        }
     }
 
-    #[test]
+    /// Enumerates features of k-mers, where nucleotides are encoded in 2 bits
+    /// and the other characters are encoded in [`INVALID_BASE`].
+    fn for_each_kmer<F>(
+        &self,
+        text: &str,
+        token_ranges: &mut Vec<Range<usize>>,
+        bases: &mut Vec<u64>,
+        mut f: F,
+    ) where
+        F: FnMut(u64, &str, &[Range<usize>]),
+    {
+        token_ranges.clear();
+        bases.clear();
+        for (offset, c) in text.char_indices() {
+            token_ranges.push(offset..offset + c.len_utf8());
+            bases.push(encode_base(c));
+        }
+        let (min_k, max_k) = (self.config.window_size, self.config.max_window_size);
+        for k in min_k..=max_k {
+            if k > bases.len() {
+                break;
+            }
+            // K-mers of different lengths must not be packed into the same space.
+            let packs = min_k == max_k && k <= 32;
+            let mask = if k >= 32 {
+                u64::MAX
+            } else {
+                (1 << (2 * k)) - 1
+            };
+            let (mut fwd, mut rev, mut valid) = (0u64, 0u64, 0);
+            for (i, &base) in bases.iter().enumerate() {
+                if base == INVALID_BASE {
+                    valid = 0;
+                    continue;
+                }
+                fwd = ((fwd << 2) | base) & mask;
+                rev = (rev >> 2) | ((3 - base) << (2 * (k.min(32) - 1)));
+                valid += 1;
+                if valid < k {
+                    continue;
+                }
+                let start = i + 1 - k;
+                let kmer = &bases[start..=i];
+                let feature = if packs {
+                    if self.config.canonical_kmers {
+                        fwd.min(rev)
+                    } else {
+                        fwd
+                    }
+                } else if self.config.canonical_kmers
+                    && kmer.iter().rev().map(|b| 3 - b).lt(kmer.iter().copied())
+                {
+                    self.config.hash(kmer.iter().rev().map(|b| 3 - b))
+                } else {
+                    self.config.hash(kmer)
+                };
+                f(feature, text, &token_ranges[start..=i]);
+            }
+        }
+    }
+
+    /// Computes the weight of a shingle as the average weight of its tokens.
+    fn weight(&self, text: &str, ranges: &[Range<usize>]) -> f64 {
+        let Some(weighter) = self.config.token_weighter.as_ref() else {
+            return 1.;
+        };
+        let (sum, cnt) = ranges
+            .iter()
+            .filter(|r| !r.is_empty())
+            // Byte tokens may not be on character boundaries.
+            .filter_map(|r| text.get(r.clone()))
+            .fold((0., 0), |(sum, cnt), t| (sum + (weighter.0)(t), cnt + 1));
+        if cnt == 0 {
+            1.
+        } else {
+            sum / f64::from(cnt)
+        }
+    }
+
+    fn record(&self, feature: u64, text: &str, ranges: &[Range<usize>]) {
+        if let Some(vocabulary) = self.vocabulary.as_ref() {
+            let bytes = text.as_bytes();
+            let tokens = ranges.iter().map(|r| &bytes[r.clone()]);
+            vocabulary.lock().unwrap().insert(feature, tokens);
+        }
+    }
+
+    /// Computes the span of a shingle, ignoring BOS and EOS.
+    fn span(ranges: &[Range<usize>]) -> Range<usize> {
+        let mut tokens = ranges.iter().filter(|r| !r.is_empty());
+        tokens.next().map_or_else(
+            || ranges[0].clone(),
+            |first| {
+                let last = tokens.next_back().unwrap_or(first);
+                first.start..last.end
+            },
+        )
+    }
+
+    fn normalize<'t>(&self, text: &'t str, normalized: &'t mut String) -> &'t str {
+        let (nfkc, lowercase) = (self.config.normalize_nfkc, self.config.lowercase);
+        if !nfkc && !lowercase && !self.config.folds_chars() {
+            return text;
+        }
+        normalized.clear();
+        let mut in_whitespace = false;
+        let mut push = |c| self.fold_char(c, normalized, &mut in_whitespace);
+        match (nfkc, lowercase) {
+            (true, true) => text.nfkc().flat_map(char::to_lowercase).for_each(&mut push),
+            (true, false) => text.nfkc().for_each(&mut push),
+            (false, true) => text
+                .chars()
+                .flat_map(char::to_lowercase)
+                .for_each(&mut push),
+            (false, false) => text.chars().for_each(&mut push),
+        }
+        normalized
+    }
+
+    /// Appends a character to `normalized` through the chain of character-class folding.
+    fn fold_char(&self, c: char, normalized: &mut String, in_whitespace: &mut bool) {
+        if self.config.strip_punctuation && is_punctuation(c) {
+            return;
+        }
+        if self.config.collapse_whitespace && c.is_whitespace() {
+            if !*in_whitespace {
+                normalized.push(' ');
+            }
+            *in_whitespace = true;
+            return;
+        }
+        *in_whitespace = false;
+        if self.config.fold_digits && c.is_numeric() {
+            normalized.push('0');
+        } else {
+            normalized.push(c);
+        }
+    }
+
+    /// Gets the stream of token ranges, where `token_ranges` is
+    /// a buffer for the tokens recognized by a user-supplied tokenizer.
+    fn tokens<'t>(
+        &'t self,
+        text: &'t str,
+        token_ranges: &'t mut Vec<Range<usize>>,
+    ) -> Box<dyn Iterator<Item = Range<usize>> + 't> {
+        let tokens: Box<dyn Iterator<Item = Range<usize>>> =
+            if let Some(tokenizer) = self.config.tokenizer.as_ref() {
+                token_ranges.clear();
+                tokenizer.0.tokenize(text, token_ranges);
+                Box::new(token_ranges.drain(..).inspect(|r| {
+                    assert!(
+                        text.get(r.clone()).is_some(),
+                        "The tokenizer returned a range {r:?} not on char boundaries."
+                    )
+                }))
+            } else if let Some(re) = self.config.delimiter_regex.as_ref() {
+                let mut matches = re.find_iter(text);
+                let mut offset = Some(0);
+                Box::new(iter::from_fn(move || {
+                    let start = offset?;
+                    if let Some(m) = matches.next() {
+                        offset = Some(m.end());
+                        Some(start..m.start())
+                    } else {
+                        offset = None;
+                        (start < text.len()).then_some(start..text.len())
+                    }
+                }))
+            } else if let Some(delim) = self.config.delimiter {
+                let mut offset = 0;
+                Box::new(iter::from_fn(move || {
+                    let start = offset;
+                    if text.len() <= start {
+                        return None;
+                    }
+                    if let Some(len) = text[start..].find(delim) {
+                        offset = start + len + delim.len_utf8();
+                        Some(start..start + len)
+                    } else {
+                        offset = text.len();
+                        Some(start..text.len())
+                    }
+                }))
+            } else if self.config.token_unit == TokenUnit::Byte {
+                Box::new((0..text.len()).map(|offset| offset..offset + 1))
+            } else if self.config.token_unit == TokenUnit::Word {
+                Box::new(
+                    text.unicode_word_indices()
+                        .map(|(offset, w)| offset..offset + w.len()),
+                )
+            } else if self.config.token_unit == TokenUnit::Grapheme {
+                Box::new(
+                    text.grapheme_indices(true)
+                        .map(|(offset, g)| offset..offset + g.len()),
+                )
+            } else {
+                Box::new(
+                    text.char_indices()
+                        .map(|(offset, c)| offset..offset + c.len_utf8()),
+                )
+            };
+        if self.config.stopwords.is_empty() {
+            return tokens;
+        }
+        Box::new(tokens.filter(move |r| {
+            // Byte tokens may not be on character boundaries.
+            !text
+                .get(r.clone())
+                .is_some_and(|t| self.config.stopwords.contains(t))
+        }))
+    }
+
+    /// Hashes the token strings of a shingle.
+    fn shingle_hash(&self, text: &str, ranges: &[Range<usize>]) -> u64 {
+        if self.config.token_unit == TokenUnit::Byte {
+            // Byte tokens may not be on character boundaries.
+            let bytes = text.as_bytes();
+            self.config.hash(ranges.iter().map(|r| &bytes[r.clone()]))
+        } else {
+            self.config.hash(ranges.iter().map(|r| &text[r.clone()]))
+        }
+    }
+
+    fn token_hash(&self, text: &str, range: &Range<usize>) -> u64 {
+        if self.config.token_unit == TokenUnit::Byte {
+            // Byte tokens may not be on character boundaries.
+            self.config.hash_token(&text.as_bytes()[range.clone()])
+        } else {
+            self.config.hash_token(&text[range.clone()])
+        }
+    }
+}
+
+fn is_punctuation(c: char) -> bool {
+    if c.is_ascii() {
+        return c.is_ascii_punctuation();
+    }
+    matches!(
+        get_general_category(c),
+        GeneralCategory::ConnectorPunctuation
+            | GeneralCategory::DashPunctuation
+            | GeneralCategory::OpenPunctuation
+            | GeneralCategory::ClosePunctuation
+            | GeneralCategory::InitialPunctuation
+            | GeneralCategory::FinalPunctuation
+            | GeneralCategory::OtherPunctuation
+    )
+}
+
+/// Code of characters other than nucleotides.
+const INVALID_BASE: u64 = u64::MAX;
+
+const fn encode_base(c: char) -> u64 {
+    match c {
+        'A' | 'a' => 0,
+        'C' | 'c' => 1,
+        'G' | 'g' => 2,
+        'T' | 't' | 'U' | 'u' => 3,
+        _ => INVALID_BASE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_char_unigram() {
+        let config = FeatureConfig::new(1, None, 42).unwrap();
+        let extractor = FeatureExtractor::new(&config);
+
+        let text = "abcd";
+        let mut feature = vec![];
+
+        extractor.extract(text, &mut feature);
+        assert_eq!(
+            feature,
+            vec!['a' as u64, 'b' as u64, 'c' as u64, 'd' as u64]
+        )
+    }
+
+    #[test]
+    fn test_char_bigram() {
+        let config = FeatureConfig::new(2, None, 42).unwrap();
+        let extractor = FeatureExtractor::new(&config);
+
+        let text = "abcd";
+        let mut feature = vec![];
+
+        extractor.extract(text, &mut feature);
+        assert_eq!(
+            feature,
+            vec![
+                config.hash(["", "a"]),
+                config.hash(["a", "b"]),
+                config.hash(["b", "c"]),
+                config.hash(["c", "d"]),
+                config.hash(["d", ""]),
+            ]
+        )
+    }
+
+    #[test]
+    fn test_stable_hash() {
+        // The values must not change for compatibility with sketches built by previous versions.
+        let config = FeatureConfig::new(2, None, 42).unwrap();
+        let extractor = FeatureExtractor::new(&config);
+
+        let mut feature = vec![];
+        extractor.extract("abc", &mut feature);
+        assert_eq!(
+            feature,
+            vec![
+                15210771562460292652,
+                18014477386878534092,
+                7976192031887607977,
+                2324391358088596075
+            ]
+        );
+
+        let config = FeatureConfig::new(2, Some(' '), 42).unwrap();
+        let extractor = FeatureExtractor::new(&config);
+
+        extractor.extract("i like rust", &mut feature);
+        assert_eq!(
+            feature,
+            vec![
+                11359203124560785916,
+                2417730235543728778,
+                13234592990259478242,
+                16798777416705923367
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rolling_hash() {
+        let config = FeatureConfig::new(3, Some(' '), 42)
+            .unwrap()
+            .rolling_hash(true);
+        let extractor = FeatureExtractor::new(&config);
+
+        let text = "a b c d";
+        let mut feature = vec![];
+
+        extractor.extract(text, &mut feature);
+        assert_eq!(
+            feature,
+            vec![
+                config.hash(["", "", "a"]),
+                config.hash(["", "a", "b"]),
+                config.hash(["a", "b", "c"]),
+                config.hash(["b", "c", "d"]),
+                config.hash(["c", "d", ""]),
+                config.hash(["d", "", ""]),
+            ]
+        );
+
+        let string_config = FeatureConfig::new(3, Some(' '), 42).unwrap();
+        assert_ne!(
+            config.hash(["a", "b", "c"]),
+            string_config.hash(["a", "b", "c"])
+        );
+
+        let config = config.window_range(2..=3).unwrap();
+        let extractor = FeatureExtractor::new(&config);
+
+        extractor.extract(text, &mut feature);
+        assert_eq!(feature.len(), 5 + 6);
+        assert_eq!(feature[1], config.hash(["a", "b"]));
+        assert_eq!(feature[7], config.hash(["a", "b", "c"]));
+    }
+
+    #[test]
     fn test_char_trigram() {
         let config = FeatureConfig::new(3, None, 42).unwrap();
         let extractor = FeatureExtractor::new(&config);
@@ -194,16 +1361,459 @@ mod tests {
         assert_eq!(
             feature,
             vec![
-                config.hash(&["", "", "a"]),
-                config.hash(&["", "a", "b"]),
-                config.hash(&["a", "b", "c"]),
-                config.hash(&["b", "c", "d"]),
-                config.hash(&["c", "d", ""]),
-                config.hash(&["d", "", ""]),
+                config.hash(["", "", "a"]),
+                config.hash(["", "a", "b"]),
+                config.hash(["a", "b", "c"]),
+                config.hash(["b", "c", "d"]),
+                config.hash(["c", "d", ""]),
+                config.hash(["d", "", ""]),
             ]
         )
     }
 
+    #[test]
+    fn test_normalization() {
+        let config = FeatureConfig::new(2, None, 42)
+            .unwrap()
+            .normalize_nfkc(true)
+            .lowercase(true);
+        let extractor = FeatureExtractor::new(&config);
+
+        let mut expected = vec![];
+        extractor.extract("abc", &mut expected);
+
+        let mut feature = vec![];
+        extractor.extract("ＡＢｃ", &mut feature);
+        assert_eq!(feature, expected);
+    }
+
+    #[test]
+    fn test_preprocessor() {
+        let config = FeatureConfig::new(1, Some(' '), 42)
+            .unwrap()
+            .preprocessor(Some(Arc::new(|text: &str| {
+                text.find("//")
+                    .map_or(Cow::Borrowed(text), |i| Cow::Owned(text[..i].to_string()))
+            })));
+        let extractor = FeatureExtractor::new(&config);
+        assert!(config.has_preprocessor());
+
+        let mut feature = vec![];
+        extractor.extract("let x // comment", &mut feature);
+        assert_eq!(feature, vec![config.hash(["let"]), config.hash(["x"])]);
+    }
+
+    #[test]
+    fn test_char_folding() {
+        let config = FeatureConfig::new(1, Some(' '), 42)
+            .unwrap()
+            .fold_digits(true)
+            .strip_punctuation(true)
+            .collapse_whitespace(true);
+        let extractor = FeatureExtractor::new(&config);
+
+        let mut feature = vec![];
+        let mut expected = vec![];
+        extractor.extract("Due:  2022-10-01,\tpaid「済」", &mut feature);
+        extractor.extract("Due 2023-04-15 paid済", &mut expected);
+        assert_eq!(feature, expected);
+        assert_eq!(
+            feature,
+            vec![
+                config.hash(["Due"]),
+                config.hash(["00000000"]),
+                config.hash(["paid済"])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_token_weighter() {
+        let config = FeatureConfig::new(2, Some(' '), 42)
+            .unwrap()
+            .token_weighter(Some(Arc::new(|t: &str| if t == "rust" { 3. } else { 1. })));
+        let extractor = FeatureExtractor::new(&config);
+
+        let mut feature = vec![];
+        extractor.extract_with_weights("i like rust", &mut feature);
+        assert_eq!(
+            feature,
+            vec![
+                (config.hash(["", "i"]), 1.),
+                (config.hash(["i", "like"]), 1.),
+                (config.hash(["like", "rust"]), 2.),
+                (config.hash(["rust", ""]), 3.),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stopwords() {
+        let config = FeatureConfig::new(2, Some(' '), 42)
+            .unwrap()
+            .stopwords(["the", "of"]);
+        let extractor = FeatureExtractor::new(&config);
+
+        let text = "the town of books";
+        let mut feature = vec![];
+
+        extractor.extract(text, &mut feature);
+        assert_eq!(
+            feature,
+            vec![
+                config.hash(["", "town"]),
+                config.hash(["town", "books"]),
+                config.hash(["books", ""]),
+            ]
+        );
+
+        extractor.extract("the of", &mut feature);
+        assert!(feature.is_empty());
+    }
+
+    #[test]
+    fn test_grapheme_bigram() {
+        let config = FeatureConfig::new(2, None, 42)
+            .unwrap()
+            .token_unit(TokenUnit::Grapheme);
+        let extractor = FeatureExtractor::new(&config);
+
+        let text = "a\u{0301}b👍🏽";
+        let mut feature = vec![];
+
+        extractor.extract(text, &mut feature);
+        assert_eq!(
+            feature,
+            vec![
+                config.hash(["", "a\u{0301}"]),
+                config.hash(["a\u{0301}", "b"]),
+                config.hash(["b", "👍🏽"]),
+                config.hash(["👍🏽", ""]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unicode_word_bigram() {
+        let config = FeatureConfig::new(2, None, 42)
+            .unwrap()
+            .token_unit(TokenUnit::Word);
+        let extractor = FeatureExtractor::new(&config);
+
+        let text = "Hello, world! It's fine.";
+        let mut feature = vec![];
+
+        extractor.extract(text, &mut feature);
+        assert_eq!(
+            feature,
+            vec![
+                config.hash(["", "Hello"]),
+                config.hash(["Hello", "world"]),
+                config.hash(["world", "It's"]),
+                config.hash(["It's", "fine"]),
+                config.hash(["fine", ""]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenizer() {
+        struct PairTokenizer;
+
+        impl Tokenizer for PairTokenizer {
+            fn tokenize(&self, text: &str, token_ranges: &mut Vec<Range<usize>>) {
+                for offset in (0..text.len()).step_by(2) {
+                    token_ranges.push(offset..text.len().min(offset + 2));
+                }
+            }
+        }
+
+        let config = FeatureConfig::new(1, Some(' '), 42)
+            .unwrap()
+            .tokenizer(Some(Arc::new(PairTokenizer)));
+        let extractor = FeatureExtractor::new(&config);
+
+        let mut feature = vec![];
+        extractor.extract("abcde", &mut feature);
+        assert_eq!(
+            feature,
+            vec![config.hash(["ab"]), config.hash(["cd"]), config.hash(["e"])]
+        );
+        assert_eq!(config.tokenizer_name(), Some("custom"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_tokenizer_invalid_range() {
+        struct ByteTokenizer;
+
+        impl Tokenizer for ByteTokenizer {
+            fn tokenize(&self, text: &str, token_ranges: &mut Vec<Range<usize>>) {
+                token_ranges.extend((0..text.len()).map(|i| i..i + 1));
+            }
+        }
+
+        let config = FeatureConfig::new(1, None, 42)
+            .unwrap()
+            .tokenizer(Some(Arc::new(ByteTokenizer)));
+        let extractor = FeatureExtractor::new(&config);
+        extractor.extract("aé", &mut vec![]);
+    }
+
+    #[test]
+    fn test_byte_bigram() {
+        let config = FeatureConfig::new(2, None, 42)
+            .unwrap()
+            .token_unit(TokenUnit::Byte);
+        let extractor = FeatureExtractor::new(&config);
+
+        let text = "aé";
+        let mut feature = vec![];
+
+        extractor.extract(text, &mut feature);
+        assert_eq!(
+            feature,
+            vec![
+                config.hash([&b""[..], b"a"]),
+                config.hash([&b"a"[..], b"\xC3"]),
+                config.hash([&b"\xC3"[..], b"\xA9"]),
+                config.hash([&b"\xA9"[..], b""]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nucleotide_kmers() {
+        let config = FeatureConfig::new(3, None, 42)
+            .unwrap()
+            .token_unit(TokenUnit::Nucleotide);
+        let extractor = FeatureExtractor::new(&config);
+
+        let mut feature = vec![];
+        extractor.extract("ACgTNacu", &mut feature);
+        // A=00, C=01, G=10, T=U=11
+        assert_eq!(feature, vec![0b000110, 0b011011, 0b000111]);
+
+        let config = config.canonical_kmers(true);
+        let extractor = FeatureExtractor::new(&config);
+        let mut revcomp = vec![];
+        extractor.extract("ACGTT", &mut feature);
+        extractor.extract("AACGT", &mut revcomp);
+        feature.sort_unstable();
+        revcomp.sort_unstable();
+        assert_eq!(feature, revcomp);
+
+        // K-mers of multiple lengths are hashed.
+        let config = FeatureConfig::new(2, None, 42)
+            .unwrap()
+            .window_range(2..=3)
+            .unwrap()
+            .token_unit(TokenUnit::Nucleotide);
+        let extractor = FeatureExtractor::new(&config);
+        extractor.extract("ACG", &mut feature);
+        assert_eq!(
+            feature,
+            vec![
+                config.hash([0u64, 1]),
+                config.hash([1u64, 2]),
+                config.hash([0u64, 1, 2]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_char_window_range() {
+        let config = FeatureConfig::new(1, None, 42)
+            .unwrap()
+            .window_range(1..=2)
+            .unwrap();
+        let extractor = FeatureExtractor::new(&config);
+
+        let text = "abc";
+        let mut feature = vec![];
+
+        extractor.extract(text, &mut feature);
+        assert_eq!(
+            feature,
+            vec![
+                config.hash(["a"]),
+                config.hash(["b"]),
+                config.hash(["c"]),
+                config.hash(["", "a"]),
+                config.hash(["a", "b"]),
+                config.hash(["b", "c"]),
+                config.hash(["c", ""]),
+            ]
+        );
+        assert!(FeatureConfig::new(1, None, 42)
+            .unwrap()
+            .window_range(0..=2)
+            .is_err());
+    }
+
+    #[test]
+    fn test_word_window_range() {
+        let config = FeatureConfig::new(1, Some(' '), 42)
+            .unwrap()
+            .window_range(1..=3)
+            .unwrap();
+        let extractor = FeatureExtractor::new(&config);
+
+        let text = "abc de fgh";
+        let mut feature = vec![];
+        extractor.extract_with_spans(text, &mut feature);
+
+        // Same as the concatenation of the features of each window size.
+        let mut expected = vec![];
+        for window_size in 1..=3 {
+            let config = FeatureConfig::new(window_size, Some(' '), 42).unwrap();
+            let mut single = vec![];
+            FeatureExtractor::new(&config).extract_with_spans(text, &mut single);
+            expected.extend(single);
+        }
+        assert_eq!(feature, expected);
+
+        extractor.extract_with_spans("", &mut feature);
+        assert!(feature.is_empty());
+    }
+
+    #[test]
+    fn test_max_features_per_doc() {
+        let config = FeatureConfig::new(1, None, 42).unwrap();
+        let extractor = FeatureExtractor::new(&config);
+        let mut all = vec![];
+        extractor.extract("abcdefgh", &mut all);
+
+        let config = config.max_features_per_doc(Some(3)).unwrap();
+        let extractor = FeatureExtractor::new(&config);
+        let mut feature = vec![];
+        extractor.extract("abcdefgh", &mut feature);
+        assert_eq!(feature.len(), 3);
+        assert!(feature.iter().all(|f| all.contains(f)));
+
+        // Features are consistently sampled across documents.
+        let mut reversed = vec![];
+        extractor.extract("hgfedcba", &mut reversed);
+        assert_eq!(feature, reversed);
+
+        let mut weighted = vec![];
+        extractor.extract_with_weights("aabbccddeeffgghh", &mut weighted);
+        assert_eq!(weighted.len(), 6);
+
+        assert!(config.max_features_per_doc(Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_position_buckets() {
+        let config = FeatureConfig::new(1, Some(' '), 42)
+            .unwrap()
+            .position_buckets(Some(2))
+            .unwrap();
+        let extractor = FeatureExtractor::new(&config);
+        let mut feature = vec![];
+        extractor.extract("a b c d", &mut feature);
+        let mut swapped = vec![];
+        extractor.extract("c d a b", &mut swapped);
+        feature.sort_unstable();
+        swapped.sort_unstable();
+        assert_eq!(feature.len(), 4);
+        assert!(feature.iter().all(|f| !swapped.contains(f)));
+
+        // Reordering tokens within a bucket does not change the features.
+        let mut same = vec![];
+        extractor.extract("a b d c", &mut same);
+        same.sort_unstable();
+        assert_eq!(same, feature);
+
+        assert!(config.position_buckets(Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_collision_stats() {
+        let config = FeatureConfig::new(2, Some(' '), 42).unwrap();
+        let extractor = FeatureExtractor::new(&config);
+        let stats = extractor.collision_stats(["a b c", "b c d", "a b"]);
+        // {"_ a", "a b", "b c", "c _", "_ b", "c d", "d _", "b _"}
+        assert_eq!(stats.num_shingles, 8);
+        assert_eq!(stats.num_collisions(), 0);
+
+        let config = FeatureConfig::new(2, None, 42)
+            .unwrap()
+            .token_unit(TokenUnit::Nucleotide)
+            .canonical_kmers(true);
+        let extractor = FeatureExtractor::new(&config);
+        let stats = extractor.collision_stats(["ACGT", "AC"]);
+        // {"AC", "CG", "GT"}, where "AC" and "GT" are reverse complements.
+        assert_eq!(stats.num_shingles, 3);
+        assert_eq!(stats.num_features, 2);
+        assert_eq!(stats.collision_rate(), 1. / 3.);
+    }
+
+    #[test]
+    fn test_extract_batch_parallel() {
+        let config = FeatureConfig::new(2, Some(' '), 42).unwrap();
+        let extractor = FeatureExtractor::new(&config);
+
+        let documents = ["abc de", "fgh", "ij kl mn"];
+        let features = extractor.extract_batch_parallel(&documents);
+        assert_eq!(features.len(), documents.len());
+        for (doc, feature) in documents.iter().zip(features) {
+            let mut expected = vec![];
+            extractor.extract(doc, &mut expected);
+            assert_eq!(feature, expected);
+        }
+    }
+
+    #[test]
+    fn test_spans() {
+        let config = FeatureConfig::new(2, Some(' '), 42).unwrap();
+        let extractor = FeatureExtractor::new(&config);
+
+        let text = "abc de fgh";
+        let mut feature = vec![];
+
+        extractor.extract_with_spans(text, &mut feature);
+        assert_eq!(
+            feature,
+            vec![
+                (config.hash(["", "abc"]), 0..3),
+                (config.hash(["abc", "de"]), 0..6),
+                (config.hash(["de", "fgh"]), 4..10),
+                (config.hash(["fgh", ""]), 7..10),
+            ]
+        );
+
+        let config = FeatureConfig::new(1, None, 42).unwrap();
+        let extractor = FeatureExtractor::new(&config);
+        extractor.extract_with_spans("aé", &mut feature);
+        assert_eq!(feature, vec![('a' as u64, 0..1), ('é' as u64, 1..3)]);
+    }
+
+    #[test]
+    fn test_vocabulary() {
+        let config = FeatureConfig::new(2, Some(' '), 42).unwrap();
+        let extractor = FeatureExtractor::new(&config).records_vocabulary(1 << 20);
+
+        let mut feature = vec![];
+        extractor.extract("abc de", &mut feature);
+
+        let vocabulary = extractor.vocabulary().unwrap();
+        assert_eq!(vocabulary.len(), 3);
+        assert_eq!(
+            vocabulary.get(config.hash(["abc", "de"])),
+            Some(&["abc".to_string(), "de".to_string()][..])
+        );
+        assert_eq!(
+            vocabulary.get(config.hash(["", "abc"])),
+            Some(&["".to_string(), "abc".to_string()][..])
+        );
+
+        let extractor = FeatureExtractor::new(&config).records_vocabulary(0);
+        extractor.extract("abc de", &mut feature);
+        assert!(extractor.vocabulary().unwrap().is_empty());
+        assert!(FeatureExtractor::new(&config).vocabulary().is_none());
+    }
+
     #[test]
     fn test_word_unigram() {
         let config = FeatureConfig::new(1, Some(' '), 42).unwrap();
@@ -216,9 +1826,9 @@ mod tests {
         assert_eq!(
             feature,
             vec![
-                config.hash(&["abc"]),
-                config.hash(&["de"]),
-                config.hash(&["fgh"]),
+                config.hash(["abc"]),
+                config.hash(["de"]),
+                config.hash(["fgh"]),
             ]
         )
     }
@@ -235,14 +1845,74 @@ mod tests {
         assert_eq!(
             feature,
             vec![
-                config.hash(&["", "abc"]),
-                config.hash(&["abc", "de"]),
-                config.hash(&["de", "fgh"]),
-                config.hash(&["fgh", ""]),
+                config.hash(["", "abc"]),
+                config.hash(["abc", "de"]),
+                config.hash(["de", "fgh"]),
+                config.hash(["fgh", ""]),
             ]
         )
     }
 
+    #[test]
+    fn test_word_bigram_regex() {
+        let config = FeatureConfig::new(2, None, 42)
+            .unwrap()
+            .delimiter_regex(Some(r"[\s\p{P}]+"))
+            .unwrap();
+        let extractor = FeatureExtractor::new(&config);
+
+        let text = "abc, de\tfgh.";
+        let mut feature = vec![];
+
+        extractor.extract(text, &mut feature);
+        assert_eq!(
+            feature,
+            vec![
+                config.hash(["", "abc"]),
+                config.hash(["abc", "de"]),
+                config.hash(["de", "fgh"]),
+                config.hash(["fgh", ""]),
+            ]
+        );
+        assert!(FeatureConfig::new(2, None, 42)
+            .unwrap()
+            .delimiter_regex(Some("["))
+            .is_err());
+        for pattern in [r"\s*", "", r"\b", "a|"] {
+            assert!(FeatureConfig::new(2, None, 42)
+                .unwrap()
+                .delimiter_regex(Some(pattern))
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_word_bigram_delimiters() {
+        let config = FeatureConfig::new(2, None, 42)
+            .unwrap()
+            .delimiters([" ", "、", "。", "--"])
+            .unwrap();
+        let extractor = FeatureExtractor::new(&config);
+
+        let text = "abc、de--fgh。";
+        let mut feature = vec![];
+
+        extractor.extract(text, &mut feature);
+        assert_eq!(
+            feature,
+            vec![
+                config.hash(["", "abc"]),
+                config.hash(["abc", "de"]),
+                config.hash(["de", "fgh"]),
+                config.hash(["fgh", ""]),
+            ]
+        );
+        assert!(FeatureConfig::new(2, None, 42)
+            .unwrap()
+            .delimiters([" ", ""])
+            .is_err());
+    }
+
     #[test]
     fn test_word_trigram() {
         let config = FeatureConfig::new(3, Some(' '), 42).unwrap();
@@ -255,11 +1925,11 @@ mod tests {
         assert_eq!(
             feature,
             vec![
-                config.hash(&["", "", "abc"]),
-                config.hash(&["", "abc", "de"]),
-                config.hash(&["abc", "de", "fgh"]),
-                config.hash(&["de", "fgh", ""]),
-                config.hash(&["fgh", "", ""]),
+                config.hash(["", "", "abc"]),
+                config.hash(["", "abc", "de"]),
+                config.hash(["abc", "de", "fgh"]),
+                config.hash(["de", "fgh", ""]),
+                config.hash(["fgh", "", ""]),
             ]
         )
     }
@@ -0,0 +1,322 @@
+//! Fingerprints for reproducing searchers.
+use crate::errors::{FindSimdocError, Result};
+use crate::feature::{ShortDocumentPolicy, TokenUnit};
+use crate::lsh::ElementHasher;
+
+/// Distance function of a searcher.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Metric {
+    /// Jaccard distance handled by [`crate::JaccardSearcher`].
+    Jaccard,
+    /// Cosine distance handled by [`crate::CosineSearcher`].
+    Cosine,
+}
+
+/// Fingerprint of all the settings used to build a searcher.
+///
+/// Building a searcher with the same fingerprint from the same documents
+/// reproduces the same sketches and search results.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fingerprint {
+    /// Version of this crate.
+    pub version: String,
+    /// Distance function.
+    pub metric: Metric,
+    /// Seed value for random values.
+    pub seed: u64,
+    /// Window size for w-shingling in feature extraction.
+    pub window_size: usize,
+    /// Maximum window size for w-shingling in feature extraction.
+    pub max_window_size: usize,
+    /// Delimiter for recognizing words as tokens in feature extraction.
+    pub delimiter: Option<char>,
+    /// Regular expression for recognizing words as tokens in feature extraction.
+    pub delimiter_regex: Option<String>,
+    /// Unit of tokens recognized when no delimiter is specified.
+    pub token_unit: TokenUnit,
+    /// Name of the tokenizer, or `None` if not set.
+    pub tokenizer: Option<String>,
+    /// Whether k-mers are identified with their reverse complements.
+    pub canonical_kmers: bool,
+    /// Whether shingles are hashed by rolling the hash values of their tokens.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rolling_hash: bool,
+    /// Whether documents are preprocessed with a user-supplied function.
+    pub preprocesses: bool,
+    /// Whether tokens are weighted with a user-supplied function.
+    pub weights_tokens: bool,
+    /// Whether documents are normalized with NFKC before tokenization.
+    pub normalizes_nfkc: bool,
+    /// Whether documents are converted into lowercase before tokenization.
+    pub lowercases: bool,
+    /// Whether numeric characters are replaced with `'0'` before tokenization.
+    pub folds_digits: bool,
+    /// Whether punctuation characters are removed before tokenization.
+    pub strips_punctuation: bool,
+    /// Whether runs of whitespace are collapsed before tokenization.
+    pub collapses_whitespace: bool,
+    /// Stopwords removed from tokens in lexicographic order.
+    pub stopwords: Vec<String>,
+    /// Maximum number of distinct features extracted from a document, or `None` if unlimited.
+    pub max_features_per_doc: Option<usize>,
+    /// Number of buckets of positions combined with features, or `None` if disabled.
+    pub position_buckets: Option<usize>,
+    /// Range of document frequencies of retained features, or `None` if disabled.
+    pub df_range: Option<(usize, usize)>,
+    /// Policy for documents from which no feature is extracted.
+    pub short_document_policy: ShortDocumentPolicy,
+    /// Number of chunks of sketches, or 0 if the sketches are not built yet.
+    pub num_chunks: usize,
+    /// Whether exact duplicate documents are separated from the search.
+    pub separates_duplicates: bool,
+    /// Number of bits in each chunk of sketches, i.e., the bits of the sketch type.
+    pub sketch_bits: usize,
+    /// Number of bits retained from each sample in the b-bit minwise hashing, or 0 for the Cosine.
+    pub minhash_bits: usize,
+    /// Parameters `(dim, depth)` of the super-bit LSH, or `None` if disabled.
+    pub superbit: Option<(usize, usize)>,
+    /// Hash function of elements in sketching.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub element_hasher: ElementHasher,
+    /// Description of the TF weighter, or `None` if disabled.
+    pub tf_weighter: Option<String>,
+    /// Description of the IDF weighter, or `None` if disabled.
+    pub idf_weighter: Option<String>,
+}
+
+impl Fingerprint {
+    #[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+    pub(crate) fn new(metric: Metric, seed: u64) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            metric,
+            seed,
+            window_size: 0,
+            max_window_size: 0,
+            delimiter: None,
+            delimiter_regex: None,
+            token_unit: TokenUnit::Char,
+            tokenizer: None,
+            canonical_kmers: false,
+            rolling_hash: false,
+            preprocesses: false,
+            weights_tokens: false,
+            normalizes_nfkc: false,
+            lowercases: false,
+            folds_digits: false,
+            strips_punctuation: false,
+            collapses_whitespace: false,
+            stopwords: vec![],
+            max_features_per_doc: None,
+            position_buckets: None,
+            df_range: None,
+            short_document_policy: ShortDocumentPolicy::Error,
+            num_chunks: 0,
+            separates_duplicates: false,
+            sketch_bits: 0,
+            minhash_bits: 0,
+            superbit: None,
+            element_hasher: ElementHasher::SplitMix64,
+            tf_weighter: None,
+            idf_weighter: None,
+        }
+    }
+
+    /// Checks if the fingerprint matches the expected one,
+    /// returning an error indicating the first mismatched setting.
+    pub fn verify(&self, expected: &Self) -> Result<()> {
+        if self.version != expected.version {
+            return Err(FindSimdocError::input("Mismatched crate version."));
+        }
+        if self.metric != expected.metric {
+            return Err(FindSimdocError::input("Mismatched metric."));
+        }
+        if self.seed != expected.seed {
+            return Err(FindSimdocError::input("Mismatched seed value."));
+        }
+        if self.window_size != expected.window_size
+            || self.max_window_size != expected.max_window_size
+        {
+            return Err(FindSimdocError::input("Mismatched window size."));
+        }
+        if self.delimiter != expected.delimiter || self.delimiter_regex != expected.delimiter_regex
+        {
+            return Err(FindSimdocError::input("Mismatched delimiter."));
+        }
+        if self.token_unit != expected.token_unit
+            || self.tokenizer != expected.tokenizer
+            || self.canonical_kmers != expected.canonical_kmers
+            || self.rolling_hash != expected.rolling_hash
+        {
+            return Err(FindSimdocError::input("Mismatched tokenization."));
+        }
+        if self.preprocesses != expected.preprocesses
+            || self.normalizes_nfkc != expected.normalizes_nfkc
+            || self.lowercases != expected.lowercases
+            || self.folds_digits != expected.folds_digits
+            || self.strips_punctuation != expected.strips_punctuation
+            || self.collapses_whitespace != expected.collapses_whitespace
+        {
+            return Err(FindSimdocError::input("Mismatched text normalization."));
+        }
+        if self.stopwords != expected.stopwords {
+            return Err(FindSimdocError::input("Mismatched stopwords."));
+        }
+        if self.position_buckets != expected.position_buckets {
+            return Err(FindSimdocError::input("Mismatched position buckets."));
+        }
+        if self.max_features_per_doc != expected.max_features_per_doc {
+            return Err(FindSimdocError::input("Mismatched feature sampling."));
+        }
+        if self.df_range != expected.df_range {
+            return Err(FindSimdocError::input("Mismatched feature pruning."));
+        }
+        if self.short_document_policy != expected.short_document_policy {
+            return Err(FindSimdocError::input("Mismatched short document policy."));
+        }
+        if self.num_chunks != expected.num_chunks {
+            return Err(FindSimdocError::input("Mismatched number of chunks."));
+        }
+        if self.sketch_bits != expected.sketch_bits {
+            return Err(FindSimdocError::input("Mismatched bits of sketches."));
+        }
+        if self.minhash_bits != expected.minhash_bits {
+            return Err(FindSimdocError::input("Mismatched bits of minhash."));
+        }
+        if self.superbit != expected.superbit {
+            return Err(FindSimdocError::input("Mismatched super-bit LSH."));
+        }
+        if self.element_hasher != expected.element_hasher {
+            return Err(FindSimdocError::input("Mismatched element hash function."));
+        }
+        if self.separates_duplicates != expected.separates_duplicates {
+            return Err(FindSimdocError::input("Mismatched duplicate separation."));
+        }
+        if self.weights_tokens != expected.weights_tokens {
+            return Err(FindSimdocError::input("Mismatched token weighting."));
+        }
+        if self.tf_weighter != expected.tf_weighter {
+            return Err(FindSimdocError::input("Mismatched TF weighting."));
+        }
+        if self.idf_weighter != expected.idf_weighter {
+            return Err(FindSimdocError::input("Mismatched IDF weighting."));
+        }
+        Ok(())
+    }
+
+    /// Gets the settings as pairs of names and values, e.g., for headers of exported sketches.
+    /// The values are formatted with [`Debug`] except for strings, numbers, and flags.
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("version", self.version.clone()),
+            ("metric", format!("{:?}", self.metric)),
+            ("seed", self.seed.to_string()),
+            ("window_size", self.window_size.to_string()),
+            ("max_window_size", self.max_window_size.to_string()),
+            ("delimiter", format!("{:?}", self.delimiter)),
+            ("delimiter_regex", format!("{:?}", self.delimiter_regex)),
+            ("token_unit", format!("{:?}", self.token_unit)),
+            ("tokenizer", format!("{:?}", self.tokenizer)),
+            ("canonical_kmers", self.canonical_kmers.to_string()),
+            ("rolling_hash", self.rolling_hash.to_string()),
+            ("preprocesses", self.preprocesses.to_string()),
+            ("weights_tokens", self.weights_tokens.to_string()),
+            ("normalizes_nfkc", self.normalizes_nfkc.to_string()),
+            ("lowercases", self.lowercases.to_string()),
+            ("folds_digits", self.folds_digits.to_string()),
+            ("strips_punctuation", self.strips_punctuation.to_string()),
+            (
+                "collapses_whitespace",
+                self.collapses_whitespace.to_string(),
+            ),
+            ("stopwords", format!("{:?}", self.stopwords)),
+            (
+                "max_features_per_doc",
+                format!("{:?}", self.max_features_per_doc),
+            ),
+            ("position_buckets", format!("{:?}", self.position_buckets)),
+            ("df_range", format!("{:?}", self.df_range)),
+            (
+                "short_document_policy",
+                format!("{:?}", self.short_document_policy),
+            ),
+            ("num_chunks", self.num_chunks.to_string()),
+            (
+                "separates_duplicates",
+                self.separates_duplicates.to_string(),
+            ),
+            ("sketch_bits", self.sketch_bits.to_string()),
+            ("minhash_bits", self.minhash_bits.to_string()),
+            ("superbit", format!("{:?}", self.superbit)),
+            ("element_hasher", format!("{:?}", self.element_hasher)),
+            ("tf_weighter", format!("{:?}", self.tf_weighter)),
+            ("idf_weighter", format!("{:?}", self.idf_weighter)),
+        ]
+    }
+}
+
+#[cfg(all(test, feature = "parallel"))]
+mod tests {
+    use crate::lsh::ElementHasher;
+    use crate::JaccardSearcher;
+
+    #[test]
+    fn test_verify() {
+        let documents = ["abcd", "abce", "xyz"];
+        let searcher = JaccardSearcher::new(2, None, Some(42))
+            .unwrap()
+            .build_sketches(documents.iter(), 2)
+            .unwrap();
+        let fingerprint = searcher.fingerprint();
+        assert_eq!(fingerprint.seed, 42);
+        assert_eq!(fingerprint.window_size, 2);
+        assert_eq!(fingerprint.num_chunks, 2);
+
+        let other = JaccardSearcher::new(2, None, Some(42))
+            .unwrap()
+            .build_sketches(documents.iter(), 2)
+            .unwrap();
+        assert!(other.verify_fingerprint(&fingerprint).is_ok());
+
+        let other = JaccardSearcher::new(2, None, Some(43))
+            .unwrap()
+            .build_sketches(documents.iter(), 2)
+            .unwrap();
+        assert!(other.verify_fingerprint(&fingerprint).is_err());
+
+        let other = JaccardSearcher::new(2, None, Some(42))
+            .unwrap()
+            .build_sketches(documents.iter(), 3)
+            .unwrap();
+        assert!(other.verify_fingerprint(&fingerprint).is_err());
+
+        let other = JaccardSearcher::new(2, None, Some(42))
+            .unwrap()
+            .element_hasher(ElementHasher::WyMix)
+            .build_sketches(documents.iter(), 2)
+            .unwrap();
+        assert!(other.verify_fingerprint(&fingerprint).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let searcher = JaccardSearcher::new(2, None, Some(42))
+            .unwrap()
+            .build_sketches(["abcd", "abce"].iter(), 2)
+            .unwrap();
+        let fingerprint = searcher.fingerprint();
+        let json = serde_json::to_string(&fingerprint).unwrap();
+        assert!(json.contains(r#""metric":"jaccard""#));
+        assert_eq!(
+            serde_json::from_str::<super::Fingerprint>(&json).unwrap(),
+            fingerprint
+        );
+    }
+}
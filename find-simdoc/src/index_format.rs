@@ -0,0 +1,517 @@
+//! Versioned index format of 64-bit sketches, designed to be read from other languages.
+//!
+//! This is the single on-disk format of sketches, written by the `index` executable and
+//! the bindings, read by the `query`, `serve`, and `join_sketches` executables, and produced by
+//! the searchers with `export_sketches()`.
+//! The sketches are stored in column-oriented blocks, i.e.,
+//! the `c`-th block holds the `c`-th chunks of all the sketches, which is the layout of
+//! the multi-index join. A block can be loaded as an array of `u64` without parsing,
+//! and the texts of documents can be optionally stored along with the sketches.
+//! Other data such as the IDF model are stored in named sections.
+//! A reference reader and writer in Python is provided in `scripts/read_index.py`.
+//!
+//! # Binary format
+//!
+//! All integers are in little endian.
+//!
+//! | Field          | Size                             | Description                                       |
+//! |----------------|----------------------------------|---------------------------------------------------|
+//! | magic          | 8 bytes                          | `b"FSIDX\x00\x00"` followed by the version `0x02` |
+//! | header length  | u64                              | Number of bytes of the header                     |
+//! | header         | header length bytes              | UTF-8 lines of `key=value` (can be empty)         |
+//! | num_chunks     | u64                              | Number of 64-bit chunks in each sketch            |
+//! | num_sketches   | u64                              | Number of sketches                                |
+//! | blocks         | 8 * num_chunks * num_sketches    | `num_chunks` blocks of `num_sketches` chunks      |
+//! | num_documents  | u64                              | Number of stored texts (0 or num_sketches)        |
+//! | offsets        | 8 * (num_documents + 1)          | Byte offsets of texts, only if num_documents > 0  |
+//! | texts          | last offset bytes                | Concatenated UTF-8 texts                          |
+//! | num_sections   | u64                              | Number of sections                                |
+//! | sections       | variable                         | `num_sections` sections in the following layout   |
+//!
+//! The `i`-th text is the bytes from `offsets[i]` to `offsets[i+1]` of the texts,
+//! where `offsets[0]` is 0.
+//! Each section consists of the name length (u64), the UTF-8 name, the data length (u64),
+//! and the data. Readers should skip sections they do not know.
+//!
+//! The header of sketches exported from a searcher holds its seed, and also the entries of its
+//! fingerprint for the Jaccard and Cosine searchers.
+//! Index files written by the `index` executable and the bindings hold the entries of
+//! [`IndexSettings`](crate::index_settings::IndexSettings) instead.
+//! The following sections are defined:
+//!
+//! | Name      | Description                                                                |
+//! |-----------|----------------------------------------------------------------------------|
+//! | `sizes`   | Numbers of features of documents as u64 values, for the containment metric |
+//! | `idf`     | IDF model serialized by [`Idf::serialize_into()`](crate::tfidf::Idf::serialize_into) |
+//! | `ids`     | JSON array of the document ids, written by the `index` executable          |
+//! | `sources` | JSON object of the document sources, written by the `index` executable     |
+//! Readers must reject versions they do not know, and the version is incremented
+//! for any change of the layout.
+use std::io::{Read, Write};
+
+use all_pairs_hamming::ChunkedJoiner;
+
+use crate::errors::{FindSimdocError, Result};
+
+const MAGIC: &[u8; 7] = b"FSIDX\x00\x00";
+
+/// Version of the index format.
+pub const VERSION: u8 = 2;
+
+/// Sketches of 64-bit chunks in column-oriented blocks with header entries of `key=value`,
+/// optional texts of documents, and named sections.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::index_format::IndexFile;
+///
+/// let mut file = IndexFile::new(2);
+/// file.push_header("metric", "simhash").unwrap();
+/// file.add([0xff, 0]).unwrap();
+/// file.add([0xfe, 1]).unwrap();
+/// file.set_documents(["abc", "abd"]);
+///
+/// let mut data = vec![];
+/// file.serialize_into(&mut data).unwrap();
+/// let file = IndexFile::deserialize_from(&data[..]).unwrap();
+/// assert_eq!(file.header_value("metric"), Some("simhash"));
+/// assert_eq!(file.block(1), &[0, 1]);
+/// assert_eq!(file.documents().unwrap(), &["abc", "abd"]);
+///
+/// let joiner = file.to_joiner().unwrap();
+/// assert_eq!(joiner.similar_pairs(0.02), vec![(0, 1, 0.015625)]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IndexFile {
+    header: Vec<(String, String)>,
+    num_sketches: usize,
+    blocks: Vec<Vec<u64>>,
+    documents: Option<Vec<String>>,
+    sections: Vec<(String, Vec<u8>)>,
+}
+
+impl IndexFile {
+    /// Creates an instance handling sketches of `num_chunks` chunks.
+    pub fn new(num_chunks: usize) -> Self {
+        Self {
+            header: vec![],
+            num_sketches: 0,
+            blocks: vec![vec![]; num_chunks],
+            documents: None,
+            sections: vec![],
+        }
+    }
+
+    /// Appends a header entry.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the key is empty or includes `=` or line breaks,
+    /// or the value includes line breaks.
+    pub fn push_header<K, V>(&mut self, key: K, value: V) -> Result<()>
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let (key, value) = (key.into(), value.into());
+        check_header_entry(&key, &value)?;
+        self.header.push((key, value));
+        Ok(())
+    }
+
+    /// Replaces the header entries, e.g., to store the settings of an application
+    /// instead of those of a searcher.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if an entry is invalid as in [`Self::push_header()`],
+    /// in which case the header is left unchanged.
+    pub fn set_header<I, K, V>(&mut self, entries: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let mut header = vec![];
+        for (key, value) in entries {
+            let (key, value) = (key.into(), value.into());
+            check_header_entry(&key, &value)?;
+            header.push((key, value));
+        }
+        self.header = header;
+        Ok(())
+    }
+
+    /// Appends a named section of arbitrary data.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the name is empty or already used.
+    pub fn push_section<K>(&mut self, name: K, data: Vec<u8>) -> Result<()>
+    where
+        K: Into<String>,
+    {
+        let name = name.into();
+        if name.is_empty() || self.section(&name).is_some() {
+            return Err(FindSimdocError::input(format!(
+                "Invalid section name: {name}"
+            )));
+        }
+        self.sections.push((name, data));
+        Ok(())
+    }
+
+    /// Appends a sketch, whose first [`Self::num_chunks()`] chunks are stored.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the sketch has fewer chunks than [`Self::num_chunks()`].
+    pub fn add<I>(&mut self, sketch: I) -> Result<()>
+    where
+        I: IntoIterator<Item = u64>,
+    {
+        let sketch: Vec<_> = sketch.into_iter().take(self.num_chunks()).collect();
+        if sketch.len() != self.num_chunks() {
+            return Err(FindSimdocError::input(format!(
+                "The input sketch must include {} chunks at least.",
+                self.num_chunks()
+            )));
+        }
+        self.push_sketch(&sketch);
+        Ok(())
+    }
+
+    /// Sets the texts of documents stored along with the sketches,
+    /// where the `i`-th text corresponds to the `i`-th sketch.
+    /// The number of texts is checked when serialized.
+    pub fn set_documents<I, D>(&mut self, documents: I)
+    where
+        I: IntoIterator<Item = D>,
+        D: Into<String>,
+    {
+        self.documents = Some(documents.into_iter().map(Into::into).collect());
+    }
+
+    /// Serializes the index into a writer.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the number of texts differs from that of sketches.
+    pub fn serialize_into<W>(&self, mut wtr: W) -> Result<()>
+    where
+        W: Write,
+    {
+        let documents = self.documents.as_deref().unwrap_or_default();
+        if self.documents.is_some() && documents.len() != self.num_sketches {
+            return Err(FindSimdocError::input(format!(
+                "The number of documents must be {}, but {}.",
+                self.num_sketches,
+                documents.len()
+            )));
+        }
+        let header: String = self
+            .header
+            .iter()
+            .map(|(key, value)| format!("{key}={value}\n"))
+            .collect();
+        wtr.write_all(MAGIC)?;
+        wtr.write_all(&[VERSION])?;
+        wtr.write_all(&(header.len() as u64).to_le_bytes())?;
+        wtr.write_all(header.as_bytes())?;
+        wtr.write_all(&(self.num_chunks() as u64).to_le_bytes())?;
+        wtr.write_all(&(self.num_sketches as u64).to_le_bytes())?;
+        for x in self.blocks.iter().flatten() {
+            wtr.write_all(&x.to_le_bytes())?;
+        }
+        wtr.write_all(&(documents.len() as u64).to_le_bytes())?;
+        if !documents.is_empty() {
+            let mut offset = 0u64;
+            wtr.write_all(&offset.to_le_bytes())?;
+            for doc in documents {
+                offset += doc.len() as u64;
+                wtr.write_all(&offset.to_le_bytes())?;
+            }
+            for doc in documents {
+                wtr.write_all(doc.as_bytes())?;
+            }
+        }
+        wtr.write_all(&(self.sections.len() as u64).to_le_bytes())?;
+        for (name, data) in &self.sections {
+            wtr.write_all(&(name.len() as u64).to_le_bytes())?;
+            wtr.write_all(name.as_bytes())?;
+            wtr.write_all(&(data.len() as u64).to_le_bytes())?;
+            wtr.write_all(data)?;
+        }
+        Ok(())
+    }
+
+    /// Deserializes the index from a reader.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the data is not valid or of an unknown version.
+    pub fn deserialize_from<R>(mut rdr: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut magic = [0; MAGIC.len() + 1];
+        rdr.read_exact(&mut magic)?;
+        if &magic[..MAGIC.len()] != MAGIC {
+            return Err(FindSimdocError::serialization("Invalid index file."));
+        }
+        if magic[MAGIC.len()] != VERSION {
+            return Err(FindSimdocError::serialization(format!(
+                "Unsupported version of the index file: {}",
+                magic[MAGIC.len()]
+            )));
+        }
+        let mut header = vec![0; read_u64(&mut rdr)? as usize];
+        rdr.read_exact(&mut header)?;
+        let header = String::from_utf8(header)
+            .map_err(|_| FindSimdocError::serialization("The header must be UTF-8."))?;
+        let mut file = Self::new(read_u64(&mut rdr)? as usize);
+        for line in header.lines() {
+            let (key, value) = line.split_once('=').ok_or_else(|| {
+                FindSimdocError::serialization(format!("Invalid header entry: {line}"))
+            })?;
+            file.push_header(key, value)?;
+        }
+        file.num_sketches = read_u64(&mut rdr)? as usize;
+        for block in &mut file.blocks {
+            for _ in 0..file.num_sketches {
+                block.push(read_u64(&mut rdr)?);
+            }
+        }
+        let num_documents = read_u64(&mut rdr)? as usize;
+        if num_documents != 0 {
+            file.documents = Some(read_documents(&mut rdr, num_documents, file.num_sketches)?);
+        }
+        let num_sections = read_u64(&mut rdr)? as usize;
+        for _ in 0..num_sections {
+            let name = String::from_utf8(read_bytes(&mut rdr)?)
+                .map_err(|_| FindSimdocError::serialization("The section name must be UTF-8."))?;
+            let data = read_bytes(&mut rdr)?;
+            file.push_section(name, data)
+                .map_err(|e| FindSimdocError::serialization(e.to_string()))?;
+        }
+        Ok(file)
+    }
+
+    /// Creates a joiner storing the sketches.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if no sketch is stored.
+    pub fn to_joiner(&self) -> Result<ChunkedJoiner<u64>> {
+        if self.is_empty() {
+            return Err(FindSimdocError::input("No sketch is stored."));
+        }
+        let mut joiner = ChunkedJoiner::new(self.num_chunks());
+        for i in 0..self.num_sketches {
+            joiner.add(self.blocks.iter().map(|block| block[i]))?;
+        }
+        Ok(joiner)
+    }
+
+    /// Gets the header entries.
+    pub fn header(&self) -> &[(String, String)] {
+        &self.header
+    }
+
+    /// Gets the value of the first header entry of `key`.
+    pub fn header_value(&self, key: &str) -> Option<&str> {
+        self.header
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Gets the number of chunks in each sketch.
+    pub fn num_chunks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Gets the number of sketches.
+    pub const fn num_sketches(&self) -> usize {
+        self.num_sketches
+    }
+
+    /// Checks if no sketch is stored.
+    pub const fn is_empty(&self) -> bool {
+        self.num_sketches == 0
+    }
+
+    /// Gets the `c`-th chunks of all the sketches.
+    pub fn block(&self, c: usize) -> &[u64] {
+        &self.blocks[c]
+    }
+
+    /// Gets the chunks of the `i`-th sketch.
+    pub fn sketch(&self, i: usize) -> Vec<u64> {
+        self.blocks.iter().map(|block| block[i]).collect()
+    }
+
+    /// Gets the texts of documents if stored.
+    pub fn documents(&self) -> Option<&[String]> {
+        self.documents.as_deref()
+    }
+
+    /// Gets the data of the section of `name` if stored.
+    pub fn section(&self, name: &str) -> Option<&[u8]> {
+        self.sections
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, data)| data.as_slice())
+    }
+
+    /// Checks that the header holds the seed value of a searcher importing the sketches.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn check_seed(&self, seed: u64) -> Result<()> {
+        match self.header_value("seed").map(str::parse::<u64>) {
+            Some(Ok(value)) if value == seed => Ok(()),
+            Some(Ok(_)) => Err(FindSimdocError::input("Mismatched seed value.")),
+            _ => Err(FindSimdocError::serialization(
+                "The header must include the seed value.",
+            )),
+        }
+    }
+
+    fn push_sketch(&mut self, sketch: &[u64]) {
+        for (block, &x) in self.blocks.iter_mut().zip(sketch) {
+            block.push(x);
+        }
+        self.num_sketches += 1;
+    }
+}
+
+fn read_documents<R>(rdr: &mut R, num_documents: usize, num_sketches: usize) -> Result<Vec<String>>
+where
+    R: Read,
+{
+    if num_documents != num_sketches {
+        return Err(FindSimdocError::serialization(
+            "The number of documents must be that of sketches.",
+        ));
+    }
+    let mut offsets = Vec::with_capacity(num_documents + 1);
+    for _ in 0..=num_documents {
+        offsets.push(read_u64(rdr)? as usize);
+    }
+    let mut texts = vec![0; offsets[num_documents]];
+    rdr.read_exact(&mut texts)?;
+    offsets
+        .windows(2)
+        .map(|w| {
+            texts
+                .get(w[0]..w[1])
+                .and_then(|text| String::from_utf8(text.to_vec()).ok())
+                .ok_or_else(|| FindSimdocError::serialization("Invalid text of a document."))
+        })
+        .collect()
+}
+
+fn read_bytes<R>(rdr: &mut R) -> Result<Vec<u8>>
+where
+    R: Read,
+{
+    let mut data = vec![0; read_u64(rdr)? as usize];
+    rdr.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// Checks that a header entry can be written as a line of `key=value`.
+fn check_header_entry(key: &str, value: &str) -> Result<()> {
+    let breaks = |s: &str| s.contains(['\n', '\r']);
+    if key.is_empty() || key.contains('=') || breaks(key) || breaks(value) {
+        return Err(FindSimdocError::input(format!(
+            "Invalid header entry: {key}={value}"
+        )));
+    }
+    Ok(())
+}
+
+fn read_u64<R>(rdr: &mut R) -> Result<u64>
+where
+    R: Read,
+{
+    let mut buf = [0; 8];
+    rdr.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> IndexFile {
+        let mut file = IndexFile::new(2);
+        file.push_header("metric", "jaccard").unwrap();
+        file.push_header("seed", "42").unwrap();
+        file.add([0xff, 0]).unwrap();
+        file.add([0xfe, 0]).unwrap();
+        file.add([u64::MAX, 1]).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut file = example();
+        let mut data = vec![];
+        file.serialize_into(&mut data).unwrap();
+        assert_eq!(IndexFile::deserialize_from(&data[..]).unwrap(), file);
+
+        file.set_documents(["ab", "", "日本"]);
+        let mut data = vec![];
+        file.serialize_into(&mut data).unwrap();
+        let other = IndexFile::deserialize_from(&data[..]).unwrap();
+        assert_eq!(other, file);
+        assert_eq!(other.block(0), &[0xff, 0xfe, u64::MAX]);
+        assert_eq!(other.sketch(2), vec![u64::MAX, 1]);
+    }
+
+    #[test]
+    fn test_sections() {
+        let mut file = example();
+        file.push_section("idf", vec![1, 2, 3]).unwrap();
+        file.push_section("ids", vec![]).unwrap();
+        assert!(file.push_section("idf", vec![]).is_err());
+        assert!(file.push_section("", vec![]).is_err());
+        let mut data = vec![];
+        file.serialize_into(&mut data).unwrap();
+        let other = IndexFile::deserialize_from(&data[..]).unwrap();
+        assert_eq!(other, file);
+        assert_eq!(other.section("idf"), Some(&[1, 2, 3][..]));
+        assert_eq!(other.section("ids"), Some(&[][..]));
+        assert_eq!(other.section("sizes"), None);
+    }
+
+    #[test]
+    fn test_header() {
+        let mut file = example();
+        assert_eq!(file.header_value("seed"), Some("42"));
+        assert_eq!(file.header_value("window_size"), None);
+        assert!(file
+            .set_header([("metric", "cosine"), ("a=b", "c")])
+            .is_err());
+        assert_eq!(file.header_value("metric"), Some("jaccard"));
+        file.set_header([("metric", "cosine")]).unwrap();
+        assert_eq!(
+            file.header(),
+            &[("metric".to_string(), "cosine".to_string())]
+        );
+        assert!(file.push_header("a", "b\nc").is_err());
+    }
+
+    #[test]
+    fn test_invalid_inputs() {
+        let mut file = example();
+        assert!(file.add([1]).is_err());
+        assert_eq!(file.num_sketches(), 3);
+        file.set_documents(["a"]);
+        assert!(file.serialize_into(vec![]).is_err());
+        assert!(IndexFile::new(2).to_joiner().is_err());
+        assert!(IndexFile::deserialize_from(&b"FSIDX\x00\x00\x01"[..]).is_err());
+        assert!(IndexFile::deserialize_from(&b"FSSKF\x00\x00\x01"[..]).is_err());
+    }
+}
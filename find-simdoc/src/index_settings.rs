@@ -0,0 +1,384 @@
+//! Settings of searchers written in the headers of index files.
+//!
+//! The settings are shared by the `index` executable and the bindings,
+//! so that an index built by one of them can be loaded by the others.
+//! The index files are in the format of [`IndexFile`].
+use crate::errors::{FindSimdocError, Result};
+use crate::index_format::IndexFile;
+use crate::lsh::ElementHasher;
+use crate::tfidf::Tf;
+#[cfg(feature = "parallel")]
+use crate::{ContainmentSearcher, CosineSearcher, JaccardSearcher, WeightedJaccardSearcher};
+
+/// Version of the settings in the headers of index files,
+/// which is incremented for any incompatible change of the entries.
+pub const INDEX_VERSION: u64 = 2;
+
+/// Settings of feature extraction and weighting, with which a searcher is restored
+/// from the sketches of an index file without the original arguments.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::index_settings::IndexSettings;
+///
+/// let mut settings = IndexSettings::new("jaccard", 3, 42);
+/// settings.lowercase = true;
+///
+/// let header = settings.header();
+/// let restored = IndexSettings::from_header(header.iter().map(|(k, v)| (*k, v.as_str()))).unwrap();
+/// assert_eq!(restored, settings);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexSettings {
+    /// Name of the distance function, e.g., `jaccard`, `cosine`, `wjac`, or `containment`.
+    pub metric: String,
+    /// Window size for w-shingling.
+    pub window_size: usize,
+    /// Maximum window size for w-shingling.
+    pub max_window_size: usize,
+    /// Delimiter for recognizing words as tokens.
+    pub delimiter: Option<char>,
+    /// Regular expression for recognizing words as tokens.
+    pub delimiter_regex: Option<String>,
+    /// Whether documents are converted into lowercase.
+    pub lowercase: bool,
+    /// Whether documents are normalized with NFKC.
+    pub nfkc: bool,
+    /// Whether punctuation characters are removed.
+    pub strip_punct: bool,
+    /// Seed value for random values.
+    pub seed: u64,
+    /// Hash function of elements in sketching.
+    pub element_hasher: ElementHasher,
+    /// TF weighting, i.e., `binary`, `standard`, or `sublinear`.
+    pub tf: String,
+    /// IDF weighting, i.e., `unary`, `standard`, or `smooth`.
+    pub idf: String,
+}
+
+impl IndexSettings {
+    /// Creates settings with the default values of the other fields,
+    /// i.e., the standard TF and smooth IDF weightings.
+    pub fn new<M>(metric: M, window_size: usize, seed: u64) -> Self
+    where
+        M: Into<String>,
+    {
+        Self {
+            metric: metric.into(),
+            window_size,
+            max_window_size: window_size,
+            delimiter: None,
+            delimiter_regex: None,
+            lowercase: false,
+            nfkc: false,
+            strip_punct: false,
+            seed,
+            element_hasher: ElementHasher::SplitMix64,
+            tf: "standard".to_string(),
+            idf: "smooth".to_string(),
+        }
+    }
+
+    /// Gets the settings as header entries of names and values,
+    /// starting with the version [`INDEX_VERSION`].
+    /// The entries of `None` are omitted.
+    pub fn header(&self) -> Vec<(&'static str, String)> {
+        let mut entries = vec![
+            ("version", INDEX_VERSION.to_string()),
+            ("metric", self.metric.clone()),
+            ("window_size", self.window_size.to_string()),
+            ("max_window_size", self.max_window_size.to_string()),
+        ];
+        if let Some(delimiter) = self.delimiter {
+            entries.push(("delimiter", delimiter.to_string()));
+        }
+        if let Some(delimiter_regex) = &self.delimiter_regex {
+            entries.push(("delimiter_regex", delimiter_regex.clone()));
+        }
+        entries.extend([
+            ("lowercase", self.lowercase.to_string()),
+            ("nfkc", self.nfkc.to_string()),
+            ("strip_punct", self.strip_punct.to_string()),
+            ("seed", self.seed.to_string()),
+            (
+                "element_hasher",
+                element_hasher_name(self.element_hasher).to_string(),
+            ),
+            ("tf", self.tf.clone()),
+            ("idf", self.idf.clone()),
+        ]);
+        entries
+    }
+
+    /// Restores the settings from header entries written by [`Self::header()`].
+    /// Unknown entries are ignored.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the version is unsupported or an entry is missing or invalid.
+    pub fn from_header<'a, I>(entries: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let entries: Vec<_> = entries.into_iter().collect();
+        let get = |key: &str| {
+            entries
+                .iter()
+                .find(|&&(k, _)| k == key)
+                .map(|&(_, value)| value)
+        };
+        let field = |key: &str| get(key).ok_or_else(|| invalid_field(key));
+        let parse = |key: &str| {
+            field(key).and_then(|value| value.parse::<u64>().map_err(|_| invalid_field(key)))
+        };
+        let flag = |key: &str| {
+            field(key).and_then(|value| value.parse::<bool>().map_err(|_| invalid_field(key)))
+        };
+        if parse("version")? != INDEX_VERSION {
+            return Err(FindSimdocError::serialization(
+                "Unsupported version of the index file.",
+            ));
+        }
+        let delimiter = match get("delimiter") {
+            Some(delimiter) => {
+                let mut chars = delimiter.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some(c),
+                    _ => return Err(invalid_field("delimiter")),
+                }
+            }
+            None => None,
+        };
+        let element_hasher = match field("element_hasher")? {
+            "splitmix64" => ElementHasher::SplitMix64,
+            "wymix" => ElementHasher::WyMix,
+            _ => return Err(invalid_field("element_hasher")),
+        };
+        Ok(Self {
+            metric: field("metric")?.to_string(),
+            window_size: parse("window_size")? as usize,
+            max_window_size: parse("max_window_size")? as usize,
+            delimiter,
+            delimiter_regex: get("delimiter_regex").map(String::from),
+            lowercase: flag("lowercase")?,
+            nfkc: flag("nfkc")?,
+            strip_punct: flag("strip_punct")?,
+            seed: parse("seed")?,
+            element_hasher,
+            tf: field("tf")?.to_string(),
+            idf: field("idf")?.to_string(),
+        })
+    }
+
+    /// Restores the settings from the header of an index file.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the version is unsupported or an entry is missing or invalid.
+    pub fn from_index_file(file: &IndexFile) -> Result<Self> {
+        Self::from_header(
+            file.header()
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+        )
+    }
+
+    /// Creates the TF weighting, or returns `None` for the binary one.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the weighting is unknown.
+    pub fn tf_weighter(&self) -> Result<Option<Tf>> {
+        match self.tf.as_str() {
+            "binary" => Ok(None),
+            "standard" => Ok(Some(Tf::new())),
+            "sublinear" => Ok(Some(Tf::new().sublinear(true))),
+            _ => Err(FindSimdocError::input(
+                "tf must be one of binary, standard, or sublinear.",
+            )),
+        }
+    }
+
+    /// Checks if the IDF weighting uses a model, i.e., is not the unary one.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the weighting is unknown.
+    pub fn uses_idf(&self) -> Result<bool> {
+        match self.idf.as_str() {
+            "unary" => Ok(false),
+            "standard" | "smooth" => Ok(true),
+            _ => Err(FindSimdocError::input(
+                "idf must be one of unary, standard, or smooth.",
+            )),
+        }
+    }
+
+    /// Creates a [`JaccardSearcher`] with the settings.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the settings are invalid.
+    #[cfg(feature = "parallel")]
+    pub fn jaccard_searcher(&self) -> Result<JaccardSearcher> {
+        Ok(
+            JaccardSearcher::new(self.window_size, self.delimiter, Some(self.seed))?
+                .lowercase(self.lowercase)
+                .normalize_nfkc(self.nfkc)
+                .strip_punctuation(self.strip_punct)
+                .delimiter_regex(self.delimiter_regex.as_deref())?
+                .window_range(self.window_size..=self.max_window_size)?
+                .element_hasher(self.element_hasher),
+        )
+    }
+
+    /// Creates a [`CosineSearcher`] with the settings including the TF weighting.
+    /// The IDF model must be given separately.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the settings are invalid.
+    #[cfg(feature = "parallel")]
+    pub fn cosine_searcher(&self) -> Result<CosineSearcher> {
+        Ok(
+            CosineSearcher::new(self.window_size, self.delimiter, Some(self.seed))?
+                .lowercase(self.lowercase)
+                .normalize_nfkc(self.nfkc)
+                .strip_punctuation(self.strip_punct)
+                .delimiter_regex(self.delimiter_regex.as_deref())?
+                .window_range(self.window_size..=self.max_window_size)?
+                .element_hasher(self.element_hasher)
+                .tf(self.tf_weighter()?),
+        )
+    }
+
+    /// Creates a [`WeightedJaccardSearcher`] with the settings including the TF weighting.
+    /// The IDF model must be given separately.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the settings are invalid.
+    #[cfg(feature = "parallel")]
+    pub fn weighted_jaccard_searcher(&self) -> Result<WeightedJaccardSearcher> {
+        Ok(
+            WeightedJaccardSearcher::new(self.window_size, self.delimiter, Some(self.seed))?
+                .lowercase(self.lowercase)
+                .normalize_nfkc(self.nfkc)
+                .strip_punctuation(self.strip_punct)
+                .delimiter_regex(self.delimiter_regex.as_deref())?
+                .window_range(self.window_size..=self.max_window_size)?
+                .tf(self.tf_weighter()?),
+        )
+    }
+
+    /// Creates a [`ContainmentSearcher`] with the settings.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the settings are invalid.
+    #[cfg(feature = "parallel")]
+    pub fn containment_searcher(&self) -> Result<ContainmentSearcher> {
+        ContainmentSearcher::new(self.window_size, self.delimiter, Some(self.seed))?
+            .lowercase(self.lowercase)
+            .normalize_nfkc(self.nfkc)
+            .strip_punctuation(self.strip_punct)
+            .delimiter_regex(self.delimiter_regex.as_deref())?
+            .window_range(self.window_size..=self.max_window_size)
+    }
+}
+
+const fn element_hasher_name(element_hasher: ElementHasher) -> &'static str {
+    match element_hasher {
+        ElementHasher::SplitMix64 => "splitmix64",
+        ElementHasher::WyMix => "wymix",
+    }
+}
+
+fn invalid_field(key: &str) -> FindSimdocError {
+    FindSimdocError::serialization(format!("Invalid field: {key}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> IndexSettings {
+        let mut settings = IndexSettings::new("cosine", 2, 42);
+        settings.max_window_size = 3;
+        settings.delimiter = Some(' ');
+        settings.delimiter_regex = Some(r"\s+".to_string());
+        settings.nfkc = true;
+        settings.element_hasher = ElementHasher::WyMix;
+        settings.tf = "sublinear".to_string();
+        settings
+    }
+
+    fn restore(header: &[(&str, String)]) -> Result<IndexSettings> {
+        IndexSettings::from_header(header.iter().map(|(k, v)| (*k, v.as_str())))
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let settings = example();
+        assert_eq!(restore(&settings.header()).unwrap(), settings);
+        let settings = IndexSettings::new("jaccard", 1, u64::MAX);
+        assert_eq!(restore(&settings.header()).unwrap(), settings);
+
+        let mut file = IndexFile::new(1);
+        file.set_header(settings.header()).unwrap();
+        assert_eq!(IndexSettings::from_index_file(&file).unwrap(), settings);
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let header = example().header();
+        let mut other = header.clone();
+        other[0].1 = "1".to_string();
+        assert!(restore(&other).is_err());
+        let other: Vec<_> = header
+            .iter()
+            .filter(|(k, _)| *k != "seed")
+            .cloned()
+            .collect();
+        assert!(restore(&other).is_err());
+        let mut other = header.clone();
+        other.push(("unknown", "1".to_string()));
+        assert!(restore(&other).is_ok());
+        let mut other = header;
+        other.retain(|(k, _)| *k != "delimiter");
+        other.push(("delimiter", "ab".to_string()));
+        assert!(restore(&other).is_err());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_searchers() {
+        let documents = ["a b c d", "a b c e", "x y z"];
+        let settings = example();
+        let expected = CosineSearcher::new(2, Some(' '), Some(42))
+            .unwrap()
+            .normalize_nfkc(true)
+            .delimiter_regex(Some(r"\s+"))
+            .unwrap()
+            .window_range(2..=3)
+            .unwrap()
+            .element_hasher(ElementHasher::WyMix)
+            .tf(Some(Tf::new().sublinear(true)))
+            .build_sketches(documents.iter(), 2)
+            .unwrap();
+        let searcher = settings
+            .cosine_searcher()
+            .unwrap()
+            .build_sketches(documents.iter(), 2)
+            .unwrap();
+        assert!(searcher.verify_fingerprint(&expected.fingerprint()).is_ok());
+        assert!(settings.uses_idf().unwrap());
+
+        let mut settings = settings;
+        settings.tf = "unknown".to_string();
+        assert!(settings.cosine_searcher().is_err());
+        settings.window_size = 4;
+        assert!(settings.jaccard_searcher().is_err());
+    }
+}
@@ -0,0 +1,60 @@
+//! Filters of search results based on document labels.
+
+/// Filter of similar pairs based on labels attached to documents,
+/// where a label indicates the source of a document (e.g., a dataset).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum LabelFilter {
+    /// Reports all pairs.
+    #[default]
+    All,
+    /// Reports only pairs whose documents have different labels.
+    CrossLabelOnly,
+    /// Reports only pairs whose documents have the same label.
+    WithinLabelOnly,
+}
+
+impl LabelFilter {
+    /// Checks if a pair of documents with given labels is reported.
+    pub const fn accepts(&self, lhs: usize, rhs: usize) -> bool {
+        match self {
+            Self::All => true,
+            Self::CrossLabelOnly => lhs != rhs,
+            Self::WithinLabelOnly => lhs == rhs,
+        }
+    }
+
+    #[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+    pub(crate) fn retain(&self, labels: &[usize], results: &mut Vec<(usize, usize, f64)>) {
+        if *self != Self::All {
+            results.retain(|&(i, j, _)| self.accepts(labels[i], labels[j]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retain() {
+        let labels = vec![0, 0, 1, 1];
+        let results = vec![(0, 1, 0.1), (0, 2, 0.2), (1, 3, 0.3), (2, 3, 0.4)];
+
+        let mut filtered = results.clone();
+        LabelFilter::All.retain(&labels, &mut filtered);
+        assert_eq!(filtered, results);
+
+        let mut filtered = results.clone();
+        LabelFilter::CrossLabelOnly.retain(&labels, &mut filtered);
+        assert_eq!(filtered, vec![(0, 2, 0.2), (1, 3, 0.3)]);
+
+        let mut filtered = results;
+        LabelFilter::WithinLabelOnly.retain(&labels, &mut filtered);
+        assert_eq!(filtered, vec![(0, 1, 0.1), (2, 3, 0.4)]);
+    }
+}
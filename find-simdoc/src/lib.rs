@@ -43,17 +43,56 @@
 //! 2. Convert the features into binary sketches through locality sensitive hashing
 //!    - [1-bit minwise hashing](https://dl.acm.org/doi/abs/10.1145/1772690.1772759) for the Jaccard similarity
 //!    - [Simplified simhash](https://dl.acm.org/doi/10.1145/1242572.1242592) for the Cosine similarity
+//!    - [Improved consistent weighted sampling](https://doi.org/10.1109/ICDM.2010.80) for the weighted Jaccard similarity
 //! 3. Search for similar sketches in the Hamming space using a modified variant of the [sketch sorting approach](https://proceedings.mlr.press/v13/tabei10a.html)
 #![deny(missing_docs)]
+#![cfg_attr(feature = "simd", feature(portable_simd))]
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "parallel")]
+pub mod containment;
+#[cfg(feature = "parallel")]
 pub mod cosine;
 pub mod errors;
+pub mod estimate;
+pub mod exact;
 pub mod feature;
+pub mod fingerprint;
+pub mod index_format;
+pub mod index_settings;
+#[cfg(feature = "parallel")]
 pub mod jaccard;
+pub mod label;
 pub mod lsh;
+#[cfg(feature = "npy")]
+pub mod npy;
+pub mod output;
+#[cfg(feature = "polars")]
+pub mod polars;
+pub mod pruning;
+#[cfg(feature = "parallel")]
+pub mod stream;
 pub mod tfidf;
+pub mod tokenizer;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+#[cfg(feature = "parallel")]
+pub mod weighted_jaccard;
 
+#[cfg(feature = "parallel")]
+mod duplicate;
 mod shingling;
+#[cfg(feature = "parallel")]
+mod skip;
+#[cfg(feature = "parallel")]
+mod spool;
 
+#[cfg(feature = "parallel")]
+pub use containment::ContainmentSearcher;
+#[cfg(feature = "parallel")]
 pub use cosine::CosineSearcher;
+#[cfg(feature = "parallel")]
 pub use jaccard::JaccardSearcher;
+#[cfg(feature = "parallel")]
+pub use weighted_jaccard::WeightedJaccardSearcher;
@@ -1,16 +1,33 @@
 //! Locality-sensitive hashings.
+pub mod bagminhash;
+pub mod cgk;
+pub mod containment;
+pub mod icws;
 pub mod minhash;
+pub mod pstable;
 pub mod simhash;
+pub mod superbit;
 
-use std::hash::Hash;
+use rand_xoshiro::rand_core::RngCore;
 
-use hashbrown::HashSet;
-use rand_xoshiro::rand_core::{RngCore, SeedableRng};
+/// Generates a hash value with [`ElementHasher::SplitMix64`], which is the same as the output of
+/// SplitMix64 seeded with `x ^ seed` without constructing the generator.
+#[inline(always)]
+pub(crate) const fn hash_u64(x: u64, seed: u64) -> u64 {
+    ElementHasher::SplitMix64.hash(x, seed)
+}
 
-/// Generates a hash value.
+/// Draws a value from the uniform distribution on (0,1).
 #[inline(always)]
-pub(crate) fn hash_u64(x: u64, seed: u64) -> u64 {
-    rand_xoshiro::SplitMix64::seed_from_u64(x ^ seed).next_u64()
+pub(crate) fn uniform(rng: &mut rand_xoshiro::SplitMix64) -> f64 {
+    ((rng.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+}
+
+/// Draws a value from the standard normal distribution with the Box-Muller transform.
+pub(crate) fn gaussian(rng: &mut rand_xoshiro::SplitMix64) -> f64 {
+    let u1 = uniform(rng);
+    let u2 = uniform(rng);
+    (-2. * u1.ln()).sqrt() * (2. * std::f64::consts::PI * u2).cos()
 }
 
 /// Computes the Jaccard distance.
@@ -18,18 +35,88 @@ pub(crate) fn hash_u64(x: u64, seed: u64) -> u64 {
 /// # Examples
 ///
 /// ```
+/// #![allow(deprecated)]
 /// use find_simdoc::lsh::jaccard_distance;
 ///
 /// let x = vec![1, 2, 4];
 /// let y = vec![1, 2, 5, 7];
 /// assert_eq!(jaccard_distance(x, y), 0.6);
 /// ```
+#[deprecated(
+    since = "0.1.2",
+    note = "use `find_simdoc::exact::jaccard_distance` instead"
+)]
+#[inline(always)]
 pub fn jaccard_distance<I, T>(lhs: I, rhs: I) -> f64
 where
     I: IntoIterator<Item = T>,
-    T: Hash + Eq,
+    T: std::hash::Hash + Eq,
 {
-    let a = HashSet::<T>::from_iter(lhs);
-    let b = HashSet::<T>::from_iter(rhs);
-    1. - (a.intersection(&b).count() as f64) / (a.union(&b).count() as f64)
+    crate::exact::jaccard_distance(lhs, rhs)
+}
+
+/// Hash functions of elements used in the hashers.
+///
+/// All the functions are deterministic for a seed, so sketches are reproducible
+/// as long as the same function and seed are used.
+/// The function is selectable in [`minhash::MinHasher`], [`simhash::SimHasher`], and
+/// [`superbit::SuperBitHasher`], i.e., in [`crate::JaccardSearcher`] and [`crate::CosineSearcher`],
+/// while the other hashers always use [`ElementHasher::SplitMix64`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ElementHasher {
+    /// The output of SplitMix64 seeded with the element (default).
+    #[default]
+    SplitMix64,
+
+    /// A wyhash-style mixer with a single 128-bit multiplication,
+    /// which is faster than [`ElementHasher::SplitMix64`].
+    WyMix,
+}
+
+impl ElementHasher {
+    /// Generates the hash value of an element with a seed.
+    #[inline(always)]
+    pub const fn hash(self, x: u64, seed: u64) -> u64 {
+        match self {
+            Self::SplitMix64 => {
+                // Equivalent to the output of the generator seeded with x ^ seed.
+                let mut z = (x ^ seed).wrapping_add(0x9e37_79b9_7f4a_7c15);
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^ (z >> 31)
+            }
+            Self::WyMix => {
+                let a = x ^ seed ^ 0xa076_1d64_78bd_642f;
+                let b = x ^ 0xe703_7ed1_a0b4_28db;
+                let m = (a as u128).wrapping_mul(b as u128);
+                (m as u64) ^ ((m >> 64) as u64)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand_xoshiro::rand_core::SeedableRng;
+
+    #[test]
+    fn test_element_hasher() {
+        for x in [0, 1, 42, u64::MAX] {
+            for seed in [0, 7, u64::MAX] {
+                let mut rng = rand_xoshiro::SplitMix64::seed_from_u64(x ^ seed);
+                assert_eq!(ElementHasher::SplitMix64.hash(x, seed), rng.next_u64());
+            }
+        }
+        let h = ElementHasher::WyMix;
+        assert_eq!(h.hash(1, 2), h.hash(1, 2));
+        assert_ne!(h.hash(1, 2), h.hash(2, 2));
+        assert_ne!(h.hash(1, 2), h.hash(1, 3));
+    }
 }
@@ -0,0 +1,292 @@
+//! BagMinHash for the Jaccard similarity of multisets.
+use hashbrown::HashMap;
+use rand_xoshiro::rand_core::{RngCore, SeedableRng};
+
+use crate::errors::{FindSimdocError, Result};
+use crate::lsh::minhash::{pack_mins, MinHasher};
+use crate::lsh::{hash_u64, uniform};
+
+/// [BagMinHash](https://doi.org/10.1145/3219819.3220089) for the bag (or multiset) Jaccard similarity,
+/// i.e., `Σ min(x_i, y_i) / Σ max(x_i, y_i)` for the multiplicities `x_i` and `y_i` of element `i`.
+///
+/// Each element of multiplicity `w` owns the points of a Poisson process on the weight range `(0, w]`
+/// over time, and each point is assigned to a random sample, whose value is the earliest time of
+/// the assigned points. The points of an element are generated in increasing order of time and
+/// stopped as soon as they are later than all the samples, so the cost is independent of
+/// the multiplicities except for a logarithmic factor, unlike repeating minwise hashing for
+/// every occurrence. As in [`MinHasher`], each sample keeps the lowest `b` bits of a hashed value.
+///
+/// The weight range is split into the dyadic intervals `(0, 1]`, `(1, 2]`, `(2, 4]`, ...,
+/// each of which draws its points from its own generator, so the points of an element are
+/// consistent for any multiplicity. This is the integer case of the interval splitting in
+/// the paper, which needs no binomial subdivision.
+pub struct BagMinHasher {
+    hasher: MinHasher,
+    seed: u64,
+}
+
+impl BagMinHasher {
+    /// Creates an instance.
+    pub const fn new(seed: u64) -> Self {
+        Self {
+            hasher: MinHasher::new(seed),
+            seed,
+        }
+    }
+
+    /// Sets the number of bits `b` retained from each sample.
+    /// See [`MinHasher::bits()`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `bits` is not 1, 2, 4, or 8.
+    pub fn bits(mut self, bits: usize) -> Result<Self> {
+        self.hasher = self.hasher.bits(bits)?;
+        Ok(self)
+    }
+
+    /// Gets the hasher with the same number of bits, which provides the distance estimation
+    /// of sketches such as [`MinHasher::hamdist()`] and [`MinHasher::distance_scale()`].
+    pub const fn hasher(&self) -> &MinHasher {
+        &self.hasher
+    }
+
+    /// Generates `num_chunks` sketches from an input feature,
+    /// where duplicate elements are counted as multiplicities.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the feature is empty.
+    pub fn sketch(&self, feature: &[u64], num_chunks: usize) -> Result<Vec<u64>> {
+        let mut counts = HashMap::new();
+        for &x in feature {
+            *counts.entry(x).or_insert(0) += 1;
+        }
+        self.sketch_bag(&counts, num_chunks)
+    }
+
+    /// Generates `num_chunks` sketches from pairs of an element and its multiplicity,
+    /// which are the same as those generated by [`Self::sketch()`] from the repeated elements.
+    /// Multiplicities of the same element are summed up.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the feature has no element with a positive multiplicity.
+    pub fn sketch_counts(&self, feature: &[(u64, u64)], num_chunks: usize) -> Result<Vec<u64>> {
+        let mut counts = HashMap::new();
+        for &(x, count) in feature {
+            *counts.entry(x).or_insert(0) += count;
+        }
+        self.sketch_bag(&counts, num_chunks)
+    }
+
+    fn sketch_bag(&self, counts: &HashMap<u64, u64>, num_chunks: usize) -> Result<Vec<u64>> {
+        if counts.values().all(|&count| count == 0) {
+            return Err(FindSimdocError::input("Input feature must not be empty."));
+        }
+        let num_samples = self.hasher.num_samples();
+        let mut samples = MaxTree::new(num_chunks * num_samples);
+        for (&x, &count) in counts {
+            if count != 0 {
+                self.update(x, count, &mut samples);
+            }
+        }
+        let mins: Vec<_> = samples
+            .values()
+            .iter()
+            .map(|t| hash_u64(t.to_bits(), self.seed))
+            .collect();
+        Ok(mins
+            .chunks(num_samples)
+            .map(|mins| pack_mins(mins, self.hasher.num_bits()))
+            .collect())
+    }
+
+    /// Updates the samples with the points of element `x` of multiplicity `count`.
+    fn update(&self, x: u64, count: u64, samples: &mut MaxTree) {
+        if samples.is_empty() {
+            return;
+        }
+        let seed = hash_u64(x, self.seed);
+        // Interval i is (2^(i-1), 2^i] for i >= 1, and the last one contains the multiplicity.
+        let num_intervals = 65 - (count - 1).leading_zeros() as usize;
+        let mut intervals: Vec<_> = (0..num_intervals)
+            .map(|i| Interval::new(i, hash_u64(i as u64, seed)))
+            .collect();
+        loop {
+            let interval = intervals
+                .iter_mut()
+                .min_by(|a, b| a.time.total_cmp(&b.time))
+                .unwrap();
+            if interval.time >= samples.max() {
+                break;
+            }
+            let weight = interval.start + uniform(&mut interval.rng) * interval.len;
+            let j = ((interval.rng.next_u64() as u128 * samples.len() as u128) >> 64) as usize;
+            if weight <= count as f64 {
+                samples.update(j, interval.time);
+            }
+            interval.advance();
+        }
+    }
+}
+
+/// Generator of the points on a weight interval in increasing order of time.
+struct Interval {
+    start: f64,
+    len: f64,
+    time: f64,
+    rng: rand_xoshiro::SplitMix64,
+}
+
+impl Interval {
+    fn new(i: usize, seed: u64) -> Self {
+        let (start, len) = if i == 0 {
+            (0., 1.)
+        } else {
+            let len = (1u64 << (i - 1)) as f64;
+            (len, len)
+        };
+        let mut interval = Self {
+            start,
+            len,
+            time: 0.,
+            rng: rand_xoshiro::SplitMix64::seed_from_u64(seed),
+        };
+        interval.advance();
+        interval
+    }
+
+    /// Moves to the next point, whose interarrival time is exponential with the rate of the length.
+    fn advance(&mut self) {
+        self.time -= uniform(&mut self.rng).ln() / self.len;
+    }
+}
+
+/// Samples with their maximum maintained in a binary tree,
+/// which gives the stopping time of the points in logarithmic time for each update.
+struct MaxTree {
+    // Node i has children 2i and 2i+1, and the samples are the leaves from index len.
+    nodes: Vec<f64>,
+}
+
+impl MaxTree {
+    fn new(len: usize) -> Self {
+        Self {
+            nodes: vec![f64::INFINITY; 2 * len],
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.nodes.len() / 2
+    }
+
+    fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Gets the maximum, which is at the root unless the tree is empty.
+    fn max(&self) -> f64 {
+        self.nodes[1]
+    }
+
+    fn values(&self) -> &[f64] {
+        &self.nodes[self.len()..]
+    }
+
+    fn update(&mut self, j: usize, time: f64) {
+        let mut i = self.len() + j;
+        if time >= self.nodes[i] {
+            return;
+        }
+        self.nodes[i] = time;
+        while i > 1 {
+            i /= 2;
+            self.nodes[i] = self.nodes[2 * i].max(self.nodes[2 * i + 1]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::exact::bag_jaccard_distance;
+
+    fn estimate(hasher: &BagMinHasher, sx: &[u64], sy: &[u64]) -> f64 {
+        let minhasher = hasher.hasher();
+        let num_diffs: usize = sx
+            .iter()
+            .zip(sy)
+            .map(|(&a, &b)| minhasher.hamdist(a, b))
+            .sum();
+        let ratio = num_diffs as f64 / (sx.len() * minhasher.num_samples()) as f64;
+        ratio * minhasher.distance_scale()
+    }
+
+    #[test]
+    fn test_max_tree() {
+        let mut tree = MaxTree::new(3);
+        assert_eq!(tree.max(), f64::INFINITY);
+        tree.update(0, 2.);
+        tree.update(1, 3.);
+        assert_eq!(tree.max(), f64::INFINITY);
+        tree.update(2, 1.);
+        assert_eq!(tree.max(), 3.);
+        tree.update(1, 4.);
+        assert_eq!(tree.max(), 3.);
+        tree.update(1, 0.5);
+        assert_eq!(tree.max(), 2.);
+        assert_eq!(tree.values(), &[2., 0.5, 1.]);
+    }
+
+    #[test]
+    fn test_sketch_counts() {
+        let hasher = BagMinHasher::new(42).bits(2).unwrap();
+        let x: Vec<u64> = [1; 5].into_iter().chain([2; 3]).chain([1]).collect();
+        let expected = hasher.sketch(&x, 4).unwrap();
+        assert_eq!(
+            hasher.sketch_counts(&[(2, 3), (1, 6)], 4).unwrap(),
+            expected
+        );
+        assert_eq!(
+            hasher
+                .sketch_counts(&[(1, 2), (2, 3), (3, 0), (1, 4)], 4)
+                .unwrap(),
+            expected
+        );
+        assert!(hasher.sketch_counts(&[(1, 0)], 4).is_err());
+    }
+
+    #[test]
+    fn test_estimate() {
+        // The sets of x and y are the same, but the multiplicities of 1 are different.
+        let x: Vec<u64> = [1; 20].into_iter().chain([2; 20]).collect();
+        let y: Vec<u64> = [1; 10].into_iter().chain([2; 20]).collect();
+        let expected = bag_jaccard_distance(&x, &y);
+        assert_eq!(expected, 0.25);
+
+        let hasher = BagMinHasher::new(42).bits(4).unwrap();
+        let num_chunks = 256;
+        let sx = hasher.sketch(&x, num_chunks).unwrap();
+        let sy = hasher.sketch(&y, num_chunks).unwrap();
+        let estimated = estimate(&hasher, &sx, &sy);
+        assert!((estimated - expected).abs() < 0.05, "{estimated}");
+        assert!(hasher.sketch(&[], 1).is_err());
+    }
+
+    #[test]
+    fn test_large_multiplicities() {
+        // The cost does not grow with the multiplicities.
+        let hasher = BagMinHasher::new(42).bits(4).unwrap();
+        let num_chunks = 256;
+        let sx = hasher
+            .sketch_counts(&[(1, 3 << 40), (2, 1 << 40)], num_chunks)
+            .unwrap();
+        let sy = hasher
+            .sketch_counts(&[(1, 1 << 40), (2, 1 << 40)], num_chunks)
+            .unwrap();
+        let estimated = estimate(&hasher, &sx, &sy);
+        assert!((estimated - 0.5).abs() < 0.05, "{estimated}");
+    }
+}
@@ -0,0 +1,155 @@
+//! CGK embedding from the edit distance into the Hamming distance.
+use rand_xoshiro::rand_core::{RngCore, SeedableRng};
+
+use crate::errors::{FindSimdocError, Result};
+
+/// Symbol padded after the end of a string in embeddings.
+const PAD: u64 = u64::MAX;
+
+/// [CGK embedding](https://dl.acm.org/doi/10.1145/2897518.2897577) from the edit distance
+/// into the Hamming distance, which randomly walks on a string of length `n`
+/// to produce an embedding of length `3n`.
+///
+/// For strings in the edit distance `k`, the Hamming distance of their embeddings
+/// is at least `k/2`, and at most `O(k^2)` with a constant probability.
+/// To search in the Hamming space, each symbol of embeddings is hashed into a bit,
+/// so that a mismatched symbol results in a mismatched bit with the probability 1/2.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::lsh::cgk::CgkEmbedder;
+///
+/// let embedder = CgkEmbedder::new(42, 10);
+/// let x = embedder.sketch("abcdefg").unwrap();
+/// let y = embedder.sketch("abcdefg").unwrap();
+/// assert_eq!(x, y);
+/// assert_eq!(x.len(), embedder.num_chunks());
+/// ```
+pub struct CgkEmbedder {
+    walk_seed: u64,
+    bit_seed: u64,
+    max_len: usize,
+}
+
+impl CgkEmbedder {
+    /// Creates an instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Seed value for random values.
+    /// * `max_len` - Maximum number of characters in input strings.
+    pub fn new(seed: u64, max_len: usize) -> Self {
+        let mut seeder = rand_xoshiro::SplitMix64::seed_from_u64(seed);
+        Self {
+            walk_seed: seeder.next_u64(),
+            bit_seed: seeder.next_u64(),
+            max_len,
+        }
+    }
+
+    /// Gets the number of symbols in embeddings, i.e., `3 * max_len`.
+    pub const fn embedding_len(&self) -> usize {
+        3 * self.max_len
+    }
+
+    /// Gets the number of chunks of 64 bits in sketches.
+    pub const fn num_chunks(&self) -> usize {
+        self.embedding_len().div_ceil(64)
+    }
+
+    /// Embeds an input string into a sequence of `3 * max_len` symbols,
+    /// where characters are represented by their code points and `u64::MAX` is padded.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the string has more than `max_len` characters.
+    pub fn embed(&self, text: &str) -> Result<Vec<u64>> {
+        let chars: Vec<_> = text.chars().map(|c| c as u64).collect();
+        if self.max_len < chars.len() {
+            return Err(FindSimdocError::input(
+                "The string must not have more than max_len characters.",
+            ));
+        }
+        let mut embedding = Vec::with_capacity(self.embedding_len());
+        let mut i = 0;
+        for j in 0..self.embedding_len() {
+            let Some(&c) = chars.get(i) else {
+                embedding.push(PAD);
+                continue;
+            };
+            embedding.push(c);
+            // Advances the pointer with a random bit depending on the step and the character.
+            let r = crate::lsh::hash_u64(((j as u64) << 32) ^ c, self.walk_seed) & 1;
+            i += r as usize;
+        }
+        Ok(embedding)
+    }
+
+    /// Generates [`Self::num_chunks()`] sketches from an input string,
+    /// where each bit corresponds to a symbol of the embedding.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the string has more than `max_len` characters.
+    pub fn sketch(&self, text: &str) -> Result<Vec<u64>> {
+        let embedding = self.embed(text)?;
+        let sketches = embedding
+            .chunks(64)
+            .enumerate()
+            .map(|(k, symbols)| {
+                symbols.iter().enumerate().fold(0, |acc, (j, &s)| {
+                    let pos = (64 * k + j) as u64;
+                    let bit = crate::lsh::hash_u64(s ^ pos.rotate_left(32), self.bit_seed) & 1;
+                    acc | (bit << j)
+                })
+            })
+            .collect();
+        Ok(sketches)
+    }
+
+    /// Maps an input edit distance into a radius in the Hamming space of sketches,
+    /// normalized by the number of bits.
+    ///
+    /// The radius is based on the quadratic distortion of the embedding, i.e.,
+    /// half of `edit_distance^2` bits are expected to mismatch in the worst case,
+    /// which bounds the Hamming distance with a constant probability.
+    /// Since the bound is not strict, verify candidates with the exact edit distance.
+    pub fn hamming_radius(&self, edit_distance: usize) -> f64 {
+        let bits = (edit_distance * edit_distance) as f64 / 2.;
+        (bits / (64 * self.num_chunks()) as f64).min(1.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hamdist(x: &[u64], y: &[u64]) -> usize {
+        x.iter().zip(y).filter(|(a, b)| a != b).count()
+    }
+
+    #[test]
+    fn test_embed() {
+        let embedder = CgkEmbedder::new(42, 20);
+        assert!(embedder.embed("find similar documents").is_err());
+
+        let x = embedder.embed("similar documents").unwrap();
+        let y = embedder.embed("similar document").unwrap();
+        let z = embedder.embed("simular documents").unwrap();
+        assert_eq!(x.len(), 60);
+        assert_eq!(x[0], 's' as u64);
+        // The Hamming distance is at least half of the edit distance.
+        assert!(hamdist(&x, &y) >= 1);
+        assert!(hamdist(&x, &z) >= 1);
+        assert!(hamdist(&x, &z) < 30);
+    }
+
+    #[test]
+    fn test_hamming_radius() {
+        let embedder = CgkEmbedder::new(42, 64);
+        assert_eq!(embedder.num_chunks(), 3);
+        assert_eq!(embedder.hamming_radius(4), 8. / 192.);
+        assert_eq!(embedder.hamming_radius(100), 1.);
+    }
+}
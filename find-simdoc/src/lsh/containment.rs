@@ -0,0 +1,221 @@
+//! Containment and related set similarity estimation with minwise hashing.
+use crate::errors::{FindSimdocError, Result};
+use crate::lsh::minhash::MinHasher;
+
+/// Minhash sketches of a set stored with the size of the set,
+/// with which the containment of sets is estimated.
+///
+/// The containment of `X` in `Y`, i.e., `|X ∩ Y| / |X|`, is asymmetric and
+/// cannot be derived from the sketches alone, but is derived from the Jaccard similarity `J`
+/// and the set sizes as `J (|X| + |Y|) / ((1 + J) |X|)`.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::lsh::containment::SizedSketch;
+/// use find_simdoc::lsh::minhash::MinHasher;
+///
+/// let hasher = MinHasher::new(42);
+/// let x: Vec<u64> = (0..50).collect();
+/// let y: Vec<u64> = (0..150).collect();
+///
+/// let x = SizedSketch::new(&hasher, &x, 64).unwrap();
+/// let y = SizedSketch::new(&hasher, &y, 64).unwrap();
+/// assert_eq!(x.size(), 50);
+///
+/// // X is entirely contained in Y.
+/// let containment = x.containment_in(&y, &hasher).unwrap();
+/// assert!(containment > 0.9);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SizedSketch {
+    sketches: Vec<u64>,
+    size: usize,
+}
+
+impl SizedSketch {
+    /// Generates the sketches of an input feature, storing the number of its distinct elements.
+    ///
+    /// # Arguments
+    ///
+    /// * `hasher` - Minhash hasher generating the sketches.
+    /// * `feature` - Elements of the set, which may contain duplicates.
+    /// * `num_chunks` - Number of chunks of sketches.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the feature is empty or `num_chunks` is 0.
+    pub fn new(hasher: &MinHasher, feature: &[u64], num_chunks: usize) -> Result<Self> {
+        if num_chunks == 0 {
+            return Err(FindSimdocError::input("#chunks must be positive."));
+        }
+        let mut elems = feature.to_vec();
+        elems.sort_unstable();
+        elems.dedup();
+        Ok(Self {
+            sketches: hasher.sketch(&elems, num_chunks)?,
+            size: elems.len(),
+        })
+    }
+
+    /// Creates an instance from sketches and the set size computed in advance.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the sketches are empty or `size` is 0.
+    pub fn from_parts(sketches: Vec<u64>, size: usize) -> Result<Self> {
+        if sketches.is_empty() || size == 0 {
+            return Err(FindSimdocError::input(
+                "Sketches and the set size must not be empty.",
+            ));
+        }
+        Ok(Self { sketches, size })
+    }
+
+    /// Gets the sketches.
+    pub fn sketches(&self) -> &[u64] {
+        &self.sketches
+    }
+
+    /// Gets the number of distinct elements in the set.
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Estimates the Jaccard similarity to another set.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the numbers of chunks are different.
+    pub fn jaccard(&self, other: &Self, hasher: &MinHasher) -> Result<f64> {
+        if self.sketches.len() != other.sketches.len() {
+            return Err(FindSimdocError::input("Mismatched numbers of chunks."));
+        }
+        let num_diffs: usize = self
+            .sketches
+            .iter()
+            .zip(&other.sketches)
+            .map(|(&x, &y)| hasher.hamdist(x, y))
+            .sum();
+        let ratio = num_diffs as f64 / (self.sketches.len() * hasher.num_samples()) as f64;
+        Ok((1. - ratio * hasher.distance_scale()).clamp(0., 1.))
+    }
+
+    /// Estimates the containment of this set in another set, i.e., `|X ∩ Y| / |X|`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the numbers of chunks are different.
+    pub fn containment_in(&self, other: &Self, hasher: &MinHasher) -> Result<f64> {
+        let jaccard = self.jaccard(other, hasher)?;
+        Ok(containment_from_jaccard(jaccard, self.size, other.size))
+    }
+
+    /// Estimates the Dice similarity to another set, i.e., `2|X ∩ Y| / (|X| + |Y|)`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the numbers of chunks are different.
+    pub fn dice(&self, other: &Self, hasher: &MinHasher) -> Result<f64> {
+        Ok(dice_from_jaccard(self.jaccard(other, hasher)?))
+    }
+
+    /// Estimates the overlap coefficient with another set, i.e., `|X ∩ Y| / min(|X|, |Y|)`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the numbers of chunks are different.
+    pub fn overlap(&self, other: &Self, hasher: &MinHasher) -> Result<f64> {
+        let jaccard = self.jaccard(other, hasher)?;
+        Ok(overlap_from_jaccard(jaccard, self.size, other.size))
+    }
+}
+
+/// Converts the Jaccard similarity of sets `X` and `Y` into the containment of `X` in `Y`,
+/// i.e., `J (|X| + |Y|) / ((1 + J) |X|)`, clamped into the range of [0,1].
+///
+/// # Arguments
+///
+/// * `jaccard` - Jaccard similarity of `X` and `Y`.
+/// * `size_x` - Size of `X` (must be positive).
+/// * `size_y` - Size of `Y`.
+pub fn containment_from_jaccard(jaccard: f64, size_x: usize, size_y: usize) -> f64 {
+    let intersection = jaccard * (size_x + size_y) as f64 / (1. + jaccard);
+    (intersection / size_x as f64).clamp(0., 1.)
+}
+
+/// Converts the Jaccard similarity into the Dice similarity, i.e., `2J / (1 + J)`,
+/// which does not depend on the set sizes.
+pub fn dice_from_jaccard(jaccard: f64) -> f64 {
+    2. * jaccard / (1. + jaccard)
+}
+
+/// Converts the Jaccard similarity of sets `X` and `Y` into their overlap coefficient,
+/// i.e., the containment of the smaller set in the larger one.
+///
+/// # Arguments
+///
+/// * `jaccard` - Jaccard similarity of `X` and `Y`.
+/// * `size_x` - Size of `X` (must be positive).
+/// * `size_y` - Size of `Y` (must be positive).
+pub fn overlap_from_jaccard(jaccard: f64, size_x: usize, size_y: usize) -> f64 {
+    if size_x <= size_y {
+        containment_from_jaccard(jaccard, size_x, size_y)
+    } else {
+        containment_from_jaccard(jaccard, size_y, size_x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_containment_from_jaccard() {
+        // |X| = 50, |Y| = 150, |X ∩ Y| = 25, |X ∪ Y| = 175.
+        let jaccard = 25. / 175.;
+        assert!((containment_from_jaccard(jaccard, 50, 150) - 0.5).abs() < 1e-12);
+        assert!((containment_from_jaccard(jaccard, 150, 50) - 25. / 150.).abs() < 1e-12);
+        assert_eq!(containment_from_jaccard(0., 10, 10), 0.);
+        assert_eq!(containment_from_jaccard(1., 10, 10), 1.);
+    }
+
+    #[test]
+    fn test_dice_and_overlap() {
+        use crate::exact::{dice_distance, jaccard_distance, overlap_distance};
+
+        let x: Vec<u64> = (0..50).collect();
+        let y: Vec<u64> = (25..175).collect();
+        let jaccard = 1. - jaccard_distance(&x, &y);
+        let dice = dice_from_jaccard(jaccard);
+        assert!((dice - (1. - dice_distance(&x, &y))).abs() < 1e-12);
+        let overlap = overlap_from_jaccard(jaccard, 50, 150);
+        assert!((overlap - (1. - overlap_distance(&x, &y))).abs() < 1e-12);
+
+        let hasher = MinHasher::new(42).bits(4).unwrap();
+        let sx = SizedSketch::new(&hasher, &x, 256).unwrap();
+        let sy = SizedSketch::new(&hasher, &y, 256).unwrap();
+        let d = sx.dice(&sy, &hasher).unwrap();
+        assert!((d - dice).abs() < 0.05, "{d}");
+        let o = sy.overlap(&sx, &hasher).unwrap();
+        assert!((o - overlap).abs() < 0.1, "{o}");
+    }
+
+    #[test]
+    fn test_containment_in() {
+        let hasher = MinHasher::new(42).bits(4).unwrap();
+        let x: Vec<u64> = (0..50).chain(0..50).collect();
+        let y: Vec<u64> = (25..175).collect();
+        let x = SizedSketch::new(&hasher, &x, 256).unwrap();
+        let y = SizedSketch::new(&hasher, &y, 256).unwrap();
+        assert_eq!(x.size(), 50);
+        let c = x.containment_in(&y, &hasher).unwrap();
+        assert!((c - 0.5).abs() < 0.1, "{c}");
+        let c = y.containment_in(&x, &hasher).unwrap();
+        assert!((c - 25. / 150.).abs() < 0.05, "{c}");
+
+        let z = SizedSketch::new(&hasher, &[1, 2, 3], 8).unwrap();
+        assert!(x.containment_in(&z, &hasher).is_err());
+        assert!(SizedSketch::new(&hasher, &[], 8).is_err());
+    }
+}
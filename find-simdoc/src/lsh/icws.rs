@@ -0,0 +1,146 @@
+//! Improved consistent weighted sampling for the weighted Jaccard similarity.
+use hashbrown::HashMap;
+use rand_xoshiro::rand_core::{RngCore, SeedableRng};
+
+use crate::errors::{FindSimdocError, Result};
+use crate::lsh::uniform;
+
+/// [Improved consistent weighted sampling (ICWS)](https://doi.org/10.1109/ICDM.2010.80)
+/// for the weighted Jaccard similarity.
+///
+/// Each sample of two weighted features collides with the probability of their
+/// weighted Jaccard similarity, i.e., `Σ min(x_i, y_i) / Σ max(x_i, y_i)`,
+/// and each bit of sketches is the lowest bit of a hashed sample (as in 1-bit minwise hashing).
+/// Weights of the same term are summed up, and terms with non-positive weights are ignored.
+pub struct IcwsHasher {
+    seed: u64,
+}
+
+impl IcwsHasher {
+    /// Creates an instance.
+    pub const fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Creates an iterator to generate sketches from an input feature.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the feature has no term with a positive weight,
+    /// since the samples of an empty set are undefined.
+    pub fn iter(&self, feature: &[(u64, f64)]) -> Result<IcwsIter> {
+        let mut weights = HashMap::new();
+        for &(term, weight) in feature {
+            *weights.entry(term).or_insert(0.) += weight;
+        }
+        let weights: Vec<_> = weights
+            .into_iter()
+            .filter(|&(_, w)| w > 0.)
+            .map(|(term, w)| (term, w.ln()))
+            .collect();
+        if weights.is_empty() {
+            return Err(FindSimdocError::input("Input feature must not be empty."));
+        }
+        Ok(IcwsIter {
+            weights,
+            seeder: rand_xoshiro::SplitMix64::seed_from_u64(self.seed),
+        })
+    }
+}
+
+/// Iterator to generate sketches with the ICWS.
+pub struct IcwsIter {
+    // Pairs of a term and the logarithm of its weight.
+    weights: Vec<(u64, f64)>,
+    seeder: rand_xoshiro::SplitMix64,
+}
+
+impl IcwsIter {
+    /// Draws the sample `(k, t)` for the seed.
+    fn sample(&self, seed: u64) -> (u64, i64) {
+        let mut best = (0, 0);
+        let mut min_a = f64::INFINITY;
+        for &(term, ln_weight) in &self.weights {
+            // Random variables are consistent for each pair of a term and a seed.
+            let mut rng = rand_xoshiro::SplitMix64::seed_from_u64(crate::lsh::hash_u64(term, seed));
+            let r = gamma2(&mut rng);
+            let c = gamma2(&mut rng);
+            let beta = uniform(&mut rng);
+            let t = (ln_weight / r + beta).floor();
+            let ln_y = r * (t - beta);
+            let ln_a = c.ln() - ln_y - r;
+            if ln_a < min_a {
+                min_a = ln_a;
+                best = (term, t as i64);
+            }
+        }
+        best
+    }
+}
+
+impl Iterator for IcwsIter {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut x = 0;
+        for _ in 0..64 {
+            let seed = self.seeder.next_u64();
+            let (term, t) = self.sample(seed);
+            let bit = crate::lsh::hash_u64(term ^ crate::lsh::hash_u64(t as u64, seed), seed) & 1;
+            x = (x << 1) | bit;
+        }
+        Some(x)
+    }
+}
+
+/// Draws a value from the Gamma(2,1) distribution.
+fn gamma2(rng: &mut rand_xoshiro::SplitMix64) -> f64 {
+    -(uniform(rng) * uniform(rng)).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collision_probability() {
+        let x = [(1, 1.), (2, 2.), (3, 3.), (4, 0.5)];
+        let y = [(1, 1.), (2, 1.), (3, 4.), (5, 1.)];
+        // (1 + 1 + 3) / (1 + 2 + 4 + 0.5 + 1)
+        let expected = 5. / 8.5;
+
+        let hasher = IcwsHasher::new(42);
+        let num_chunks = 256;
+        let num_equal: u32 = hasher
+            .iter(&x)
+            .unwrap()
+            .zip(hasher.iter(&y).unwrap())
+            .take(num_chunks)
+            .map(|(a, b)| (!(a ^ b)).count_ones())
+            .sum();
+        let prob = f64::from(num_equal) / (num_chunks * 64) as f64;
+        // Bits collide with the probability (1 + J) / 2.
+        let estimated = 2. * prob - 1.;
+        assert!((estimated - expected).abs() < 0.05, "{estimated}");
+    }
+
+    #[test]
+    fn test_scale() {
+        let x = [(1, 1.), (2, 2.), (1, 1.)];
+        let y = [(1, 2.), (2, 2.)];
+        let hasher = IcwsHasher::new(42);
+        assert!(hasher
+            .iter(&x)
+            .unwrap()
+            .zip(hasher.iter(&y).unwrap())
+            .take(4)
+            .all(|(a, b)| a == b));
+    }
+
+    #[test]
+    fn test_empty_feature() {
+        let hasher = IcwsHasher::new(42);
+        assert!(hasher.iter(&[]).is_err());
+        assert!(hasher.iter(&[(1, 0.), (2, -1.)]).is_err());
+    }
+}
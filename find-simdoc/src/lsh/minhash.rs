@@ -1,47 +1,439 @@
-//! 1-bit minwise hashing for the Jaccard similarity.
+//! b-bit minwise hashing for the Jaccard similarity.
+use std::marker::PhantomData;
+
+use all_pairs_hamming::sketch::Sketch;
 use rand_xoshiro::rand_core::{RngCore, SeedableRng};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::errors::{FindSimdocError, Result};
+use crate::lsh::ElementHasher;
 
-/// [1-bit minwise hashing](https://dl.acm.org/doi/abs/10.1145/1772690.1772759) for the Jaccard similarity.
+/// [b-bit minwise hashing](https://dl.acm.org/doi/abs/10.1145/1772690.1772759) for the Jaccard similarity,
+/// where each sketch of 64 bits consists of `64/b` samples of the lowest `b` bits of minimum hash values.
+///
+/// The 1-bit variant is used by default.
 pub struct MinHasher {
     seed: u64,
+    bits: usize,
+    element_hasher: ElementHasher,
 }
 
 impl MinHasher {
     /// Creates an instance.
     pub const fn new(seed: u64) -> Self {
-        Self { seed }
+        Self {
+            seed,
+            bits: 1,
+            element_hasher: ElementHasher::SplitMix64,
+        }
+    }
+
+    /// Sets the hash function of elements.
+    /// The default value is [`ElementHasher::SplitMix64`].
+    pub const fn element_hasher(mut self, element_hasher: ElementHasher) -> Self {
+        self.element_hasher = element_hasher;
+        self
+    }
+
+    /// Gets the hash function of elements.
+    pub const fn element_hasher_value(&self) -> ElementHasher {
+        self.element_hasher
+    }
+
+    /// Sets the number of bits `b` retained from each minimum hash value.
+    /// A larger value lowers the variance of estimates at the cost of the number of samples.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `bits` is not 1, 2, 4, or 8.
+    pub fn bits(mut self, bits: usize) -> Result<Self> {
+        if !matches!(bits, 1 | 2 | 4 | 8) {
+            return Err(FindSimdocError::input(
+                "The number of bits must be 1, 2, 4, or 8.",
+            ));
+        }
+        self.bits = bits;
+        Ok(self)
+    }
+
+    /// Gets the number of bits retained from each minimum hash value.
+    pub const fn num_bits(&self) -> usize {
+        self.bits
+    }
+
+    /// Gets the number of samples in a sketch of 64 bits.
+    pub const fn num_samples(&self) -> usize {
+        64 / self.bits
+    }
+
+    /// Counts the samples with different values in two sketches.
+    pub const fn hamdist(&self, x: u64, y: u64) -> usize {
+        let mut z = x ^ y;
+        let mut s = 1;
+        while s < self.bits {
+            z |= z >> s;
+            s <<= 1;
+        }
+        let mask = match self.bits {
+            1 => !0,
+            2 => 0x5555_5555_5555_5555,
+            4 => 0x1111_1111_1111_1111,
+            _ => 0x0101_0101_0101_0101,
+        };
+        (z & mask).count_ones() as usize
+    }
+
+    /// Counts the samples with different values in two sketches of type `S`,
+    /// which is the same as [`Self::hamdist()`] for [`u64`].
+    pub fn hamdist_as<S>(&self, x: S, y: S) -> usize
+    where
+        S: Sketch,
+    {
+        if self.bits == 1 {
+            return x.hamdist(y);
+        }
+        let mut z = x ^ y;
+        let mut s = 1;
+        while s < self.bits {
+            z = z | (z >> s);
+            s <<= 1;
+        }
+        // Masks the lowest bit of each sample.
+        let mask = (0..S::dim())
+            .step_by(self.bits)
+            .fold(S::zero(), |mask, i| mask | (S::one() << i));
+        (z & mask).count_ones() as usize
+    }
+
+    /// Gets the scale converting the ratio of different samples into the Jaccard distance.
+    ///
+    /// Two samples are equal by chance with the probability `2^-b` even when
+    /// the minimum hash values are different, so the ratio is corrected with `1/(1-2^-b)`.
+    pub fn distance_scale(&self) -> f64 {
+        1. / (1. - 0.5f64.powi(self.bits as i32))
+    }
+
+    /// Generates `num_chunks` sketches from an input feature, which are the same as
+    /// those generated by [`Self::iter()`].
+    ///
+    /// The seeds of all the samples are precomputed, and all the minimums are
+    /// updated in a single pass over the feature.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the feature is empty.
+    pub fn sketch(&self, feature: &[u64], num_chunks: usize) -> Result<Vec<u64>> {
+        self.sketch_as(feature, num_chunks)
+    }
+
+    /// Generates `num_chunks` sketches of type `S` from an input feature, which are the same as
+    /// those generated by [`Self::iter_as()`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the feature is empty.
+    pub fn sketch_as<S>(&self, feature: &[u64], num_chunks: usize) -> Result<Vec<S>>
+    where
+        S: Sketch,
+    {
+        let num_samples = S::dim() / self.bits;
+        Ok(self
+            .signature(feature, num_chunks * num_samples)?
+            .chunks(num_samples)
+            .map(|mins| pack_mins(mins, self.bits))
+            .collect())
+    }
+
+    /// Generates `num_chunks` sketches from an input feature in parallel over chunks,
+    /// which are the same as those generated by [`Self::sketch()`].
+    ///
+    /// This is effective for a long feature and many chunks.
+    /// Without the `parallel` feature, the chunks are generated sequentially.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the feature is empty.
+    pub fn sketch_in_parallel(&self, feature: &[u64], num_chunks: usize) -> Result<Vec<u64>> {
+        check_feature(feature)?;
+        let mut seeder = rand_xoshiro::SplitMix64::seed_from_u64(self.seed);
+        let seeds: Vec<_> = (0..num_chunks * self.num_samples())
+            .map(|_| seeder.next_u64())
+            .collect();
+        let sketch = |seeds: &[u64]| {
+            let mut mins = vec![u64::MAX; seeds.len()];
+            update_mins(self.element_hasher, feature, seeds, &mut mins);
+            pack_mins(&mins, self.bits)
+        };
+        #[cfg(feature = "parallel")]
+        let sketches = seeds.par_chunks(self.num_samples()).map(sketch).collect();
+        #[cfg(not(feature = "parallel"))]
+        let sketches = seeds.chunks(self.num_samples()).map(sketch).collect();
+        Ok(sketches)
+    }
+
+    /// Generates the classical minhash signature of `num_hashes` minimum hash values
+    /// from an input feature, without the b-bit compression.
+    ///
+    /// The signature is compatible with systems expecting full minhash signatures.
+    /// The lowest `b` bits of the `i`-th value are the `i`-th sample of the sketches
+    /// generated by [`Self::sketch()`], packed from the most significant bits.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the feature is empty.
+    pub fn signature(&self, feature: &[u64], num_hashes: usize) -> Result<Vec<u64>> {
+        check_feature(feature)?;
+        let mut seeder = rand_xoshiro::SplitMix64::seed_from_u64(self.seed);
+        let seeds: Vec<_> = (0..num_hashes).map(|_| seeder.next_u64()).collect();
+        let mut mins = vec![u64::MAX; seeds.len()];
+        update_mins(self.element_hasher, feature, &seeds, &mut mins);
+        Ok(mins)
+    }
+
+    /// Generates the minhash signature of [`Self::signature()`] truncated to the lowest 32 bits,
+    /// which is the common format of 32-bit signatures.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the feature is empty.
+    pub fn signature_u32(&self, feature: &[u64], num_hashes: usize) -> Result<Vec<u32>> {
+        Ok(self
+            .signature(feature, num_hashes)?
+            .into_iter()
+            .map(|h| h as u32)
+            .collect())
     }
 
     /// Creates an iterator to generate sketches from an input feature.
-    pub fn iter<'a>(&self, feature: &'a [u64]) -> MinHashIter<'a> {
-        MinHashIter {
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the feature is empty,
+    /// since the minimum hash values of an empty set are undefined.
+    pub fn iter<'a>(&self, feature: &'a [u64]) -> Result<MinHashIter<'a>> {
+        self.iter_as(feature)
+    }
+
+    /// Creates an iterator to generate sketches of type `S` from an input feature,
+    /// where each sketch consists of `S::dim()/b` samples.
+    ///
+    /// The samples are drawn in the same order for any type, e.g., a sketch of [`u128`]
+    /// is the concatenation of the first two sketches of [`u64`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the feature is empty.
+    pub fn iter_as<'a, S>(&self, feature: &'a [u64]) -> Result<MinHashIter<'a, S>>
+    where
+        S: Sketch,
+    {
+        check_feature(feature)?;
+        let num_samples = S::dim() / self.bits;
+        Ok(MinHashIter {
             feature,
+            bits: self.bits,
+            element_hasher: self.element_hasher,
             seeder: rand_xoshiro::SplitMix64::seed_from_u64(self.seed),
-        }
+            seeds: vec![0; num_samples],
+            mins: vec![u64::MAX; num_samples],
+            _sketch: PhantomData,
+        })
     }
 }
 
-/// Iterator to generate sketches with the 1-bit minwise hashing.
-pub struct MinHashIter<'a> {
+/// Iterator to generate sketches with the b-bit minwise hashing.
+pub struct MinHashIter<'a, S = u64> {
     feature: &'a [u64],
+    bits: usize,
+    element_hasher: ElementHasher,
     seeder: rand_xoshiro::SplitMix64,
+    seeds: Vec<u64>,
+    mins: Vec<u64>,
+    _sketch: PhantomData<S>,
 }
 
-impl Iterator for MinHashIter<'_> {
-    type Item = u64;
+impl<S> Iterator for MinHashIter<'_, S>
+where
+    S: Sketch,
+{
+    type Item = S;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut x = 0;
-        for _ in 0..64 {
-            let seed = self.seeder.next_u64();
-            let h = self
-                .feature
-                .iter()
-                .map(|&i| crate::lsh::hash_u64(i, seed))
-                .min()
-                .unwrap();
-            x = (x << 1) | (h & 1);
-        }
-        Some(x)
+        self.seeds.fill_with(|| self.seeder.next_u64());
+        self.mins.fill(u64::MAX);
+        update_mins(
+            self.element_hasher,
+            self.feature,
+            &self.seeds,
+            &mut self.mins,
+        );
+        Some(pack_mins(&self.mins, self.bits))
+    }
+}
+
+fn check_feature(feature: &[u64]) -> Result<()> {
+    if feature.is_empty() {
+        return Err(FindSimdocError::input("Input feature must not be empty."));
+    }
+    Ok(())
+}
+
+/// Updates the minimum hash values for the seeds in a single pass over the feature.
+fn update_mins(element_hasher: ElementHasher, feature: &[u64], seeds: &[u64], mins: &mut [u64]) {
+    for &i in feature {
+        for (&seed, min) in seeds.iter().zip(mins.iter_mut()) {
+            *min = (*min).min(element_hasher.hash(i, seed));
+        }
+    }
+}
+
+/// Packs the lowest bits of the minimum hash values into a sketch.
+pub(crate) fn pack_mins<S>(mins: &[u64], bits: usize) -> S
+where
+    S: Sketch,
+{
+    let mask = (1 << bits) - 1;
+    mins.iter().fold(S::zero(), |x, &h| {
+        // Avoids the overflow of shifting a sketch of `bits` bits.
+        let x = if bits < S::dim() {
+            x << bits
+        } else {
+            S::zero()
+        };
+        x | S::from_u64(h & mask).unwrap()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hamdist() {
+        let hasher = MinHasher::new(42);
+        assert_eq!(hasher.hamdist(0b1011, 0b0110), 3);
+        let hasher = hasher.bits(2).unwrap();
+        assert_eq!(hasher.hamdist(0b1011, 0b0110), 2);
+        let hasher = hasher.bits(4).unwrap();
+        assert_eq!(hasher.hamdist(0b1011, 0b0110), 1);
+        assert_eq!(hasher.hamdist(0x1000_0000_0000_0001, 0), 2);
+        let hasher = hasher.bits(8).unwrap();
+        assert_eq!(hasher.hamdist(0x0180, 0), 2);
+        assert!(MinHasher::new(42).bits(3).is_err());
+    }
+
+    #[test]
+    fn test_hamdist_as() {
+        for bits in [1, 2, 4, 8] {
+            let hasher = MinHasher::new(42).bits(bits).unwrap();
+            for (x, y) in [(0b1011u64, 0b0110), (0x1000_0000_0000_0001, 0), (0x0180, 0)] {
+                assert_eq!(hasher.hamdist_as(x, y), hasher.hamdist(x, y));
+                let expected = hasher.hamdist(x & 0xff, y & 0xff);
+                assert_eq!(hasher.hamdist_as(x as u8, y as u8), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sketch() {
+        let feature: Vec<_> = (0..10).collect();
+        for bits in [1, 2, 4, 8] {
+            let hasher = MinHasher::new(42).bits(bits).unwrap();
+            let expected: Vec<_> = hasher.iter(&feature).unwrap().take(3).collect();
+            assert_eq!(hasher.sketch(&feature, 3).unwrap(), expected);
+            assert_eq!(hasher.sketch_in_parallel(&feature, 3).unwrap(), expected);
+            let expected: Vec<u16> = hasher.iter_as(&feature).unwrap().take(3).collect();
+            assert_eq!(hasher.sketch_as::<u16>(&feature, 3).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_empty_feature() {
+        let hasher = MinHasher::new(42);
+        assert!(hasher.iter(&[]).is_err());
+        assert!(hasher.iter_as::<u128>(&[]).is_err());
+        assert!(hasher.sketch(&[], 1).is_err());
+        assert!(hasher.signature(&[], 1).is_err());
+    }
+
+    #[test]
+    fn test_element_hasher() {
+        let x: Vec<_> = (0..100).collect();
+        let y: Vec<_> = (50..150).collect();
+        let hasher = MinHasher::new(42).element_hasher(ElementHasher::WyMix);
+        assert_eq!(
+            hasher.sketch(&x, 2).unwrap(),
+            hasher.iter(&x).unwrap().take(2).collect::<Vec<_>>()
+        );
+        assert_ne!(
+            hasher.sketch(&x, 2).unwrap(),
+            MinHasher::new(42).sketch(&x, 2).unwrap()
+        );
+        let num_hashes = 1000;
+        let x = hasher.signature(&x, num_hashes).unwrap();
+        let y = hasher.signature(&y, num_hashes).unwrap();
+        let ratio = x.iter().zip(&y).filter(|(a, b)| a == b).count() as f64 / num_hashes as f64;
+        assert!((ratio - 1. / 3.).abs() < 0.05, "{ratio}");
+    }
+
+    #[test]
+    fn test_signature() {
+        let x: Vec<_> = (0..100).collect();
+        let y: Vec<_> = (50..150).collect();
+        let hasher = MinHasher::new(42).bits(4).unwrap();
+        let sig = hasher.signature(&x, 32).unwrap();
+        assert_eq!(sig.len(), 32);
+        let packed = sig
+            .chunks(16)
+            .map(|mins| pack_mins(mins, 4))
+            .collect::<Vec<u64>>();
+        assert_eq!(packed, hasher.sketch(&x, 2).unwrap());
+        let sig32 = hasher.signature_u32(&x, 32).unwrap();
+        assert!(sig.iter().zip(&sig32).all(|(&a, &b)| a as u32 == b));
+
+        // The ratio of equal minimums estimates the Jaccard similarity 50/150.
+        let num_hashes = 1000;
+        let x = hasher.signature(&x, num_hashes).unwrap();
+        let y = hasher.signature(&y, num_hashes).unwrap();
+        let ratio = x.iter().zip(&y).filter(|(a, b)| a == b).count() as f64 / num_hashes as f64;
+        assert!((ratio - 1. / 3.).abs() < 0.05, "{ratio}");
+    }
+
+    #[test]
+    fn test_iter_as() {
+        let feature: Vec<_> = (0..10).collect();
+        for bits in [1, 2, 4, 8] {
+            let hasher = MinHasher::new(42).bits(bits).unwrap();
+            let expected: Vec<_> = hasher.iter(&feature).unwrap().take(2).collect();
+            let x = hasher.iter_as::<u128>(&feature).unwrap().next().unwrap();
+            assert_eq!(x, (u128::from(expected[0]) << 64) | u128::from(expected[1]));
+            let x = hasher.iter_as::<u32>(&feature).unwrap().next().unwrap();
+            assert_eq!(u64::from(x), expected[0] >> 32);
+            let x = hasher.iter_as::<u8>(&feature).unwrap().next().unwrap();
+            assert_eq!(u64::from(x), expected[0] >> 56);
+        }
+    }
+
+    #[test]
+    fn test_estimate() {
+        let x: Vec<_> = (0..100).collect();
+        let y: Vec<_> = (50..150).collect();
+        // The Jaccard distance is 1 - 50/150.
+        let expected = 2. / 3.;
+        for bits in [1, 2, 4, 8] {
+            let hasher = MinHasher::new(42).bits(bits).unwrap();
+            let num_chunks = 64 * bits;
+            let num_diffs: usize = hasher
+                .iter(&x)
+                .unwrap()
+                .zip(hasher.iter(&y).unwrap())
+                .take(num_chunks)
+                .map(|(a, b)| hasher.hamdist(a, b))
+                .sum();
+            let ratio = num_diffs as f64 / (num_chunks * hasher.num_samples()) as f64;
+            let estimated = ratio * hasher.distance_scale();
+            assert!((estimated - expected).abs() < 0.05, "{bits}: {estimated}");
+        }
     }
 }
@@ -0,0 +1,153 @@
+//! p-stable locality-sensitive hashing for the Euclidean distance.
+use std::f64::consts::PI;
+
+use rand_xoshiro::rand_core::{RngCore, SeedableRng};
+
+use crate::errors::{FindSimdocError, Result};
+use crate::lsh::{gaussian, uniform};
+
+/// [p-stable LSH](https://dl.acm.org/doi/10.1145/997817.997857) for the Euclidean distance
+/// of dense vectors, with Gaussian projections quantized into buckets of width `w`.
+///
+/// Each bit of sketches is the lowest bit of a hashed bucket id, so that
+/// the sketches can be searched in the Hamming space. Two vectors in the Euclidean distance `c`
+/// have the same bit with the probability `(1 + p(c)) / 2`, where `p(c)` is the probability
+/// that they fall into the same bucket (see [`Self::collision_probability()`]).
+pub struct PStableHasher {
+    seed: u64,
+    dim: usize,
+    width: f64,
+}
+
+impl PStableHasher {
+    /// Creates an instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Seed value for random values.
+    /// * `dim` - Number of dimensions of input vectors.
+    /// * `width` - Width of buckets (must be positive).
+    pub fn new(seed: u64, dim: usize, width: f64) -> Result<Self> {
+        if dim == 0 {
+            return Err(FindSimdocError::input("dim must not be 0."));
+        }
+        if width.is_nan() || width <= 0. {
+            return Err(FindSimdocError::input("width must be positive."));
+        }
+        Ok(Self { seed, dim, width })
+    }
+
+    /// Gets the probability that two vectors in an input Euclidean distance
+    /// fall into the same bucket.
+    pub fn collision_probability(&self, distance: f64) -> f64 {
+        if distance <= 0. {
+            return 1.;
+        }
+        let r = self.width / distance;
+        1. - 2. * normal_cdf(-r) - 2. / ((2. * PI).sqrt() * r) * (1. - (-r * r / 2.).exp())
+    }
+
+    /// Maps an input Euclidean distance into the expected normalized Hamming distance of sketches,
+    /// with which a radius in the Euclidean space is converted into that in the Hamming space.
+    pub fn hamming_radius(&self, distance: f64) -> f64 {
+        (1. - self.collision_probability(distance)) / 2.
+    }
+
+    /// Creates an iterator to generate sketches from an input vector.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the vector does not have `dim` dimensions.
+    pub fn iter<'a>(&self, vector: &'a [f64]) -> Result<PStableIter<'a>> {
+        if vector.len() != self.dim {
+            return Err(FindSimdocError::input(
+                "The vector must have dim dimensions.",
+            ));
+        }
+        Ok(PStableIter {
+            vector,
+            width: self.width,
+            seeder: rand_xoshiro::SplitMix64::seed_from_u64(self.seed),
+        })
+    }
+}
+
+/// Iterator to generate sketches with the p-stable LSH.
+pub struct PStableIter<'a> {
+    vector: &'a [f64],
+    width: f64,
+    seeder: rand_xoshiro::SplitMix64,
+}
+
+impl Iterator for PStableIter<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut x = 0;
+        for _ in 0..64 {
+            let seed = self.seeder.next_u64();
+            // The projection is regenerated from the seed for each vector.
+            let mut rng = rand_xoshiro::SplitMix64::seed_from_u64(seed);
+            let p: f64 = self.vector.iter().map(|&v| gaussian(&mut rng) * v).sum();
+            let b = uniform(&mut rng) * self.width;
+            let bucket = ((p + b) / self.width).floor() as i64;
+            x = (x << 1) | (crate::lsh::hash_u64(bucket as u64, seed) & 1);
+        }
+        Some(x)
+    }
+}
+
+/// Computes the CDF of the standard normal distribution.
+fn normal_cdf(x: f64) -> f64 {
+    // Abramowitz and Stegun 7.1.26, whose error is less than 1.5e-7.
+    let z = x.abs() / 2f64.sqrt();
+    let t = 1. / (1. + 0.327_591_1 * z);
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let erfc = poly * (-z * z).exp();
+    if x >= 0. {
+        1. - erfc / 2.
+    } else {
+        erfc / 2.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normal_cdf() {
+        assert!((normal_cdf(0.) - 0.5).abs() < 1e-6);
+        assert!((normal_cdf(1.96) - 0.975).abs() < 1e-4);
+        assert!((normal_cdf(-1.) - 0.158_655).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_estimate() {
+        let x = [1., 2., 3., 4.];
+        let y = [1.5, 2., 2., 4.5];
+        let distance = (0.25f64 + 1. + 0.25).sqrt();
+
+        let hasher = PStableHasher::new(42, 4, 4.).unwrap();
+        let num_chunks = 64;
+        let hamdist: u32 = hasher
+            .iter(&x)
+            .unwrap()
+            .zip(hasher.iter(&y).unwrap())
+            .take(num_chunks)
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum();
+        let estimated = f64::from(hamdist) / (num_chunks * 64) as f64;
+        let expected = hasher.hamming_radius(distance);
+        assert!(
+            (estimated - expected).abs() < 0.03,
+            "{estimated} {expected}"
+        );
+
+        assert!(hasher.iter(&[1.]).is_err());
+        assert!(PStableHasher::new(42, 4, 0.).is_err());
+    }
+}
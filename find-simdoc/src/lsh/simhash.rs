@@ -1,57 +1,375 @@
 //! Simplified simhash for the Cosine similarity.
+use std::marker::PhantomData;
+
+use all_pairs_hamming::sketch::Sketch;
+use hashbrown::HashMap;
 use rand_xoshiro::rand_core::{RngCore, SeedableRng};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::lsh::ElementHasher;
 
 /// [Simplified simhash](https://dl.acm.org/doi/10.1145/2063576.2063737) for Cosine similarity.
 pub struct SimHasher {
     seed: u64,
+    element_hasher: ElementHasher,
+    accumulates_in_f32: bool,
 }
 
 impl SimHasher {
     /// Creates an instance.
     pub const fn new(seed: u64) -> Self {
-        Self { seed }
+        Self {
+            seed,
+            element_hasher: ElementHasher::SplitMix64,
+            accumulates_in_f32: false,
+        }
+    }
+
+    /// Sets the hash function of elements.
+    /// The default value is [`ElementHasher::SplitMix64`].
+    pub const fn element_hasher(mut self, element_hasher: ElementHasher) -> Self {
+        self.element_hasher = element_hasher;
+        self
+    }
+
+    /// Gets the hash function of elements.
+    pub const fn element_hasher_value(&self) -> ElementHasher {
+        self.element_hasher
+    }
+
+    /// Sets whether the weights of dimensions are accumulated in [`f32`] instead of [`f64`],
+    /// which doubles the lanes of SIMD with the feature `simd`.
+    /// Sketches can differ from those in [`f64`] in the dimensions whose sums are close to zero.
+    /// The default value is `false`.
+    pub const fn accumulates_in_f32(mut self, yes: bool) -> Self {
+        self.accumulates_in_f32 = yes;
+        self
+    }
+
+    /// Generates `num_chunks` sketches from an input feature, which are the same as
+    /// those generated by [`Self::iter()`].
+    pub fn sketch(&self, feature: &[(u64, f64)], num_chunks: usize) -> Vec<u64> {
+        self.sketch_as(feature, num_chunks)
+    }
+
+    /// Generates `num_chunks` sketches of type `S` from an input feature, which are the same as
+    /// those generated by [`Self::iter_as()`].
+    pub fn sketch_as<S>(&self, feature: &[(u64, f64)], num_chunks: usize) -> Vec<S>
+    where
+        S: Sketch,
+    {
+        self.iter_as(feature).take(num_chunks).collect()
+    }
+
+    /// Generates `num_chunks` sketches from an input feature in parallel over chunks,
+    /// which are the same as those generated by [`Self::sketch()`].
+    ///
+    /// This is effective for a long feature and many chunks.
+    /// Without the `parallel` feature, the chunks are generated sequentially.
+    pub fn sketch_in_parallel(&self, feature: &[(u64, f64)], num_chunks: usize) -> Vec<u64> {
+        let iter = self.iter(feature);
+        let mut seeder = rand_xoshiro::SplitMix64::seed_from_u64(self.seed);
+        let seeds: Vec<_> = (0..num_chunks).map(|_| seeder.next_u64()).collect();
+        let sketch = |signs: &mut Vec<_>, seed| {
+            if self.accumulates_in_f32 {
+                let mut weights = [0f32; 64];
+                iter.accumulate(seed, signs, &mut weights);
+                pack_weights(&weights)
+            } else {
+                let mut weights = [0f64; 64];
+                iter.accumulate(seed, signs, &mut weights);
+                pack_weights(&weights)
+            }
+        };
+        #[cfg(feature = "parallel")]
+        let sketches = seeds
+            .into_par_iter()
+            .map_init(|| vec![0; iter.terms.len()], sketch)
+            .collect();
+        #[cfg(not(feature = "parallel"))]
+        let sketches = {
+            let mut signs = vec![0; iter.terms.len()];
+            seeds
+                .into_iter()
+                .map(|seed| sketch(&mut signs, seed))
+                .collect()
+        };
+        sketches
     }
 
     /// Creates an iterator to generate sketches from an input feature.
+    ///
+    /// The random sign pattern of each distinct term is computed once per chunk,
+    /// so repeated terms in the feature do not increase the number of hash computations.
     pub fn iter<'a>(&self, feature: &'a [(u64, f64)]) -> SimHashIter<'a> {
+        self.iter_as(feature)
+    }
+
+    /// Creates an iterator to generate sketches of type `S` from an input feature.
+    ///
+    /// The random values are drawn in the same order for any type, e.g., a sketch of [`u128`]
+    /// is the concatenation of the first two sketches of [`u64`].
+    pub fn iter_as<'a, S>(&self, feature: &'a [(u64, f64)]) -> SimHashIter<'a, S>
+    where
+        S: Sketch,
+    {
+        let mut ids = HashMap::new();
+        let mut terms = vec![];
+        let term_ids = feature
+            .iter()
+            .map(|&(i, _)| {
+                *ids.entry(i).or_insert_with(|| {
+                    terms.push(i);
+                    terms.len() - 1
+                })
+            })
+            .collect();
         SimHashIter {
             feature,
+            term_ids,
+            signs: vec![0; terms.len()],
+            terms,
+            element_hasher: self.element_hasher,
+            accumulates_in_f32: self.accumulates_in_f32,
             seeder: rand_xoshiro::SplitMix64::seed_from_u64(self.seed),
-            weights: [0.; 64],
+            _sketch: PhantomData,
         }
     }
 }
 
 /// Iterator to generate sketches with the simplified simhash.
-pub struct SimHashIter<'a> {
+pub struct SimHashIter<'a, S = u64> {
     feature: &'a [(u64, f64)],
+    // Ids of distinct terms for the elements of the feature.
+    term_ids: Vec<usize>,
+    // Distinct terms and their sign patterns for the current chunk.
+    terms: Vec<u64>,
+    signs: Vec<u64>,
+    element_hasher: ElementHasher,
+    accumulates_in_f32: bool,
     seeder: rand_xoshiro::SplitMix64,
-    weights: [f64; 64],
+    _sketch: PhantomData<S>,
 }
 
-impl Iterator for SimHashIter<'_> {
-    type Item = u64;
+impl<S> SimHashIter<'_, S> {
+    /// Adds the weights of the feature to up to 64 dimensions with the random signs for the seed.
+    fn accumulate<W>(&self, seed: u64, signs: &mut [u64], weights: &mut [W])
+    where
+        W: Weight,
+    {
+        for (sign, &i) in signs.iter_mut().zip(&self.terms) {
+            *sign = self.element_hasher.hash(i, seed);
+        }
+        let signs = &*signs;
+        W::accumulate(
+            weights,
+            self.term_ids
+                .iter()
+                .zip(self.feature)
+                .map(|(&id, &(_, x))| (signs[id], x)),
+        );
+    }
+
+    /// Generates the next sketch with the weights accumulated in `W`.
+    fn next_with<W>(&mut self) -> S
+    where
+        W: Weight,
+        S: Sketch,
+    {
+        // Up to 128 dimensions of [`u128`].
+        let mut weights = [W::ZERO; 128];
+        let weights = &mut weights[..S::dim()];
+        let mut signs = std::mem::take(&mut self.signs);
+        // Each seed generates the random signs of up to 64 dimensions.
+        for weights in weights.chunks_mut(64) {
+            let seed = self.seeder.next_u64();
+            self.accumulate(seed, &mut signs, weights);
+        }
+        self.signs = signs;
+        pack_weights(weights)
+    }
+}
+
+impl<S> Iterator for SimHashIter<'_, S>
+where
+    S: Sketch,
+{
+    type Item = S;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.weights.fill(0.);
-        let seed = self.seeder.next_u64();
-        for (h, x) in self
-            .feature
-            .iter()
-            .map(|&(i, x)| (crate::lsh::hash_u64(i, seed), x))
-        {
-            for (j, w) in self.weights.iter_mut().enumerate() {
-                if (h >> j) & 1 == 0 {
-                    *w += x;
-                } else {
-                    *w -= x;
+        if self.accumulates_in_f32 {
+            Some(self.next_with::<f32>())
+        } else {
+            Some(self.next_with::<f64>())
+        }
+    }
+}
+
+/// Floating-point type accumulating the weights of dimensions.
+trait Weight: Copy {
+    const ZERO: Self;
+
+    /// Adds each weight `x` to the `j`-th dimension with the sign of the `j`-th bit of `h`,
+    /// i.e., positive for 0 and negative for 1.
+    ///
+    /// Each dimension is summed up in the order of the weights, so the results are the same
+    /// with and without SIMD.
+    fn accumulate<I>(weights: &mut [Self], feature: I)
+    where
+        I: Iterator<Item = (u64, f64)> + Clone;
+
+    fn is_nonnegative(self) -> bool;
+}
+
+/// Accumulates the weights of dimensions from `offset` without explicit SIMD.
+macro_rules! accumulate_lanes {
+    ($float:ty, $bits:ty, $weights:expr, $offset:expr, $feature:expr) => {
+        for (h, x) in $feature {
+            let x = (x as $float).to_bits();
+            // Flips the sign bit of the weight instead of branching,
+            // so that the loop over the lanes is vectorized by the compiler.
+            for (j, w) in $weights.iter_mut().enumerate() {
+                let flip = (((h >> ($offset + j)) & 1) as $bits) << (<$bits>::BITS - 1);
+                *w += <$float>::from_bits(x ^ flip);
+            }
+        }
+    };
+}
+
+macro_rules! impl_weight {
+    ($float:ty, $bits:ty, $simd_float:ident, $simd_bits:ident, $lanes:expr) => {
+        impl Weight for $float {
+            const ZERO: Self = 0.;
+
+            #[cfg(not(feature = "simd"))]
+            fn accumulate<I>(weights: &mut [Self], feature: I)
+            where
+                I: Iterator<Item = (u64, f64)> + Clone,
+            {
+                accumulate_lanes!($float, $bits, weights, 0, feature);
+            }
+
+            #[cfg(feature = "simd")]
+            fn accumulate<I>(weights: &mut [Self], feature: I)
+            where
+                I: Iterator<Item = (u64, f64)> + Clone,
+            {
+                use std::simd::prelude::*;
+
+                let shifts = $simd_bits::from_array(std::array::from_fn(|j| j as $bits));
+                let one = $simd_bits::splat(1);
+                let sign = $simd_bits::splat(<$bits>::BITS as $bits - 1);
+                let mut chunks = weights.chunks_exact_mut($lanes);
+                let mut offset = 0;
+                // Keeps the lanes of dimensions in a register over the feature.
+                for chunk in &mut chunks {
+                    let mut acc = $simd_float::from_slice(chunk);
+                    for (h, x) in feature.clone() {
+                        let h = $simd_bits::splat((h >> offset) as $bits);
+                        let x = $simd_bits::splat((x as $float).to_bits());
+                        acc += $simd_float::from_bits(x ^ (((h >> shifts) & one) << sign));
+                    }
+                    acc.copy_to_slice(chunk);
+                    offset += $lanes;
                 }
+                let remainder = chunks.into_remainder();
+                accumulate_lanes!($float, $bits, remainder, offset, feature);
+            }
+
+            fn is_nonnegative(self) -> bool {
+                self >= 0.
             }
         }
-        Some(
-            self.weights
+    };
+}
+
+impl_weight!(f64, u64, f64x8, u64x8, 8);
+impl_weight!(f32, u32, f32x16, u32x16, 16);
+
+/// Packs the signs of the weights into a sketch from the most significant bit.
+fn pack_weights<W, S>(weights: &[W]) -> S
+where
+    W: Weight,
+    S: Sketch,
+{
+    weights.iter().fold(S::zero(), |acc, w| {
+        if w.is_nonnegative() {
+            (acc << 1) | S::one()
+        } else {
+            acc << 1
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iter_as() {
+        let feature = [(1, 0.5), (2, -1.25), (3, 2.)];
+        let hasher = SimHasher::new(42);
+        let expected: Vec<_> = hasher.iter(&feature).take(2).collect();
+        let x = hasher.iter_as::<u128>(&feature).next().unwrap();
+        assert_eq!(x, (u128::from(expected[0]) << 64) | u128::from(expected[1]));
+        let x = hasher.iter_as::<u16>(&feature).next().unwrap();
+        assert_eq!(u64::from(x), expected[0] >> 48);
+        assert_eq!(
+            hasher.sketch_as::<u128>(&feature, 1),
+            vec![(u128::from(expected[0]) << 64) | u128::from(expected[1])]
+        );
+    }
+
+    #[test]
+    fn test_sketch_in_parallel() {
+        let feature = [(1, 0.5), (2, -1.25), (3, 2.), (1, 0.75)];
+        let hasher = SimHasher::new(42);
+        let expected: Vec<_> = hasher.iter(&feature).take(10).collect();
+        assert_eq!(hasher.sketch(&feature, 10), expected);
+        assert_eq!(hasher.sketch_in_parallel(&feature, 10), expected);
+    }
+
+    #[test]
+    fn test_accumulates_in_f32() {
+        // The sums are exact in both types, so the sketches are the same.
+        let feature = [(1, 0.5), (2, -1.25), (3, 2.), (1, 0.75)];
+        let hasher = SimHasher::new(42);
+        let hasher_f32 = SimHasher::new(42).accumulates_in_f32(true);
+        let expected: Vec<_> = hasher.iter(&feature).take(10).collect();
+        assert_eq!(hasher_f32.sketch(&feature, 10), expected);
+        assert_eq!(hasher_f32.sketch_in_parallel(&feature, 10), expected);
+        assert_eq!(
+            hasher_f32.sketch_as::<u128>(&feature, 2),
+            hasher.sketch_as::<u128>(&feature, 2)
+        );
+        assert_eq!(
+            hasher_f32.sketch_as::<u8>(&feature, 10),
+            hasher.sketch_as::<u8>(&feature, 10)
+        );
+    }
+
+    #[test]
+    fn test_branchless() {
+        let feature = [(1, 0.5), (2, -1.25), (3, 2.), (1, 0.75), (4, 1e-3)];
+        let mut seeder = rand_xoshiro::SplitMix64::seed_from_u64(42);
+        for sketch in SimHasher::new(42).iter(&feature).take(4) {
+            let seed = seeder.next_u64();
+            let mut weights = [0.; 64];
+            for &(i, x) in &feature {
+                let h = crate::lsh::hash_u64(i, seed);
+                for (j, w) in weights.iter_mut().enumerate() {
+                    if (h >> j) & 1 == 0 {
+                        *w += x;
+                    } else {
+                        *w -= x;
+                    }
+                }
+            }
+            let expected = weights
                 .iter()
-                .fold(0, |acc, w| if *w >= 0. { (acc << 1) | 1 } else { acc << 1 }),
-        )
+                .fold(0, |acc, w| if *w >= 0. { (acc << 1) | 1 } else { acc << 1 });
+            assert_eq!(sketch, expected);
+        }
     }
 }
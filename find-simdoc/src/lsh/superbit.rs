@@ -0,0 +1,161 @@
+//! Super-bit locality-sensitive hashing for the Cosine similarity.
+use rand_xoshiro::rand_core::SeedableRng;
+
+use crate::errors::{FindSimdocError, Result};
+use crate::lsh::{gaussian, ElementHasher};
+
+/// [Super-bit LSH](https://papers.nips.cc/paper/2012/hash/072b030ba126b2f4b2374f342be9ed44-Abstract.html)
+/// for the Cosine similarity, which orthogonalizes batches of random projections
+/// to reduce the variance of estimates at the same number of bits.
+///
+/// Since the projections are materialized, features are hashed into a space of `dim` dimensions,
+/// and the projections are orthogonalized in batches of `depth` (i.e., the super-bit depth).
+pub struct SuperBitHasher {
+    seed: u64,
+    dim: usize,
+    depth: usize,
+    element_hasher: ElementHasher,
+    // Projections of `64 * num_chunks` rows and `dim` columns.
+    projections: Vec<f64>,
+}
+
+impl SuperBitHasher {
+    /// Creates an instance generating up to `num_chunks` sketches.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Seed value for random values.
+    /// * `dim` - Number of dimensions into which features are hashed.
+    /// * `depth` - Number of orthogonalized projections in a batch, in the range of `[1, dim]`.
+    /// * `num_chunks` - Maximum number of sketches generated from a feature.
+    pub fn new(seed: u64, dim: usize, depth: usize, num_chunks: usize) -> Result<Self> {
+        if dim == 0 {
+            return Err(FindSimdocError::input("dim must not be 0."));
+        }
+        if depth == 0 || dim < depth {
+            return Err(FindSimdocError::input(
+                "depth must be in the range of [1, dim].",
+            ));
+        }
+        let mut rng = rand_xoshiro::SplitMix64::seed_from_u64(seed);
+        let num_rows = 64 * num_chunks;
+        let mut projections: Vec<_> = (0..num_rows * dim).map(|_| gaussian(&mut rng)).collect();
+        for batch in projections.chunks_mut(depth * dim) {
+            orthogonalize(batch, dim);
+        }
+        Ok(Self {
+            seed,
+            dim,
+            depth,
+            element_hasher: ElementHasher::SplitMix64,
+            projections,
+        })
+    }
+
+    /// Sets the hash function of elements, with which features are hashed into the dimensions.
+    /// The default value is [`ElementHasher::SplitMix64`].
+    pub const fn element_hasher(mut self, element_hasher: ElementHasher) -> Self {
+        self.element_hasher = element_hasher;
+        self
+    }
+
+    /// Gets the number of dimensions into which features are hashed.
+    pub const fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Gets the number of orthogonalized projections in a batch.
+    pub const fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Gets the maximum number of sketches generated from a feature.
+    pub fn num_chunks(&self) -> usize {
+        self.projections.len() / (64 * self.dim)
+    }
+
+    /// Generates `num_chunks` sketches from an input feature.
+    ///
+    /// # Panics
+    ///
+    /// It panics if `num_chunks` is more than [`Self::num_chunks()`].
+    pub fn sketch(&self, feature: &[(u64, f64)], num_chunks: usize) -> Vec<u64> {
+        assert!(num_chunks <= self.num_chunks());
+        let feature: Vec<_> = feature
+            .iter()
+            .map(|&(term, x)| {
+                let idx = self.element_hasher.hash(term, self.seed) % self.dim as u64;
+                (idx as usize, x)
+            })
+            .collect();
+        self.projections
+            .chunks(64 * self.dim)
+            .take(num_chunks)
+            .map(|rows| {
+                rows.chunks(self.dim).fold(0, |acc, row| {
+                    let p: f64 = feature.iter().map(|&(idx, x)| row[idx] * x).sum();
+                    if p >= 0. {
+                        (acc << 1) | 1
+                    } else {
+                        acc << 1
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+/// Orthonormalizes rows of `dim` columns with the Gram-Schmidt process.
+fn orthogonalize(rows: &mut [f64], dim: usize) {
+    for i in 0..rows.len() / dim {
+        let (prev, rest) = rows.split_at_mut(i * dim);
+        let row = &mut rest[..dim];
+        for other in prev.chunks(dim) {
+            let dot: f64 = row.iter().zip(other).map(|(x, y)| x * y).sum();
+            row.iter_mut().zip(other).for_each(|(x, y)| *x -= dot * y);
+        }
+        let norm = row.iter().map(|x| x * x).sum::<f64>().sqrt();
+        if norm > 0. {
+            row.iter_mut().for_each(|x| *x /= norm);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orthogonal() {
+        let hasher = SuperBitHasher::new(42, 16, 8, 1).unwrap();
+        let rows: Vec<_> = hasher.projections.chunks(16).collect();
+        for i in 0..8 {
+            for j in 0..8 {
+                let dot: f64 = rows[i].iter().zip(rows[j]).map(|(x, y)| x * y).sum();
+                let expected = if i == j { 1. } else { 0. };
+                assert!((dot - expected).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_estimate() {
+        let x = [(1, 1.), (2, 2.), (3, 1.)];
+        let y = [(1, 1.), (2, 1.), (4, 1.)];
+        // The angle between x and y divided by π.
+        let cos = 3. / (6f64.sqrt() * 3f64.sqrt());
+        let expected = cos.acos() / std::f64::consts::PI;
+
+        let hasher = SuperBitHasher::new(42, 64, 32, 64).unwrap();
+        let hamdist: u32 = hasher
+            .sketch(&x, 64)
+            .iter()
+            .zip(hasher.sketch(&y, 64))
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum();
+        let estimated = f64::from(hamdist) / (64 * 64) as f64;
+        assert!((estimated - expected).abs() < 0.05, "{estimated}");
+
+        assert!(SuperBitHasher::new(42, 8, 9, 1).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+//! Readers of dense float matrices in the NumPy `.npy` and `.npz` formats,
+//! enabled with the `npy` feature.
+//!
+//! Precomputed embeddings can be loaded as rows of `f64` vectors and sketched with
+//! the dense LSH such as [`PStableHasher`](crate::lsh::pstable::PStableHasher).
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::Cursor;
+//!
+//! use all_pairs_hamming::ChunkedJoiner;
+//! use find_simdoc::lsh::pstable::PStableHasher;
+//! use find_simdoc::npy::DenseMatrix;
+//! use npyz::WriterBuilder;
+//!
+//! // Writes an npy file of 3x2 as an example.
+//! let mut data = vec![];
+//! let mut wtr = npyz::WriteOptions::new()
+//!     .default_dtype()
+//!     .shape(&[3, 2])
+//!     .writer(Cursor::new(&mut data))
+//!     .begin_nd()
+//!     .unwrap();
+//! wtr.extend([0.0f32, 1.0, 0.1, 1.0, 5.0, -3.0]).unwrap();
+//! wtr.finish().unwrap();
+//!
+//! let matrix = DenseMatrix::from_npy(&data[..]).unwrap();
+//! let hasher = PStableHasher::new(42, matrix.dim(), 4.0).unwrap();
+//! let mut joiner = ChunkedJoiner::<u64>::new(8);
+//! for row in matrix.rows() {
+//!     joiner.add(hasher.iter(row).unwrap()).unwrap();
+//! }
+//! let radius = hasher.hamming_radius(0.5);
+//! assert!(joiner.similar_pairs(radius).iter().any(|&(i, j, _)| (i, j) == (0, 1)));
+//! ```
+use std::io::{Read, Seek};
+
+use npyz::npz::NpzArchive;
+use npyz::{DType, NpyFile, Order, TypeChar};
+
+use crate::errors::{FindSimdocError, Result};
+
+/// Row-major matrix of `f64` values, whose rows are dense vectors.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DenseMatrix {
+    dim: usize,
+    data: Vec<f64>,
+}
+
+impl DenseMatrix {
+    /// Reads a matrix from an `.npy` file.
+    ///
+    /// The array must be two-dimensional of `float32` or `float64`, in either C or Fortran order.
+    /// A one-dimensional array is read as a matrix of a single row.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the data is not valid or not such an array.
+    pub fn from_npy<R>(rdr: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        Self::from_npy_file(NpyFile::new(rdr)?)
+    }
+
+    /// Reads a matrix from an array in an `.npz` archive. See [`Self::from_npy()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `rdr` - Reader of the archive.
+    /// * `name` - Name of the array. If `None`, the archive must include only one array.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the archive is not valid or the array is not found.
+    pub fn from_npz<R>(rdr: R, name: Option<&str>) -> Result<Self>
+    where
+        R: Read + Seek,
+    {
+        let mut archive = NpzArchive::new(rdr)?;
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => {
+                let names: Vec<_> = archive.array_names().collect();
+                if names.len() != 1 {
+                    return Err(FindSimdocError::input(format!(
+                        "The array name must be given for the archive of {} arrays.",
+                        names.len()
+                    )));
+                }
+                names[0].to_string()
+            }
+        };
+        let file = archive
+            .by_name(&name)?
+            .ok_or_else(|| FindSimdocError::input(format!("Array not found: {name}")))?;
+        Self::from_npy_file(file)
+    }
+
+    fn from_npy_file<R>(file: NpyFile<R>) -> Result<Self>
+    where
+        R: Read,
+    {
+        let (num_rows, dim) = match *file.shape() {
+            [dim] => (1, dim as usize),
+            [num_rows, dim] => (num_rows as usize, dim as usize),
+            ref shape => {
+                return Err(FindSimdocError::input(format!(
+                    "The array must be two-dimensional, but of shape {shape:?}."
+                )))
+            }
+        };
+        let order = file.order();
+        let data: Vec<f64> = match file.dtype() {
+            DType::Plain(ty) if ty.type_char() == TypeChar::Float && ty.size_field() == 4 => {
+                let data: Vec<f32> = file.into_vec()?;
+                data.into_iter().map(f64::from).collect()
+            }
+            DType::Plain(ty) if ty.type_char() == TypeChar::Float && ty.size_field() == 8 => {
+                file.into_vec()?
+            }
+            dtype => {
+                return Err(FindSimdocError::input(format!(
+                    "The array must be of float32 or float64, but {}.",
+                    dtype.descr()
+                )))
+            }
+        };
+        let data = match order {
+            Order::C => data,
+            Order::Fortran => (0..num_rows * dim)
+                .map(|k| data[(k % dim) * num_rows + k / dim])
+                .collect(),
+        };
+        Ok(Self { dim, data })
+    }
+
+    /// Gets the number of rows.
+    pub fn num_rows(&self) -> usize {
+        self.data.len().checked_div(self.dim).unwrap_or(0)
+    }
+
+    /// Gets the number of dimensions of rows.
+    pub const fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Gets the `i`-th row.
+    pub fn row(&self, i: usize) -> &[f64] {
+        &self.data[i * self.dim..(i + 1) * self.dim]
+    }
+
+    /// Creates an iterator over the rows.
+    pub fn rows(&self) -> impl Iterator<Item = &[f64]> {
+        (0..self.num_rows()).map(|i| self.row(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    use npyz::npz::NpzWriter;
+    use npyz::WriterBuilder;
+
+    fn npy<T>(shape: &[u64], order: Order, values: &[T]) -> Vec<u8>
+    where
+        T: npyz::AutoSerialize + Copy,
+    {
+        let mut data = vec![];
+        let mut wtr = npyz::WriteOptions::new()
+            .default_dtype()
+            .shape(shape)
+            .order(order)
+            .writer(Cursor::new(&mut data))
+            .begin_nd()
+            .unwrap();
+        wtr.extend(values.iter().copied()).unwrap();
+        wtr.finish().unwrap();
+        data
+    }
+
+    #[test]
+    fn test_npy() {
+        let data = npy(&[2, 3], Order::C, &[1f32, 2., 3., 4., 5., 6.]);
+        let matrix = DenseMatrix::from_npy(&data[..]).unwrap();
+        assert_eq!(matrix.num_rows(), 2);
+        assert_eq!(matrix.dim(), 3);
+        assert_eq!(matrix.row(1), &[4., 5., 6.]);
+
+        let data = npy(&[2, 3], Order::Fortran, &[1f64, 4., 2., 5., 3., 6.]);
+        assert_eq!(DenseMatrix::from_npy(&data[..]).unwrap(), matrix);
+
+        let data = npy(&[3], Order::C, &[1f64, 2., 3.]);
+        let matrix = DenseMatrix::from_npy(&data[..]).unwrap();
+        assert_eq!(matrix.rows().collect::<Vec<_>>(), vec![&[1., 2., 3.]]);
+    }
+
+    #[test]
+    fn test_npz() {
+        let mut data = vec![];
+        let mut npz = NpzWriter::new(Cursor::new(&mut data));
+        for (name, values) in [("x", [1f64, 2.]), ("y", [3., 4.])] {
+            let mut wtr = npz
+                .array::<f64>(name, Default::default())
+                .unwrap()
+                .default_dtype()
+                .shape(&[1, 2])
+                .begin_nd()
+                .unwrap();
+            wtr.extend(values).unwrap();
+            wtr.finish().unwrap();
+        }
+        drop(npz);
+
+        let matrix = DenseMatrix::from_npz(Cursor::new(&data), Some("y")).unwrap();
+        assert_eq!(matrix.row(0), &[3., 4.]);
+        assert!(DenseMatrix::from_npz(Cursor::new(&data), Some("z")).is_err());
+        assert!(DenseMatrix::from_npz(Cursor::new(&data), None).is_err());
+    }
+
+    #[test]
+    fn test_invalid_inputs() {
+        let data = npy(&[2, 1], Order::C, &[1i32, 2]);
+        assert!(DenseMatrix::from_npy(&data[..]).is_err());
+        let data = npy(&[1, 1, 2], Order::C, &[1f64, 2.]);
+        assert!(DenseMatrix::from_npy(&data[..]).is_err());
+        assert!(DenseMatrix::from_npy(&b"abc"[..]).is_err());
+    }
+}
@@ -0,0 +1,263 @@
+//! Writers of search results.
+use std::io::{self, Write};
+
+/// Format of search results written by searchers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum OutputFormat {
+    /// Comma-separated values with the header `i,j,dist`.
+    #[default]
+    Csv,
+    /// JSON Lines, i.e., one object such as `{"i":0,"j":1,"dist":0.1}` per line.
+    JsonLines,
+}
+
+/// Similar pair of the left-side id, the right-side id, and their distance,
+/// i.e., a named form of the triplets returned by searchers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimilarPair {
+    /// Left-side id.
+    pub i: usize,
+    /// Right-side id.
+    pub j: usize,
+    /// Distance.
+    pub dist: f64,
+}
+
+impl From<(usize, usize, f64)> for SimilarPair {
+    fn from((i, j, dist): (usize, usize, f64)) -> Self {
+        Self { i, j, dist }
+    }
+}
+
+impl From<SimilarPair> for (usize, usize, f64) {
+    fn from(pair: SimilarPair) -> Self {
+        (pair.i, pair.j, pair.dist)
+    }
+}
+
+/// Value of an additional column written by [`PairWriter::write_with_values()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value<'a> {
+    /// String quoted if needed.
+    Str(&'a str),
+    /// Number written as it is.
+    Num(f64),
+}
+
+impl<'a> From<&'a str> for Value<'a> {
+    fn from(s: &'a str) -> Self {
+        Self::Str(s)
+    }
+}
+
+impl From<f64> for Value<'_> {
+    fn from(x: f64) -> Self {
+        Self::Num(x)
+    }
+}
+
+/// Writer of triplets of the left-side id, the right-side id, and their distance.
+pub struct PairWriter<W> {
+    writer: W,
+    format: OutputFormat,
+    columns: Vec<String>,
+}
+
+impl<W> PairWriter<W>
+where
+    W: Write,
+{
+    /// Creates an instance, writing the header if needed.
+    pub fn new(writer: W, format: OutputFormat) -> io::Result<Self> {
+        Self::with_columns::<&str>(writer, format, &[])
+    }
+
+    /// Creates an instance with additional columns following the distance,
+    /// such as ids or texts of documents, writing the header if needed.
+    pub fn with_columns<S>(mut writer: W, format: OutputFormat, columns: &[S]) -> io::Result<Self>
+    where
+        S: AsRef<str>,
+    {
+        let columns: Vec<_> = columns.iter().map(|c| c.as_ref().to_string()).collect();
+        if format == OutputFormat::Csv {
+            write!(writer, "i,j,dist")?;
+            for column in &columns {
+                write!(writer, ",{}", escape_csv(column))?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(Self {
+            writer,
+            format,
+            columns,
+        })
+    }
+
+    /// Writes a pair.
+    pub fn write(&mut self, i: usize, j: usize, dist: f64) -> io::Result<()> {
+        self.write_with_values::<&str>(i, j, dist, &[])
+    }
+
+    /// Writes a pair with the values of the additional columns.
+    ///
+    /// # Panics
+    ///
+    /// It panics if the number of values is different from that of the additional columns.
+    pub fn write_with_values<'v, V>(
+        &mut self,
+        i: usize,
+        j: usize,
+        dist: f64,
+        values: &[V],
+    ) -> io::Result<()>
+    where
+        V: Clone + Into<Value<'v>>,
+    {
+        assert_eq!(values.len(), self.columns.len());
+        match self.format {
+            OutputFormat::Csv => {
+                write!(self.writer, "{i},{j},{dist}")?;
+                for value in values {
+                    match value.clone().into() {
+                        Value::Str(s) => write!(self.writer, ",{}", escape_csv(s))?,
+                        Value::Num(x) => write!(self.writer, ",{x}")?,
+                    }
+                }
+                writeln!(self.writer)
+            }
+            OutputFormat::JsonLines => {
+                write!(self.writer, "{{\"i\":{i},\"j\":{j},\"dist\":{dist}")?;
+                for (column, value) in self.columns.iter().zip(values) {
+                    write!(self.writer, ",{}:", escape_json(column))?;
+                    match value.clone().into() {
+                        Value::Str(s) => write!(self.writer, "{}", escape_json(s))?,
+                        Value::Num(x) => write!(self.writer, "{x}")?,
+                    }
+                }
+                writeln!(self.writer, "}}")
+            }
+        }
+    }
+
+    /// Flushes the written pairs.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Quotes a CSV field if it contains special characters.
+fn escape_csv(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Quotes a JSON string.
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv() {
+        let mut buf = vec![];
+        let mut writer = PairWriter::new(&mut buf, OutputFormat::Csv).unwrap();
+        writer.write(0, 1, 0.5).unwrap();
+        writer.write(2, 3, 0.25).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "i,j,dist\n0,1,0.5\n2,3,0.25\n"
+        );
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let mut buf = vec![];
+        let mut writer = PairWriter::new(&mut buf, OutputFormat::JsonLines).unwrap();
+        writer.write(0, 1, 0.5).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "{\"i\":0,\"j\":1,\"dist\":0.5}\n"
+        );
+    }
+
+    #[test]
+    fn test_with_columns() {
+        let mut buf = vec![];
+        let mut writer =
+            PairWriter::with_columns(&mut buf, OutputFormat::Csv, &["id_i", "id_j"]).unwrap();
+        writer
+            .write_with_values(0, 1, 0.5, &["a", "b,\"c\""])
+            .unwrap();
+        writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "i,j,dist,id_i,id_j\n0,1,0.5,a,\"b,\"\"c\"\"\"\n"
+        );
+
+        let mut buf = vec![];
+        let mut writer =
+            PairWriter::with_columns(&mut buf, OutputFormat::JsonLines, &["text_i", "text_j"])
+                .unwrap();
+        writer
+            .write_with_values(0, 1, 0.5, &["a\"b", "c\n\u{1}"])
+            .unwrap();
+        writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "{\"i\":0,\"j\":1,\"dist\":0.5,\"text_i\":\"a\\\"b\",\"text_j\":\"c\\n\\u0001\"}\n"
+        );
+
+        let mut buf = vec![];
+        let mut writer =
+            PairWriter::with_columns(&mut buf, OutputFormat::JsonLines, &["exact", "id"]).unwrap();
+        writer
+            .write_with_values(0, 1, 0.5, &[Value::Num(0.25), Value::Str("a")])
+            .unwrap();
+        writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "{\"i\":0,\"j\":1,\"dist\":0.5,\"exact\":0.25,\"id\":\"a\"}\n"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let pair = SimilarPair::from((0, 1, 0.5));
+        let json = serde_json::to_string(&pair).unwrap();
+        assert_eq!(json, r#"{"i":0,"j":1,"dist":0.5}"#);
+        assert_eq!(serde_json::from_str::<SimilarPair>(&json).unwrap(), pair);
+        assert_eq!(
+            serde_json::to_string(&OutputFormat::JsonLines).unwrap(),
+            r#""json_lines""#
+        );
+    }
+}
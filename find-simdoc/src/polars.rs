@@ -0,0 +1,162 @@
+//! Integration with [Polars](https://pola.rs/) data frames, enabled with the `polars` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use find_simdoc::polars::similar_pairs;
+//! use find_simdoc::JaccardSearcher;
+//! use polars::prelude::*;
+//!
+//! let df = df!("text" => [
+//!     "Welcome to Jimbocho, the town of books and curry!",
+//!     "Welcome to Jimbocho, the city of books and curry!",
+//!     "We welcome you to Jimbocho, the town of books and curry.",
+//! ])
+//! .unwrap();
+//!
+//! let searcher = JaccardSearcher::new(3, None, Some(42)).unwrap();
+//! let pairs = similar_pairs(searcher, &df, "text", 20, 0.25).unwrap();
+//! assert_eq!(pairs.get_column_names(), ["i", "j", "dist"]);
+//! ```
+use all_pairs_hamming::sketch::Sketch;
+use polars::prelude::{Column, DataFrame, PolarsError, StringChunked};
+
+use crate::errors::{FindSimdocError, Result};
+use crate::{CosineSearcher, JaccardSearcher};
+
+fn polars_error(e: PolarsError) -> FindSimdocError {
+    FindSimdocError::input(e.to_string())
+}
+
+/// Gets the string column of a data frame without nulls.
+fn string_column<'a>(df: &'a DataFrame, column: &str) -> Result<&'a StringChunked> {
+    let texts = df
+        .column(column)
+        .and_then(Column::str)
+        .map_err(polars_error)?;
+    if texts.null_count() != 0 {
+        return Err(FindSimdocError::input(format!(
+            "Column {column} must not include nulls."
+        )));
+    }
+    Ok(texts)
+}
+
+/// Converts similar pairs into a data frame of the columns `i` and `j` of `UInt64`
+/// and `dist` of `Float64`.
+pub fn pairs_to_dataframe(results: &[(usize, usize, f64)]) -> Result<DataFrame> {
+    let is: Vec<_> = results.iter().map(|&(i, _, _)| i as u64).collect();
+    let js: Vec<_> = results.iter().map(|&(_, j, _)| j as u64).collect();
+    let dists: Vec<_> = results.iter().map(|&(_, _, dist)| dist).collect();
+    DataFrame::new(vec![
+        Column::new("i".into(), is),
+        Column::new("j".into(), js),
+        Column::new("dist".into(), dists),
+    ])
+    .map_err(polars_error)
+}
+
+/// Finds all pairs of similar texts in a string column within `radius` in the Jaccard space,
+/// returning a data frame of `(i, j, dist)` with the row numbers of the pairs.
+///
+/// # Arguments
+///
+/// * `searcher` - Searcher with the settings of feature extraction.
+/// * `df` - Data frame of the texts.
+/// * `column` - Name of the string column, which must not include nulls.
+/// * `num_chunks` - Number of chunks of sketches.
+/// * `radius` - Search radius in the range of [0,1].
+pub fn similar_pairs<S>(
+    searcher: JaccardSearcher<S>,
+    df: &DataFrame,
+    column: &str,
+    num_chunks: usize,
+    radius: f64,
+) -> Result<DataFrame>
+where
+    S: Sketch + Send + Sync,
+{
+    let searcher = searcher.build_sketches_from_dataframe(df, column, num_chunks)?;
+    pairs_to_dataframe(&searcher.search_similar_pairs(radius))
+}
+
+impl<S> JaccardSearcher<S>
+where
+    S: Sketch + Send + Sync,
+{
+    /// Builds the database of sketches from a string column of a data frame in parallel.
+    /// See [`Self::build_sketches_in_parallel()`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the column is not found, is not of the string type, or includes nulls.
+    pub fn build_sketches_from_dataframe(
+        self,
+        df: &DataFrame,
+        column: &str,
+        num_chunks: usize,
+    ) -> Result<Self> {
+        let texts = string_column(df, column)?;
+        self.build_sketches_in_parallel(
+            texts.downcast_iter().flat_map(|texts| texts.values_iter()),
+            num_chunks,
+        )
+    }
+}
+
+impl<S> CosineSearcher<S>
+where
+    S: Sketch + Send + Sync,
+{
+    /// Builds the database of sketches from a string column of a data frame in parallel.
+    /// See [`Self::build_sketches_in_parallel()`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the column is not found, is not of the string type, or includes nulls.
+    pub fn build_sketches_from_dataframe(
+        self,
+        df: &DataFrame,
+        column: &str,
+        num_chunks: usize,
+    ) -> Result<Self> {
+        let texts = string_column(df, column)?;
+        self.build_sketches_in_parallel(
+            texts.downcast_iter().flat_map(|texts| texts.values_iter()),
+            num_chunks,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use polars::prelude::df;
+
+    #[test]
+    fn test_similar_pairs() {
+        let df = df!(
+            "text" => ["abcdefgh", "abcdefgi", "xyz"],
+            "number" => [1, 2, 3],
+        )
+        .unwrap();
+        let searcher = JaccardSearcher::new(2, None, Some(42)).unwrap();
+        let pairs = similar_pairs(searcher, &df, "text", 16, 0.5).unwrap();
+        assert_eq!(pairs.height(), 1);
+        assert_eq!(pairs.column("i").unwrap().u64().unwrap().get(0), Some(0));
+        assert_eq!(pairs.column("j").unwrap().u64().unwrap().get(0), Some(1));
+
+        let searcher = JaccardSearcher::new(2, None, Some(42)).unwrap();
+        assert!(similar_pairs(searcher, &df, "number", 16, 0.5).is_err());
+        let searcher = JaccardSearcher::new(2, None, Some(42)).unwrap();
+        assert!(similar_pairs(searcher, &df, "missing", 16, 0.5).is_err());
+    }
+
+    #[test]
+    fn test_nulls() {
+        let df = df!("text" => [Some("abc"), None]).unwrap();
+        let searcher = JaccardSearcher::new(2, None, Some(42)).unwrap();
+        assert!(similar_pairs(searcher, &df, "text", 16, 0.5).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+//! Pruning of features based on document frequencies.
+use crate::errors::{FindSimdocError, Result};
+use crate::feature::FeatureConfig;
+use crate::tfidf::Idf;
+
+/// Pruner of features appearing in too many or too few documents,
+/// which is trained in a pre-pass over the corpus before sketching.
+///
+/// Dropping boilerplate features (e.g., navigation text shared by most web pages)
+/// shrinks feature sets and improves the discrimination of sketches.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::pruning::DfPruner;
+/// use find_simdoc::JaccardSearcher;
+///
+/// let documents = ["abc xyz", "abd xyz", "abe xyz"];
+/// let searcher = JaccardSearcher::new(1, Some(' '), Some(42)).unwrap();
+///
+/// // Drops features appearing in more than 50% of documents.
+/// let pruner = DfPruner::new()
+///     .max_df_ratio(0.5)
+///     .unwrap()
+///     .build(documents.iter(), searcher.config())
+///     .unwrap();
+///
+/// let searcher = searcher
+///     .df_pruner(Some(pruner))
+///     .build_sketches(documents.iter(), 10)
+///     .unwrap();
+/// ```
+pub struct DfPruner {
+    idf: Idf<u64>,
+    min_df: usize,
+    max_df_ratio: f64,
+}
+
+impl Default for DfPruner {
+    fn default() -> Self {
+        Self {
+            idf: Idf::new(),
+            min_df: 1,
+            max_df_ratio: 1.,
+        }
+    }
+}
+
+impl DfPruner {
+    /// Creates an instance retaining all features.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the minimum number of documents in which a retained feature appears.
+    /// The default value is 1.
+    pub const fn min_df(mut self, min_df: usize) -> Self {
+        self.min_df = min_df;
+        self
+    }
+
+    /// Sets the maximum ratio of documents in which a retained feature appears.
+    /// The default value is 1.0.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the ratio is not in the range of [0,1].
+    pub fn max_df_ratio(mut self, ratio: f64) -> Result<Self> {
+        if !(0. ..=1.).contains(&ratio) {
+            return Err(FindSimdocError::input(
+                "The ratio of documents must be in the range of [0,1].",
+            ));
+        }
+        self.max_df_ratio = ratio;
+        Ok(self)
+    }
+
+    /// Trains the document frequencies of features from input documents.
+    ///
+    /// # Arguments
+    ///
+    /// * `documents` - List of documents.
+    /// * `config` - Configuration of feature extraction. Use the same configuration as that in search.
+    pub fn build<I, D>(mut self, documents: I, config: &FeatureConfig) -> Result<Self>
+    where
+        I: IntoIterator<Item = D>,
+        D: AsRef<str>,
+    {
+        self.idf = self.idf.build(documents, config)?;
+        Ok(self)
+    }
+
+    /// Gets the minimum number of documents in which a retained feature appears.
+    pub const fn min_df_count(&self) -> usize {
+        self.min_df
+    }
+
+    /// Gets the maximum number of documents in which a retained feature appears.
+    pub fn max_df_count(&self) -> usize {
+        (self.max_df_ratio * self.idf.num_docs() as f64).floor() as usize
+    }
+
+    /// Checks if an input feature is retained.
+    pub fn accepts(&self, term: u64) -> bool {
+        let df = self.idf.df(term);
+        self.min_df <= df && df <= self.max_df_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::feature::FeatureExtractor;
+
+    #[test]
+    fn test_accepts() {
+        let documents = ["a b c", "a b d", "a e f", "a b g"];
+        let config = FeatureConfig::new(1, Some(' '), 42).unwrap();
+        let pruner = DfPruner::new()
+            .min_df(2)
+            .max_df_ratio(0.75)
+            .unwrap()
+            .build(documents.iter(), &config)
+            .unwrap();
+        assert_eq!(pruner.max_df_count(), 3);
+
+        let extractor = FeatureExtractor::new(&config);
+        let mut feature = vec![];
+        extractor.extract("a b c e", &mut feature);
+        feature.retain(|&term| pruner.accepts(term));
+        assert_eq!(feature, vec![config.hash(["b"])]);
+
+        assert!(DfPruner::new().max_df_ratio(1.5).is_err());
+    }
+}
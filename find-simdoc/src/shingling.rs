@@ -1,31 +1,101 @@
-pub struct ShingleIter<'a, T> {
-    tokens: &'a [T],
+use std::collections::VecDeque;
+
+/// Iterator over windows of a stream of tokens, which retains only the last tokens
+/// in an internal ring buffer so that all the tokens are not materialized.
+///
+/// Since windows borrow the internal buffer, they are obtained with [`Self::next_window()`]
+/// instead of [`Iterator::next()`].
+pub struct ShingleIter<I>
+where
+    I: Iterator,
+{
+    tokens: I,
     window_size: usize,
-    position: usize,
+    buffer: Vec<I::Item>,
+    start: usize,
 }
 
-impl<'a, T> ShingleIter<'a, T> {
-    pub fn new(tokens: &'a [T], window_size: usize) -> Self {
-        assert!(!tokens.is_empty());
-        assert!(window_size <= tokens.len());
+impl<I> ShingleIter<I>
+where
+    I: Iterator,
+{
+    pub fn new(tokens: I, window_size: usize) -> Self {
+        assert!(window_size != 0);
         Self {
             tokens,
             window_size,
-            position: 0,
+            buffer: Vec::with_capacity(2 * window_size),
+            start: 0,
         }
     }
+
+    /// Gets the next window, or `None` if the tokens are exhausted.
+    pub fn next_window(&mut self) -> Option<&[I::Item]> {
+        if self.buffer.len() - self.start == self.window_size {
+            self.start += 1;
+        }
+        while self.buffer.len() - self.start < self.window_size {
+            let token = self.tokens.next()?;
+            if self.buffer.len() == 2 * self.window_size {
+                // Moves the partial window to the front, in amortized constant time per token.
+                self.buffer.drain(..self.start);
+                self.start = 0;
+            }
+            self.buffer.push(token);
+        }
+        Some(&self.buffer[self.start..])
+    }
 }
 
-impl<'a, T> Iterator for ShingleIter<'a, T> {
-    type Item = &'a [T];
+/// Base of the polynomial rolling hash over hash values of tokens.
+pub const ROLLING_BASE: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Finalizes a polynomial rolling hash of `len` elements into a well-mixed value.
+pub const fn finish_rolling_hash(hash: u64, len: usize) -> u64 {
+    // The finalizer of SplitMix64.
+    let mut z = hash ^ (len as u64).wrapping_mul(0xff51_afd7_ed55_8ccd);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Hasher of windows over hash values of tokens,
+/// updating the polynomial rolling hash in constant time per token.
+pub struct RollingHasher {
+    window: VecDeque<u64>,
+    window_size: usize,
+    top: u64,
+    hash: u64,
+}
+
+impl RollingHasher {
+    pub fn new(window_size: usize) -> Self {
+        assert!(window_size != 0);
+        let mut top = 1u64;
+        for _ in 1..window_size {
+            top = top.wrapping_mul(ROLLING_BASE);
+        }
+        Self {
+            window: VecDeque::with_capacity(window_size),
+            window_size,
+            top,
+            hash: 0,
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.tokens.len() < self.position + self.window_size {
-            return None;
+    /// Appends the hash value of the next token, removing the first one if the window is full.
+    pub fn push(&mut self, h: u64) {
+        if self.window.len() == self.window_size {
+            let first = self.window.pop_front().unwrap();
+            self.hash = self.hash.wrapping_sub(first.wrapping_mul(self.top));
         }
-        let window = &self.tokens[self.position..self.position + self.window_size];
-        self.position += 1;
-        Some(window)
+        self.hash = self.hash.wrapping_mul(ROLLING_BASE).wrapping_add(h);
+        self.window.push_back(h);
+    }
+
+    /// Computes the hash value of the current window.
+    pub fn finish(&self) -> u64 {
+        finish_rolling_hash(self.hash, self.window.len())
     }
 }
 
@@ -35,35 +105,68 @@ mod tests {
 
     #[test]
     fn test_q1() {
-        let tokens = vec!["a", "b", "c"];
-        let mut iter = ShingleIter::new(&tokens, 1);
-        assert_eq!(iter.next(), Some(&tokens[0..1]));
-        assert_eq!(iter.next(), Some(&tokens[1..2]));
-        assert_eq!(iter.next(), Some(&tokens[2..3]));
-        assert_eq!(iter.next(), None);
+        let tokens = ["a", "b", "c"];
+        let mut iter = ShingleIter::new(tokens.iter().copied(), 1);
+        assert_eq!(iter.next_window(), Some(&tokens[0..1]));
+        assert_eq!(iter.next_window(), Some(&tokens[1..2]));
+        assert_eq!(iter.next_window(), Some(&tokens[2..3]));
+        assert_eq!(iter.next_window(), None);
     }
 
     #[test]
     fn test_q2() {
-        let tokens = vec!["a", "b", "c"];
-        let mut iter = ShingleIter::new(&tokens, 2);
-        assert_eq!(iter.next(), Some(&tokens[0..2]));
-        assert_eq!(iter.next(), Some(&tokens[1..3]));
-        assert_eq!(iter.next(), None);
+        let tokens = ["a", "b", "c"];
+        let mut iter = ShingleIter::new(tokens.iter().copied(), 2);
+        assert_eq!(iter.next_window(), Some(&tokens[0..2]));
+        assert_eq!(iter.next_window(), Some(&tokens[1..3]));
+        assert_eq!(iter.next_window(), None);
     }
 
     #[test]
     fn test_q3() {
-        let tokens = vec!["a", "b", "c"];
-        let mut iter = ShingleIter::new(&tokens, 3);
-        assert_eq!(iter.next(), Some(&tokens[0..3]));
-        assert_eq!(iter.next(), None);
+        let tokens = ["a", "b", "c"];
+        let mut iter = ShingleIter::new(tokens.iter().copied(), 3);
+        assert_eq!(iter.next_window(), Some(&tokens[0..3]));
+        assert_eq!(iter.next_window(), None);
+    }
+
+    #[test]
+    fn test_rolling_hash() {
+        let hashes = [3, 1, 4, 1, 5];
+        let naive = |window: &[u64]| {
+            let hash = window
+                .iter()
+                .fold(0u64, |h, &x| h.wrapping_mul(ROLLING_BASE).wrapping_add(x));
+            finish_rolling_hash(hash, window.len())
+        };
+        for window_size in 1..=5 {
+            let expected: Vec<_> = hashes.windows(window_size).map(naive).collect();
+            let mut hasher = RollingHasher::new(window_size);
+            let mut results = vec![];
+            for (i, &h) in hashes.iter().enumerate() {
+                hasher.push(h);
+                if window_size <= i + 1 {
+                    results.push(hasher.finish());
+                }
+            }
+            assert_eq!(results, expected);
+        }
     }
 
     #[test]
-    #[should_panic]
     fn test_q4() {
-        let tokens = vec!["a", "b", "c"];
-        ShingleIter::new(&tokens, 4);
+        let tokens = ["a", "b", "c"];
+        let mut iter = ShingleIter::new(tokens.iter().copied(), 4);
+        assert_eq!(iter.next_window(), None);
+    }
+
+    #[test]
+    fn test_long_stream() {
+        let tokens: Vec<_> = (0..100).collect();
+        let mut iter = ShingleIter::new(tokens.iter().copied(), 3);
+        for i in 0..98 {
+            assert_eq!(iter.next_window(), Some(&tokens[i..i + 3]));
+        }
+        assert_eq!(iter.next_window(), None);
     }
 }
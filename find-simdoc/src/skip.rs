@@ -0,0 +1,62 @@
+//! Mapping of ids of documents skipped in building.
+
+/// Mapping between the ids of input documents and those of documents retained in building.
+///
+/// Documents are registered in order, and the `k`-th retained document
+/// corresponds to the `retained[k]`-th input document.
+#[derive(Default)]
+pub struct SkipMap {
+    retained: Vec<usize>,
+    num_docs: usize,
+}
+
+impl SkipMap {
+    /// Registers the next document, which is retained or skipped.
+    pub fn push(&mut self, retained: bool) {
+        if retained {
+            self.retained.push(self.num_docs);
+        }
+        self.num_docs += 1;
+    }
+
+    /// Gets the input id of the `k`-th retained document.
+    pub fn input_id(&self, k: usize) -> usize {
+        self.retained[k]
+    }
+
+    /// Gets the retained id of the `i`-th input document, or `None` if skipped.
+    pub fn retained_id(&self, i: usize) -> Option<usize> {
+        self.retained.binary_search(&i).ok()
+    }
+
+    /// Gets the number of registered documents.
+    pub const fn num_docs(&self) -> usize {
+        self.num_docs
+    }
+
+    /// Gets the ids of skipped documents in ascending order.
+    pub fn skipped(&self) -> Vec<usize> {
+        let mut retained = self.retained.iter().peekable();
+        (0..self.num_docs)
+            .filter(|&i| retained.next_if_eq(&&i).is_none())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push() {
+        let mut skips = SkipMap::default();
+        for retained in [true, false, true, false, false, true] {
+            skips.push(retained);
+        }
+        assert_eq!(skips.num_docs(), 6);
+        assert_eq!(skips.input_id(1), 2);
+        assert_eq!(skips.retained_id(5), Some(2));
+        assert_eq!(skips.retained_id(3), None);
+        assert_eq!(skips.skipped(), vec![1, 3, 4]);
+    }
+}
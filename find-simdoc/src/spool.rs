@@ -0,0 +1,182 @@
+//! Buffer of weighted features held in memory or spilled to a file.
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::errors::Result;
+
+/// Counter distinguishing temporary files created in the same process.
+static NUM_SPILL_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Temporary file removed when dropped, even if spilling or reading fails.
+pub struct SpillFile {
+    path: PathBuf,
+}
+
+impl SpillFile {
+    /// Creates a new file in `dir`, never overwriting an existing one.
+    fn create(dir: &Path) -> Result<(Self, File)> {
+        loop {
+            let path = dir.join(format!(
+                "find-simdoc-spool-{}-{}",
+                std::process::id(),
+                NUM_SPILL_FILES.fetch_add(1, Ordering::Relaxed)
+            ));
+            match OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(file) => return Ok((Self { path }, file)),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Buffer of weighted features, which are read back in the pushed order.
+pub enum FeatureSpool {
+    Memory(Vec<Vec<(u64, f64)>>),
+    File {
+        wtr: BufWriter<File>,
+        // Declared after the writer so that the file is closed before removed.
+        file: SpillFile,
+        len: usize,
+    },
+}
+
+impl FeatureSpool {
+    /// Creates a buffer spilling features to a new temporary file in the directory `dir` if given,
+    /// or holding them in memory otherwise. The file is removed when the buffer is dropped.
+    pub fn new(dir: Option<&Path>) -> Result<Self> {
+        Ok(match dir {
+            Some(dir) => {
+                let (file, handle) = SpillFile::create(dir)?;
+                Self::File {
+                    wtr: BufWriter::new(handle),
+                    file,
+                    len: 0,
+                }
+            }
+            None => Self::Memory(vec![]),
+        })
+    }
+
+    pub fn push(&mut self, feature: &[(u64, f64)]) -> Result<()> {
+        match self {
+            Self::Memory(features) => features.push(feature.to_vec()),
+            Self::File { wtr, len, .. } => {
+                wtr.write_all(&(feature.len() as u64).to_le_bytes())?;
+                for &(term, weight) in feature {
+                    wtr.write_all(&term.to_le_bytes())?;
+                    wtr.write_all(&weight.to_le_bytes())?;
+                }
+                *len += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls `f` for each feature in the pushed order, removing the spilled file afterward.
+    pub fn for_each<F>(self, mut f: F) -> Result<()>
+    where
+        F: FnMut(Vec<(u64, f64)>) -> Result<()>,
+    {
+        match self {
+            Self::Memory(features) => features.into_iter().try_for_each(f),
+            Self::File { wtr, file, len } => {
+                let mut handle = wtr.into_inner().map_err(|e| e.into_error())?;
+                handle.rewind()?;
+                let result = Self::read_file(handle, len, &mut f);
+                drop(file);
+                result
+            }
+        }
+    }
+
+    fn read_file<F>(handle: File, len: usize, f: &mut F) -> Result<()>
+    where
+        F: FnMut(Vec<(u64, f64)>) -> Result<()>,
+    {
+        let mut rdr = BufReader::new(handle);
+        let mut buf = [0; 8];
+        for _ in 0..len {
+            rdr.read_exact(&mut buf)?;
+            let n = u64::from_le_bytes(buf) as usize;
+            let mut feature = Vec::with_capacity(n);
+            for _ in 0..n {
+                rdr.read_exact(&mut buf)?;
+                let term = u64::from_le_bytes(buf);
+                rdr.read_exact(&mut buf)?;
+                feature.push((term, f64::from_le_bytes(buf)));
+            }
+            f(feature)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_spill() {
+        let features = [vec![(1, 0.5), (2, 1.5)], vec![], vec![(3, 2.)]];
+        let dir = test_dir("find-simdoc-spool");
+        for dir in [None, Some(dir.as_path())] {
+            let mut spool = FeatureSpool::new(dir).unwrap();
+            for feature in &features {
+                spool.push(feature).unwrap();
+            }
+            let mut results = vec![];
+            spool
+                .for_each(|feature| {
+                    results.push(feature);
+                    Ok(())
+                })
+                .unwrap();
+            assert_eq!(results, features);
+        }
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cleanup_on_error() {
+        let dir = test_dir("find-simdoc-spool-error");
+        let existing = dir.join("existing");
+        fs::write(&existing, b"kept").unwrap();
+
+        let mut spool = FeatureSpool::new(Some(&dir)).unwrap();
+        spool.push(&[(1, 0.5)]).unwrap();
+        spool.push(&[(2, 1.5)]).unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        let result = spool.for_each(|_| Err(crate::errors::FindSimdocError::input("failed")));
+        assert!(result.is_err());
+
+        // A spool dropped before reading also removes its file.
+        let mut spool = FeatureSpool::new(Some(&dir)).unwrap();
+        spool.push(&[(1, 0.5)]).unwrap();
+        drop(spool);
+
+        assert_eq!(fs::read(&existing).unwrap(), b"kept");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,247 @@
+//! Streaming deduplication of documents against a rolling window in the Jaccard space.
+use std::ops::RangeInclusive;
+
+use crate::errors::{FindSimdocError, Result};
+use crate::feature::{FeatureConfig, FeatureExtractor, TokenBuffer};
+use crate::lsh::minhash::MinHasher;
+
+use rand::{RngCore, SeedableRng};
+use rayon::prelude::*;
+
+/// Deduplicator of documents arriving one by one, which keeps the sketches of
+/// the last distinct documents in a rolling window of a fixed capacity.
+///
+/// Each document is compared with all the sketches in the window, so the memory usage
+/// is constant regardless of the length of the stream.
+/// Documents near-duplicate to those before the window are not detected.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::stream::StreamDeduplicator;
+///
+/// let mut dedup = StreamDeduplicator::new(3, None, Some(42))
+///     .unwrap()
+///     .num_chunks(8)
+///     .unwrap()
+///     .capacity(1000)
+///     .unwrap();
+///
+/// assert!(dedup.insert("Welcome to Jimbocho, the town of books and curry!", 0.1).unwrap());
+/// assert!(!dedup.insert("Welcome to Jimbocho, the town of books and curry!", 0.1).unwrap());
+/// assert!(dedup.insert("Hello, world!", 0.1).unwrap());
+/// assert_eq!(dedup.len(), 2);
+/// ```
+pub struct StreamDeduplicator {
+    config: FeatureConfig,
+    hasher: MinHasher,
+    num_chunks: usize,
+    capacity: usize,
+    // Ring buffer of sketches, whose oldest one is overwritten when full.
+    sketches: Vec<u64>,
+    head: usize,
+    feature: Vec<u64>,
+    buffer: TokenBuffer,
+}
+
+impl StreamDeduplicator {
+    /// Creates an instance with 8 chunks and the capacity of 1M documents.
+    ///
+    /// # Arguments
+    ///
+    /// * `window_size` - Window size for w-shingling in feature extraction (must be more than 0).
+    /// * `delimiter` - Delimiter for recognizing words as tokens in feature extraction.
+    ///   If `None`, characters are used for tokens.
+    /// * `seed` - Seed value for random values.
+    pub fn new(window_size: usize, delimiter: Option<char>, seed: Option<u64>) -> Result<Self> {
+        let seed = seed.unwrap_or_else(rand::random::<u64>);
+        let mut seeder = rand_xoshiro::SplitMix64::seed_from_u64(seed);
+        let config = FeatureConfig::new(window_size, delimiter, seeder.next_u64())?;
+        let hasher = MinHasher::new(seeder.next_u64());
+        Ok(Self {
+            config,
+            hasher,
+            num_chunks: 8,
+            capacity: 1_000_000,
+            sketches: vec![],
+            head: 0,
+            feature: vec![],
+            buffer: TokenBuffer::new(),
+        })
+    }
+
+    /// Sets the number of 64-bit chunks of sketches.
+    /// The window is cleared.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the number is 0.
+    pub fn num_chunks(mut self, num_chunks: usize) -> Result<Self> {
+        if num_chunks == 0 {
+            return Err(FindSimdocError::input("num_chunks must not be 0."));
+        }
+        self.num_chunks = num_chunks;
+        self.clear();
+        Ok(self)
+    }
+
+    /// Sets the maximum number of documents kept in the window.
+    /// The window is cleared.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the capacity is 0.
+    pub fn capacity(mut self, capacity: usize) -> Result<Self> {
+        if capacity == 0 {
+            return Err(FindSimdocError::input("capacity must not be 0."));
+        }
+        self.capacity = capacity;
+        self.clear();
+        Ok(self)
+    }
+
+    /// Sets a regular expression for recognizing words as tokens in feature extraction,
+    /// used instead of the delimiter character.
+    /// See [`FeatureConfig::delimiter_regex()`].
+    pub fn delimiter_regex(mut self, pattern: Option<&str>) -> Result<Self> {
+        self.config = self.config.delimiter_regex(pattern)?;
+        Ok(self)
+    }
+
+    /// Sets the range of window sizes for w-shingling in feature extraction.
+    /// See [`FeatureConfig::window_range()`].
+    pub fn window_range(mut self, range: RangeInclusive<usize>) -> Result<Self> {
+        self.config = self.config.window_range(range)?;
+        Ok(self)
+    }
+
+    /// Applies the Unicode NFKC normalization to documents before tokenization?
+    /// See [`FeatureConfig::normalize_nfkc()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn normalize_nfkc(mut self, yes: bool) -> Self {
+        self.config = self.config.normalize_nfkc(yes);
+        self
+    }
+
+    /// Converts documents into lowercase before tokenization?
+    /// See [`FeatureConfig::lowercase()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn lowercase(mut self, yes: bool) -> Self {
+        self.config = self.config.lowercase(yes);
+        self
+    }
+
+    /// Removes punctuation characters before tokenization?
+    /// See [`FeatureConfig::strip_punctuation()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn strip_punctuation(mut self, yes: bool) -> Self {
+        self.config = self.config.strip_punctuation(yes);
+        self
+    }
+
+    /// Checks if a document is distinct from all the documents in the window,
+    /// i.e., no document is within the Jaccard distance of `radius`, and adds it
+    /// to the window if so.
+    ///
+    /// Documents without features are always distinct but not added to the window.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the radius is not in the range of [0,1].
+    pub fn insert(&mut self, doc: &str, radius: f64) -> Result<bool> {
+        if !(0. ..=1.).contains(&radius) {
+            return Err(FindSimdocError::input("radius must be in [0,1]."));
+        }
+        let extractor = FeatureExtractor::new(&self.config);
+        extractor.extract_buffered(doc, &mut self.feature, &mut self.buffer);
+        if self.feature.is_empty() {
+            return Ok(true);
+        }
+        let sketch = self.hasher.sketch(&self.feature, self.num_chunks)?;
+
+        // The estimated distance is the mean of those of chunks, so the radius is
+        // converted into the total number of different samples to stop scanning early.
+        let num_samples = self.hasher.num_samples() * self.num_chunks;
+        let max_hamdist =
+            (radius / self.hasher.distance_scale() * num_samples as f64).floor() as usize;
+        let duplicated = self
+            .sketches
+            .par_chunks_exact(self.num_chunks)
+            .any(|other| {
+                let mut hamdist = 0;
+                for (&x, &y) in other.iter().zip(sketch.iter()) {
+                    hamdist += self.hasher.hamdist(x, y);
+                    if max_hamdist < hamdist {
+                        return false;
+                    }
+                }
+                true
+            });
+        if duplicated {
+            return Ok(false);
+        }
+
+        if self.len() < self.capacity {
+            self.sketches.extend(sketch);
+        } else {
+            let offset = self.head * self.num_chunks;
+            self.sketches[offset..offset + self.num_chunks].copy_from_slice(&sketch);
+            self.head = (self.head + 1) % self.capacity;
+        }
+        Ok(true)
+    }
+
+    /// Removes all the documents in the window.
+    pub fn clear(&mut self) {
+        self.sketches.clear();
+        self.head = 0;
+    }
+
+    /// Gets the number of documents in the window.
+    pub fn len(&self) -> usize {
+        self.sketches.len() / self.num_chunks
+    }
+
+    /// Checks if the window is empty.
+    pub fn is_empty(&self) -> bool {
+        self.sketches.is_empty()
+    }
+
+    /// Gets the number of bytes of the sketches when the window is full.
+    pub const fn memory_in_bytes(&self) -> usize {
+        self.capacity * self.num_chunks * std::mem::size_of::<u64>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert() {
+        let mut dedup = StreamDeduplicator::new(3, None, Some(42)).unwrap();
+        assert!(dedup.insert("abcdefghijklmnopqrstuvwxyz", 0.1).unwrap());
+        assert!(!dedup.insert("abcdefghijklmnopqrstuvwxyz", 0.1).unwrap());
+        assert!(!dedup.insert("abcdefghijklmnopqrstuvwxyz", 0.).unwrap());
+        assert!(dedup.insert("zyxwvutsrqponmlkjihgfedcba", 0.1).unwrap());
+        assert!(dedup.insert("", 0.1).unwrap());
+        assert_eq!(dedup.len(), 2);
+        assert!(dedup.insert("abc", 1.5).is_err());
+    }
+
+    #[test]
+    fn test_rolling_window() {
+        let mut dedup = StreamDeduplicator::new(3, None, Some(42))
+            .unwrap()
+            .capacity(2)
+            .unwrap();
+        assert!(dedup.insert("abcdefghijklmnopqrstuvwxyz", 0.1).unwrap());
+        assert!(dedup.insert("ABCDEFGHIJKLMNOPQRSTUVWXYZ", 0.1).unwrap());
+        assert!(dedup.insert("0123456789", 0.1).unwrap());
+        assert_eq!(dedup.len(), 2);
+        // The first document has been evicted from the window.
+        assert!(dedup.insert("abcdefghijklmnopqrstuvwxyz", 0.1).unwrap());
+        assert!(!dedup.insert("0123456789", 0.1).unwrap());
+        assert_eq!(dedup.memory_in_bytes(), 2 * 8 * 8);
+    }
+}
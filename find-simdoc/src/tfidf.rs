@@ -1,16 +1,35 @@
 //! Weighters of TF-IDF.
+use std::fs::File;
 use std::hash::Hash;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
 
 use hashbrown::{HashMap, HashSet};
 
 use crate::errors::{FindSimdocError, Result};
-use crate::feature::{FeatureConfig, FeatureExtractor};
+use crate::feature::{FeatureConfig, FeatureExtractor, TokenBuffer};
+use crate::lsh::ElementHasher;
+
+/// Weighter of terms in a document, which can be plugged into
+/// [`CosineSearcher`](crate::CosineSearcher) to supply arbitrary weighting schemes.
+///
+/// [`Tf`], [`Bm25`], and [`Idf`] implement this trait.
+pub trait TermWeighter: Send + Sync {
+    /// Multiplies the weights of input terms in a document in place.
+    fn weight(&self, terms: &mut [(u64, f64)]);
+
+    /// Gets the description identifying the weighter and its parameters in fingerprints.
+    fn description(&self) -> String {
+        "custom".to_string()
+    }
+}
 
 /// Weighter of inverse document frequency.
 #[derive(Default)]
 pub struct Idf<T> {
     counter: HashMap<T, usize>,
     dedup: HashSet<T>,
+    dropped: HashSet<T>,
     num_docs: usize,
     smooth: bool,
 }
@@ -30,8 +49,21 @@ where
         self
     }
 
+    /// Checks if smoothing is enabled.
+    pub const fn is_smooth(&self) -> bool {
+        self.smooth
+    }
+
     /// Trains the frequency of terms for a document.
-    pub fn add(&mut self, terms: &[T]) {
+    ///
+    /// It can be called after [`Idf::build()`] to update the model incrementally.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if terms have been dropped with [`Self::prune()`],
+    /// since their frequencies are no longer kept.
+    pub fn add(&mut self, terms: &[T]) -> Result<()> {
+        self.check_unpruned()?;
         self.dedup.clear();
         for &term in terms {
             if self.dedup.insert(term) {
@@ -42,6 +74,74 @@ where
             }
         }
         self.num_docs += 1;
+        Ok(())
+    }
+
+    /// Merges the model trained on another set of documents,
+    /// e.g., a shard trained in parallel.
+    /// The smoothing setting of `self` is retained.
+    ///
+    /// Prune the merged model instead of each shard, so that the thresholds
+    /// are applied to the document frequencies over all the documents.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if terms have been dropped from either model with [`Self::prune()`].
+    pub fn merge(&mut self, other: &Self) -> Result<()> {
+        self.check_unpruned()?;
+        other.check_unpruned()?;
+        for (&term, &cnt) in &other.counter {
+            self.counter
+                .entry(term)
+                .and_modify(|c| *c += cnt)
+                .or_insert(cnt);
+        }
+        self.num_docs += other.num_docs;
+        Ok(())
+    }
+
+    fn check_unpruned(&self) -> Result<()> {
+        if !self.dropped.is_empty() {
+            return Err(FindSimdocError::input(
+                "The model must not be trained after terms are dropped.",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Drops terms appearing in fewer than `min_df` documents or
+    /// in more than `max_df_ratio` of documents, which is performed after training.
+    /// The IDF of a dropped term is 0, i.e., the term is ignored in weighting.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the ratio is not in the range of [0,1].
+    pub fn prune(mut self, min_df: usize, max_df_ratio: f64) -> Result<Self> {
+        if !(0. ..=1.).contains(&max_df_ratio) {
+            return Err(FindSimdocError::input(
+                "The ratio of documents must be in the range of [0,1].",
+            ));
+        }
+        let max_df = (max_df_ratio * self.num_docs as f64).floor() as usize;
+        let dropped = &mut self.dropped;
+        self.counter.retain(|&term, &mut cnt| {
+            let retained = min_df <= cnt && cnt <= max_df;
+            if !retained {
+                dropped.insert(term);
+            }
+            retained
+        });
+        Ok(self)
+    }
+
+    /// Checks if an input term is dropped with [`Self::prune()`].
+    pub fn is_dropped(&self, term: T) -> bool {
+        self.dropped.contains(&term)
+    }
+
+    /// Gets the terms dropped with [`Self::prune()`] in arbitrary order.
+    pub fn dropped_terms(&self) -> impl Iterator<Item = T> + '_ {
+        self.dropped.iter().copied()
     }
 
     /// Gets the number of input documents.
@@ -49,11 +149,30 @@ where
         self.num_docs
     }
 
-    /// Computes the IDF of an input term.
+    /// Gets the number of documents in which an input term appears.
+    pub fn df(&self, term: T) -> usize {
+        self.counter.get(&term).copied().unwrap_or(0)
+    }
+
+    /// Gets the pairs of a term and the number of documents in which the term appears,
+    /// in arbitrary order. The dropped terms are not included.
+    ///
+    /// Use it with [`Self::num_docs()`] to analyze the trained vocabulary.
+    pub fn document_frequencies(&self) -> impl Iterator<Item = (T, usize)> + '_ {
+        self.counter.iter().map(|(&term, &cnt)| (term, cnt))
+    }
+
+    /// Computes the IDF of an input term, or 0 if the term is dropped.
+    ///
+    /// A term not in the trained documents is regarded as appearing in one document,
+    /// which can occur when the model is reused for new documents.
     pub fn idf(&self, term: T) -> f64 {
+        if self.is_dropped(term) {
+            return 0.;
+        }
         let c = usize::from(self.smooth);
-        let n = (self.num_docs + c) as f64;
-        let m = (*self.counter.get(&term).unwrap() + c) as f64;
+        let n = (self.num_docs.max(1) + c) as f64;
+        let m = (self.df(term).max(1) + c) as f64;
         (n / m).log10() + 1.
     }
 }
@@ -65,6 +184,9 @@ impl Idf<u64> {
     ///
     /// * `documents` - List of documents.
     /// * `config` - Configuration of feature extraction. Use the same configuration as that in search.
+    ///
+    /// If the model has already been trained, the statistics of input documents are added to it,
+    /// which results in an error if terms have been dropped with [`Self::prune()`].
     pub fn build<I, D>(mut self, documents: I, config: &FeatureConfig) -> Result<Self>
     where
         I: IntoIterator<Item = D>,
@@ -72,22 +194,161 @@ impl Idf<u64> {
     {
         let extractor = FeatureExtractor::new(config);
         let mut feature = vec![];
+        let mut buffer = TokenBuffer::new();
         for doc in documents {
             let doc = doc.as_ref();
             if doc.is_empty() {
                 return Err(FindSimdocError::input("Input document must not be empty."));
             }
-            extractor.extract(doc, &mut feature);
-            self.add(&feature);
+            extractor.extract_buffered(doc, &mut feature, &mut buffer);
+            self.add(&feature)?;
         }
         Ok(self)
     }
+
+    /// Saves the trained model to a file, with which the document frequencies
+    /// trained on a large corpus can be reused without re-scanning it.
+    pub fn save<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let mut wtr = BufWriter::new(File::create(path)?);
+        self.serialize_into(&mut wtr)?;
+        wtr.flush()?;
+        Ok(())
+    }
+
+    /// Loads the model saved with [`Self::save()`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file is not a valid model.
+    pub fn load<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::deserialize_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Computes a digest of the document frequencies and the dropped terms,
+    /// which is independent of the order of the hash tables.
+    fn digest(&self) -> u64 {
+        let hasher = ElementHasher::SplitMix64;
+        let mut digest = 0u64;
+        for (&term, &cnt) in &self.counter {
+            digest = digest.wrapping_add(hasher.hash(term, hasher.hash(cnt as u64, 0)));
+        }
+        for &term in &self.dropped {
+            digest = digest.wrapping_add(hasher.hash(term, u64::MAX));
+        }
+        digest
+    }
+
+    /// Serializes the trained model into a writer in a binary format.
+    pub fn serialize_into<W>(&self, mut wtr: W) -> Result<()>
+    where
+        W: Write,
+    {
+        // Terms are sorted so that the same model is serialized into the same bytes.
+        let mut counter: Vec<_> = self.counter.iter().map(|(&t, &c)| (t, c)).collect();
+        counter.sort_unstable();
+        let mut dropped: Vec<_> = self.dropped.iter().copied().collect();
+        dropped.sort_unstable();
+        wtr.write_all(IDF_MAGIC)?;
+        wtr.write_all(&[u8::from(self.smooth)])?;
+        wtr.write_all(&(self.num_docs as u64).to_le_bytes())?;
+        wtr.write_all(&(counter.len() as u64).to_le_bytes())?;
+        for (term, cnt) in counter {
+            wtr.write_all(&term.to_le_bytes())?;
+            wtr.write_all(&(cnt as u64).to_le_bytes())?;
+        }
+        wtr.write_all(&(dropped.len() as u64).to_le_bytes())?;
+        for term in dropped {
+            wtr.write_all(&term.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Deserializes the model serialized with [`Self::serialize_into()`] from a reader.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the data is not a valid model.
+    pub fn deserialize_from<R>(mut rdr: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut magic = [0; IDF_MAGIC.len()];
+        rdr.read_exact(&mut magic)?;
+        if &magic != IDF_MAGIC {
+            return Err(FindSimdocError::serialization("Invalid IDF model."));
+        }
+        let mut smooth = [0];
+        rdr.read_exact(&mut smooth)?;
+        let num_docs = read_u64(&mut rdr)? as usize;
+        let len = read_u64(&mut rdr)? as usize;
+        let mut counter = HashMap::new();
+        for _ in 0..len {
+            let term = read_u64(&mut rdr)?;
+            let cnt = read_u64(&mut rdr)? as usize;
+            if cnt == 0 || num_docs < cnt {
+                return Err(FindSimdocError::serialization(
+                    "Document frequencies must be in the range of [1, #documents].",
+                ));
+            }
+            counter.insert(term, cnt);
+        }
+        let len = read_u64(&mut rdr)? as usize;
+        let mut dropped = HashSet::new();
+        for _ in 0..len {
+            dropped.insert(read_u64(&mut rdr)?);
+        }
+        Ok(Self {
+            counter,
+            dedup: HashSet::new(),
+            dropped,
+            num_docs,
+            smooth: smooth[0] != 0,
+        })
+    }
+}
+
+impl TermWeighter for Idf<u64> {
+    fn weight(&self, terms: &mut [(u64, f64)]) {
+        for (term, weight) in terms {
+            *weight *= self.idf(*term);
+        }
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "idf(smooth={},num_docs={},num_dropped={},digest={:016x})",
+            self.smooth,
+            self.num_docs,
+            self.dropped.len(),
+            self.digest()
+        )
+    }
+}
+
+/// Magic bytes identifying serialized IDF models, including the format version.
+const IDF_MAGIC: &[u8; 8] = b"FSIDF\x00\x00\x01";
+
+fn read_u64<R>(rdr: &mut R) -> Result<u64>
+where
+    R: Read,
+{
+    let mut buf = [0; 8];
+    rdr.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
 }
 
 /// Weighter of term frequency.
 #[derive(Default)]
 pub struct Tf {
     sublinear: bool,
+    pivot_slope: Option<f64>,
+    avg_doc_len: Option<f64>,
 }
 
 impl Tf {
@@ -102,35 +363,297 @@ impl Tf {
         self
     }
 
+    /// Sets the slope of the pivoted length normalization, or disables it with `None`.
+    /// TFs are divided by `1 - slope + slope * doc_len / avg_doc_len`,
+    /// so that long documents are not systematically over-weighted.
+    ///
+    /// The average length must be set in advance with [`Self::avg_doc_len()`] or [`Self::build()`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the slope is not in the range of [0,1]
+    /// or the average length is not set.
+    pub fn pivot_slope(mut self, slope: Option<f64>) -> Result<Self> {
+        if let Some(slope) = slope {
+            if !(0. ..=1.).contains(&slope) {
+                return Err(FindSimdocError::input(
+                    "Pivot slope must be in the range of [0,1].",
+                ));
+            }
+            if self.avg_doc_len.is_none() {
+                return Err(FindSimdocError::input(
+                    "Average document length must be set before the pivot slope.",
+                ));
+            }
+        }
+        self.pivot_slope = slope;
+        Ok(self)
+    }
+
+    /// Sets the average number of terms in documents used as the pivot,
+    /// which is usually trained with [`Self::build()`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the length is not positive.
+    pub fn avg_doc_len(mut self, len: f64) -> Result<Self> {
+        if len.is_nan() || len <= 0. {
+            return Err(FindSimdocError::input(
+                "Average document length must be positive.",
+            ));
+        }
+        self.avg_doc_len = Some(len);
+        Ok(self)
+    }
+
+    /// Trains the average number of terms in input documents used as the pivot.
+    ///
+    /// # Arguments
+    ///
+    /// * `documents` - List of documents.
+    /// * `config` - Configuration of feature extraction. Use the same configuration as that in search.
+    pub fn build<I, D>(self, documents: I, config: &FeatureConfig) -> Result<Self>
+    where
+        I: IntoIterator<Item = D>,
+        D: AsRef<str>,
+    {
+        self.avg_doc_len(average_doc_len(documents, config)?)
+    }
+
+    /// Checks if sublinear normalization is enabled.
+    pub const fn is_sublinear(&self) -> bool {
+        self.sublinear
+    }
+
+    /// Gets the slope of the pivoted length normalization, or `None` if disabled.
+    pub const fn pivot_slope_value(&self) -> Option<f64> {
+        self.pivot_slope
+    }
+
+    /// Gets the average number of terms in documents used as the pivot, or `None` if not set.
+    pub const fn average_doc_len(&self) -> Option<f64> {
+        self.avg_doc_len
+    }
+
     /// Computes the TF of input terms.
     pub fn tf<T>(&self, terms: &mut [(T, f64)])
     where
         T: Hash + Eq + Copy + Default,
     {
-        let counter = self.count(terms);
+        terms.iter_mut().for_each(|(_, weight)| *weight = 1.);
+        self.scale(terms);
+    }
+
+    fn scale<T>(&self, terms: &mut [(T, f64)])
+    where
+        T: Hash + Eq + Copy + Default,
+    {
+        let counter = count_terms(terms);
         let total = terms.len() as f64;
+        // The average length is always set with the slope.
+        let norm = self.pivot_slope.map_or(1., |slope| {
+            1. - slope + slope * total / self.avg_doc_len.unwrap()
+        });
         for (term, weight) in terms {
             let cnt = *counter.get(term).unwrap() as f64;
-            *weight = if self.sublinear {
+            *weight *= if self.sublinear {
                 cnt.log10() + 1.
             } else {
                 cnt / total
-            };
+            } / norm;
+        }
+    }
+}
+
+impl TermWeighter for Tf {
+    fn weight(&self, terms: &mut [(u64, f64)]) {
+        self.scale(terms);
+    }
+
+    fn description(&self) -> String {
+        let pivot = self.pivot_slope.zip(self.avg_doc_len);
+        format!("tf(sublinear={},pivot={pivot:?})", self.sublinear)
+    }
+}
+
+/// Weighter of term frequency in [Okapi BM25](https://en.wikipedia.org/wiki/Okapi_BM25),
+/// which saturates frequencies of terms and normalizes them with lengths of documents.
+/// Combine it with [`Idf`] for the BM25 weighting.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::tfidf::{Bm25, Idf};
+/// use find_simdoc::CosineSearcher;
+///
+/// let documents = ["a b c a", "a b d", "a b c a e f g h"];
+/// let searcher = CosineSearcher::new(1, Some(' '), Some(42)).unwrap();
+/// let bm25 = Bm25::build(documents.iter(), searcher.config()).unwrap();
+/// let idf = Idf::new()
+///     .build(documents.iter(), searcher.config())
+///     .unwrap();
+/// let searcher = searcher
+///     .bm25(Some(bm25))
+///     .idf(Some(idf))
+///     .build_sketches(documents.iter(), 10)
+///     .unwrap();
+/// ```
+pub struct Bm25 {
+    k1: f64,
+    b: f64,
+    avg_doc_len: f64,
+}
+
+impl Bm25 {
+    /// Creates an instance with the average number of terms in documents,
+    /// which is usually trained with [`Self::build()`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the length is not positive.
+    pub fn new(avg_doc_len: f64) -> Result<Self> {
+        if avg_doc_len.is_nan() || avg_doc_len <= 0. {
+            return Err(FindSimdocError::input(
+                "Average document length must be positive.",
+            ));
+        }
+        Ok(Self {
+            k1: 1.2,
+            b: 0.75,
+            avg_doc_len,
+        })
+    }
+
+    /// Sets the parameter saturating frequencies of terms.
+    /// The default value is 1.2.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the parameter is negative.
+    pub fn k1(mut self, k1: f64) -> Result<Self> {
+        if !(0. ..).contains(&k1) {
+            return Err(FindSimdocError::input("k1 must not be negative."));
         }
+        self.k1 = k1;
+        Ok(self)
+    }
+
+    /// Sets the parameter of the length normalization.
+    /// The default value is 0.75.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the parameter is not in the range of [0,1].
+    pub fn b(mut self, b: f64) -> Result<Self> {
+        if !(0. ..=1.).contains(&b) {
+            return Err(FindSimdocError::input("b must be in the range of [0,1]."));
+        }
+        self.b = b;
+        Ok(self)
+    }
+
+    /// Creates an instance with the average number of terms in input documents.
+    ///
+    /// # Arguments
+    ///
+    /// * `documents` - List of documents.
+    /// * `config` - Configuration of feature extraction. Use the same configuration as that in search.
+    pub fn build<I, D>(documents: I, config: &FeatureConfig) -> Result<Self>
+    where
+        I: IntoIterator<Item = D>,
+        D: AsRef<str>,
+    {
+        Self::new(average_doc_len(documents, config)?)
+    }
+
+    /// Gets the parameter saturating frequencies of terms.
+    pub const fn k1_value(&self) -> f64 {
+        self.k1
+    }
+
+    /// Gets the parameter of the length normalization.
+    pub const fn b_value(&self) -> f64 {
+        self.b
+    }
+
+    /// Gets the average number of terms in documents.
+    pub const fn average_doc_len(&self) -> f64 {
+        self.avg_doc_len
     }
 
-    fn count<T>(&self, terms: &[(T, f64)]) -> HashMap<T, usize>
+    /// Computes the BM25 TF of input terms in a document.
+    pub fn tf<T>(&self, terms: &mut [(T, f64)])
     where
         T: Hash + Eq + Copy + Default,
     {
-        let mut counter = HashMap::new();
-        for &(term, _) in terms.iter() {
-            counter.entry(term).and_modify(|c| *c += 1).or_insert(1);
+        terms.iter_mut().for_each(|(_, weight)| *weight = 1.);
+        self.scale(terms);
+    }
+
+    fn scale<T>(&self, terms: &mut [(T, f64)])
+    where
+        T: Hash + Eq + Copy + Default,
+    {
+        let counter = count_terms(terms);
+        let doc_len = terms.len() as f64;
+        let norm = self.k1 * (1. - self.b + self.b * doc_len / self.avg_doc_len);
+        for (term, weight) in terms {
+            let cnt = *counter.get(term).unwrap() as f64;
+            *weight *= cnt * (self.k1 + 1.) / (cnt + norm);
         }
-        counter
     }
 }
 
+impl TermWeighter for Bm25 {
+    fn weight(&self, terms: &mut [(u64, f64)]) {
+        self.scale(terms);
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "bm25(k1={},b={},avg_doc_len={})",
+            self.k1, self.b, self.avg_doc_len
+        )
+    }
+}
+
+fn average_doc_len<I, D>(documents: I, config: &FeatureConfig) -> Result<f64>
+where
+    I: IntoIterator<Item = D>,
+    D: AsRef<str>,
+{
+    let extractor = FeatureExtractor::new(config);
+    let mut feature = vec![];
+    let mut buffer = TokenBuffer::new();
+    let (mut num_terms, mut num_docs) = (0, 0);
+    for doc in documents {
+        let doc = doc.as_ref();
+        if doc.is_empty() {
+            return Err(FindSimdocError::input("Input document must not be empty."));
+        }
+        extractor.extract_buffered(doc, &mut feature, &mut buffer);
+        num_terms += feature.len();
+        num_docs += 1;
+    }
+    if num_terms == 0 {
+        return Err(FindSimdocError::input(
+            "Input documents must include at least one feature.",
+        ));
+    }
+    Ok(num_terms as f64 / num_docs as f64)
+}
+
+fn count_terms<T>(terms: &[(T, f64)]) -> HashMap<T, usize>
+where
+    T: Hash + Eq + Copy + Default,
+{
+    let mut counter = HashMap::new();
+    for &(term, _) in terms.iter() {
+        counter.entry(term).and_modify(|c| *c += 1).or_insert(1);
+    }
+    counter
+}
+
 #[cfg(test)]
 mod tests {
     use std::vec;
@@ -140,9 +663,9 @@ mod tests {
     #[test]
     fn test_idf() {
         let mut idf = Idf::new();
-        idf.add(&['A', 'A', 'C']);
-        idf.add(&['A', 'C']);
-        idf.add(&['B', 'A']);
+        idf.add(&['A', 'A', 'C']).unwrap();
+        idf.add(&['A', 'C']).unwrap();
+        idf.add(&['B', 'A']).unwrap();
 
         assert_eq!(idf.num_docs(), 3);
 
@@ -157,6 +680,112 @@ mod tests {
         assert_eq!(idf.idf('C'), (4f64 / 3f64).log10() + 1.);
     }
 
+    #[test]
+    fn test_document_frequencies() {
+        let mut idf = Idf::new();
+        idf.add(&['A', 'A', 'C']).unwrap();
+        idf.add(&['A', 'C']).unwrap();
+        idf.add(&['B', 'A']).unwrap();
+
+        let mut dfs: Vec<_> = idf.document_frequencies().collect();
+        dfs.sort_unstable();
+        assert_eq!(dfs, vec![('A', 3), ('B', 1), ('C', 2)]);
+
+        let idf = idf.prune(2, 1.).unwrap();
+        let mut dfs: Vec<_> = idf.document_frequencies().collect();
+        dfs.sort_unstable();
+        assert_eq!(dfs, vec![('A', 3), ('C', 2)]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut idf = Idf::new();
+        idf.add(&['A', 'A', 'C']).unwrap();
+        let mut other = Idf::new();
+        other.add(&['A', 'C']).unwrap();
+        other.add(&['B', 'A']).unwrap();
+        idf.merge(&other).unwrap();
+
+        assert_eq!(idf.num_docs(), 3);
+        assert_eq!(idf.df('A'), 3);
+        assert_eq!(idf.df('B'), 1);
+        assert_eq!(idf.df('C'), 2);
+        assert_eq!(idf.idf('D'), idf.idf('B'));
+
+        idf.add(&['D']).unwrap();
+        assert_eq!(idf.num_docs(), 4);
+        assert_eq!(idf.df('D'), 1);
+    }
+
+    #[test]
+    fn test_merge_pruned_shards() {
+        // 'A' appears in one document of each shard, i.e., in two documents overall.
+        let mut shards = [Idf::new(), Idf::new()];
+        for shard in &mut shards {
+            shard.add(&['A', 'B']).unwrap();
+            shard.add(&['B']).unwrap();
+        }
+
+        let [first, second] = shards;
+        let mut merged = Idf::new();
+        merged.merge(&first).unwrap();
+        merged.merge(&second).unwrap();
+        let merged = merged.prune(2, 1.).unwrap();
+        assert!(!merged.is_dropped('A'));
+        assert_eq!(merged.df('A'), 2);
+
+        // Pruning each shard would drop 'A' with its local frequency.
+        let pruned = first.prune(2, 1.).unwrap();
+        assert!(pruned.is_dropped('A'));
+        let mut merged = Idf::new();
+        assert!(merged.merge(&pruned).is_err());
+        let mut pruned = pruned;
+        assert!(pruned.merge(&second).is_err());
+        assert!(pruned.add(&['A']).is_err());
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut idf = Idf::new();
+        idf.add(&['A', 'B', 'C']).unwrap();
+        idf.add(&['A', 'B', 'D']).unwrap();
+        idf.add(&['A', 'E', 'F']).unwrap();
+        idf.add(&['A', 'B', 'G']).unwrap();
+
+        let idf = idf.prune(2, 0.75).unwrap();
+        let mut dropped: Vec<_> = idf.dropped_terms().collect();
+        dropped.sort_unstable();
+        assert_eq!(dropped, vec!['A', 'C', 'D', 'E', 'F', 'G']);
+        assert!(!idf.is_dropped('B'));
+        assert!(idf.is_dropped('A'));
+        assert_eq!(idf.idf('A'), 0.);
+        assert_eq!(idf.idf('B'), (4f64 / 3f64).log10() + 1.);
+
+        assert!(Idf::<char>::new().prune(1, 1.5).is_err());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mut idf = Idf::new().smooth(true);
+        idf.add(&[1, 1, 3]).unwrap();
+        idf.add(&[1, 3]).unwrap();
+        idf.add(&[2, 1]).unwrap();
+        let idf = idf.prune(1, 0.9).unwrap();
+
+        let mut data = vec![];
+        idf.serialize_into(&mut data).unwrap();
+        let other = Idf::deserialize_from(&data[..]).unwrap();
+        assert_eq!(other.num_docs(), 3);
+        assert!(other.is_smooth());
+        for term in 1..=3 {
+            assert_eq!(other.df(term), idf.df(term));
+        }
+        assert!(other.is_dropped(1));
+
+        assert!(Idf::deserialize_from(&data[1..]).is_err());
+        assert!(Idf::deserialize_from(&data[..data.len() - 1]).is_err());
+    }
+
     #[test]
     fn test_tf() {
         let mut tf = Tf::new();
@@ -180,4 +809,89 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_tf_pivot() {
+        let tf = Tf::new()
+            .avg_doc_len(2.)
+            .unwrap()
+            .pivot_slope(Some(0.5))
+            .unwrap();
+        let mut terms = vec![('A', 0.), ('B', 0.), ('A', 0.)];
+        tf.tf(&mut terms);
+
+        let norm = 1. - 0.5 + 0.5 * 3. / 2.;
+        assert_eq!(
+            terms,
+            vec![
+                ('A', 2. / 3. / norm),
+                ('B', 1. / 3. / norm),
+                ('A', 2. / 3. / norm)
+            ]
+        );
+
+        assert!(Tf::new()
+            .avg_doc_len(1.)
+            .unwrap()
+            .pivot_slope(Some(1.5))
+            .is_err());
+        assert!(Tf::new().avg_doc_len(0.).is_err());
+        assert!(Tf::new().pivot_slope(Some(0.5)).is_err());
+    }
+
+    #[test]
+    fn test_term_weighter() {
+        let mut idf = Idf::new();
+        idf.add(&[1, 2]).unwrap();
+        idf.add(&[1]).unwrap();
+        let weighters: [&dyn TermWeighter; 2] = [&Tf::new(), &idf];
+
+        let mut terms = vec![(1, 2.), (2, 1.), (1, 2.)];
+        for weighter in weighters {
+            weighter.weight(&mut terms);
+        }
+        let a = 2. * 2. / 3. * idf.idf(1);
+        let b = 1. / 3. * idf.idf(2);
+        assert_eq!(terms, vec![(1, a), (2, b), (1, a)]);
+        assert_eq!(weighters[0].description(), "tf(sublinear=false,pivot=None)");
+    }
+
+    #[test]
+    fn test_idf_description() {
+        let mut idf = Idf::new();
+        idf.add(&[1, 2]).unwrap();
+        idf.add(&[1]).unwrap();
+        let mut other = Idf::new();
+        other.add(&[1]).unwrap();
+        other.add(&[2, 1]).unwrap();
+        assert_eq!(idf.description(), other.description());
+
+        // Same number of documents, but different frequencies.
+        let mut other = Idf::new();
+        other.add(&[1, 2]).unwrap();
+        other.add(&[2]).unwrap();
+        assert_ne!(idf.description(), other.description());
+
+        let mut pruned = Idf::new();
+        pruned.add(&[1, 2]).unwrap();
+        pruned.add(&[1]).unwrap();
+        let pruned = pruned.prune(2, 1.).unwrap();
+        assert_ne!(idf.description(), pruned.description());
+    }
+
+    #[test]
+    fn test_bm25() {
+        let bm25 = Bm25::new(2.).unwrap();
+        let mut terms = vec![('A', 0.), ('B', 0.), ('A', 0.)];
+        bm25.tf(&mut terms);
+
+        let norm = 1.2 * (1. - 0.75 + 0.75 * 3. / 2.);
+        let a = 2. * 2.2 / (2. + norm);
+        let b = 1. * 2.2 / (1. + norm);
+        assert_eq!(terms, vec![('A', a), ('B', b), ('A', a)]);
+
+        assert!(Bm25::new(2.).unwrap().k1(-1.).is_err());
+        assert!(Bm25::new(2.).unwrap().b(1.5).is_err());
+        assert!(Bm25::new(0.).is_err());
+    }
 }
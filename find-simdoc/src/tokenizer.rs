@@ -0,0 +1,117 @@
+//! Pluggable tokenizers for feature extraction.
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Tokenizer recognizing tokens in texts, which can be plugged into
+/// [`FeatureConfig::tokenizer()`](crate::feature::FeatureConfig::tokenizer)
+/// to handle languages without explicit word delimiters, such as Japanese or Chinese.
+pub trait Tokenizer: Send + Sync {
+    /// Appends the byte ranges of tokens in an input text to `token_ranges`.
+    ///
+    /// Each range must be within `text` and fall on char boundaries,
+    /// i.e., `text.get(range)` must return `Some`.
+    ///
+    /// # Panics
+    ///
+    /// Feature extraction panics if a returned range violates the above condition.
+    fn tokenize(&self, text: &str, token_ranges: &mut Vec<Range<usize>>);
+
+    /// Gets the name identifying the tokenizer in fingerprints.
+    fn name(&self) -> &str {
+        "custom"
+    }
+}
+
+/// Sentence splitter recognizing sentences as tokens
+/// at the Unicode sentence boundaries ([UAX #29](https://www.unicode.org/reports/tr29/)),
+/// with which features are shingles of sentences (i.e., sentence n-grams).
+///
+/// Whitespace around sentences is trimmed, and sentences consisting only of whitespace are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::tokenizer::{SentenceTokenizer, Tokenizer};
+///
+/// let text = "Hello world. How are you? 元気です。";
+/// let mut ranges = vec![];
+/// SentenceTokenizer.tokenize(text, &mut ranges);
+///
+/// let sentences: Vec<_> = ranges.into_iter().map(|r| &text[r]).collect();
+/// assert_eq!(sentences, vec!["Hello world.", "How are you?", "元気です。"]);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SentenceTokenizer;
+
+impl Tokenizer for SentenceTokenizer {
+    fn tokenize(&self, text: &str, token_ranges: &mut Vec<Range<usize>>) {
+        for (offset, s) in text.split_sentence_bound_indices() {
+            let start = offset + s.len() - s.trim_start().len();
+            let end = offset + s.trim_end().len();
+            if start < end {
+                token_ranges.push(start..end);
+            }
+        }
+    }
+
+    fn name(&self) -> &str {
+        "sentence"
+    }
+}
+
+#[cfg(feature = "vaporetto")]
+pub use self::vaporetto::VaporettoTokenizer;
+
+#[cfg(feature = "vaporetto")]
+mod vaporetto {
+    use std::io::Read;
+    use std::ops::Range;
+
+    use vaporetto::{Model, Predictor, Sentence};
+
+    use super::Tokenizer;
+    use crate::errors::{FindSimdocError, Result};
+
+    /// Word segmenter of [Vaporetto](https://github.com/daac-tools/vaporetto)
+    /// for Japanese or Chinese texts, enabled with the `vaporetto` feature.
+    pub struct VaporettoTokenizer {
+        predictor: Predictor,
+    }
+
+    impl VaporettoTokenizer {
+        /// Creates an instance from a model file of Vaporetto.
+        ///
+        /// # Errors
+        ///
+        /// An error is returned if the model is invalid.
+        pub fn new<R>(rdr: R) -> Result<Self>
+        where
+            R: Read,
+        {
+            let model = Model::read(rdr).map_err(|e| FindSimdocError::input(e.to_string()))?;
+            let predictor =
+                Predictor::new(model, false).map_err(|e| FindSimdocError::input(e.to_string()))?;
+            Ok(Self { predictor })
+        }
+    }
+
+    impl Tokenizer for VaporettoTokenizer {
+        fn tokenize(&self, text: &str, token_ranges: &mut Vec<Range<usize>>) {
+            let Ok(mut sentence) = Sentence::from_raw(text) else {
+                return;
+            };
+            self.predictor.predict(&mut sentence);
+            let mut offset = 0;
+            for token in sentence.iter_tokens() {
+                let len = token.surface().len();
+                token_ranges.push(offset..offset + len);
+                offset += len;
+            }
+        }
+
+        fn name(&self) -> &str {
+            "vaporetto"
+        }
+    }
+}
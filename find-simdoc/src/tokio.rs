@@ -0,0 +1,174 @@
+//! Asynchronous building from [Tokio](https://tokio.rs/) readers, enabled with the `tokio` feature.
+//!
+//! Documents are read line by line from an [`AsyncBufRead`] such as a network stream or
+//! an object in a storage service, without blocking a runtime thread on the IO.
+//! Feature extraction and hashing are performed on the calling task for each batch of lines.
+//!
+//! # Examples
+//!
+//! ```
+//! use find_simdoc::JaccardSearcher;
+//!
+//! let documents: &[u8] = b"Welcome to Jimbocho, the town of books and curry!
+//! Welcome to Jimbocho, the city of books and curry!
+//! We welcome you to Jimbocho, the town of books and curry.
+//! ";
+//!
+//! let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+//! let searcher = runtime.block_on(async {
+//!     JaccardSearcher::new(3, None, Some(42))
+//!         .unwrap()
+//!         .build_sketches_from_async(documents, 20)
+//!         .await
+//! })
+//! .unwrap();
+//! assert_eq!(searcher.len(), 3);
+//! ```
+use all_pairs_hamming::sketch::Sketch;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+use crate::errors::Result;
+use crate::{CosineSearcher, JaccardSearcher};
+
+/// Number of lines read before the documents are added to the database.
+const BATCH_SIZE: usize = 1024;
+
+/// Reads lines from `reader` in batches and passes each of them to `add`,
+/// whose progress is reported to the hook of the searcher.
+async fn read_batches<R, T, F>(reader: R, mut searcher: T, mut add: F) -> Result<T>
+where
+    R: AsyncBufRead + Unpin,
+    F: FnMut(T, &[String]) -> Result<T>,
+{
+    let mut lines = reader.lines();
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    while let Some(line) = lines.next_line().await? {
+        batch.push(line);
+        if batch.len() == BATCH_SIZE {
+            searcher = add(searcher, &batch)?;
+            batch.clear();
+        }
+    }
+    add(searcher, &batch)
+}
+
+impl<S> JaccardSearcher<S>
+where
+    S: Sketch + Send + Sync,
+{
+    /// Builds the database of sketches from lines read asynchronously from `reader`,
+    /// regarding each line as a document.
+    /// The sketches are the same as those built with [`Self::build_sketches()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader of documents separated by newlines. Empty lines are handled
+    ///   according to [`Self::short_document_policy()`].
+    /// * `num_chunks` - Number of chunks of sketches, indicating that
+    ///   the number of dimensions in the Hamming space is `num_chunks*S::dim()`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if reading fails, a line is not valid UTF-8,
+    /// or a line is rejected with the short document policy.
+    pub async fn build_sketches_from_async<R>(self, reader: R, num_chunks: usize) -> Result<Self>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let searcher = self.build_sketches(std::iter::empty::<&str>(), num_chunks)?;
+        read_batches(reader, searcher, |searcher, batch| {
+            searcher.add_documents(batch)
+        })
+        .await
+    }
+}
+
+impl<S> CosineSearcher<S>
+where
+    S: Sketch + Send + Sync,
+{
+    /// Builds the database of sketches from lines read asynchronously from `reader`,
+    /// regarding each line as a document.
+    /// The sketches are the same as those built with [`Self::build_sketches()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader of documents separated by newlines. Empty lines are handled
+    ///   according to [`Self::short_document_policy()`].
+    /// * `num_chunks` - Number of chunks of sketches, indicating that
+    ///   the number of dimensions in the Hamming space is `num_chunks*S::dim()`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if reading fails, a line is not valid UTF-8,
+    /// or a line is rejected with the short document policy.
+    pub async fn build_sketches_from_async<R>(self, reader: R, num_chunks: usize) -> Result<Self>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let searcher = self.build_sketches(std::iter::empty::<&str>(), num_chunks)?;
+        read_batches(reader, searcher, |searcher, batch| {
+            searcher.add_documents(batch)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_jaccard() {
+        let documents: Vec<_> = (0..BATCH_SIZE + 10)
+            .map(|i| format!("document {}", i % 100))
+            .collect();
+        let text = documents.join("\n");
+
+        let expected = JaccardSearcher::new(2, None, Some(42))
+            .unwrap()
+            .build_sketches(&documents, 4)
+            .unwrap();
+        let searcher = block_on(
+            JaccardSearcher::new(2, None, Some(42))
+                .unwrap()
+                .build_sketches_from_async(text.as_bytes(), 4),
+        )
+        .unwrap();
+        assert_eq!(searcher.len(), documents.len());
+        assert_eq!(
+            searcher.search_similar_pairs(0.1),
+            expected.search_similar_pairs(0.1)
+        );
+    }
+
+    #[test]
+    fn test_cosine() {
+        let text = "abcdefgh\nabcdefgi\nxyz\n";
+        let searcher = block_on(
+            CosineSearcher::new(2, None, Some(42))
+                .unwrap()
+                .build_sketches_from_async(text.as_bytes(), 16),
+        )
+        .unwrap();
+        assert_eq!(searcher.len(), 3);
+    }
+
+    #[test]
+    fn test_empty_line() {
+        let text = "abcdefgh\n\nxyz\n";
+        let result = block_on(
+            JaccardSearcher::new(2, None, Some(42))
+                .unwrap()
+                .build_sketches_from_async(text.as_bytes(), 16),
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,459 @@
+//! Searcher for all pairs of similar documents in the weighted Jaccard space.
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::errors::{FindSimdocError, Result};
+use crate::estimate::Estimate;
+use crate::feature::{FeatureConfig, FeatureExtractor, TokenBuffer};
+use crate::index_format::IndexFile;
+use crate::lsh::icws::IcwsHasher;
+use crate::tfidf::{Idf, TermWeighter, Tf};
+
+use all_pairs_hamming::chunked_join::ChunkedJoiner;
+use all_pairs_hamming::progress::{ProgressHook, Stage};
+use all_pairs_hamming::sketch::Sketch;
+use rand::{RngCore, SeedableRng};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+/// Searcher for all pairs of similar documents in the weighted Jaccard space,
+/// where the weighted Jaccard similarity of weighted features `x` and `y` is
+/// `Σ min(x_i, y_i) / Σ max(x_i, y_i)`.
+///
+/// # Approach
+///
+/// The search steps consist of
+///
+/// 1. Extracts features from documents,
+///    where a feature is a tfidf-weighted vector representation of character or word ngrams.
+/// 2. Convert the features into binary sketches through the [`IcwsHasher`].
+/// 3. Search for similar sketches in the Hamming space using [`ChunkedJoiner`].
+///
+/// # Examples
+///
+/// ```
+/// use find_simdoc::tfidf::Tf;
+/// use find_simdoc::WeightedJaccardSearcher;
+///
+/// let documents = vec![
+///     "Welcome to Jimbocho, the town of books and curry!",
+///     "Welcome to Jimbocho, the city of books and curry!",
+///     "We welcome you to Jimbocho, the town of books and curry.",
+///     "Welcome to the town of books and curry, Jimbocho!",
+/// ];
+///
+/// // Creates a searcher for word unigrams (with random seed value 42)
+/// // weighted by the term frequency.
+/// let searcher = WeightedJaccardSearcher::new(1, Some(' '), Some(42))
+///     .unwrap()
+///     .tf(Some(Tf::new()))
+///     // where binary sketches are in the Hamming space of 10*64 dimensions.
+///     .build_sketches_in_parallel(documents.iter(), 10)
+///     .unwrap();
+///
+/// // Searches all similar pairs within radius 0.25.
+/// let results = searcher.search_similar_pairs(0.25);
+/// ```
+pub struct WeightedJaccardSearcher {
+    seed: u64,
+    config: FeatureConfig,
+    hasher: IcwsHasher,
+    tf: Option<Arc<dyn TermWeighter>>,
+    idf: Option<Arc<dyn TermWeighter>>,
+    joiner: Option<ChunkedJoiner<u64>>,
+    thread_pool: Option<Arc<ThreadPool>>,
+    progress: Option<ProgressHook>,
+}
+
+impl WeightedJaccardSearcher {
+    /// Creates an instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `window_size` - Window size for w-shingling in feature extraction (must be more than 0).
+    /// * `delimiter` - Delimiter for recognizing words as tokens in feature extraction.
+    ///   If `None`, characters are used for tokens.
+    /// * `seed` - Seed value for random values.
+    pub fn new(window_size: usize, delimiter: Option<char>, seed: Option<u64>) -> Result<Self> {
+        let seed = seed.unwrap_or_else(rand::random::<u64>);
+        let mut seeder = rand_xoshiro::SplitMix64::seed_from_u64(seed);
+        let config = FeatureConfig::new(window_size, delimiter, seeder.next_u64())?;
+        let hasher = IcwsHasher::new(seeder.next_u64());
+        Ok(Self {
+            seed,
+            config,
+            hasher,
+            tf: None,
+            idf: None,
+            joiner: None,
+            thread_pool: None,
+            progress: None,
+        })
+    }
+
+    /// Sets a regular expression for recognizing words as tokens in feature extraction,
+    /// used instead of the delimiter character.
+    /// See [`FeatureConfig::delimiter_regex()`].
+    pub fn delimiter_regex(mut self, pattern: Option<&str>) -> Result<Self> {
+        self.config = self.config.delimiter_regex(pattern)?;
+        Ok(self)
+    }
+
+    /// Sets the range of window sizes for w-shingling in feature extraction.
+    /// See [`FeatureConfig::window_range()`].
+    pub fn window_range(mut self, range: RangeInclusive<usize>) -> Result<Self> {
+        self.config = self.config.window_range(range)?;
+        Ok(self)
+    }
+
+    /// Applies the Unicode NFKC normalization to documents before tokenization?
+    /// See [`FeatureConfig::normalize_nfkc()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn normalize_nfkc(mut self, yes: bool) -> Self {
+        self.config = self.config.normalize_nfkc(yes);
+        self
+    }
+
+    /// Converts documents into lowercase before tokenization?
+    /// See [`FeatureConfig::lowercase()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn lowercase(mut self, yes: bool) -> Self {
+        self.config = self.config.lowercase(yes);
+        self
+    }
+
+    /// Removes punctuation characters before tokenization?
+    /// See [`FeatureConfig::strip_punctuation()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn strip_punctuation(mut self, yes: bool) -> Self {
+        self.config = self.config.strip_punctuation(yes);
+        self
+    }
+
+    /// Emits the progress of building sketches and the similarity join as [`tracing`] events
+    /// with [`ProgressHook::tracing()`]?
+    /// This replaces the hook set with [`Self::progress_hook()`].
+    pub fn shows_progress(mut self, yes: bool) -> Self {
+        self.progress = yes.then(ProgressHook::tracing);
+        self
+    }
+
+    /// Sets a hook receiving the progress of reading documents, building sketches,
+    /// and the similarity join, so that applications can show it in their own way.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn progress_hook(mut self, hook: Option<ProgressHook>) -> Self {
+        self.progress = hook;
+        self
+    }
+
+    /// Sets the scheme of TF weighting.
+    /// If `None`, each distinct term has the weight of its token weights (1 by default).
+    pub fn tf(mut self, tf: Option<Tf>) -> Self {
+        self.tf = tf.map(|tf| Arc::new(tf) as Arc<dyn TermWeighter>);
+        self
+    }
+
+    /// Sets the scheme of IDF weighting.
+    pub fn idf(mut self, idf: Option<Idf<u64>>) -> Self {
+        self.idf = idf.map(|idf| Arc::new(idf) as Arc<dyn TermWeighter>);
+        self
+    }
+
+    /// Sets the thread pool used in parallel processing.
+    /// If `None`, the global thread pool of [`rayon`] is used.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn thread_pool(mut self, thread_pool: Option<Arc<ThreadPool>>) -> Self {
+        self.thread_pool = thread_pool;
+        self
+    }
+
+    /// Sets a new thread pool of `num_threads` threads used in parallel processing.
+    /// If `num_threads` is 0, the number of threads is determined by [`rayon`].
+    pub fn num_threads(self, num_threads: usize) -> Result<Self> {
+        let thread_pool = ThreadPoolBuilder::new().num_threads(num_threads).build()?;
+        Ok(self.thread_pool(Some(Arc::new(thread_pool))))
+    }
+
+    /// Builds the database of sketches from input documents in parallel.
+    ///
+    /// # Arguments
+    ///
+    /// * `documents` - List of documents, each of which must include at least one feature.
+    /// * `num_chunks` - Number of chunks of sketches, indicating that
+    ///   the number of dimensions in the Hamming space is `num_chunks*64`.
+    pub fn build_sketches_in_parallel<I, D>(
+        mut self,
+        documents: I,
+        num_chunks: usize,
+    ) -> Result<Self>
+    where
+        I: Iterator<Item = D> + Send,
+        D: AsRef<str> + Send,
+    {
+        let _span = tracing::info_span!("build_sketches_in_parallel", num_chunks).entered();
+        let extractor = FeatureExtractor::new(&self.config);
+        let processed = AtomicUsize::new(0);
+        self.report_progress(Stage::DocumentsRead, 0, false);
+        self.report_progress(Stage::SketchesBuilt, 0, false);
+        let sketches = self.install(|| -> Result<Vec<_>> {
+            let mut sketches: Vec<_> = documents
+                .into_iter()
+                .enumerate()
+                .inspect(|&(i, _)| self.report_progress(Stage::DocumentsRead, i + 1, false))
+                .par_bridge()
+                .map_init(TokenBuffer::new, |buffer, (i, doc)| {
+                    let cnt = processed.fetch_add(1, Ordering::Relaxed) + 1;
+                    self.report_progress(Stage::SketchesBuilt, cnt, false);
+                    let mut feature = vec![];
+                    self.extract(&extractor, doc.as_ref(), &mut feature, buffer)?;
+                    Ok((i, self.sketch(&feature, num_chunks)?))
+                })
+                .collect::<Result<_>>()?;
+            sketches.par_sort_by_key(|&(i, _)| i);
+            Ok(sketches)
+        })?;
+        self.report_progress(Stage::DocumentsRead, sketches.len(), true);
+        self.report_progress(Stage::SketchesBuilt, sketches.len(), true);
+
+        let mut joiner = ChunkedJoiner::<u64>::new(num_chunks).progress_hook(self.progress.clone());
+        for (_, sketch) in sketches {
+            joiner.add(sketch)?;
+        }
+        self.joiner = Some(joiner);
+        Ok(self)
+    }
+
+    /// Reports the progress of a stage per 10000 documents,
+    /// or the final number of documents if `finished`.
+    fn report_progress(&self, stage: Stage, done: usize, finished: bool) {
+        if let Some(progress) = &self.progress {
+            if finished {
+                progress.report(stage, done, Some(done));
+            } else if done.is_multiple_of(10000) {
+                progress.report(stage, done, None);
+            }
+        }
+    }
+
+    /// Exports the sketches into an [`IndexFile`], with which the database can be restored by
+    /// [`Self::import_sketches()`] without re-hashing documents.
+    /// The header holds the seed value, and the `i`-th sketch in the file corresponds to
+    /// the `i`-th document.
+    ///
+    /// The settings of the searcher are not restored except for checking the seed value,
+    /// so the same settings must be given to the searcher importing the sketches.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the sketches are not built.
+    pub fn export_sketches(&self) -> Result<IndexFile> {
+        let joiner = self
+            .joiner
+            .as_ref()
+            .ok_or_else(|| FindSimdocError::input("Sketches must be built."))?;
+        let mut file = IndexFile::new(joiner.num_chunks());
+        file.push_header("seed", self.seed.to_string())?;
+        for i in 0..joiner.num_sketches() {
+            file.add(joiner.sketch(i))?;
+        }
+        Ok(file)
+    }
+
+    /// Restores the database from the sketches exported with [`Self::export_sketches()`],
+    /// replacing the existing one.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the seed value in the header is mismatched.
+    pub fn import_sketches(mut self, file: &IndexFile) -> Result<Self> {
+        file.check_seed(self.seed)?;
+        let mut joiner = ChunkedJoiner::new(file.num_chunks()).progress_hook(self.progress.clone());
+        for i in 0..file.num_sketches() {
+            joiner.add(file.sketch(i))?;
+        }
+        self.joiner = Some(joiner);
+        Ok(self)
+    }
+
+    /// Searches for all pairs of similar documents within an input radius, returning
+    /// triplets of the left-side id, the right-side id, and their distance.
+    pub fn search_similar_pairs(&self, radius: f64) -> Vec<(usize, usize, f64)> {
+        let _span = tracing::info_span!("search_similar_pairs", radius).entered();
+        let Some(joiner) = self.joiner.as_ref() else {
+            return vec![];
+        };
+        let mut results = vec![];
+        self.install(|| {
+            // As in 1-bit minhash, the collision probability is multiplied by 2 over the original.
+            // Thus, we should search with the half of the actual radius.
+            joiner.for_each_similar_pair(radius / 2., |i, j, dist| {
+                results.push((i, j, dist * 2.));
+            });
+        });
+        tracing::info!("Found {} similar pairs", results.len());
+        results
+    }
+
+    /// Searches for documents similar to a query within an input radius, returning
+    /// pairs of the document id and the distance in the ascending order of distances.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the sketches are not built or the query has no feature.
+    pub fn search_similar_documents(&self, query: &str, radius: f64) -> Result<Vec<(usize, f64)>> {
+        let _span = tracing::info_span!("search_similar_documents", radius).entered();
+        let joiner = self
+            .joiner
+            .as_ref()
+            .ok_or_else(|| FindSimdocError::input("Sketches must be built."))?;
+        let extractor = FeatureExtractor::new(&self.config);
+        let mut feature = vec![];
+        self.extract(&extractor, query, &mut feature, &mut TokenBuffer::new())
+            .map_err(|_| FindSimdocError::input("The query must include at least one feature."))?;
+        let sketch = self
+            .sketch(&feature, joiner.num_chunks())
+            .map_err(|_| FindSimdocError::input("The query must include at least one feature."))?;
+        let mut results: Vec<_> = self.install(|| {
+            (0..joiner.num_sketches())
+                .into_par_iter()
+                .filter_map(|k| {
+                    let hamdists = joiner
+                        .sketch(k)
+                        .zip(sketch.iter())
+                        .map(|(x, &y)| x.hamdist(y));
+                    let dist = Estimate::from_chunks(hamdists, u64::dim(), 2.).distance;
+                    (dist <= radius).then_some((k, dist))
+                })
+                .collect()
+        });
+        results.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        Ok(results)
+    }
+
+    fn extract(
+        &self,
+        extractor: &FeatureExtractor,
+        doc: &str,
+        feature: &mut Vec<(u64, f64)>,
+        buffer: &mut TokenBuffer,
+    ) -> Result<()> {
+        if doc.is_empty() {
+            return Err(FindSimdocError::input("Input document must not be empty."));
+        }
+        extractor.extract_with_weights_buffered(doc, feature, buffer);
+        if feature.is_empty() {
+            return Err(FindSimdocError::input(
+                "Input document must include at least one feature.",
+            ));
+        }
+        // The weighters multiply the token weights, so they are combined.
+        if let Some(tf) = self.tf.as_ref() {
+            tf.weight(feature);
+        }
+        if let Some(idf) = self.idf.as_ref() {
+            idf.weight(feature);
+        }
+        Ok(())
+    }
+
+    fn sketch(&self, feature: &[(u64, f64)], num_chunks: usize) -> Result<Vec<u64>> {
+        Ok(self.hasher.iter(feature)?.take(num_chunks).collect())
+    }
+
+    fn install<OP, R>(&self, op: OP) -> R
+    where
+        OP: FnOnce() -> R + Send,
+        R: Send,
+    {
+        match self.thread_pool.as_ref() {
+            Some(thread_pool) => thread_pool.install(op),
+            None => op(),
+        }
+    }
+
+    /// Gets the number of input documents.
+    pub fn len(&self) -> usize {
+        self.joiner
+            .as_ref()
+            .map_or(0, |joiner| joiner.num_sketches())
+    }
+
+    /// Checks if the database is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the memory usage in bytes.
+    pub fn memory_in_bytes(&self) -> usize {
+        self.joiner
+            .as_ref()
+            .map_or(0, |joiner| joiner.memory_in_bytes())
+    }
+
+    /// Gets the configuration of feature extraction.
+    pub const fn config(&self) -> &FeatureConfig {
+        &self.config
+    }
+
+    /// Gets the seed value for random values.
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_similar_pairs() {
+        let documents = ["aaaabbbbcccc", "aaaabbbbcccd", "xyzxyzxyzxyz"];
+        let searcher = WeightedJaccardSearcher::new(1, None, Some(42))
+            .unwrap()
+            .tf(Some(Tf::new()))
+            .build_sketches_in_parallel(documents.iter(), 8)
+            .unwrap();
+        assert_eq!(searcher.len(), 3);
+        let results = searcher.search_similar_pairs(0.3);
+        assert_eq!(results.len(), 1);
+        assert_eq!((results[0].0, results[0].1), (0, 1));
+
+        let results = searcher
+            .search_similar_documents("aaaabbbbcccc", 0.3)
+            .unwrap();
+        assert_eq!(results.iter().map(|&(i, _)| i).collect::<Vec<_>>(), [0, 1]);
+        assert!(searcher.search_similar_documents("", 0.3).is_err());
+    }
+
+    #[test]
+    fn test_import_sketches() {
+        let documents = ["abcdefg", "abcdefh", "abcxyzw", "hijklmn"];
+        let searcher = WeightedJaccardSearcher::new(2, None, Some(42))
+            .unwrap()
+            .build_sketches_in_parallel(documents.iter(), 4)
+            .unwrap();
+        let mut data = vec![];
+        searcher
+            .export_sketches()
+            .unwrap()
+            .serialize_into(&mut data)
+            .unwrap();
+        let file = IndexFile::deserialize_from(&data[..]).unwrap();
+
+        let other = WeightedJaccardSearcher::new(2, None, Some(42))
+            .unwrap()
+            .import_sketches(&file)
+            .unwrap();
+        assert_eq!(other.len(), searcher.len());
+        assert_eq!(
+            other.search_similar_pairs(0.5),
+            searcher.search_similar_pairs(0.5)
+        );
+
+        assert!(WeightedJaccardSearcher::new(2, None, Some(43))
+            .unwrap()
+            .import_sketches(&file)
+            .is_err());
+    }
+}
@@ -0,0 +1,56 @@
+//! Measures the sketching time of simhash, e.g.,
+//! `cargo run --release -p simhash-timeperf` on stable and
+//! `cargo +nightly run --release -p simhash-timeperf --features simd` with std::simd.
+use std::time::Instant;
+
+use find_simdoc::lsh::simhash::SimHasher;
+use rand::{Rng, SeedableRng};
+
+const TRIALS: usize = 3;
+const FEATURE_LENS: [usize; 3] = [100, 1_000, 10_000];
+const CHUNKS: [usize; 2] = [8, 64];
+
+fn main() {
+    let simd = if cfg!(feature = "simd") {
+        "std"
+    } else {
+        "auto"
+    };
+    let max_len = *FEATURE_LENS.last().unwrap();
+    // The same feature is generated in every run, so the checksums of sketches are comparable.
+    let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+    let feature: Vec<_> = (0..max_len)
+        .map(|_| (rng.gen::<u64>(), rng.gen::<f64>() - 0.5))
+        .collect();
+    for accumulates_in_f32 in [false, true] {
+        let hasher = SimHasher::new(42).accumulates_in_f32(accumulates_in_f32);
+        let weight = if accumulates_in_f32 { "f32" } else { "f64" };
+        for &feature_len in &FEATURE_LENS {
+            for &num_chunks in &CHUNKS {
+                let feature = &feature[..feature_len];
+                let mut checksum = 0;
+                let elapsed_sec = measure(TRIALS, || {
+                    checksum ^= hasher
+                        .sketch(feature, num_chunks)
+                        .into_iter()
+                        .fold(0, |acc, x| acc ^ x);
+                });
+                println!(
+                    "[simd={simd},weight={weight},feature_len={feature_len},num_chunks={num_chunks},checksum={checksum:x}] {elapsed_sec} sec"
+                );
+            }
+        }
+    }
+}
+
+fn measure<F>(num_trials: usize, mut func: F) -> f64
+where
+    F: FnMut(),
+{
+    let start = Instant::now();
+    for _ in 0..num_trials {
+        func();
+    }
+    let duration = start.elapsed();
+    duration.as_secs_f64() / num_trials as f64
+}
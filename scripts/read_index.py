@@ -0,0 +1,131 @@
+#!/usr/bin/env python3
+'''
+Reference reader and writer of the index format documented in the find_simdoc::index_format module,
+depending only on the standard library.
+Prints the header and sketches as hexadecimal chunks separated by commas, the texts if stored,
+and the names and sizes of the sections.
+'''
+
+import struct
+import sys
+from argparse import ArgumentParser
+from array import array
+
+MAGIC = b'FSIDX\x00\x00'
+VERSION = 2
+
+
+def read_u64(fin):
+    return struct.unpack('<Q', fin.read(8))[0]
+
+
+def read_u64_array(fin, n):
+    values = array('Q')
+    values.frombytes(fin.read(8 * n))
+    if len(values) != n:
+        raise ValueError('unexpected end of file')
+    if sys.byteorder == 'big':
+        values.byteswap()
+    return values
+
+
+def write_u64(fout, value):
+    fout.write(struct.pack('<Q', value))
+
+
+def write_u64_array(fout, values):
+    values = array('Q', values)
+    if sys.byteorder == 'big':
+        values.byteswap()
+    fout.write(values.tobytes())
+
+
+def read_index(path):
+    '''
+    Returns a dict of the header entries, a list of the blocks of chunks,
+    where blocks[c][i] is the c-th chunk of the i-th sketch, a list of texts or None,
+    and a dict of the sections from names to bytes.
+    '''
+    with open(path, 'rb') as fin:
+        magic = fin.read(len(MAGIC) + 1)
+        if magic[:len(MAGIC)] != MAGIC:
+            raise ValueError('invalid index file')
+        if magic[len(MAGIC)] != VERSION:
+            raise ValueError(f'unsupported version: {magic[len(MAGIC)]}')
+        header = fin.read(read_u64(fin)).decode('utf-8')
+        header = dict(line.split('=', 1) for line in header.splitlines())
+        num_chunks = read_u64(fin)
+        num_sketches = read_u64(fin)
+        blocks = [read_u64_array(fin, num_sketches) for _ in range(num_chunks)]
+        num_documents = read_u64(fin)
+        documents = None
+        if num_documents != 0:
+            offsets = read_u64_array(fin, num_documents + 1)
+            texts = fin.read(offsets[-1])
+            documents = [texts[offsets[i]:offsets[i + 1]].decode('utf-8')
+                         for i in range(num_documents)]
+        sections = {}
+        for _ in range(read_u64(fin)):
+            name = fin.read(read_u64(fin)).decode('utf-8')
+            sections[name] = fin.read(read_u64(fin))
+    return header, blocks, documents, sections
+
+
+def write_index(path, header, blocks, documents=None, sections=None):
+    '''
+    Writes the index from a dict of the header entries, a list of the blocks of chunks,
+    where blocks[c][i] is the c-th chunk of the i-th sketch, an optional list of texts,
+    and an optional dict of the sections from names to bytes.
+    '''
+    num_sketches = len(blocks[0]) if blocks else 0
+    if any(len(block) != num_sketches for block in blocks):
+        raise ValueError('blocks must have the same length')
+    if documents is not None and len(documents) != num_sketches:
+        raise ValueError('the number of documents must be that of sketches')
+    with open(path, 'wb') as fout:
+        fout.write(MAGIC + bytes([VERSION]))
+        header = ''.join(f'{key}={value}\n' for key, value in header.items()).encode('utf-8')
+        write_u64(fout, len(header))
+        fout.write(header)
+        write_u64(fout, len(blocks))
+        write_u64(fout, num_sketches)
+        for block in blocks:
+            write_u64_array(fout, block)
+        texts = [doc.encode('utf-8') for doc in documents or []]
+        write_u64(fout, len(texts))
+        if texts:
+            offsets = [0]
+            for text in texts:
+                offsets.append(offsets[-1] + len(text))
+            write_u64_array(fout, offsets)
+            fout.write(b''.join(texts))
+        sections = sections or {}
+        write_u64(fout, len(sections))
+        for name, data in sections.items():
+            name = name.encode('utf-8')
+            write_u64(fout, len(name))
+            fout.write(name)
+            write_u64(fout, len(data))
+            fout.write(data)
+
+
+def main():
+    parser = ArgumentParser()
+    parser.add_argument('path')
+    args = parser.parse_args()
+
+    header, blocks, documents, sections = read_index(args.path)
+    for key, value in header.items():
+        print(f'# {key}={value}')
+    for name, data in sections.items():
+        print(f'# [{name}] {len(data)} bytes')
+    num_sketches = len(blocks[0]) if blocks else 0
+    for i in range(num_sketches):
+        line = ','.join(f'{block[i]:016x}' for block in blocks)
+        if documents is not None:
+            line += '\t' + documents[i]
+        print(line)
+
+
+if __name__ == "__main__":
+    main()